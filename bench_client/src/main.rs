@@ -1,8 +1,19 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use futures_util::{SinkExt, StreamExt};
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use std::time::Instant;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::{Duration, Instant};
 use chrono::Utc;
+use base64::Engine;
+use common::{
+    attest,
+    params::{Operation, ParamSet},
+    stats,
+};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, Message},
+    Connector, MaybeTlsStream, WebSocketStream,
+};
 
 #[derive(Parser)]
 #[command(name = "bench_client")]
@@ -11,7 +22,9 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    #[arg(long, default_value="json", global = true)]
+    /// json or csv; or ndjson with kem, which streams every sample into --file as the server
+    /// sends it and prints the usual JSON summary
+    #[arg(long, alias = "format", default_value="json", global = true)]
     output: String,
 
     #[arg(long, global = true)]
@@ -19,29 +32,147 @@ struct Cli {
 
     #[arg(long, default_value = "default", global = true)]
     label: String,
+
+    /// Request raw per-iteration KEM and ZK timings (inline for JSON, <file>.raw.csv for CSV)
+    #[arg(long, global = true)]
+    raw: bool,
+
+    /// Keep the server's keygen/encaps/decaps breakdown of full_handshake: one extra CSV row
+    /// per phase, or a phases field in JSON
+    #[arg(long, global = true)]
+    phase_rows: bool,
+
+    /// Don't ask for gzip/brotli-compressed responses
+    #[arg(long, global = true)]
+    no_compression: bool,
+
+    /// Open a fresh connection for every request instead of reusing idle ones
+    #[arg(long, global = true)]
+    no_keep_alive: bool,
+
+    /// Speak HTTP/2 with prior knowledge (h2c) to http:// URLs; https:// negotiates it anyway
+    #[arg(long, global = true, conflicts_with = "no_keep_alive")]
+    http2: bool,
+
+    /// Wire format for lattice_service benchmark requests and responses
+    #[arg(long, value_enum, default_value_t = Encoding::Json, global = true)]
+    encoding: Encoding,
+
+    /// Bearer token sent with every request, for servers started with an auth token
+    #[arg(long, env = "BENCH_TOKEN", hide_env_values = true, global = true)]
+    token: Option<String>,
+
+    /// PEM CA certificate to trust for https:// URLs, on top of the system roots
+    #[arg(long, global = true)]
+    ca: Option<String>,
+
+    /// Skip TLS certificate verification (self-signed dev certificates only)
+    #[arg(long, global = true)]
+    insecure: bool,
+
+    /// Submit KEM benchmarks as async jobs and poll for results, for proxies that cut off long requests
+    #[arg(long = "async", global = true)]
+    async_jobs: bool,
+
+    /// Stream KEM benchmarks and show a live progress line while each one runs
+    #[arg(long, global = true, conflicts_with = "async_jobs")]
+    progress: bool,
+
+    /// Check every lattice_service response against the server's GET /signing_key before
+    /// writing results; rows that can't be verified get signature_verified = false
+    #[arg(long, global = true)]
+    verify_signatures: bool,
+}
+
+/// How a KEM benchmark request waits for its result
+#[derive(Clone, Copy, PartialEq)]
+enum RequestMode {
+    /// One request that returns when the benchmark does
+    Blocking,
+    /// Submit a job, then poll until it finishes
+    AsyncJob,
+    /// Read Server-Sent Events, rendering progress until the result arrives
+    Stream,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Encoding {
+    Json,
+    Cbor,
+    Msgpack,
+}
+
+impl Encoding {
+    fn content_type(self) -> &'static str {
+        match self {
+            Encoding::Json => "application/json",
+            Encoding::Cbor => "application/cbor",
+            Encoding::Msgpack => "application/msgpack",
+        }
+    }
+
+    fn encode<T: Serialize>(self, value: &T) -> Vec<u8> {
+        match self {
+            Encoding::Json => serde_json::to_vec(value).unwrap(),
+            Encoding::Cbor => {
+                let mut buffer = Vec::new();
+                ciborium::into_writer(value, &mut buffer).unwrap();
+                buffer
+            }
+            Encoding::Msgpack => rmp_serde::to_vec_named(value).unwrap(),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(self, body: &[u8]) -> Option<T> {
+        match self {
+            Encoding::Json => serde_json::from_slice(body).ok(),
+            Encoding::Cbor => ciborium::from_reader(body).ok(),
+            Encoding::Msgpack => rmp_serde::from_slice(body).ok(),
+        }
+    }
+
+    // Send the body in this encoding and ask for the response in it too
+    fn post(self, client: &Client, endpoint: &str, body: Vec<u8>) -> reqwest::RequestBuilder {
+        client
+            .post(endpoint)
+            .header("Content-Type", self.content_type())
+            .header("Accept", self.content_type())
+            .body(body)
+    }
 }
 
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Benchmark KEM operations
+    /// Benchmark KEM and signature operations
     Kem {
-        /// Service URL
+        /// Service URL, or unix:/path/to.sock for a service on a Unix domain socket
         #[arg(long, default_value = "http://localhost:8000")]
         url: String,
 
-        /// Parameter set: ml_kem_512, ml_kem_768, ml_kem_1024
+        /// Parameter set: ml_kem_512, ml_kem_768, ml_kem_1024, x_wing, x25519, rsa_2048, ml_dsa_44, ml_dsa_65, ml_dsa_87, slh_dsa_sha2_128s, slh_dsa_sha2_128f.
+        /// Aliases such as ML-KEM-768 or kyber768 are accepted and sent under the canonical name
         #[arg(long, default_value = "ml_kem_768")]
-        param_set: String,
+        param_set: ParamSet,
 
-        /// Operation: keygen, encaps, decaps, full_handshake
+        /// Operation: keygen, encaps, decaps, encaps_decaps, full_handshake (ML-KEM, X-Wing, X25519, RSA), decaps_reject, ct_compare, serialize_keys, deserialize_keys, kem_dem_encrypt, kem_dem_decrypt (ML-KEM); keygen, sign, verify, sign_verify (ML-DSA)
         #[arg(long, default_value = "full_handshake")]
-        operation: String,
+        operation: Operation,
 
         /// Iterations per request
         #[arg(long, default_value = "100")]
         iterations: u32,
 
+        /// Have the server repeat the measured loop this many times per request (at most 20);
+        /// CSV output gets one extra row per run
+        #[arg(long)]
+        runs: Option<u32>,
+
+        /// Have the server timestamp each iteration and warn when latency drifts over the run,
+        /// e.g. from thermal throttling
+        #[arg(long)]
+        record_timestamps: bool,
+
         /// Number of requests to make (for client-side concurrency testing)
         #[arg(long, default_value = "1")]
         requests: u32,
@@ -52,14 +183,26 @@ enum Commands {
     },
     /// Benchmark ZK proving
     ZkProve {
-        /// Service URL
+        /// Service URL, or unix:/path/to.sock for a service on a Unix domain socket
         #[arg(long, default_value = "http://localhost:8001")]
         url: String,
 
-        /// Circuit ID: multiply, cube_root
+        /// Circuit ID: multiply, cube_root, chain
         #[arg(long, default_value = "multiply")]
         circuit_id: String,
 
+        /// Steps in the chain circuit; required with --circuit-id chain
+        #[arg(long)]
+        chain_length: Option<u32>,
+
+        /// Pairing curve: bn254 or bls12_381
+        #[arg(long, default_value = "bn254")]
+        curve: String,
+
+        /// Proof system: groth16 or marlin
+        #[arg(long, default_value = "groth16")]
+        proof_system: String,
+
         /// Iterations per request
         #[arg(long, default_value = "10")]
         iterations: u32,
@@ -71,17 +214,33 @@ enum Commands {
         /// Concurrent requests
         #[arg(long, default_value = "1")]
         concurrency: u32,
+
+        /// Rayon threads the server proves on; its ZK_PROVER_THREADS when unset
+        #[arg(long)]
+        prover_threads: Option<u32>,
     },
     /// Benchmark ZK verification
     ZkVerify {
-        /// Service URL
+        /// Service URL, or unix:/path/to.sock for a service on a Unix domain socket
         #[arg(long, default_value = "http://localhost:8001")]
         url: String,
 
-        /// Circuit ID: multiply, cube_root
+        /// Circuit ID: multiply, cube_root, chain
         #[arg(long, default_value = "multiply")]
         circuit_id: String,
 
+        /// Steps in the chain circuit; required with --circuit-id chain
+        #[arg(long)]
+        chain_length: Option<u32>,
+
+        /// Pairing curve: bn254 or bls12_381
+        #[arg(long, default_value = "bn254")]
+        curve: String,
+
+        /// Proof system: groth16 or marlin
+        #[arg(long, default_value = "groth16")]
+        proof_system: String,
+
         /// Iterations per request
         #[arg(long, default_value = "100")]
         iterations: u32,
@@ -93,14 +252,43 @@ enum Commands {
         /// Concurrent requests
         #[arg(long, default_value = "1")]
         concurrency: u32,
+
+        /// fixed (the server's default) verifies one proof every iteration; fresh verifies a
+        /// different proof of a different statement each time
+        #[arg(long)]
+        verify_mode: Option<String>,
+    },
+    /// Re-run a KEM benchmark over one WebSocket connection, as a live dashboard would
+    Ws {
+        /// Service URL (http://, https:// or unix:/path/to.sock; the WebSocket is opened at /ws)
+        #[arg(long, default_value = "http://localhost:8000")]
+        url: String,
+
+        #[arg(long, default_value = "ml_kem_768")]
+        param_set: ParamSet,
+
+        #[arg(long, default_value = "full_handshake")]
+        operation: Operation,
+
+        /// Iterations per run
+        #[arg(long, default_value = "100")]
+        iterations: u32,
+
+        /// Runs to send over the socket, one after another
+        #[arg(long, default_value = "10")]
+        runs: u32,
+
+        /// Pause between runs, in milliseconds
+        #[arg(long, default_value = "0")]
+        interval_ms: u64,
     },
     /// Run full benchmark suite
     Suite {
-        /// Lattice service URL
+        /// Lattice service URL, or unix:/path/to.sock
         #[arg(long, default_value = "http://localhost:8000")]
         lattice_url: String,
 
-        /// ZK service URL
+        /// ZK service URL, or unix:/path/to.sock
         #[arg(long, default_value = "http://localhost:8001")]
         zk_url: String,
 
@@ -115,14 +303,84 @@ enum Commands {
 }
 
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct KemBenchRequest {
     param_set: String,
     iterations: u32,
     operation: String,
+    include_raw_timings: bool,
+    include_machine_info: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    runs: Option<u32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    record_timestamps: bool,
 }
 
+impl Commands {
+    fn urls_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Commands::Kem { url, .. }
+            | Commands::ZkProve { url, .. }
+            | Commands::ZkVerify { url, .. }
+            | Commands::Ws { url, .. } => vec![url],
+            Commands::Suite { lattice_url, zk_url, .. } => vec![lattice_url, zk_url],
+        }
+    }
+}
+
+// ============ Unix Domain Sockets ============
+//
+// reqwest 0.11 has no hook for a custom connector, so a unix: URL is served by a loopback
+// TCP listener that pipes each connection through to the socket. Every request, WebSockets
+// included, then takes the usual http:// path.
+
+const UNIX_SCHEME: &str = "unix:";
+
+// unix:/run/lattice.sock and unix:///run/lattice.sock name the same socket
+async fn resolve_url(url: String) -> String {
+    let Some(path) = url.strip_prefix(UNIX_SCHEME) else {
+        return url;
+    };
+    let path = std::path::PathBuf::from(path.strip_prefix("//").unwrap_or(path));
+    #[cfg(not(unix))]
+    {
+        eprintln!("error: {} URLs need a Unix platform ({})", UNIX_SCHEME, path.display());
+        std::process::exit(2);
+    }
+    #[cfg(unix)]
+    {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("failed to bind loopback forwarder");
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut tcp, _)) = listener.accept().await {
+                let path = path.clone();
+                tokio::spawn(async move {
+                    match tokio::net::UnixStream::connect(&path).await {
+                        Ok(mut socket) => {
+                            let _ = tokio::io::copy_bidirectional(&mut tcp, &mut socket).await;
+                        }
+                        Err(e) => eprintln!("  could not connect to {}: {}", path.display(), e),
+                    }
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+}
+
+/// Async job status from GET /jobs/{id}
+#[derive(Deserialize)]
+struct KemJob {
+    job_id: String,
+    status: String,
+    result: Option<KemBenchResponse>,
+    error: Option<String>,
+}
+
+// Deserialized through the manual impl below, which keeps the response as sent for
+// signature checks: a signature covers every field, including ones this client ignores
 #[derive(Deserialize, Debug)]
+#[serde(remote = "Self")]
 struct KemBenchResponse {
     operation: String,
     param_set: String,
@@ -131,81 +389,695 @@ struct KemBenchResponse {
     min_us: f64,
     max_us: f64,
     p95_us: f64,
+    stddev_us: f64,
+    median_us: f64,
+    #[serde(default)]
+    ci95_low_us: Option<f64>,
+    #[serde(default)]
+    ci95_high_us: Option<f64>,
     throughput_ops_sec: f64,
+    #[serde(default)]
+    measured_loop_ops_sec: Option<f64>,
+    #[serde(default)]
+    avg_cpu_us: Option<f64>,
+    #[serde(default)]
+    cpu_time_ratio: Option<f64>,
+    #[serde(default)]
+    effective_iterations: Option<u32>,
+    #[serde(default)]
+    encapsulation_key_bytes: Option<usize>,
+    #[serde(default)]
+    decapsulation_key_bytes: Option<usize>,
+    #[serde(default)]
+    ciphertext_bytes: Option<usize>,
+    #[serde(default)]
+    shared_secret_bytes: Option<usize>,
+    #[serde(default)]
+    histogram: Option<serde_json::Value>,
+    #[serde(default)]
+    timings_us: Option<Vec<u64>>,
+    #[serde(default)]
+    timings_truncated: Option<bool>,
+    #[serde(default)]
+    machine_info: Option<MachineInfo>,
+    #[serde(default)]
+    per_run: Option<Vec<RunStats>>,
+    #[serde(default)]
+    keygen_stats: Option<LatencyStats>,
+    #[serde(default)]
+    encaps_stats: Option<LatencyStats>,
+    #[serde(default)]
+    decaps_stats: Option<LatencyStats>,
+    #[serde(default)]
+    drift: Option<Drift>,
     timestamp: u64,
+    #[serde(skip)]
+    raw: serde_json::Value,
+}
+
+impl<'de> Deserialize<'de> for KemBenchResponse {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = serde_json::Value::deserialize(deserializer)?;
+        let mut resp = KemBenchResponse::deserialize(&raw).map_err(serde::de::Error::custom)?;
+        resp.raw = raw;
+        Ok(resp)
+    }
+}
+
+/// Server's verdict on whether latency moved between the start and end of a run
+#[derive(Deserialize, Debug)]
+struct Drift {
+    first_decile_avg_us: f64,
+    last_decile_avg_us: f64,
+    drift_detected: bool,
+}
+
+/// Summary stats of one run or one full_handshake phase, as the server reports them
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct LatencyStats {
+    avg_us: f64,
+    min_us: f64,
+    max_us: f64,
+    p95_us: f64,
+    stddev_us: f64,
+    median_us: f64,
+}
+
+/// One repetition of the server's measured loop, present when a request set runs
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct RunStats {
+    completed_iterations: u32,
+    #[serde(flatten)]
+    stats: LatencyStats,
+}
+
+/// A full_handshake phase across every request behind a result
+#[derive(Serialize, Debug, Clone)]
+struct PhaseStats {
+    phase: &'static str,
+    #[serde(flatten)]
+    stats: LatencyStats,
 }
 
 #[derive(Serialize)]
 struct ZkBenchRequest {
     circuit_id: String,
     iterations: u32,
+    include_machine_info: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chain_length: Option<u32>,
+    curve: String,
+    proof_system: String,
+    include_raw_timings: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verify_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prover_threads: Option<u32>,
+}
+
+// Marlin's universal SRS and per-circuit index, reported apart from the proving times
+#[derive(Deserialize, Debug, Clone)]
+struct MarlinSetup {
+    srs_size_bytes: usize,
+    #[serde(default)]
+    index_ms: Option<f64>,
+}
+
+// One timed phase of a zk prove iteration
+#[derive(Deserialize, Debug, Clone)]
+struct ProvePhaseStats {
+    avg_ms: f64,
+}
+
+// Host the server ran on; absent when the server has machine info disabled
+#[derive(Deserialize, Debug, Clone)]
+struct MachineInfo {
+    hostname: Option<String>,
+    cpu_model: Option<String>,
+    logical_cores: usize,
+    build_profile: String,
 }
 
 #[derive(Deserialize, Debug)]
 struct ZkProveBenchResponse {
-    circuit_id: String,
-    iterations: u32,
     avg_prove_ms: f64,
     min_prove_ms: f64,
     max_prove_ms: f64,
     p95_prove_ms: f64,
+    #[serde(default)]
+    stddev_prove_ms: Option<f64>,
+    #[serde(default)]
+    median_prove_ms: Option<f64>,
+    #[serde(default)]
+    ci95_low_prove_ms: Option<f64>,
+    #[serde(default)]
+    ci95_high_prove_ms: Option<f64>,
     avg_proof_size_bytes: usize,
     throughput_proofs_sec: f64,
-    timestamp: u64,
+    /// Absent from servers that only prove over BN254
+    #[serde(default)]
+    curve: Option<String>,
+    #[serde(default)]
+    proof_system: Option<String>,
+    #[serde(default)]
+    marlin: Option<MarlinSetup>,
+    #[serde(default)]
+    wall_clock_proofs_sec: Option<f64>,
+    /// Absent from servers that time each proof as a single call
+    #[serde(default)]
+    witness_stats: Option<ProvePhaseStats>,
+    #[serde(default)]
+    prove_stats: Option<ProvePhaseStats>,
+    #[serde(default)]
+    effective_iterations: Option<u32>,
+    #[serde(default)]
+    machine_info: Option<MachineInfo>,
+    /// Every circuit on current servers; only the chain circuit on older ones, as constraints
+    #[serde(default, alias = "constraints")]
+    num_constraints: Option<usize>,
+    #[serde(default)]
+    proving_key_bytes: Option<usize>,
+    #[serde(default)]
+    verifying_key_bytes: Option<usize>,
+    #[serde(default)]
+    setup_ms: Option<f64>,
+    #[serde(default)]
+    timings_ms: Option<Vec<f64>>,
+    #[serde(default)]
+    timings_truncated: Option<bool>,
+    /// Absent from servers that prove on rayon's global pool
+    #[serde(default)]
+    prover_threads: Option<usize>,
+    #[serde(default)]
+    prover_speedup: Option<f64>,
 }
 
 #[derive(Deserialize, Debug)]
 struct ZkVerifyBenchResponse {
-    circuit_id: String,
-    iterations: u32,
     avg_verify_ms: f64,
     min_verify_ms: f64,
     max_verify_ms: f64,
     p95_verify_ms: f64,
+    #[serde(default)]
+    stddev_verify_ms: Option<f64>,
+    #[serde(default)]
+    median_verify_ms: Option<f64>,
+    #[serde(default)]
+    ci95_low_verify_ms: Option<f64>,
+    #[serde(default)]
+    ci95_high_verify_ms: Option<f64>,
     throughput_verifies_sec: f64,
-    timestamp: u64,
+    #[serde(default)]
+    proof_size_bytes: Option<usize>,
+    #[serde(default)]
+    curve: Option<String>,
+    #[serde(default)]
+    proof_system: Option<String>,
+    #[serde(default)]
+    marlin: Option<MarlinSetup>,
+    #[serde(default)]
+    wall_clock_verifies_sec: Option<f64>,
+    #[serde(default)]
+    effective_iterations: Option<u32>,
+    #[serde(default)]
+    machine_info: Option<MachineInfo>,
+    /// Every circuit on current servers; only the chain circuit on older ones, as constraints
+    #[serde(default, alias = "constraints")]
+    num_constraints: Option<usize>,
+    #[serde(default)]
+    proving_key_bytes: Option<usize>,
+    #[serde(default)]
+    verifying_key_bytes: Option<usize>,
+    #[serde(default)]
+    setup_ms: Option<f64>,
+    #[serde(default)]
+    timings_ms: Option<Vec<f64>>,
+    #[serde(default)]
+    timings_truncated: Option<bool>,
+    #[serde(default)]
+    verify_mode: Option<String>,
+    #[serde(default)]
+    distinct_statements: Option<u32>,
 }
 
 // ============ Unified Result Type ============
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct BenchmarkResult {
     timestamp: String,
     label: String,
     service: String,
     operation: String,
     param_set: String,
+    /// Set on the extra per-run CSV rows, numbered from 1 across every request; empty on
+    /// the row aggregating them
+    run: Option<usize>,
+    /// keygen, encaps or decaps on the extra full_handshake rows of --phase-rows
+    phase: Option<&'static str>,
+    /// Iterations the server actually ran per request
     iterations: u32,
+    requested_iterations: u32,
     requests: u32,
     concurrency: u32,
     avg_latency_ms: f64,
     min_latency_ms: f64,
     max_latency_ms: f64,
     p95_latency_ms: f64,
+    /// Sample standard deviation of every iteration across the requests, pooled from each
+    /// one's count, mean and standard deviation, so the spread between requests counts too
+    stddev_latency_ms: Option<f64>,
+    /// Median of every iteration when the raw timings were fetched (--raw) and complete;
+    /// otherwise the mean of each request's median, which is not a median of the whole.
+    /// Per-phase rows always hold the mean of medians
+    median_latency_ms: Option<f64>,
+    /// Whether median_latency_ms came from the raw timings; empty on per-run and phase rows
+    median_from_raw_timings: Option<bool>,
+    /// Whether any request's server saw latency drift over its run; empty unless
+    /// --record-timestamps was set
+    drift_detected: Option<bool>,
+    /// Widest 95% confidence interval for the mean across the requests, not an average of them
+    ci95_low_latency_ms: Option<f64>,
+    ci95_high_latency_ms: Option<f64>,
+    /// 1 / average server-side latency, so it ignores threading and per-iteration overhead
     throughput_ops_sec: f64,
+    /// Server-measured operations per second over the wall-clock span of its measured loop
+    measured_loop_ops_sec: Option<f64>,
+    /// Server thread CPU time per operation, averaged across the requests
+    avg_cpu_ms: Option<f64>,
+    /// Lowest server CPU/wall-clock ratio across the requests
+    cpu_time_ratio: Option<f64>,
+    /// Set when cpu_time_ratio is below CONTENDED_CPU_RATIO: the server's benchmark threads
+    /// were descheduled for part of the run, so its latencies include waiting for a core
+    contended: Option<bool>,
     client_total_time_ms: f64,
     client_avg_request_ms: f64,
     error_count: u32,
+    /// Subset of error_count rejected with 429 by the server's concurrency limit
+    throttled_count: u32,
+    encapsulation_key_bytes: Option<usize>,
+    decapsulation_key_bytes: Option<usize>,
+    ciphertext_bytes: Option<usize>,
+    shared_secret_bytes: Option<usize>,
+    /// Pairing curve of a zk benchmark, as the server reported it
+    curve: Option<String>,
+    /// groth16 or marlin, for zk benchmarks
+    proof_system: Option<String>,
+    /// Compressed proof size of a zk benchmark, which differs by curve and proof system
+    proof_size_bytes: Option<usize>,
+    /// Marlin only: its universal SRS size, and the time to index the circuit against it
+    /// when one of these requests did
+    srs_size_bytes: Option<usize>,
+    index_ms: Option<f64>,
+    /// Chain circuit only, for plotting time against size
+    chain_length: Option<u32>,
+    /// Constraint count of a zk benchmark's circuit, to normalize its timings per constraint
+    constraints: Option<usize>,
+    /// Compressed key sizes of a zk benchmark's circuit
+    proving_key_bytes: Option<usize>,
+    verifying_key_bytes: Option<usize>,
+    /// Trusted setup time, when one of these requests was the first for its circuit
+    setup_ms: Option<f64>,
+    /// zk prove only: server time synthesizing each witness, and proving from it;
+    /// avg_latency_ms is the two together
+    avg_witness_ms: Option<f64>,
+    avg_proving_ms: Option<f64>,
+    /// zk verify only: fixed or fresh, and how many distinct statements were verified
+    verify_mode: Option<String>,
+    distinct_statements: Option<u32>,
+    /// zk prove only: rayon threads the server proved on, and its speedup over proving on one
+    prover_threads: Option<usize>,
+    prover_speedup: Option<f64>,
+    /// Build that produced the result, from the server's GET /version
+    server_version: Option<String>,
+    server_git_commit: Option<String>,
+    server_build_profile: Option<String>,
+    server_rustc_version: Option<String>,
+    server_ml_kem_version: Option<String>,
+    /// Host the server ran on, so results merged from several machines stay attributable
+    machine_hostname: Option<String>,
+    machine_cpu_model: Option<String>,
+    machine_logical_cores: Option<usize>,
+    machine_build_profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw_timings_us: Option<Vec<u64>>,
+    /// Server histograms as returned, one per successful request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    histograms: Option<Vec<serde_json::Value>>,
+    /// Every request's per-run stats, in order, when runs was set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    per_run: Option<Vec<RunStats>>,
+    /// For full_handshake with --phase-rows: each phase, aggregated like the row itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phases: Option<Vec<PhaseStats>>,
+    /// Whether every response behind the row carried a valid server signature; empty unless
+    /// --verify-signatures was set
+    signature_verified: Option<bool>,
+    /// Responses as received, for --verify-signatures
+    #[serde(skip)]
+    signed_responses: Vec<serde_json::Value>,
+}
+
+impl BenchmarkResult {
+    // This row with a run's or a phase's latencies, and without what only the whole has
+    fn with_latencies(&self, stats: &LatencyStats) -> BenchmarkResult {
+        BenchmarkResult {
+            avg_latency_ms: stats.avg_us / 1000.0,
+            min_latency_ms: stats.min_us / 1000.0,
+            max_latency_ms: stats.max_us / 1000.0,
+            p95_latency_ms: stats.p95_us / 1000.0,
+            stddev_latency_ms: Some(stats.stddev_us / 1000.0),
+            median_latency_ms: Some(stats.median_us / 1000.0),
+            median_from_raw_timings: None,
+            ci95_low_latency_ms: None,
+            ci95_high_latency_ms: None,
+            throughput_ops_sec: if stats.avg_us > 0.0 { 1_000_000.0 / stats.avg_us } else { 0.0 },
+            measured_loop_ops_sec: None,
+            avg_cpu_ms: None,
+            cpu_time_ratio: None,
+            contended: None,
+            ..self.clone()
+        }
+    }
+
+    // The aggregate row followed by one row per run, then one per full_handshake phase
+    fn csv_rows(&self) -> Vec<BenchmarkResult> {
+        let aggregate = BenchmarkResult { raw_timings_us: None, histograms: None, per_run: None, phases: None, ..self.clone() };
+        let runs = self.per_run.iter().flatten().enumerate().map(|(i, run)| BenchmarkResult {
+            run: Some(i + 1),
+            iterations: run.completed_iterations,
+            ..aggregate.with_latencies(&run.stats)
+        });
+        let phases = self.phases.iter().flatten().map(|phase| BenchmarkResult {
+            phase: Some(phase.phase),
+            ..aggregate.with_latencies(&phase.stats)
+        });
+        std::iter::once(aggregate.clone()).chain(runs).chain(phases).collect()
+    }
+}
+
+// ============ Server Discovery ============
+
+#[derive(Deserialize)]
+struct ParamSetInfo {
+    name: String,
+    operations: Vec<String>,
+    iteration_cap: u32,
+}
+
+#[derive(Deserialize)]
+struct ServerParams {
+    param_sets: Vec<ParamSetInfo>,
+}
+
+impl ServerParams {
+    fn check(&self, param_set: &str, operation: &str) -> Result<(), String> {
+        let Some(info) = self.param_sets.iter().find(|p| p.name == param_set) else {
+            let names: Vec<&str> = self.param_sets.iter().map(|p| p.name.as_str()).collect();
+            return Err(format!(
+                "param_set '{}' is not supported by the server. Supported: {}",
+                param_set, names.join(", ")
+            ));
+        };
+        if !info.operations.iter().any(|o| o == operation) {
+            return Err(format!(
+                "operation '{}' is not supported for {}. Supported: {}",
+                operation, param_set, info.operations.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    fn iteration_cap(&self, param_set: &str) -> Option<u32> {
+        self.param_sets.iter().find(|p| p.name == param_set).map(|p| p.iteration_cap)
+    }
+}
+
+// None if the server is unreachable or predates GET /params; callers then skip validation
+async fn fetch_server_params(client: &Client, url: &str) -> Option<ServerParams> {
+    let response = client.get(format!("{}/params", url)).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.json().await.ok()
+}
+
+#[derive(Deserialize)]
+struct CircuitInfo {
+    id: String,
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct ServerCircuits {
+    circuits: Vec<CircuitInfo>,
+    /// Circuits sized by the request, such as chain; absent on older servers
+    #[serde(default)]
+    parametric_circuits: Vec<CircuitInfo>,
+}
+
+impl ServerCircuits {
+    fn check(&self, circuit_id: &str) -> Result<(), String> {
+        let all = || self.circuits.iter().chain(&self.parametric_circuits);
+        if all().any(|c| c.id == circuit_id) {
+            return Ok(());
+        }
+        let list: Vec<String> = all().map(|c| format!("  {:<12} {}", c.id, c.description)).collect();
+        Err(format!("circuit '{}' is not supported by the server. Supported:\n{}", circuit_id, list.join("\n")))
+    }
+}
+
+// None if the server is unreachable or predates GET /circuits;
+// callers then skip validation
+async fn fetch_server_circuits(client: &Client, url: &str) -> Option<ServerCircuits> {
+    let response = client.get(format!("{}/circuits", url)).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.json().await.ok()
+}
+
+// Exits with the server's list of circuits if it doesn't know `circuit_id`
+async fn check_circuit(client: &Client, url: &str, circuit_id: &str) {
+    match fetch_server_circuits(client, url).await {
+        Some(circuits) => {
+            if let Err(message) = circuits.check(circuit_id) {
+                eprintln!("error: {}", message);
+                std::process::exit(2);
+            }
+        }
+        None => eprintln!("warning: could not fetch {}/circuits, sending request unchecked", url),
+    }
+}
+
+#[derive(Deserialize)]
+struct ServerVersion {
+    version: String,
+    git_commit: String,
+    build_profile: String,
+    rustc_version: String,
+    ml_kem_version: String,
+}
+
+impl ServerVersion {
+    fn stamp(&self, result: &mut BenchmarkResult) {
+        result.server_version = Some(self.version.clone());
+        result.server_git_commit = Some(self.git_commit.clone());
+        result.server_build_profile = Some(self.build_profile.clone());
+        result.server_rustc_version = Some(self.rustc_version.clone());
+        result.server_ml_kem_version = Some(self.ml_kem_version.clone());
+    }
+}
+
+// None if the server is unreachable or predates GET /version; results are then left unstamped
+async fn fetch_server_version(client: &Client, url: &str) -> Option<ServerVersion> {
+    let response = client.get(format!("{}/version", url)).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.json().await.ok()
+}
+
+// None if the server is unreachable or predates GET /environment. Kept as sent, since the
+// client only passes it through to the output header
+async fn fetch_environment(client: &Client, url: &str) -> Option<serde_json::Value> {
+    let response = client.get(format!("{}/environment", url)).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.json().await.ok()
+}
+
+// None if the server is unreachable, predates GET /signing_key or sends a malformed key
+async fn fetch_signing_key(client: &Client, url: &str) -> Option<Vec<u8>> {
+    #[derive(Deserialize)]
+    struct SigningKey {
+        public_key: String,
+    }
+
+    let response = client.get(format!("{}/signing_key", url)).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let key: SigningKey = response.json().await.ok()?;
+    base64::engine::general_purpose::STANDARD.decode(key.public_key).ok()
+}
+
+// A row counts as verified only if it has responses and every one of them checks out, so
+// zk_service rows and rows whose requests all failed are marked false too
+fn verify_signatures(results: &mut [BenchmarkResult], public_key: Option<&[u8]>) {
+    for result in results {
+        let failures = match public_key {
+            Some(key) => result.signed_responses.iter().filter(|r| attest::verify(key, r).is_err()).count(),
+            None => result.signed_responses.len(),
+        };
+        let verified = public_key.is_some() && !result.signed_responses.is_empty() && failures == 0;
+        if result.signed_responses.is_empty() {
+            eprintln!(
+                "warning: {} {} {} has no signed responses to verify",
+                result.service, result.param_set, result.operation
+            );
+        } else if failures > 0 && public_key.is_some() {
+            eprintln!(
+                "warning: {} of {} responses for {} {} failed signature verification",
+                failures, result.signed_responses.len(), result.param_set, result.operation
+            );
+        }
+        result.signature_verified = Some(verified);
+    }
 }
 
 // =====
 
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Why a benchmark request produced no result
+enum RequestFailure {
+    Throttled,
+    Failed,
+}
+
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, RequestFailure> {
+    match response.status() {
+        reqwest::StatusCode::TOO_MANY_REQUESTS => Err(RequestFailure::Throttled),
+        status if status.is_success() => Ok(response),
+        status => {
+            // Errors are always JSON, whatever encoding was requested
+            let message = response.text().await.unwrap_or_default();
+            eprintln!("  request failed with {}: {}", status, message);
+            Err(RequestFailure::Failed)
+        }
+    }
+}
+
+async fn success_body(response: reqwest::Response) -> Result<Vec<u8>, RequestFailure> {
+    let response = check_status(response).await?;
+    response.bytes().await.map(|b| b.to_vec()).map_err(|_| RequestFailure::Failed)
+}
+
+/// Progress snapshot from a `progress` event on /kem_bench/stream
+#[derive(Deserialize)]
+struct ProgressUpdate {
+    completed_iterations: u32,
+    total_iterations: u32,
+    avg_us: f64,
+    elapsed_ms: u64,
+}
+
+async fn read_kem_stream(mut response: reqwest::Response) -> Result<KemBenchResponse, RequestFailure> {
+    let mut buffer = String::new();
+    while let Some(chunk) = response.chunk().await.map_err(|_| RequestFailure::Failed)? {
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+        while let Some(end) = buffer.find("\n\n") {
+            let event: String = buffer.drain(..end + 2).collect();
+            let name = event.lines().find_map(|l| l.strip_prefix("event:")).map(str::trim);
+            let data = event.lines().find_map(|l| l.strip_prefix("data:")).map(str::trim).unwrap_or_default();
+            match name {
+                Some("progress") => {
+                    if let Ok(p) = serde_json::from_str::<ProgressUpdate>(data) {
+                        eprint!(
+                            "\r  {}/{} iterations, avg {:.1}us, {:.1}s elapsed   ",
+                            p.completed_iterations, p.total_iterations, p.avg_us, p.elapsed_ms as f64 / 1000.0
+                        );
+                    }
+                }
+                Some("result") => {
+                    eprintln!();
+                    return serde_json::from_str(data).map_err(|_| RequestFailure::Failed);
+                }
+                Some("error") => {
+                    eprintln!("\n  benchmark failed: {}", data);
+                    return Err(RequestFailure::Failed);
+                }
+                // Keep-alive comments and unknown events
+                _ => {}
+            }
+        }
+    }
+    eprintln!("\n  stream ended without a result");
+    Err(RequestFailure::Failed)
+}
+
+async fn send_kem_request(
+    client: Client, url: String, body: Vec<u8>, encoding: Encoding, mode: RequestMode,
+) -> Result<KemBenchResponse, RequestFailure> {
+    let endpoint = match mode {
+        RequestMode::Blocking => format!("{}/kem_bench", url),
+        RequestMode::AsyncJob => format!("{}/kem_bench?async=true", url),
+        RequestMode::Stream => format!("{}/kem_bench/stream", url),
+    };
+    let mut request = encoding.post(&client, &endpoint, body);
+    if mode == RequestMode::Stream {
+        request = request.header("Accept", "text/event-stream");
+    }
+    let response = request.send().await.map_err(|_| RequestFailure::Failed)?;
+    if mode == RequestMode::Stream {
+        return read_kem_stream(check_status(response).await?).await;
+    }
+    let body = success_body(response).await?;
+    if mode == RequestMode::Blocking {
+        return encoding.decode(&body).ok_or(RequestFailure::Failed);
+    }
+
+    let mut job: KemJob = encoding.decode(&body).ok_or(RequestFailure::Failed)?;
+    loop {
+        match job.status.as_str() {
+            "done" => return job.result.ok_or(RequestFailure::Failed),
+            "failed" => {
+                eprintln!("  job {} failed: {}", job.job_id, job.error.unwrap_or_default());
+                return Err(RequestFailure::Failed);
+            }
+            _ => tokio::time::sleep(JOB_POLL_INTERVAL).await,
+        }
+        let response = client
+            .get(format!("{}/jobs/{}", url, job.job_id))
+            .header("Accept", encoding.content_type())
+            .send()
+            .await
+            .map_err(|_| RequestFailure::Failed)?;
+        job = encoding.decode(&success_body(response).await?).ok_or(RequestFailure::Failed)?;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn run_kem_benchmark( 
     client: &Client, url: &str, param_set: &str, operation: &str,
-    iterations: u32, requests: u32, concurrency: u32,
-    label: &str ) 
+    iterations: u32, runs: Option<u32>, record_timestamps: bool, requests: u32, concurrency: u32,
+    label: &str, raw: bool, encoding: Encoding, mode: RequestMode )
     -> BenchmarkResult {
 
-    let endpoint = format!("{}/kem_bench", url);
     let req_body = KemBenchRequest {
         param_set: param_set.to_string(),
         iterations,
         operation: operation.to_string(),
+        include_raw_timings: raw,
+        include_machine_info: true,
+        runs,
+        record_timestamps,
     };
 
     let start = Instant::now();
     let mut results: Vec<KemBenchResponse> = Vec::new();
-    let mut errors = 0u32;
+    let mut errors = ErrorCounts::default();
 
     // Run requests with concurrency
     let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(concurrency as usize));
@@ -214,16 +1086,11 @@ async fn run_kem_benchmark(
     for _ in 0..requests {
         let permit = semaphore.clone().acquire_owned().await.unwrap();
         let client = client.clone();
-        let endpoint = endpoint.clone();
-        let body = serde_json::to_string(&req_body).unwrap();
+        let url = url.to_string();
+        let body = encoding.encode(&req_body);
 
         handles.push(tokio::spawn(async move {
-            let res = client
-                .post(&endpoint)
-                .header("Content-Type", "application/json")
-                .body(body)
-                .send()
-                .await;
+            let res = send_kem_request(client, url, body, encoding, mode).await;
             drop(permit);
             res
         }));
@@ -231,18 +1098,265 @@ async fn run_kem_benchmark(
 
     for handle in handles {
         match handle.await {
-            Ok(Ok(response)) => {
-                if let Ok(data) = response.json::<KemBenchResponse>().await {
-                    results.push(data);
-                } else {
-                    errors += 1;
-                }
+            Ok(Ok(data)) => results.push(data),
+            Ok(Err(RequestFailure::Throttled)) => errors.record(true),
+            _ => errors.record(false),
+        }
+    }
+    if errors.throttled > 0 {
+        eprintln!(
+            "warning: {} of {} requests were throttled (429) by the server's concurrency limit",
+            errors.throttled, requests
+        );
+    }
+
+    let total_time = start.elapsed().as_millis() as f64;
+    summarize_kem_results(&req_body, results, errors, requests, concurrency, total_time, label)
+}
+
+// Passes /kem_bench/stream_ndjson through to `path` byte for byte; the last line is the
+// summary (or error) the result row is built from
+#[allow(clippy::too_many_arguments)]
+async fn run_kem_ndjson(
+    client: &Client, url: &str, param_set: &str, operation: &str, iterations: u32,
+    runs: Option<u32>, record_timestamps: bool, label: &str, path: &str,
+) -> BenchmarkResult {
+    let req_body = KemBenchRequest {
+        param_set: param_set.to_string(),
+        iterations,
+        operation: operation.to_string(),
+        include_raw_timings: false,
+        include_machine_info: true,
+        runs,
+        record_timestamps,
+    };
+    let start = Instant::now();
+    let response = stream_ndjson(client, url, &req_body, path).await;
+    let total_time = start.elapsed().as_millis() as f64;
+    let mut errors = ErrorCounts::default();
+    let results = match response {
+        Ok(resp) => vec![resp],
+        Err(failure) => {
+            errors.record(matches!(failure, RequestFailure::Throttled));
+            Vec::new()
+        }
+    };
+    summarize_kem_results(&req_body, results, errors, 1, 1, total_time, label)
+}
+
+async fn stream_ndjson(
+    client: &Client, url: &str, req_body: &KemBenchRequest, path: &str,
+) -> Result<KemBenchResponse, RequestFailure> {
+    use std::io::Write;
+
+    let response = Encoding::Json
+        .post(client, &format!("{}/kem_bench/stream_ndjson", url), Encoding::Json.encode(req_body))
+        .header("Accept", "application/x-ndjson")
+        .send()
+        .await
+        .map_err(|_| RequestFailure::Failed)?;
+    let mut response = check_status(response).await?;
+    let mut file = std::fs::File::create(path).unwrap_or_else(|e| panic!("failed to create {}: {}", path, e));
+    let (mut pending, mut last_line) = (String::new(), String::new());
+    while let Some(chunk) = response.chunk().await.map_err(|_| RequestFailure::Failed)? {
+        file.write_all(&chunk).expect("failed to write NDJSON samples");
+        pending.push_str(&String::from_utf8_lossy(&chunk));
+        while let Some(end) = pending.find('\n') {
+            last_line = pending.drain(..=end).collect();
+        }
+    }
+
+    let last: serde_json::Value = serde_json::from_str(&last_line).map_err(|_| {
+        eprintln!("  stream ended without a summary");
+        RequestFailure::Failed
+    })?;
+    match last.get("summary") {
+        Some(summary) => serde_json::from_value(summary.clone()).map_err(|_| RequestFailure::Failed),
+        None => {
+            eprintln!("  benchmark failed: {}", last);
+            Err(RequestFailure::Failed)
+        }
+    }
+}
+
+type WsStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn connect_ws(url: &str, token: Option<&str>, ca_pem: Option<&[u8]>, insecure: bool) -> Result<WsStream, String> {
+    // http:// becomes ws:// and https:// becomes wss://
+    let ws_url = format!("{}/ws", url.replacen("http", "ws", 1));
+    let mut request = ws_url.as_str().into_client_request().map_err(|e| e.to_string())?;
+    if let Some(token) = token {
+        let value = format!("Bearer {}", token).parse().map_err(|_| "--token must be a valid header value")?;
+        request.headers_mut().insert("Authorization", value);
+    }
+    let mut tls = native_tls::TlsConnector::builder();
+    tls.danger_accept_invalid_certs(insecure);
+    if let Some(pem) = ca_pem {
+        tls.add_root_certificate(native_tls::Certificate::from_pem(pem).map_err(|e| e.to_string())?);
+    }
+    let connector = Connector::NativeTls(tls.build().map_err(|e| e.to_string())?);
+    let (socket, _) = tokio_tungstenite::connect_async_tls_with_config(request, None, false, Some(connector))
+        .await
+        .map_err(|e| format!("{}: {}", ws_url, e))?;
+    Ok(socket)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_kem_ws(
+    socket: &mut WsStream, param_set: &str, operation: &str, iterations: u32,
+    runs: u32, interval: Duration, label: &str, raw: bool,
+) -> BenchmarkResult {
+    let req_body = KemBenchRequest {
+        param_set: param_set.to_string(),
+        iterations,
+        operation: operation.to_string(),
+        include_raw_timings: raw,
+        include_machine_info: true,
+        runs: None,
+        record_timestamps: false,
+    };
+    let message = serde_json::to_string(&req_body).unwrap();
+
+    let start = Instant::now();
+    let mut results: Vec<KemBenchResponse> = Vec::new();
+    let mut errors = ErrorCounts::default();
+    for run in 0..runs {
+        if run > 0 {
+            tokio::time::sleep(interval).await;
+        }
+        if socket.send(Message::text(message.clone())).await.is_err() {
+            eprintln!("  socket closed after {} runs", run);
+            errors.record(false);
+            break;
+        }
+        // Skip pings and other control frames until the reply arrives
+        let reply = loop {
+            match socket.next().await {
+                Some(Ok(Message::Text(text))) => break Some(text),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break None,
+                Some(Ok(_)) => continue,
+            }
+        };
+        let Some(reply) = reply else {
+            eprintln!("  socket closed after {} runs", run);
+            errors.record(false);
+            break;
+        };
+        match serde_json::from_str::<KemBenchResponse>(&reply) {
+            Ok(data) => {
+                println!("  run {}: avg {:.1}us, p95 {:.1}us", run + 1, data.avg_us, data.p95_us);
+                results.push(data);
+            }
+            Err(_) => {
+                let throttled = reply.contains("\"status\":429");
+                eprintln!("  run {} failed: {}", run + 1, reply);
+                errors.record(throttled);
             }
-            _ => errors += 1,
         }
     }
+    let _ = socket.close(None).await;
 
     let total_time = start.elapsed().as_millis() as f64;
+    summarize_kem_results(&req_body, results, errors, runs, 1, total_time, label)
+}
+
+#[derive(Default, Clone, Copy)]
+struct ErrorCounts {
+    total: u32,
+    throttled: u32,
+}
+
+impl ErrorCounts {
+    fn record(&mut self, throttled: bool) {
+        self.total += 1;
+        self.throttled += u32::from(throttled);
+    }
+}
+
+// Servers cap iterations per request and report what they actually ran; older ones don't,
+// in which case the request is assumed to have run as sent
+fn effective_iterations(requested: u32, reported: impl Iterator<Item = Option<u32>>, what: &str) -> u32 {
+    let effective = reported.flatten().min().unwrap_or(requested);
+    if effective != requested {
+        eprintln!(
+            "WARNING: server ran {} iterations per request for {}, not the {} requested; results reflect {}",
+            effective, what, requested, effective
+        );
+    }
+    effective
+}
+
+// Below this CPU/wall-clock ratio a run counts as contended; a quiet host sits close to 1.0
+const CONTENDED_CPU_RATIO: f64 = 0.8;
+
+// Mean over the responses that carry the figure; None when the server is too old to send it
+fn mean_reported(values: impl Iterator<Item = Option<f64>>) -> Option<f64> {
+    let values: Vec<f64> = values.flatten().collect();
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+// Lowest low and highest high over the responses that carry an interval, so the result is
+// never narrower than any single request's
+fn widest_interval(intervals: impl Iterator<Item = (Option<f64>, Option<f64>)>) -> (Option<f64>, Option<f64>) {
+    let intervals: Vec<_> = intervals.collect();
+    let low = intervals.iter().filter_map(|&(low, _)| low).reduce(f64::min);
+    let high = intervals.iter().filter_map(|&(_, high)| high).reduce(f64::max);
+    (low, high)
+}
+
+// Sample standard deviation of every iteration behind the responses, from each one's
+// iteration count, mean and standard deviation. A mean of the standard deviations would
+// leave out how far the requests' means sit apart, which is where a bimodal run shows up.
+// Responses without a standard deviation are left out; None when none had one
+fn pooled_stddev(samples: impl Iterator<Item = (u32, f64, Option<f64>)>) -> Option<f64> {
+    let samples: Vec<(f64, f64, f64)> = samples.filter_map(|(n, mean, stddev)| stddev.map(|s| (n as f64, mean, s))).collect();
+    if samples.is_empty() {
+        return None;
+    }
+    let total: f64 = samples.iter().map(|&(n, _, _)| n).sum();
+    if total < 2.0 {
+        return Some(0.0);
+    }
+    let mean = samples.iter().map(|&(n, m, _)| n * m).sum::<f64>() / total;
+    let squares: f64 = samples.iter().map(|&(n, m, s)| (n - 1.0).max(0.0) * s * s + n * (m - mean).powi(2)).sum();
+    Some((squares / (total - 1.0)).sqrt())
+}
+
+// Median of every iteration, when the server returned all of their raw timings
+fn raw_median_us(raw_us: Option<&Vec<u64>>, truncated: bool) -> Option<f64> {
+    let raw: Vec<u128> = raw_us.filter(|_| !truncated)?.iter().map(|&t| t as u128).collect();
+    (!raw.is_empty()).then(|| stats::compute_stats(&raw).median)
+}
+
+// Folded the way a row's own latencies are: extremes of min and max, the standard
+// deviation pooled over each request's iterations, and means of the rest
+fn aggregate_latencies(stats: &[(u32, &LatencyStats)]) -> LatencyStats {
+    let mean = |field: fn(&LatencyStats) -> f64| stats.iter().map(|(_, s)| field(s)).sum::<f64>() / stats.len() as f64;
+    LatencyStats {
+        avg_us: mean(|s| s.avg_us),
+        min_us: stats.iter().map(|(_, s)| s.min_us).fold(f64::MAX, f64::min),
+        max_us: stats.iter().map(|(_, s)| s.max_us).fold(0.0, f64::max),
+        p95_us: mean(|s| s.p95_us),
+        stddev_us: pooled_stddev(stats.iter().map(|&(n, s)| (n, s.avg_us, Some(s.stddev_us)))).unwrap_or(0.0),
+        median_us: mean(|s| s.median_us),
+    }
+}
+
+fn summarize_kem_results(
+    req: &KemBenchRequest,
+    mut results: Vec<KemBenchResponse>,
+    errors: ErrorCounts,
+    requests: u32,
+    concurrency: u32,
+    total_time: f64,
+    label: &str,
+) -> BenchmarkResult {
+    let (param_set, operation) = (&req.param_set, &req.operation);
+    let iterations = effective_iterations(
+        req.iterations,
+        results.iter().map(|r| r.effective_iterations),
+        &format!("{} {}", param_set, operation),
+    );
 
     // Aggregate results
     let (avg_lat, min_lat, max_lat, p95_lat, throughput) = if !results.is_empty() {
@@ -256,6 +1370,47 @@ async fn run_kem_benchmark(
         (0.0, 0.0, 0.0, 0.0, 0.0)
     };
 
+    let truncated = results.iter().any(|r| r.timings_truncated == Some(true));
+    if truncated {
+        eprintln!("warning: server truncated raw timings for {} {}", param_set, operation);
+    }
+    let raw_timings_us = req.include_raw_timings.then(|| {
+        results.iter().flat_map(|r| r.timings_us.iter().flatten().copied()).collect()
+    });
+    let counts = |r: &KemBenchResponse| r.effective_iterations.unwrap_or(req.iterations);
+    let stddev_lat = pooled_stddev(results.iter().map(|r| (counts(r), r.avg_us, Some(r.stddev_us)))).unwrap_or(0.0) / 1000.0;
+    let raw_median = raw_median_us(raw_timings_us.as_ref(), truncated);
+    let median_lat = raw_median.or(mean_reported(results.iter().map(|r| Some(r.median_us)))).unwrap_or(0.0) / 1000.0;
+    let histograms: Vec<serde_json::Value> = results.iter_mut().filter_map(|r| r.histogram.take()).collect();
+    let per_run: Vec<RunStats> = results.iter_mut().filter_map(|r| r.per_run.take()).flatten().collect();
+    let phases: Vec<PhaseStats> = [
+        ("keygen", results.iter().filter_map(|r| Some((counts(r), r.keygen_stats.as_ref()?))).collect::<Vec<_>>()),
+        ("encaps", results.iter().filter_map(|r| Some((counts(r), r.encaps_stats.as_ref()?))).collect()),
+        ("decaps", results.iter().filter_map(|r| Some((counts(r), r.decaps_stats.as_ref()?))).collect()),
+    ]
+    .into_iter()
+    .filter(|(_, stats)| !stats.is_empty())
+    .map(|(phase, stats)| PhaseStats { phase, stats: aggregate_latencies(&stats) })
+    .collect();
+    let (ci95_low_us, ci95_high_us) = widest_interval(results.iter().map(|r| (r.ci95_low_us, r.ci95_high_us)));
+    for drift in results.iter().filter_map(|r| r.drift.as_ref()).filter(|d| d.drift_detected) {
+        eprintln!(
+            "warning: latency drifted during {} {} (first decile {:.1}us, last decile {:.1}us); results may mix throttled and unthrottled runs",
+            param_set, operation, drift.first_decile_avg_us, drift.last_decile_avg_us
+        );
+    }
+    let cpu_time_ratio = results.iter().filter_map(|r| r.cpu_time_ratio).reduce(f64::min);
+    if let Some(ratio) = cpu_time_ratio.filter(|&ratio| ratio < CONTENDED_CPU_RATIO) {
+        eprintln!(
+            "warning: server threads got only {:.0}% of their wall-clock time on the CPU during {} {}; the host looks contended",
+            ratio * 100.0, param_set, operation
+        );
+    }
+    let drift_detected = results.iter().any(|r| r.drift.is_some()).then(|| results.iter().any(|r| r.drift.as_ref().is_some_and(|d| d.drift_detected)));
+    // Sizes are fixed per param set, so any successful response will do
+    let sizes = results.first();
+    let machine = results.iter().find_map(|r| r.machine_info.as_ref());
+
     BenchmarkResult {
         timestamp: Utc::now().to_rfc3339(),
         label: label.to_string(),
@@ -263,32 +1418,94 @@ async fn run_kem_benchmark(
         operation: operation.to_string(),
         param_set: param_set.to_string(),
         iterations,
+        run: None,
+        phase: None,
+        requested_iterations: req.iterations,
         requests,
         concurrency,
         avg_latency_ms: avg_lat,
         min_latency_ms: min_lat,
         max_latency_ms: max_lat,
         p95_latency_ms: p95_lat,
+        stddev_latency_ms: Some(stddev_lat),
+        median_latency_ms: Some(median_lat),
+        median_from_raw_timings: Some(raw_median.is_some()),
+        drift_detected,
+        ci95_low_latency_ms: ci95_low_us.map(|us| us / 1000.0),
+        ci95_high_latency_ms: ci95_high_us.map(|us| us / 1000.0),
         throughput_ops_sec: throughput,
+        measured_loop_ops_sec: mean_reported(results.iter().map(|r| r.measured_loop_ops_sec)),
+        avg_cpu_ms: mean_reported(results.iter().map(|r| r.avg_cpu_us)).map(|us| us / 1000.0),
+        cpu_time_ratio,
+        contended: cpu_time_ratio.map(|ratio| ratio < CONTENDED_CPU_RATIO),
         client_total_time_ms: total_time,
         client_avg_request_ms: total_time / requests as f64,
-        error_count: errors,
+        error_count: errors.total,
+        throttled_count: errors.throttled,
+        encapsulation_key_bytes: sizes.and_then(|r| r.encapsulation_key_bytes),
+        decapsulation_key_bytes: sizes.and_then(|r| r.decapsulation_key_bytes),
+        ciphertext_bytes: sizes.and_then(|r| r.ciphertext_bytes),
+        shared_secret_bytes: sizes.and_then(|r| r.shared_secret_bytes),
+        curve: None,
+        proof_system: None,
+        proof_size_bytes: None,
+        srs_size_bytes: None,
+        index_ms: None,
+        chain_length: None,
+        constraints: None,
+        proving_key_bytes: None,
+        verifying_key_bytes: None,
+        setup_ms: None,
+        avg_witness_ms: None,
+        avg_proving_ms: None,
+        verify_mode: None,
+        distinct_statements: None,
+        prover_threads: None,
+        prover_speedup: None,
+        server_version: None,
+        server_git_commit: None,
+        server_build_profile: None,
+        server_rustc_version: None,
+        server_ml_kem_version: None,
+        machine_hostname: machine.and_then(|m| m.hostname.clone()),
+        machine_cpu_model: machine.and_then(|m| m.cpu_model.clone()),
+        machine_logical_cores: machine.map(|m| m.logical_cores),
+        machine_build_profile: machine.map(|m| m.build_profile.clone()),
+        raw_timings_us,
+        histograms: (!histograms.is_empty()).then_some(histograms),
+        per_run: (!per_run.is_empty()).then_some(per_run),
+        phases: (!phases.is_empty()).then_some(phases),
+        signature_verified: None,
+        signed_responses: results.into_iter().map(|r| r.raw).collect(),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_zk_prove_benchmark(
     client: &Client,
     url: &str,
     circuit_id: &str,
+    chain_length: Option<u32>,
+    curve: &str,
+    proof_system: &str,
     iterations: u32,
     requests: u32,
     concurrency: u32,
     label: &str,
+    raw: bool,
+    prover_threads: Option<u32>,
 ) -> BenchmarkResult {
     let endpoint = format!("{}/zk_prove_bench", url);
     let req_body = ZkBenchRequest {
         circuit_id: circuit_id.to_string(),
         iterations,
+        include_machine_info: true,
+        chain_length,
+        curve: curve.to_string(),
+        proof_system: proof_system.to_string(),
+        include_raw_timings: raw,
+        verify_mode: None,
+        prover_threads,
     };
 
     let start = Instant::now();
@@ -330,6 +1547,11 @@ async fn run_zk_prove_benchmark(
     }
 
     let total_time = start.elapsed().as_millis() as f64;
+    let effective = effective_iterations(
+        iterations,
+        results.iter().map(|r| r.effective_iterations),
+        &format!("zk prove {}", circuit_id),
+    );
 
     let (avg_lat, min_lat, max_lat, p95_lat, throughput) = if !results.is_empty() {
         let avg = results.iter().map(|r| r.avg_prove_ms).sum::<f64>() / results.len() as f64;
@@ -342,39 +1564,107 @@ async fn run_zk_prove_benchmark(
         (0.0, 0.0, 0.0, 0.0, 0.0)
     };
 
+    let (ci95_low_ms, ci95_high_ms) =
+        widest_interval(results.iter().map(|r| (r.ci95_low_prove_ms, r.ci95_high_prove_ms)));
+    let machine = results.iter().find_map(|r| r.machine_info.as_ref());
+    let raw_timings_us = zk_raw_timings_us(results.iter().map(|r| (&r.timings_ms, r.timings_truncated)), circuit_id);
+    let raw_median_ms = raw_median_us(raw_timings_us.as_ref(), results.iter().any(|r| r.timings_truncated == Some(true))).map(|us| us / 1000.0);
+
     BenchmarkResult {
         timestamp: Utc::now().to_rfc3339(),
         label: label.to_string(),
         service: "zk_service".to_string(),
         operation: "prove".to_string(),
         param_set: circuit_id.to_string(),
-        iterations,
+        iterations: effective,
+        run: None,
+        phase: None,
+        requested_iterations: iterations,
         requests,
         concurrency,
         avg_latency_ms: avg_lat,
         min_latency_ms: min_lat,
         max_latency_ms: max_lat,
         p95_latency_ms: p95_lat,
+        stddev_latency_ms: pooled_stddev(results.iter().map(|r| (r.effective_iterations.unwrap_or(iterations), r.avg_prove_ms, r.stddev_prove_ms))),
+        median_latency_ms: raw_median_ms.or(mean_reported(results.iter().map(|r| r.median_prove_ms))),
+        median_from_raw_timings: Some(raw_median_ms.is_some()),
+        drift_detected: None,
+        ci95_low_latency_ms: ci95_low_ms,
+        ci95_high_latency_ms: ci95_high_ms,
         throughput_ops_sec: throughput,
+        measured_loop_ops_sec: mean_reported(results.iter().map(|r| r.wall_clock_proofs_sec)),
+        avg_cpu_ms: None,
+        cpu_time_ratio: None,
+        contended: None,
         client_total_time_ms: total_time,
         client_avg_request_ms: total_time / requests as f64,
         error_count: errors,
+        throttled_count: 0,
+        encapsulation_key_bytes: None,
+        decapsulation_key_bytes: None,
+        ciphertext_bytes: None,
+        shared_secret_bytes: None,
+        curve: results.iter().find_map(|r| r.curve.clone()),
+        proof_system: results.iter().find_map(|r| r.proof_system.clone()),
+        proof_size_bytes: results.first().map(|r| r.avg_proof_size_bytes),
+        srs_size_bytes: results.iter().find_map(|r| r.marlin.as_ref().map(|m| m.srs_size_bytes)),
+        index_ms: results.iter().find_map(|r| r.marlin.as_ref().and_then(|m| m.index_ms)),
+        chain_length,
+        constraints: results.iter().find_map(|r| r.num_constraints),
+        proving_key_bytes: results.iter().find_map(|r| r.proving_key_bytes),
+        verifying_key_bytes: results.iter().find_map(|r| r.verifying_key_bytes),
+        setup_ms: results.iter().find_map(|r| r.setup_ms),
+        avg_witness_ms: mean_reported(results.iter().map(|r| r.witness_stats.as_ref().map(|w| w.avg_ms))),
+        avg_proving_ms: mean_reported(results.iter().map(|r| r.prove_stats.as_ref().map(|p| p.avg_ms))),
+        verify_mode: None,
+        distinct_statements: None,
+        prover_threads: results.iter().find_map(|r| r.prover_threads),
+        prover_speedup: mean_reported(results.iter().map(|r| r.prover_speedup)),
+        server_version: None,
+        server_git_commit: None,
+        server_build_profile: None,
+        server_rustc_version: None,
+        server_ml_kem_version: None,
+        machine_hostname: machine.and_then(|m| m.hostname.clone()),
+        machine_cpu_model: machine.and_then(|m| m.cpu_model.clone()),
+        machine_logical_cores: machine.map(|m| m.logical_cores),
+        machine_build_profile: machine.map(|m| m.build_profile.clone()),
+        raw_timings_us,
+        histograms: None,
+        per_run: None,
+        phases: None,
+        signature_verified: None,
+        signed_responses: Vec::new(),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_zk_verify_benchmark(
     client: &Client,
     url: &str,
     circuit_id: &str,
+    chain_length: Option<u32>,
+    curve: &str,
+    proof_system: &str,
     iterations: u32,
     requests: u32,
     concurrency: u32,
     label: &str,
+    raw: bool,
+    verify_mode: Option<&str>,
 ) -> BenchmarkResult {
     let endpoint = format!("{}/zk_verify_bench", url);
     let req_body = ZkBenchRequest {
         circuit_id: circuit_id.to_string(),
         iterations,
+        include_machine_info: true,
+        chain_length,
+        curve: curve.to_string(),
+        proof_system: proof_system.to_string(),
+        include_raw_timings: raw,
+        verify_mode: verify_mode.map(str::to_string),
+        prover_threads: None,
     };
 
     let start = Instant::now();
@@ -416,6 +1706,11 @@ async fn run_zk_verify_benchmark(
     }
 
     let total_time = start.elapsed().as_millis() as f64;
+    let effective = effective_iterations(
+        iterations,
+        results.iter().map(|r| r.effective_iterations),
+        &format!("zk verify {}", circuit_id),
+    );
 
     let (avg_lat, min_lat, max_lat, p95_lat, throughput) = if !results.is_empty() {
         let avg = results.iter().map(|r| r.avg_verify_ms).sum::<f64>() / results.len() as f64;
@@ -428,38 +1723,266 @@ async fn run_zk_verify_benchmark(
         (0.0, 0.0, 0.0, 0.0, 0.0)
     };
 
+    let (ci95_low_ms, ci95_high_ms) =
+        widest_interval(results.iter().map(|r| (r.ci95_low_verify_ms, r.ci95_high_verify_ms)));
+    let machine = results.iter().find_map(|r| r.machine_info.as_ref());
+    let raw_timings_us = zk_raw_timings_us(results.iter().map(|r| (&r.timings_ms, r.timings_truncated)), circuit_id);
+    let raw_median_ms = raw_median_us(raw_timings_us.as_ref(), results.iter().any(|r| r.timings_truncated == Some(true))).map(|us| us / 1000.0);
+
     BenchmarkResult {
         timestamp: Utc::now().to_rfc3339(),
         label: label.to_string(),
         service: "zk_service".to_string(),
         operation: "verify".to_string(),
         param_set: circuit_id.to_string(),
-        iterations,
+        iterations: effective,
+        run: None,
+        phase: None,
+        requested_iterations: iterations,
         requests,
         concurrency,
         avg_latency_ms: avg_lat,
         min_latency_ms: min_lat,
         max_latency_ms: max_lat,
         p95_latency_ms: p95_lat,
+        stddev_latency_ms: pooled_stddev(results.iter().map(|r| (r.effective_iterations.unwrap_or(iterations), r.avg_verify_ms, r.stddev_verify_ms))),
+        median_latency_ms: raw_median_ms.or(mean_reported(results.iter().map(|r| r.median_verify_ms))),
+        median_from_raw_timings: Some(raw_median_ms.is_some()),
+        drift_detected: None,
+        ci95_low_latency_ms: ci95_low_ms,
+        ci95_high_latency_ms: ci95_high_ms,
         throughput_ops_sec: throughput,
+        measured_loop_ops_sec: mean_reported(results.iter().map(|r| r.wall_clock_verifies_sec)),
+        avg_cpu_ms: None,
+        cpu_time_ratio: None,
+        contended: None,
         client_total_time_ms: total_time,
         client_avg_request_ms: total_time / requests as f64,
         error_count: errors,
+        throttled_count: 0,
+        encapsulation_key_bytes: None,
+        decapsulation_key_bytes: None,
+        ciphertext_bytes: None,
+        shared_secret_bytes: None,
+        curve: results.iter().find_map(|r| r.curve.clone()),
+        proof_system: results.iter().find_map(|r| r.proof_system.clone()),
+        proof_size_bytes: results.iter().find_map(|r| r.proof_size_bytes),
+        srs_size_bytes: results.iter().find_map(|r| r.marlin.as_ref().map(|m| m.srs_size_bytes)),
+        index_ms: results.iter().find_map(|r| r.marlin.as_ref().and_then(|m| m.index_ms)),
+        chain_length,
+        constraints: results.iter().find_map(|r| r.num_constraints),
+        proving_key_bytes: results.iter().find_map(|r| r.proving_key_bytes),
+        verifying_key_bytes: results.iter().find_map(|r| r.verifying_key_bytes),
+        setup_ms: results.iter().find_map(|r| r.setup_ms),
+        avg_witness_ms: None,
+        avg_proving_ms: None,
+        verify_mode: results.iter().find_map(|r| r.verify_mode.clone()),
+        distinct_statements: results.iter().find_map(|r| r.distinct_statements),
+        prover_threads: None,
+        prover_speedup: None,
+        server_version: None,
+        server_git_commit: None,
+        server_build_profile: None,
+        server_rustc_version: None,
+        server_ml_kem_version: None,
+        machine_hostname: machine.and_then(|m| m.hostname.clone()),
+        machine_cpu_model: machine.and_then(|m| m.cpu_model.clone()),
+        machine_logical_cores: machine.map(|m| m.logical_cores),
+        machine_build_profile: machine.map(|m| m.build_profile.clone()),
+        raw_timings_us,
+        histograms: None,
+        per_run: None,
+        phases: None,
+        signature_verified: None,
+        signed_responses: Vec::new(),
+    }
+}
+
+// The server answers each batch job with either a full response or its own error
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KemBatchEntry {
+    Ok(Box<KemBenchResponse>),
+    Err {
+        error: String,
+        #[serde(default)]
+        status: u16,
+    },
+}
+
+// Runs every job in one /kem_bench_batch round trip. Returns None if the batch can't be
+// used (e.g. an older server without the endpoint) so the caller can fall back.
+async fn run_kem_batch(
+    client: &Client,
+    url: &str,
+    jobs: &[KemBenchRequest],
+    label: &str,
+    encoding: Encoding,
+) -> Option<Vec<BenchmarkResult>> {
+    let start = Instant::now();
+    let response = encoding
+        .post(client, &format!("{}/kem_bench_batch", url), encoding.encode(&jobs))
+        .send()
+        .await
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
     }
+    let entries: Vec<KemBatchEntry> = encoding.decode(&response.bytes().await.ok()?)?;
+
+    // Client time is only known for the whole batch, so each job gets an even share
+    let total_time = start.elapsed().as_millis() as f64 / jobs.len().max(1) as f64;
+
+    let results = jobs
+        .iter()
+        .zip(entries)
+        .map(|(job, entry)| match entry {
+            KemBatchEntry::Ok(resp) => {
+                summarize_kem_results(job, vec![*resp], ErrorCounts::default(), 1, 1, total_time, label)
+            }
+            KemBatchEntry::Err { error, status } => {
+                eprintln!("  {} {} failed: {}", job.param_set, job.operation, error);
+                let mut errors = ErrorCounts::default();
+                errors.record(status == 429);
+                summarize_kem_results(job, Vec::new(), errors, 1, 1, total_time, label)
+            }
+        })
+        .collect();
+    Some(results)
+}
+
+// The suite's KEM and DSA jobs: every param set of a group against every operation of it
+const SUITE_MATRICES: [(&[&str], &[&str]); 2] = [
+    (
+        &["ml_kem_512", "ml_kem_768", "ml_kem_1024", "x_wing", "x25519", "rsa_2048"],
+        &["keygen", "encaps", "decaps", "encaps_decaps", "full_handshake"],
+    ),
+    (&["ml_dsa_44", "ml_dsa_65", "ml_dsa_87"], &["keygen", "sign", "verify", "sign_verify"]),
+];
+
+// The server answers each matrix cell with either a full response or that pair's error
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KemMatrixCell {
+    Ok(Box<KemBenchResponse>),
+    Err {
+        param_set: String,
+        operation: String,
+        error: String,
+        #[serde(default)]
+        status: u16,
+    },
+}
+
+// Every param set against every operation in one /kem_bench_matrix request, with the rest
+// of `template` applied to each. None if the server doesn't have the endpoint.
+async fn run_kem_matrix(
+    client: &Client,
+    url: &str,
+    template: &KemBenchRequest,
+    param_sets: &[&str],
+    operations: &[&str],
+    label: &str,
+    encoding: Encoding,
+) -> Option<Vec<BenchmarkResult>> {
+    let mut body = serde_json::to_value(template).unwrap();
+    body["param_set"] = serde_json::json!(param_sets);
+    body["operation"] = serde_json::json!(operations);
+    let start = Instant::now();
+    let response = encoding
+        .post(client, &format!("{}/kem_bench_matrix", url), encoding.encode(&body))
+        .send()
+        .await
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let cells: Vec<KemMatrixCell> = encoding.decode(&response.bytes().await.ok()?)?;
+
+    // As with a batch, client time is only known for the whole matrix
+    let total_time = start.elapsed().as_millis() as f64 / cells.len().max(1) as f64;
+
+    let results = cells
+        .into_iter()
+        .map(|cell| {
+            let (param_set, operation, responses, errors) = match cell {
+                KemMatrixCell::Ok(resp) => (resp.param_set.clone(), resp.operation.clone(), vec![*resp], ErrorCounts::default()),
+                KemMatrixCell::Err { param_set, operation, error, status } => {
+                    eprintln!("  {} {} failed: {}", param_set, operation, error);
+                    let mut errors = ErrorCounts::default();
+                    errors.record(status == 429);
+                    (param_set, operation, Vec::new(), errors)
+                }
+            };
+            let job = KemBenchRequest { param_set, operation, ..template.clone() };
+            summarize_kem_results(&job, responses, errors, 1, 1, total_time, label)
+        })
+        .collect();
+    Some(results)
+}
+
+// Every request's raw ZK samples, converted to the microseconds the sidecar file holds;
+// None unless the server returned some
+fn zk_raw_timings_us<'a>(raw: impl Iterator<Item = (&'a Option<Vec<f64>>, Option<bool>)>, circuit_id: &str) -> Option<Vec<u64>> {
+    let mut samples: Option<Vec<u64>> = None;
+    for (timings_ms, truncated) in raw {
+        if truncated == Some(true) {
+            eprintln!("warning: server truncated raw timings for zk {}", circuit_id);
+        }
+        if let Some(timings_ms) = timings_ms {
+            samples.get_or_insert_with(Vec::new).extend(timings_ms.iter().map(|ms| (ms * 1000.0).round() as u64));
+        }
+    }
+    samples
 }
 
 // ============ Output ============
 
-fn output_results(results: &[BenchmarkResult], format: &str, file: Option<&str>) {
+// One row per raw sample, keyed back to the result row it belongs to
+fn write_raw_timings_csv(results: &[BenchmarkResult], path: &str) {
+    let mut wtr = csv::Writer::from_path(path).expect("Failed to create raw timings file");
+    wtr.write_record(["row", "label", "service", "operation", "param_set", "sample", "timing_us"]).unwrap();
+    for (row, r) in results.iter().enumerate() {
+        for (sample, us) in r.raw_timings_us.iter().flatten().enumerate() {
+            wtr.write_record([
+                row.to_string(), r.label.clone(), r.service.clone(), r.operation.clone(),
+                r.param_set.clone(), sample.to_string(), us.to_string(),
+            ]).unwrap();
+        }
+    }
+    wtr.flush().unwrap();
+}
+
+// With an environment (the suite's GET /environment, null if the server couldn't provide
+// it), JSON output becomes {"environment", "results"} and CSV output starts with a
+// "# environment: {...}" comment line
+fn output_results(results: &[BenchmarkResult], format: &str, file: Option<&str>, environment: Option<&serde_json::Value>) {
     let output = match format {
         "csv" => {
+            // Raw samples and histograms don't fit a flat row; raw samples go to a sidecar file
+            if results.iter().any(|r| r.raw_timings_us.is_some()) {
+                match file {
+                    Some(path) => write_raw_timings_csv(results, &format!("{}.raw.csv", path)),
+                    None => eprintln!("note: raw timings are only written for CSV output with --file"),
+                }
+            }
+
             let mut wtr = csv::Writer::from_writer(vec![]);
-            for r in results {
-                wtr.serialize(r).unwrap();
+            for row in results.iter().flat_map(BenchmarkResult::csv_rows) {
+                wtr.serialize(row).unwrap();
+            }
+            let rows = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+            match environment {
+                Some(environment) => format!("# environment: {}\n{}", environment, rows),
+                None => rows,
             }
-            String::from_utf8(wtr.into_inner().unwrap()).unwrap()
         }
-        _ => serde_json::to_string_pretty(results).unwrap(),
+        _ => match environment {
+            Some(environment) => {
+                serde_json::to_string_pretty(&serde_json::json!({ "environment": environment, "results": results })).unwrap()
+            }
+            None => serde_json::to_string_pretty(results).unwrap(),
+        },
     };
 
     match file {
@@ -471,56 +1994,234 @@ fn output_results(results: &[BenchmarkResult], format: &str, file: Option<&str>)
 
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
-    let client = Client::new();
+    let mut cli = Cli::parse();
+    for url in cli.command.urls_mut() {
+        *url = resolve_url(std::mem::take(url)).await;
+    }
+    // With compression on, reqwest sends Accept-Encoding and decompresses transparently
+    let mut default_headers = reqwest::header::HeaderMap::new();
+    if let Some(token) = &cli.token {
+        let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+            .expect("--token must be a valid header value");
+        value.set_sensitive(true);
+        default_headers.insert(reqwest::header::AUTHORIZATION, value);
+    }
+    let mut builder = Client::builder()
+        .gzip(!cli.no_compression)
+        .brotli(!cli.no_compression)
+        .default_headers(default_headers)
+        .danger_accept_invalid_certs(cli.insecure);
+    if cli.no_keep_alive {
+        builder = builder.pool_max_idle_per_host(0);
+    }
+    if cli.http2 {
+        builder = builder.http2_prior_knowledge();
+    }
+    let ca_pem = cli.ca.as_ref().map(|path| std::fs::read(path).unwrap_or_else(|e| panic!("failed to read --ca {}: {}", path, e)));
+    if let Some(pem) = &ca_pem {
+        builder = builder.add_root_certificate(reqwest::Certificate::from_pem(pem).expect("--ca is not a PEM certificate"));
+    }
+    if cli.insecure {
+        eprintln!("warning: --insecure set, TLS certificates are not verified");
+    }
+    let client = builder.build().expect("failed to build HTTP client");
+    let mode = if cli.async_jobs {
+        RequestMode::AsyncJob
+    } else if cli.progress {
+        RequestMode::Stream
+    } else {
+        RequestMode::Blocking
+    };
     let mut results = Vec::new();
+    // NDJSON samples take over --file, so the summary is printed as JSON instead
+    let ndjson_file = match (cli.output.as_str(), &cli.command, cli.file.as_deref()) {
+        ("ndjson", Commands::Kem { requests: 1, .. }, Some(path)) => Some(path.to_string()),
+        ("ndjson", Commands::Kem { requests: 1, .. }, None) => {
+            eprintln!("error: --output ndjson needs --file for the samples");
+            std::process::exit(2);
+        }
+        ("ndjson", _, _) => {
+            eprintln!("error: --output ndjson streams a single kem request; drop --requests or use json/csv");
+            std::process::exit(2);
+        }
+        _ => None,
+    };
 
+    // Only lattice_service signs its responses
+    let signing_url = match &cli.command {
+        Commands::Kem { url, .. } | Commands::Ws { url, .. } => Some(url.clone()),
+        Commands::Suite { lattice_url, .. } => Some(lattice_url.clone()),
+        Commands::ZkProve { .. } | Commands::ZkVerify { .. } => None,
+    };
+
+    // Only the suite records the server's environment, in the output file header
+    let mut environment = None;
     match cli.command {
-        Commands::Kem {url, param_set, operation, iterations, requests, concurrency } => {
+        Commands::Kem {url, param_set, operation, iterations, runs, record_timestamps, requests, concurrency } => {
+            let server_version = fetch_server_version(&client, &url).await;
+            if server_version.is_none() {
+                eprintln!("warning: could not fetch {}/version, results won't record the server build", url);
+            }
+            match fetch_server_params(&client, &url).await {
+                Some(params) => {
+                    if let Err(message) = params.check(param_set.name(), operation.name()) {
+                        eprintln!("error: {}", message);
+                        std::process::exit(2);
+                    }
+                    if let Some(cap) = params.iteration_cap(param_set.name()).filter(|&cap| iterations > cap) {
+                        eprintln!("note: server caps {} at {} iterations per request", param_set, cap);
+                    }
+                }
+                None => eprintln!("warning: could not fetch {}/params, sending request unchecked", url),
+            }
+
             println!("Running KEM benchmark: {} {} x{}", param_set, operation, iterations);
-            let result = run_kem_benchmark(
-                &client, &url, &param_set, &operation, iterations, requests, concurrency, &cli.label
-            ).await;
+            let mut result = match &ndjson_file {
+                Some(path) => run_kem_ndjson(
+                    &client, &url, param_set.name(), operation.name(), iterations, runs, record_timestamps, &cli.label, path
+                ).await,
+                None => run_kem_benchmark(
+                    &client, &url, param_set.name(), operation.name(), iterations, runs, record_timestamps, requests, concurrency, &cli.label, cli.raw, cli.encoding, mode
+                ).await,
+            };
+            if let Some(v) = &server_version {
+                v.stamp(&mut result);
+            }
             results.push(result);
         }
-        Commands::ZkProve { url, circuit_id, iterations, requests, concurrency } => {
-            println!("Running ZK prove benchmark: {} x{}", circuit_id, iterations);
+        Commands::ZkProve { url, circuit_id, chain_length, curve, proof_system, iterations, requests, concurrency, prover_threads } => {
+            check_circuit(&client, &url, &circuit_id).await;
+            println!("Running ZK prove benchmark: {} {} on {} x{}", proof_system, circuit_id, curve, iterations);
             let result = run_zk_prove_benchmark(
-                &client, &url, &circuit_id, iterations, requests, concurrency, &cli.label
+                &client, &url, &circuit_id, chain_length, &curve, &proof_system, iterations, requests, concurrency, &cli.label, cli.raw, prover_threads
             ).await;
             results.push(result);
         }
-        Commands::ZkVerify { url, circuit_id, iterations, requests, concurrency } => {
-            println!("Running ZK verify benchmark: {} x{}", circuit_id, iterations);
+        Commands::ZkVerify { url, circuit_id, chain_length, curve, proof_system, iterations, requests, concurrency, verify_mode } => {
+            check_circuit(&client, &url, &circuit_id).await;
+            println!("Running ZK verify benchmark: {} {} on {} x{}", proof_system, circuit_id, curve, iterations);
             let result = run_zk_verify_benchmark(
-                &client, &url, &circuit_id, iterations, requests, concurrency, &cli.label
+                &client, &url, &circuit_id, chain_length, &curve, &proof_system, iterations, requests, concurrency, &cli.label, cli.raw, verify_mode.as_deref()
+            ).await;
+            results.push(result);
+        }
+        Commands::Ws { url, param_set, operation, iterations, runs, interval_ms } => {
+            let mut socket = match connect_ws(&url, cli.token.as_deref(), ca_pem.as_deref(), cli.insecure).await {
+                Ok(socket) => socket,
+                Err(e) => {
+                    eprintln!("error: could not open WebSocket: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            println!("Running KEM benchmark over WebSocket: {} {} x{}, {} runs", param_set, operation, iterations, runs);
+            let result = run_kem_ws(
+                &mut socket, param_set.name(), operation.name(), iterations, runs, Duration::from_millis(interval_ms), &cli.label, cli.raw
             ).await;
             results.push(result);
         }
         Commands::Suite { lattice_url, zk_url, kem_iterations, zk_iterations } => {
             println!("Running full benchmark suite...\n");
+            let fetched = fetch_environment(&client, &lattice_url).await;
+            if fetched.is_none() {
+                eprintln!("warning: could not fetch {}/environment, the output header will record it as null", lattice_url);
+            }
+            environment = Some(fetched.unwrap_or(serde_json::Value::Null));
+            let server_version = fetch_server_version(&client, &lattice_url).await;
+            if server_version.is_none() {
+                eprintln!("warning: could not fetch {}/version, results won't record the server build", lattice_url);
+            }
             
-            for param_set in ["ml_kem_512", "ml_kem_768", "ml_kem_1024"] {
-                for operation in ["keygen", "encaps", "decaps", "full_handshake"] {
-                    println!(" KEM: {} {}", param_set, operation);
-                    let result = run_kem_benchmark(
-                        &client, &lattice_url, param_set, operation, kem_iterations, 1, 1, &cli.label
-                    ).await;
-                    results.push(result);
+            let server_params = fetch_server_params(&client, &lattice_url).await;
+            if server_params.is_none() {
+                eprintln!("warning: could not fetch {}/params, sending suite jobs unchecked", lattice_url);
+            }
+            let template = KemBenchRequest {
+                param_set: String::new(),
+                iterations: kem_iterations,
+                operation: String::new(),
+                include_raw_timings: cli.raw,
+                include_machine_info: true,
+                runs: None,
+                record_timestamps: false,
+            };
+            let suite_jobs = SUITE_MATRICES
+                .iter()
+                .flat_map(|&(param_sets, operations)| param_sets.iter().flat_map(move |&p| operations.iter().map(move |&o| (p, o))));
+            let jobs: Vec<KemBenchRequest> = suite_jobs
+                .clone()
+                .filter(|&(param_set, operation)| match server_params.as_ref().map(|p| p.check(param_set, operation)) {
+                    Some(Err(message)) => {
+                        eprintln!(" skipping: {}", message);
+                        false
+                    }
+                    _ => true,
+                })
+                .map(|(param_set, operation)| KemBenchRequest {
+                    param_set: param_set.to_string(),
+                    operation: operation.to_string(),
+                    ..template.clone()
+                })
+                .collect();
+
+            // A matrix or batch is one long silent request, which is what --async and --progress
+            // avoid. A matrix runs every pair, so it's only used when none had to be skipped.
+            let batch = if mode != RequestMode::Blocking {
+                println!(" KEM/DSA: {} jobs, one request each", jobs.len());
+                None
+            } else {
+                let mut matrix = (jobs.len() == suite_jobs.count()).then(Vec::new);
+                if matrix.is_some() {
+                    println!(" KEM/DSA: {} jobs via /kem_bench_matrix", jobs.len());
+                }
+                for (param_sets, operations) in SUITE_MATRICES {
+                    let Some(results) = matrix.as_mut() else { break };
+                    match run_kem_matrix(&client, &lattice_url, &template, param_sets, operations, &cli.label, cli.encoding).await {
+                        Some(cells) => results.extend(cells),
+                        None => {
+                            println!(" Matrix endpoint unavailable, falling back to /kem_bench_batch");
+                            matrix = None;
+                        }
+                    }
+                }
+                if matrix.is_none() {
+                    println!(" KEM/DSA: {} jobs via /kem_bench_batch", jobs.len());
+                    matrix = run_kem_batch(&client, &lattice_url, &jobs, &cli.label, cli.encoding).await;
+                    if matrix.is_none() {
+                        println!(" Batch endpoint unavailable, falling back to one request per job");
+                    }
+                }
+                matrix
+            };
+            let mut kem_results = match batch {
+                Some(batch) => batch,
+                None => {
+                    let mut kem_results = Vec::new();
+                    for job in &jobs {
+                        println!(" KEM: {} {}", job.param_set, job.operation);
+                        let result = run_kem_benchmark(
+                            &client, &lattice_url, &job.param_set, &job.operation, kem_iterations, None, false, 1, 1, &cli.label, cli.raw, cli.encoding, mode
+                        ).await;
+                        kem_results.push(result);
+                    }
+                    kem_results
                 }
+            };
+            if let Some(v) = &server_version {
+                kem_results.iter_mut().for_each(|r| v.stamp(r));
             }
+            results.extend(kem_results);
 
             for circuit_id in ["multiply", "cube_root"] {
                 println!(" ZK prove: {}", circuit_id);
                 let result = run_zk_prove_benchmark(
-                    &client, &zk_url, circuit_id, zk_iterations, 1, 1, &cli.label
+                    &client, &zk_url, circuit_id, None, "bn254", "groth16", zk_iterations, 1, 1, &cli.label, cli.raw, None
                 ).await;
                 results.push(result);
         
 
                 println!("  ZK verify: {}", circuit_id);
                 let result = run_zk_verify_benchmark(
-                    &client, &zk_url, circuit_id, zk_iterations * 10, 1, 1, &cli.label
+                    &client, &zk_url, circuit_id, None, "bn254", "groth16", zk_iterations * 10, 1, 1, &cli.label, cli.raw, None
                 ).await;
                 results.push(result);
             }
@@ -528,5 +2229,24 @@ async fn main() {
         println!("\nSuite complete.");
         }
     }
-    output_results(&results, &cli.output, cli.file.as_deref());
+    if !cli.phase_rows {
+        results.iter_mut().for_each(|r| r.phases = None);
+    }
+    if cli.verify_signatures {
+        let public_key = match &signing_url {
+            Some(url) => {
+                let key = fetch_signing_key(&client, url).await;
+                if key.is_none() {
+                    eprintln!("warning: could not fetch {}/signing_key, no results can be verified", url);
+                }
+                key
+            }
+            None => None,
+        };
+        verify_signatures(&mut results, public_key.as_deref());
+    }
+    match ndjson_file {
+        Some(_) => output_results(&results, "json", None, None),
+        None => output_results(&results, &cli.output, cli.file.as_deref(), environment.as_ref()),
+    }
 }
@@ -0,0 +1,184 @@
+//! Detached ML-DSA-65 signatures over benchmark responses, so a results file can be traced
+//! back to the service instance that produced it and shown not to have been edited.
+//!
+//! What gets signed is the response's canonical JSON with its `signature` field removed:
+//!
+//! - object keys sorted by their UTF-8 bytes, at every level of nesting
+//! - no whitespace between tokens
+//! - strings escaped and numbers written exactly as serde_json writes them, so floats use
+//!   their shortest round-trip form and a decoded-then-re-encoded response signs the same
+//!
+//! That last rule relies on serde_json's float_roundtrip feature, which this crate turns on:
+//! its default float parser can land one ULP off, and the re-encoded number then differs.
+//!
+//! Signatures and public keys travel as standard base64.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ml_dsa::{
+    signature::{Signer, Verifier},
+    EncodedVerifyingKey, KeyGen, KeyPair, MlDsa65, Signature, VerifyingKey, B32,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Name reported next to the public key
+pub const ALGORITHM: &str = "ml_dsa_65";
+
+/// Response field carrying the signature; left out of the bytes it covers
+pub const SIGNATURE_FIELD: &str = "signature";
+
+/// Bytes in the seed a signing key is derived from
+pub const SEED_BYTES: usize = 32;
+
+/// Writes `value` under the canonicalization rules above
+pub fn canonical_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::from(key.as_str()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+// The canonical JSON of a response minus its signature field
+fn signed_bytes(response: &Value) -> Vec<u8> {
+    let mut response = response.clone();
+    if let Value::Object(map) = &mut response {
+        map.remove(SIGNATURE_FIELD);
+    }
+    canonical_json(&response).into_bytes()
+}
+
+/// Signs responses with a key derived from a 32-byte seed. Signing is deterministic, so the
+/// same key and response always give the same signature.
+pub struct ResponseSigner {
+    keypair: KeyPair<MlDsa65>,
+}
+
+impl ResponseSigner {
+    pub fn from_seed(seed: &[u8; SEED_BYTES]) -> Self {
+        ResponseSigner { keypair: MlDsa65::key_gen_internal(&B32::from(*seed)) }
+    }
+
+    /// The encoded verifying key
+    pub fn public_key(&self) -> Vec<u8> {
+        self.keypair.verifying_key().encode().to_vec()
+    }
+
+    /// Base64 signature over `response`'s canonical JSON; any signature field already on it
+    /// is ignored
+    pub fn sign(&self, response: &impl Serialize) -> String {
+        let value = serde_json::to_value(response).expect("responses serialize to JSON");
+        let signature: Signature<MlDsa65> = self.keypair.signing_key().sign(&signed_bytes(&value));
+        STANDARD.encode(signature.encode())
+    }
+}
+
+/// Checks the base64 signature carried in `response` against an encoded verifying key
+pub fn verify(public_key: &[u8], response: &Value) -> Result<(), String> {
+    let encoded = EncodedVerifyingKey::<MlDsa65>::try_from(public_key)
+        .map_err(|_| format!("public key must be {} bytes, got {}", EncodedVerifyingKey::<MlDsa65>::default().len(), public_key.len()))?;
+    let key = VerifyingKey::<MlDsa65>::decode(&encoded);
+    let signature = response
+        .get(SIGNATURE_FIELD)
+        .and_then(Value::as_str)
+        .ok_or_else(|| "response carries no signature".to_string())?;
+    let signature = STANDARD
+        .decode(signature)
+        .map_err(|e| format!("signature is not base64: {}", e))?;
+    let signature = Signature::<MlDsa65>::try_from(signature.as_slice())
+        .map_err(|_| "signature is malformed".to_string())?;
+    key.verify(&signed_bytes(response), &signature)
+        .map_err(|_| "signature does not match the response".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn signer() -> ResponseSigner {
+        ResponseSigner::from_seed(&[7; SEED_BYTES])
+    }
+
+    #[test]
+    fn canonical_json_sorts_keys_and_drops_whitespace() {
+        let value: Value = serde_json::from_str(
+            r#"{ "b": [3, {"z": 1, "a": null}], "a": "x\"y\n", "B": 1.5, "é": true, "c": 1e300, "d": 12.0 }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            canonical_json(&value),
+            r#"{"B":1.5,"a":"x\"y\n","b":[3,{"a":null,"z":1}],"c":1e+300,"d":12.0,"é":true}"#
+        );
+        // Reordering or reformatting the input doesn't change the output
+        let reordered: Value = serde_json::from_str(
+            r#"{"d":12.0,"c":1e300,"é":true,"B":1.5,"a":"x\"y\n","b":[3,{"a":null,"z":1}]}"#,
+        )
+        .unwrap();
+        assert_eq!(canonical_json(&reordered), canonical_json(&value));
+    }
+
+    #[test]
+    fn signatures_verify_and_survive_a_round_trip() {
+        let signer = signer();
+        let mut response = json!({"param_set": "ml_kem_768", "avg_us": 41.25, "iterations": 100});
+        response[SIGNATURE_FIELD] = signer.sign(&response).into();
+
+        let received: Value = serde_json::from_str(&serde_json::to_string_pretty(&response).unwrap()).unwrap();
+        assert_eq!(verify(&signer.public_key(), &received), Ok(()));
+        // Deterministic, and the signature field itself isn't covered
+        assert_eq!(signer.sign(&received), received[SIGNATURE_FIELD]);
+        assert_eq!(ResponseSigner::from_seed(&[7; SEED_BYTES]).public_key(), signer.public_key());
+    }
+
+    #[test]
+    fn floats_parse_back_to_the_same_canonical_form() {
+        let floats: Vec<f64> = (1..2000).map(|i| 1e6 / (i as f64 * 7.3)).collect();
+        let value = json!(floats);
+        let parsed: Value = serde_json::from_str(&value.to_string()).unwrap();
+        assert_eq!(canonical_json(&parsed), canonical_json(&value));
+    }
+
+    #[test]
+    fn edited_or_unsigned_responses_fail() {
+        let signer = signer();
+        let mut response = json!({"avg_us": 41.25});
+        response[SIGNATURE_FIELD] = signer.sign(&response).into();
+
+        let mut edited = response.clone();
+        edited["avg_us"] = json!(40.0);
+        assert!(verify(&signer.public_key(), &edited).is_err());
+        let other = ResponseSigner::from_seed(&[8; SEED_BYTES]);
+        assert!(verify(&other.public_key(), &response).is_err());
+        assert_eq!(verify(&signer.public_key(), &json!({"avg_us": 41.25})), Err("response carries no signature".to_string()));
+        assert!(verify(&[0; 4], &response).unwrap_err().starts_with("public key must be"));
+    }
+}
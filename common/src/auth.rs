@@ -0,0 +1,142 @@
+//! Optional bearer-token authentication, as a tower layer both services wrap their routers in.
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+use crate::secret;
+
+/// Paths that stay open so orchestration probes keep working without the token
+const EXEMPT_PATHS: &[&str] = &["/health", "/live", "/ready"];
+
+/// Requires `Authorization: Bearer <token>` on every request outside [`EXEMPT_PATHS`];
+/// anything else gets a 401 with a JSON `{"error": ...}` body.
+#[derive(Clone)]
+pub struct BearerAuthLayer {
+    token: Arc<str>,
+}
+
+impl BearerAuthLayer {
+    pub fn new(token: &str) -> Self {
+        BearerAuthLayer { token: token.into() }
+    }
+}
+
+impl<S> Layer<S> for BearerAuthLayer {
+    type Service = BearerAuth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BearerAuth { inner, token: self.token.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct BearerAuth<S> {
+    inner: S,
+    token: Arc<str>,
+}
+
+impl<S> BearerAuth<S> {
+    fn authorized(&self, req: &Request<Body>) -> bool {
+        if EXEMPT_PATHS.contains(&req.uri().path()) {
+            return true;
+        }
+        req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|presented| secret::ct_eq(presented.as_bytes(), self.token.as_bytes()))
+    }
+}
+
+impl<S> Service<Request<Body>> for BearerAuth<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Error: Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        if self.authorized(&req) {
+            Box::pin(self.inner.call(req))
+        } else {
+            Box::pin(std::future::ready(Ok(unauthorized())))
+        }
+    }
+}
+
+fn unauthorized() -> Response {
+    let body = serde_json::json!({ "error": "missing or invalid bearer token" });
+    (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+
+    fn router() -> Router {
+        Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .route("/live", get(|| async { "ok" }))
+            .route("/ready", get(|| async { "ok" }))
+            .route("/kem_bench", get(|| async { "ran" }))
+            .layer(BearerAuthLayer::new("s3cret"))
+    }
+
+    async fn status(authorization: Option<&str>, path: &str) -> StatusCode {
+        let mut req = Request::get(path);
+        if let Some(value) = authorization {
+            req = req.header(header::AUTHORIZATION, value);
+        }
+        router().oneshot(req.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn requests_need_the_exact_token() {
+        assert_eq!(status(Some("Bearer s3cret"), "/kem_bench").await, StatusCode::OK);
+        assert_eq!(status(None, "/kem_bench").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some("Bearer s3cre"), "/kem_bench").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some("Bearer s3cret2"), "/kem_bench").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some("Basic s3cret"), "/kem_bench").await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn health_stays_open() {
+        assert_eq!(status(None, "/health").await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn probes_stay_open() {
+        assert_eq!(status(None, "/live").await, StatusCode::OK);
+        assert_eq!(status(None, "/ready").await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rejection_is_a_json_error() {
+        let resp = router()
+            .oneshot(Request::get("/kem_bench").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"], "missing or invalid bearer token");
+    }
+}
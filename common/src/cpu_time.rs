@@ -0,0 +1,63 @@
+//! Per-thread CPU time, for telling how much of a benchmark loop's wall-clock time the
+//! thread actually spent running.
+//!
+//! Returns `None` where the platform has no per-thread CPU clock, so callers can leave the
+//! figures out instead of reporting something misleading.
+
+use std::time::Duration;
+
+/// CPU time consumed by the calling thread so far: CLOCK_THREAD_CPUTIME_ID, falling back
+/// to getrusage(RUSAGE_THREAD) on Linux kernels that refuse the clock.
+#[cfg(unix)]
+pub fn thread_cpu_time() -> Option<Duration> {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    if unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) } == 0 {
+        return Some(Duration::new(u64::try_from(ts.tv_sec).ok()?, u32::try_from(ts.tv_nsec).ok()?));
+    }
+    rusage_thread_cpu_time()
+}
+
+#[cfg(not(unix))]
+pub fn thread_cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn rusage_thread_cpu_time() -> Option<Duration> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+    if unsafe { libc::getrusage(libc::RUSAGE_THREAD, usage.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let usage = unsafe { usage.assume_init() };
+    let timeval = |tv: libc::timeval| {
+        Some(Duration::from_secs(u64::try_from(tv.tv_sec).ok()?) + Duration::from_micros(u64::try_from(tv.tv_usec).ok()?))
+    };
+    Some(timeval(usage.ru_utime)? + timeval(usage.ru_stime)?)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn rusage_thread_cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn busy_threads_accrue_cpu_time_and_sleeping_ones_dont() {
+        let start = thread_cpu_time().expect("Linux has a thread CPU clock");
+        let spin_until = Instant::now() + Duration::from_millis(30);
+        while Instant::now() < spin_until {
+            std::hint::black_box(0);
+        }
+        let spun = thread_cpu_time().unwrap() - start;
+        assert!(spun >= Duration::from_millis(10), "{:?}", spun);
+
+        let before_sleep = thread_cpu_time().unwrap();
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(thread_cpu_time().unwrap() - before_sleep < Duration::from_millis(10));
+        assert!(rusage_thread_cpu_time().unwrap() >= spun);
+    }
+}
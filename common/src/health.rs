@@ -0,0 +1,269 @@
+//! /live, /ready and /health, with the same JSON shape in both services.
+//!
+//! /live only says the process is up. /ready additionally says it can take benchmark
+//! traffic: it's 503 until startup work is done, after a failed self-test, while too many
+//! benchmarks are running, and while the service is shedding load. Shedding uses the same
+//! check that turns new benchmarks away, so orchestrators stop routing to an instance
+//! exactly when it would refuse the work. Fields only some services have, such as
+//! `selftest_passed`, are left out of the responses of the others.
+
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+use crate::memory;
+use crate::metrics::ServiceMetrics;
+
+pub struct Readiness {
+    service: &'static str,
+    initialized: AtomicBool,
+    /// Outcome of the most recent self-test; None for services without one
+    selftest_passed: Option<AtomicBool>,
+    in_flight: AtomicUsize,
+    max_in_flight: usize,
+    shed: ShedLimits,
+}
+
+/// Thresholds past which new benchmarks are refused; None disables a threshold
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ShedLimits {
+    pub max_rss_kb: Option<u64>,
+    pub max_in_flight: Option<usize>,
+}
+
+/// Counts one running benchmark until dropped
+pub struct InFlightGuard(Arc<Readiness>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Readiness {
+    /// Not ready until `mark_initialized`; `service` names the service in every response
+    pub fn new(service: &'static str, max_in_flight: usize) -> Self {
+        Readiness {
+            service,
+            initialized: AtomicBool::new(false),
+            selftest_passed: None,
+            in_flight: AtomicUsize::new(0),
+            max_in_flight,
+            shed: ShedLimits::default(),
+        }
+    }
+
+    pub fn with_shed_limits(self, shed: ShedLimits) -> Self {
+        Readiness { shed, ..self }
+    }
+
+    /// Reports a self-test outcome, passing until one is recorded
+    pub fn with_selftest(self) -> Self {
+        Readiness { selftest_passed: Some(AtomicBool::new(true)), ..self }
+    }
+
+    /// Why a new benchmark should be turned away right now, if it should. An RSS that
+    /// can't be read doesn't count as pressure.
+    pub fn pressure(&self) -> Option<String> {
+        if let Some((rss_kb, limit)) = memory::rss_kb().zip(self.shed.max_rss_kb).filter(|(rss, limit)| rss > limit) {
+            return Some(format!("resident memory is {} KiB, above the {} KiB limit", rss_kb, limit));
+        }
+        let in_flight = self.in_flight.load(Ordering::SeqCst);
+        match self.shed.max_in_flight {
+            Some(limit) if in_flight >= limit => Some(format!("{} benchmarks in flight, at the limit of {}", in_flight, limit)),
+            _ => None,
+        }
+    }
+
+    pub fn mark_initialized(&self) {
+        self.initialized.store(true, Ordering::SeqCst);
+    }
+
+    /// Ignored unless built `with_selftest`
+    pub fn record_selftest(&self, passed: bool) {
+        if let Some(selftest) = &self.selftest_passed {
+            selftest.store(passed, Ordering::SeqCst);
+        }
+    }
+
+    pub fn track(self: &Arc<Self>) -> InFlightGuard {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self.clone())
+    }
+
+    fn status(&self) -> ReadyResponse {
+        let in_flight = self.in_flight.load(Ordering::SeqCst);
+        let selftest_passed = self.selftest_passed.as_ref().map(|passed| passed.load(Ordering::SeqCst));
+        let pressure = self.pressure();
+        let status = if !self.initialized.load(Ordering::SeqCst) {
+            "initializing"
+        } else if selftest_passed == Some(false) {
+            "selftest_failed"
+        } else if pressure.is_some() {
+            "shedding_load"
+        } else if in_flight >= self.max_in_flight {
+            "overloaded"
+        } else {
+            "ready"
+        };
+
+        ReadyResponse {
+            status,
+            service: self.service,
+            ready: status == "ready",
+            selftest_passed,
+            in_flight_benchmarks: in_flight,
+            max_concurrent_benchmarks: self.max_in_flight,
+            shedding_reason: pressure,
+            timestamp: now(),
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+#[derive(Serialize)]
+pub struct LiveResponse {
+    status: &'static str,
+    service: &'static str,
+    timestamp: u64,
+}
+
+#[derive(Serialize)]
+pub struct ReadyResponse {
+    status: &'static str,
+    service: &'static str,
+    ready: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    selftest_passed: Option<bool>,
+    in_flight_benchmarks: usize,
+    max_concurrent_benchmarks: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shedding_reason: Option<String>,
+    timestamp: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthResponse {
+    status: &'static str,
+    service: &'static str,
+    live: bool,
+    ready: bool,
+    uptime_seconds: u64,
+    /// Requests answered since startup; the /health request being answered isn't counted yet
+    requests_total: u64,
+    /// Keyed by route template, e.g. "/keys/{id}"
+    requests_by_endpoint: BTreeMap<String, u64>,
+    /// Responses with a 4xx or 5xx status since startup
+    errors_total: u64,
+    /// AppError responses by kind, e.g. "validation_failed"; same counts as <service>_errors_total
+    errors_by_kind: BTreeMap<String, u64>,
+    timestamp: u64,
+}
+
+pub async fn live(State(readiness): State<Arc<Readiness>>) -> Json<LiveResponse> {
+    Json(LiveResponse { status: "alive", service: readiness.service, timestamp: now() })
+}
+
+pub async fn ready(State(readiness): State<Arc<Readiness>>) -> (StatusCode, Json<ReadyResponse>) {
+    let status = readiness.status();
+    let code = if status.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (code, Json(status))
+}
+
+/// Kept for existing probes; always 200 while the process is up, with readiness and
+/// request counters as fields
+#[utoipa::path(get, path = "/health", responses((status = 200, description = "Process is up", body = HealthResponse)))]
+pub async fn health(
+    State(readiness): State<Arc<Readiness>>,
+    State(metrics): State<Arc<ServiceMetrics>>,
+) -> Json<HealthResponse> {
+    let counts = metrics.request_counts();
+    Json(HealthResponse {
+        status: "healthy",
+        service: readiness.service,
+        live: true,
+        ready: readiness.status().ready,
+        uptime_seconds: metrics.uptime().as_secs(),
+        requests_total: counts.total,
+        requests_by_endpoint: counts.by_endpoint,
+        errors_total: counts.errors,
+        errors_by_kind: counts.errors_by_kind,
+        timestamp: now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn ready_reflects_initialization_and_load() {
+        let readiness = Arc::new(Readiness::new("test_service", 2));
+        let (code, Json(status)) = ready(State(readiness.clone())).await;
+        assert_eq!((code, status.status), (StatusCode::SERVICE_UNAVAILABLE, "initializing"));
+
+        readiness.mark_initialized();
+        let first = readiness.track();
+        let (code, _) = ready(State(readiness.clone())).await;
+        assert_eq!(code, StatusCode::OK);
+
+        let second = readiness.track();
+        let (code, Json(status)) = ready(State(readiness.clone())).await;
+        assert_eq!((code, status.status, status.in_flight_benchmarks), (StatusCode::SERVICE_UNAVAILABLE, "overloaded", 2));
+        let Json(health) = health(State(readiness.clone()), State(Arc::new(ServiceMetrics::new("test")))).await;
+        assert!(health.live && !health.ready);
+
+        drop((first, second));
+        let (code, Json(status)) = ready(State(readiness)).await;
+        assert_eq!((code, status.in_flight_benchmarks), (StatusCode::OK, 0));
+    }
+
+    #[tokio::test]
+    async fn selftest_is_only_reported_by_services_with_one() {
+        let without = Arc::new(Readiness::new("test_service", 2));
+        without.mark_initialized();
+        without.record_selftest(false);
+        let (code, Json(status)) = ready(State(without)).await;
+        assert_eq!((code, status.selftest_passed), (StatusCode::OK, None));
+        assert!(!serde_json::to_string(&status).unwrap().contains("selftest_passed"));
+
+        let with = Arc::new(Readiness::new("test_service", 2).with_selftest());
+        with.mark_initialized();
+        let (_, Json(status)) = ready(State(with.clone())).await;
+        assert_eq!(status.selftest_passed, Some(true));
+        with.record_selftest(false);
+        let (code, Json(status)) = ready(State(with)).await;
+        assert_eq!((code, status.status), (StatusCode::SERVICE_UNAVAILABLE, "selftest_failed"));
+
+        let Json(live) = live(State(Arc::new(Readiness::new("test_service", 2)))).await;
+        assert!(serde_json::to_string(&live).unwrap().contains(r#""service":"test_service""#));
+    }
+
+    #[tokio::test]
+    async fn ready_sheds_load_past_either_limit() {
+        let readiness = Arc::new(Readiness::new("test_service", 8).with_shed_limits(ShedLimits { max_rss_kb: None, max_in_flight: Some(1) }));
+        readiness.mark_initialized();
+        assert!(readiness.pressure().is_none());
+        let running = readiness.track();
+        let (code, Json(status)) = ready(State(readiness.clone())).await;
+        assert_eq!((code, status.status), (StatusCode::SERVICE_UNAVAILABLE, "shedding_load"));
+        assert_eq!(status.shedding_reason.as_deref(), Some("1 benchmarks in flight, at the limit of 1"));
+        drop(running);
+        assert!(readiness.pressure().is_none());
+
+        // Any running process is above a 1 KiB limit
+        let readiness = Readiness::new("test_service", 8).with_shed_limits(ShedLimits { max_rss_kb: Some(1), max_in_flight: None });
+        readiness.mark_initialized();
+        if memory::rss_kb().is_some() {
+            assert!(readiness.pressure().unwrap().starts_with("resident memory is"));
+            assert_eq!(readiness.status().status, "shedding_load");
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! HTTP/1.1 keep-alive and HTTP/2 settings, plain-HTTP serving over TCP with them, and
+//! graceful shutdown for every way the services serve.
+
+use axum::{serve::Listener, Router};
+use axum_server::Handle;
+use hyper_util::rt::{TokioExecutor, TokioTimer};
+use hyper_util::server::conn::auto::Builder;
+use serde::Serialize;
+use std::future::{Future, IntoFuture};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use utoipa::ToSchema;
+
+use crate::limits::ConnectionLimit;
+
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 60;
+pub const DEFAULT_HTTP2_MAX_CONCURRENT_STREAMS: u32 = 256;
+pub const DEFAULT_HTTP2_KEEP_ALIVE_INTERVAL_SECS: u64 = 20;
+
+// How long an HTTP/2 keep-alive ping may go unanswered before the connection is closed
+const HTTP2_KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Connection settings a service was started with, as reported to clients. HTTP/1.1
+/// connections are always kept alive; HTTP/2 is negotiated via ALPN over TLS, and spoken
+/// with prior knowledge (h2c) over plain TCP.
+#[derive(Clone, Copy, Debug, Serialize, ToSchema)]
+pub struct HttpTuning {
+    /// Seconds an HTTP/1.1 connection may sit idle between requests, or take to send one
+    /// request's headers, before it's closed
+    pub idle_timeout_secs: u64,
+    /// Requests a single HTTP/2 connection may have in flight at once
+    pub http2_max_concurrent_streams: u32,
+    /// Seconds between pings on an HTTP/2 connection, so dead peers are noticed
+    pub http2_keep_alive_interval_secs: u64,
+}
+
+impl Default for HttpTuning {
+    fn default() -> Self {
+        HttpTuning {
+            idle_timeout_secs: DEFAULT_IDLE_TIMEOUT_SECS,
+            http2_max_concurrent_streams: DEFAULT_HTTP2_MAX_CONCURRENT_STREAMS,
+            http2_keep_alive_interval_secs: DEFAULT_HTTP2_KEEP_ALIVE_INTERVAL_SECS,
+        }
+    }
+}
+
+impl HttpTuning {
+    /// Applies these settings to an axum-server connection builder
+    pub fn configure(&self, builder: &mut Builder<TokioExecutor>) {
+        // hyper starts the header read timer as soon as it waits for a request, so on a
+        // kept-alive connection it's also the idle timeout
+        builder
+            .http1()
+            .timer(TokioTimer::new())
+            .keep_alive(true)
+            .header_read_timeout(Duration::from_secs(self.idle_timeout_secs));
+        builder
+            .http2()
+            .timer(TokioTimer::new())
+            .max_concurrent_streams(self.http2_max_concurrent_streams)
+            .keep_alive_interval(Duration::from_secs(self.http2_keep_alive_interval_secs))
+            .keep_alive_timeout(HTTP2_KEEP_ALIVE_TIMEOUT);
+    }
+}
+
+/// Serves HTTP/1.1 and h2c on `listener`. Once `shutdown` resolves, stop accepting
+/// connections and give in-flight requests `drain_timeout` to finish.
+pub async fn serve(
+    listener: std::net::TcpListener,
+    router: Router,
+    tuning: HttpTuning,
+    connections: ConnectionLimit,
+    drain_timeout: Duration,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let (shutdown, _) = announce(shutdown, drain_timeout);
+    let handle = Handle::new();
+    let draining = handle.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        draining.graceful_shutdown(Some(drain_timeout));
+    });
+
+    let mut server = axum_server::from_tcp(listener).acceptor(connections).handle(handle);
+    tuning.configure(server.http_builder());
+    server.serve(router.into_make_service_with_connect_info::<SocketAddr>()).await
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix (what `docker stop` sends)
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("failed to install Ctrl-C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Serves on any axum listener, e.g. a Unix domain socket, without the TCP tuning of
+/// `serve`. Once `shutdown` resolves, stop accepting connections and let in-flight requests
+/// finish; anything still running after `drain_timeout` is abandoned. Peers have no
+/// ConnectInfo, so handlers see no client address.
+pub async fn serve_with_drain<L>(
+    listener: L,
+    router: Router,
+    drain_timeout: Duration,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()>
+where
+    L: Listener,
+    L::Addr: std::fmt::Debug,
+{
+    let (graceful, draining) = announce(shutdown, drain_timeout);
+    let server = axum::serve(listener, router).with_graceful_shutdown(graceful);
+    tokio::select! {
+        result = server.into_future() => result,
+        _ = async {
+            draining.notified().await;
+            tokio::time::sleep(drain_timeout).await;
+        } => {
+            tracing::warn!("drain timeout elapsed, exiting with requests still in flight");
+            Ok(())
+        }
+    }
+}
+
+// Wraps `shutdown` to log the start of the drain and notify the returned handle
+pub(crate) fn announce(
+    shutdown: impl Future<Output = ()> + Send + 'static,
+    drain_timeout: Duration,
+) -> (impl Future<Output = ()> + Send + 'static, Arc<Notify>) {
+    let draining = Arc::new(Notify::new());
+    let notify = draining.clone();
+    let graceful = async move {
+        shutdown.await;
+        tracing::info!(?drain_timeout, "shutdown signal received, draining in-flight requests");
+        notify.notify_one();
+    };
+    (graceful, draining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Request;
+    use axum::routing::get;
+
+    async fn spawn_server(tuning: HttpTuning) -> (SocketAddr, tokio::sync::oneshot::Sender<()>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new().route("/version", get(|req: Request| async move { format!("{:?}", req.version()) }));
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(serve(listener, router, tuning, ConnectionLimit::default(), Duration::from_secs(1), async {
+            let _ = stopped.await;
+        }));
+        (addr, stop)
+    }
+
+    #[tokio::test]
+    async fn serves_http1_and_h2c_on_one_port() {
+        let (addr, _stop) = spawn_server(HttpTuning::default()).await;
+        let url = format!("http://{}/version", addr);
+
+        let http1 = reqwest::get(&url).await.unwrap();
+        assert_eq!(http1.version(), reqwest::Version::HTTP_11);
+        assert_eq!(http1.text().await.unwrap(), "HTTP/1.1");
+
+        let h2c = reqwest::Client::builder().http2_prior_knowledge().build().unwrap();
+        let http2 = h2c.get(&url).send().await.unwrap();
+        assert_eq!(http2.version(), reqwest::Version::HTTP_2);
+        assert_eq!(http2.text().await.unwrap(), "HTTP/2.0");
+    }
+
+    #[tokio::test]
+    async fn idle_http1_connections_are_closed() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let tuning = HttpTuning { idle_timeout_secs: 1, ..HttpTuning::default() };
+        let (addr, _stop) = spawn_server(tuning).await;
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+
+        // Two requests over one connection, then silence until the server hangs up
+        for _ in 0..2 {
+            stream.write_all(b"GET /version HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+            let mut buf = [0u8; 1024];
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+        }
+        let mut buf = [0u8; 1024];
+        let closed = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf)).await;
+        assert!(matches!(closed, Ok(Ok(0))), "idle connection was kept open");
+    }
+}
@@ -1,14 +1,19 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
-}
+//! Helpers shared between the benchmark services.
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
-}
+pub mod attest;
+pub mod auth;
+pub mod cpu_time;
+pub mod cors;
+pub mod health;
+pub mod http;
+pub mod limits;
+pub mod listen;
+pub mod machine;
+pub mod memory;
+pub mod metrics;
+pub mod params;
+pub mod request;
+pub mod secret;
+pub mod stats;
+pub mod tls;
+pub mod uds;
@@ -0,0 +1,224 @@
+//! Request and connection limits both services put in front of their routes.
+
+use axum::extract::DefaultBodyLimit;
+use axum::http::StatusCode;
+use axum::serve::Listener;
+use axum::Router;
+use axum_server::accept::Accept;
+use serde::Serialize;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::timeout::TimeoutLayer;
+use utoipa::ToSchema;
+
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+pub const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// Limits a service was started with, as reported to clients
+#[derive(Clone, Copy, Debug, Serialize, ToSchema)]
+pub struct Limits {
+    /// Largest request body accepted; bigger ones get a 413
+    pub max_body_bytes: usize,
+    /// Longest a handler may run before the request gets a 408. Benchmark endpoints are
+    /// exempt and keep to their own deadline.
+    pub request_timeout_secs: u64,
+    /// Connections kept open at once; further ones wait to be accepted (None: no limit)
+    pub max_connections: Option<usize>,
+}
+
+impl Limits {
+    /// Answers requests whose handler runs past the timeout with a 408. Applied only to the
+    /// routes in `router`, so long-running ones are merged in afterwards.
+    pub fn time_out<S: Clone + Send + Sync + 'static>(&self, router: Router<S>) -> Router<S> {
+        router.layer(TimeoutLayer::with_status_code(
+            StatusCode::REQUEST_TIMEOUT,
+            Duration::from_secs(self.request_timeout_secs),
+        ))
+    }
+
+    /// Refuses bodies over max_body_bytes with a 413. axum's own 2 MiB default is lifted, so
+    /// this is the only body limit.
+    pub fn limit_body<S: Clone + Send + Sync + 'static>(&self, router: Router<S>) -> Router<S> {
+        router
+            .layer(DefaultBodyLimit::disable())
+            .layer(RequestBodyLimitLayer::new(self.max_body_bytes))
+    }
+
+    pub fn connections(&self) -> ConnectionLimit {
+        ConnectionLimit {
+            permits: self.max_connections.map(|max| Arc::new(Semaphore::new(max))),
+        }
+    }
+}
+
+/// Caps the connections a listener keeps open. A connection holds its permit until it
+/// closes; once they're all taken, nothing more is accepted and new clients wait in the
+/// listen backlog. The default is no limit.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionLimit {
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl ConnectionLimit {
+    async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        match &self.permits {
+            // Never closed, so acquiring only ever waits
+            Some(permits) => Some(permits.clone().acquire_owned().await.expect("connection semaphore closed")),
+            None => None,
+        }
+    }
+
+    /// Wraps a listener for axum::serve
+    pub fn listener<L: Listener>(self, inner: L) -> LimitedListener<L> {
+        LimitedListener { inner, limit: self }
+    }
+}
+
+pub struct LimitedListener<L> {
+    inner: L,
+    limit: ConnectionLimit,
+}
+
+impl<L: Listener> Listener for LimitedListener<L> {
+    type Io = Limited<L::Io>;
+    type Addr = L::Addr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        let permit = self.limit.acquire().await;
+        let (io, addr) = self.inner.accept().await;
+        (Limited { io, _permit: permit }, addr)
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        self.inner.local_addr()
+    }
+}
+
+/// As an axum-server acceptor, for HTTPS. axum-server has already accepted the socket by
+/// then, so connections over the limit wait here instead, within the TLS handshake timeout.
+impl<I: Send + 'static, S: Send + 'static> Accept<I, S> for ConnectionLimit {
+    type Stream = Limited<I>;
+    type Service = S;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(Limited<I>, S)>> + Send>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let limit = self.clone();
+        Box::pin(async move { Ok((Limited { io: stream, _permit: limit.acquire().await }, service)) })
+    }
+}
+
+/// A connection, with the permit it gives back when dropped
+pub struct Limited<I> {
+    io: I,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl<I: AsyncRead + Unpin> AsyncRead for Limited<I> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl<I: AsyncWrite + Unpin> AsyncWrite for Limited<I> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::{get, post};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tower::ServiceExt;
+
+    fn limits(max_connections: Option<usize>) -> Limits {
+        Limits { max_body_bytes: 16, request_timeout_secs: 1, max_connections }
+    }
+
+    async fn status(router: Router, method: &str, path: &str, body: &'static str) -> StatusCode {
+        let req = Request::builder().method(method).uri(path).body(Body::from(body)).unwrap();
+        router.oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_are_413s() {
+        let limits = limits(None);
+        let router = limits.limit_body(Router::new().route("/echo", post(|body: String| async move { body })));
+        assert_eq!(status(router.clone(), "POST", "/echo", "sixteen bytes!!!").await, StatusCode::OK);
+        assert_eq!(status(router, "POST", "/echo", "seventeen bytes!!").await, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn only_timed_routes_time_out() {
+        let slow = || async {
+            tokio::time::sleep(Duration::from_millis(1500)).await;
+            "done"
+        };
+        let limits = limits(None);
+        let router = limits.time_out(Router::new().route("/slow", get(slow))).route("/bench", get(slow));
+        assert_eq!(status(router.clone(), "GET", "/slow", "").await, StatusCode::REQUEST_TIMEOUT);
+        assert_eq!(status(router, "GET", "/bench", "").await, StatusCode::OK);
+    }
+
+    async fn get_over(stream: &mut TcpStream) -> String {
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        String::from_utf8_lossy(&buf[..n]).into_owned()
+    }
+
+    #[tokio::test]
+    async fn connections_past_the_limit_wait_for_one_to_close() {
+        let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = tcp.local_addr().unwrap();
+        let listener = limits(Some(1)).connections().listener(tcp);
+        tokio::spawn(async move { axum::serve(listener, Router::new().route("/", get(|| async { "ok" }))).await });
+
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        assert!(get_over(&mut first).await.starts_with("HTTP/1.1 200"));
+
+        // Connects via the backlog, but isn't served while the first stays open
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        let waiting = tokio::time::timeout(Duration::from_millis(300), get_over(&mut second)).await;
+        assert!(waiting.is_err(), "served past the connection limit");
+
+        drop(first);
+        let served = tokio::time::timeout(Duration::from_secs(5), async {
+            let mut buf = [0u8; 1024];
+            let n = second.read(&mut buf).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).into_owned()
+        });
+        assert!(served.await.unwrap().starts_with("HTTP/1.1 200"));
+    }
+}
@@ -0,0 +1,53 @@
+//! The TCP address both services listen on, set with --bind or <SERVICE>_BIND.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
+
+/// Every IPv4 interface, so a service is reachable from other containers by default
+pub const ALL_INTERFACES: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
+/// Parses a bind address: "host:port" ("[::1]:8001" for IPv6), a bare port to listen on
+/// every interface, or a bare IP address to listen on `default_port`. Port 0 lets the OS
+/// pick a free port.
+pub fn parse_bind(s: &str, default_port: u16) -> Result<SocketAddr, String> {
+    let s = s.trim();
+    if let Ok(addr) = s.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    if let Ok(port) = s.parse::<u16>() {
+        return Ok(SocketAddr::new(ALL_INTERFACES, port));
+    }
+    if let Ok(ip) = s.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, default_port));
+    }
+    Err(format!("'{}' is not an address to bind, such as 0.0.0.0:{}, 127.0.0.1, [::]:{} or {}", s, default_port, default_port, default_port))
+}
+
+/// Listens on `addr`, returning the address actually bound, which is where to look for a
+/// port 0 server. The listener is non-blocking, ready for tokio or axum-server.
+pub fn bind(addr: SocketAddr) -> io::Result<(TcpListener, SocketAddr)> {
+    let listener = TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    let bound = listener.local_addr()?;
+    Ok((listener, bound))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_addresses_parse_with_defaults_filled_in() {
+        assert_eq!(parse_bind("127.0.0.1:9000", 8001), Ok("127.0.0.1:9000".parse().unwrap()));
+        assert_eq!(parse_bind("9000", 8001), Ok("0.0.0.0:9000".parse().unwrap()));
+        assert_eq!(parse_bind("127.0.0.1", 8001), Ok("127.0.0.1:8001".parse().unwrap()));
+        assert_eq!(parse_bind("[::]:0", 8001), Ok("[::]:0".parse().unwrap()));
+        assert_eq!(parse_bind("::1", 8001), Ok("[::1]:8001".parse().unwrap()));
+        assert!(parse_bind("localhost:8001", 8001).is_err());
+        assert!(parse_bind("127.0.0.1:70000", 8001).is_err());
+
+        let (_listener, bound) = bind(parse_bind("127.0.0.1:0", 8001).unwrap()).unwrap();
+        assert_eq!(bound.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_ne!(bound.port(), 0);
+    }
+}
@@ -0,0 +1,92 @@
+//! Host metadata attached to benchmark responses, so merged result files can
+//! be traced back to the machine that produced them.
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct MachineInfo {
+    /// Null if the hostname can't be read
+    pub hostname: Option<String>,
+    /// CPU model string as reported by the OS; null where it isn't exposed
+    pub cpu_model: Option<String>,
+    pub logical_cores: usize,
+    /// "release" or "debug"
+    pub build_profile: &'static str,
+}
+
+impl MachineInfo {
+    /// Probes the host. Reads files and makes syscalls, so call it once at startup.
+    pub fn detect() -> Self {
+        MachineInfo {
+            hostname: hostname(),
+            cpu_model: cpu_model(),
+            logical_cores: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            build_profile: if cfg!(debug_assertions) { "debug" } else { "release" },
+        }
+    }
+}
+
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    let name = String::from_utf8_lossy(&buf[..len]).into_owned();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_model() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    parse_cpu_model(&cpuinfo)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_model() -> Option<String> {
+    None
+}
+
+// x86 uses "model name"; some ARM kernels only provide "Processor" or "Hardware"
+#[cfg(any(test, target_os = "linux"))]
+fn parse_cpu_model(cpuinfo: &str) -> Option<String> {
+    ["model name", "Processor", "Hardware"].iter().find_map(|key| {
+        cpuinfo.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            let value = value.trim();
+            (name.trim() == *key && !value.is_empty()).then(|| value.to_string())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_x86_model_name() {
+        let cpuinfo = "processor\t: 0\nvendor_id\t: GenuineIntel\nmodel name\t: Intel(R) Xeon(R) CPU @ 2.20GHz\n\nprocessor\t: 1\nmodel name\t: Intel(R) Xeon(R) CPU @ 2.20GHz\n";
+        assert_eq!(parse_cpu_model(cpuinfo).as_deref(), Some("Intel(R) Xeon(R) CPU @ 2.20GHz"));
+    }
+
+    #[test]
+    fn falls_back_to_arm_fields() {
+        let cpuinfo = "processor\t: 0\nBogoMIPS\t: 48.00\n\nHardware\t: BCM2835\n";
+        assert_eq!(parse_cpu_model(cpuinfo).as_deref(), Some("BCM2835"));
+        assert_eq!(parse_cpu_model("processor\t: 0\n"), None);
+    }
+
+    #[test]
+    fn detect_reports_at_least_one_core() {
+        let info = MachineInfo::detect();
+        assert!(info.logical_cores >= 1);
+        assert!(matches!(info.build_profile, "debug" | "release"));
+    }
+}
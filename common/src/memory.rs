@@ -0,0 +1,194 @@
+//! Process memory sampling for benchmark responses.
+//!
+//! Every sampler returns `None` where the platform doesn't expose the number,
+//! so callers can report null instead of a misleading 0.
+
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Current resident set size in KiB, read from /proc/self/statm.
+#[cfg(target_os = "linux")]
+pub fn rss_kb() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    // Fields are in pages: size, resident, shared, ...
+    let resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        return None;
+    }
+    Some(resident_pages * page_size as u64 / 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn rss_kb() -> Option<u64> {
+    None
+}
+
+/// High-water mark of the resident set size in KiB since process start, via getrusage.
+#[cfg(unix)]
+pub fn peak_rss_kb() -> Option<u64> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::zeroed();
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let max_rss = unsafe { usage.assume_init() }.ru_maxrss;
+    let max_rss = u64::try_from(max_rss).ok()?;
+    // macOS reports ru_maxrss in bytes, Linux and the BSDs in KiB
+    let peak = if cfg!(target_os = "macos") { max_rss / 1024 } else { max_rss };
+    // Linux folds per-thread RSS counters into the high-water mark lazily, so it can
+    // briefly trail the current size; never report a peak below what is resident now
+    Some(rss_kb().map_or(peak, |rss| rss.max(peak)))
+}
+
+#[cfg(not(unix))]
+pub fn peak_rss_kb() -> Option<u64> {
+    None
+}
+
+/// How often a [`PeakSampler`] reads the resident set size by default.
+pub const PEAK_SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The resident set size over one benchmark, as a [`PeakSampler`] saw it.
+#[derive(Clone, Copy, Debug)]
+pub struct PeakSample {
+    pub before_kb: u64,
+    pub peak_kb: u64,
+    /// Readings taken on the sampler thread, and the time spent taking them
+    pub samples: u32,
+    pub sampling_time: Duration,
+}
+
+impl PeakSample {
+    /// How far the resident set grew above where it started.
+    pub fn delta_kb(&self) -> u64 {
+        self.peak_kb.saturating_sub(self.before_kb)
+    }
+}
+
+/// Polls the resident set size on a thread of its own while a benchmark runs.
+///
+/// `peak_rss_kb` is the high-water mark for the whole process lifetime, so once the
+/// process has been big it can't show what a later, smaller run peaked at. Polling
+/// can, down to the sampling interval; anything shorter-lived is only caught if it set
+/// a new lifetime high-water mark, which [`PeakSampler::finish`] also checks. Each
+/// reading is one read of /proc/self/statm, tens of microseconds every interval, and the
+/// sampler thread sleeps in between, so the benchmark's threads are left alone.
+pub struct PeakSampler {
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<PeakSample>,
+    lifetime_peak_before_kb: Option<u64>,
+}
+
+impl PeakSampler {
+    /// Starts sampling every `interval`, or `None` where the resident set size can't be read.
+    pub fn start(interval: Duration) -> Option<PeakSampler> {
+        let before_kb = rss_kb()?;
+        let lifetime_peak_before_kb = peak_rss_kb();
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = std::thread::Builder::new()
+            .name("rss-sampler".to_string())
+            .spawn(move || {
+                let mut sample = PeakSample { before_kb, peak_kb: before_kb, samples: 0, sampling_time: Duration::ZERO };
+                // A stop message, or the sampler being dropped, ends the loop
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    let start = Instant::now();
+                    if let Some(rss) = rss_kb() {
+                        sample.peak_kb = sample.peak_kb.max(rss);
+                    }
+                    sample.samples += 1;
+                    sample.sampling_time += start.elapsed();
+                }
+                sample
+            })
+            .ok()?;
+        Some(PeakSampler { stop, thread, lifetime_peak_before_kb })
+    }
+
+    /// Stops sampling and returns the peak, taking in a last reading and the lifetime
+    /// high-water mark if it rose while sampling.
+    pub fn finish(self) -> PeakSample {
+        let _ = self.stop.send(());
+        let mut sample = self.thread.join().expect("the RSS sampler thread panicked");
+        if let Some(rss) = rss_kb() {
+            sample.peak_kb = sample.peak_kb.max(rss);
+        }
+        if let (Some(before), Some(after)) = (self.lifetime_peak_before_kb, peak_rss_kb()) {
+            if after > before {
+                sample.peak_kb = sample.peak_kb.max(after);
+            }
+        }
+        sample
+    }
+}
+
+/// Runs `f` under a [`PeakSampler`] polling every `interval`, where the platform allows.
+pub fn sample_peak<T>(interval: Duration, f: impl FnOnce() -> T) -> (T, Option<PeakSample>) {
+    let sampler = PeakSampler::start(interval);
+    let result = f();
+    (result, sampler.map(PeakSampler::finish))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_available_on_linux() {
+        let rss = rss_kb().expect("statm should be readable");
+        let peak = peak_rss_kb().expect("getrusage should succeed");
+        assert!(rss > 0);
+        assert!(peak >= rss, "peak {} below current {}", peak, rss);
+    }
+
+    #[test]
+    fn allocation_shows_up_in_rss() {
+        let before = rss_kb().unwrap();
+        // Touch every page so the allocation is actually resident
+        let buf = vec![1u8; 64 * 1024 * 1024];
+        let after = rss_kb().unwrap();
+        assert!(after >= before + 32 * 1024, "before {} after {}", before, after);
+        drop(buf);
+    }
+
+    #[test]
+    fn the_sampler_catches_a_peak_freed_before_it_finishes() {
+        let ((), sample) = sample_peak(Duration::from_millis(10), || {
+            let buf = vec![1u8; 64 * 1024 * 1024];
+            std::thread::sleep(Duration::from_millis(100));
+            drop(std::hint::black_box(buf));
+        });
+        let sample = sample.unwrap();
+        assert!(sample.delta_kb() >= 32 * 1024, "{:?}", sample);
+        assert!(sample.samples > 0);
+    }
+
+    // A benchmark doing nothing but arithmetic, timed with and without the sampler at
+    // far shorter an interval than benchmarks use
+    #[test]
+    fn the_sampler_barely_costs_a_no_op_benchmark() {
+        fn no_op() -> Duration {
+            let start = Instant::now();
+            let mut x = 0u64;
+            for i in 0..20_000_000u64 {
+                x = std::hint::black_box(x.wrapping_add(i));
+            }
+            start.elapsed()
+        }
+        let fastest = |sampled: bool| {
+            (0..5)
+                .map(|_| if sampled { sample_peak(Duration::from_millis(1), no_op).0 } else { no_op() })
+                .min()
+                .unwrap()
+        };
+        let (plain, sampled) = (fastest(false), fastest(true));
+        assert!(sampled < plain * 3 / 2 + Duration::from_millis(5), "plain {:?} sampled {:?}", plain, sampled);
+
+        let ((), sample) = sample_peak(Duration::from_millis(1), || {
+            std::thread::sleep(Duration::from_millis(200));
+        });
+        let sample = sample.unwrap();
+        // Even at 1ms, reading statm takes under a tenth of the time sampled
+        assert!(sample.sampling_time < Duration::from_millis(20), "{:?}", sample);
+    }
+}
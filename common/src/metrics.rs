@@ -0,0 +1,143 @@
+//! Prometheus metrics both services expose at /metrics.
+//!
+//! [`ServiceMetrics`] holds the registry and the HTTP series every service has, named
+//! `<service>_http_requests_total`, `<service>_http_request_duration_seconds` and
+//! `<service>_errors_total`, so dashboards work against either service with only the
+//! prefix changed. Each service registers its own benchmark series alongside them.
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{core::Collector, proto, Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// AppError variant name, attached to error responses so the middleware can count them
+#[derive(Clone, Copy)]
+pub struct ErrorKind(pub &'static str);
+
+pub struct ServiceMetrics {
+    prefix: &'static str,
+    registry: Registry,
+    started: Instant,
+    pub http_requests: IntCounterVec,
+    pub http_latency: HistogramVec,
+    pub errors: IntCounterVec,
+}
+
+impl ServiceMetrics {
+    /// The HTTP series, named after `prefix` (e.g. "lattice"), in a registry of their own
+    pub fn new(prefix: &'static str) -> Self {
+        let http_requests = IntCounterVec::new(
+            Opts::new(format!("{}_http_requests_total", prefix), "HTTP requests by endpoint and status"),
+            &["method", "path", "status"],
+        )
+        .unwrap();
+        let http_latency = HistogramVec::new(
+            HistogramOpts::new(format!("{}_http_request_duration_seconds", prefix), "Handler latency by endpoint"),
+            &["method", "path"],
+        )
+        .unwrap();
+        let errors = IntCounterVec::new(
+            Opts::new(format!("{}_errors_total", prefix), "Error responses by AppError variant"),
+            &["kind"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_latency.clone())).unwrap();
+        registry.register(Box::new(errors.clone())).unwrap();
+
+        ServiceMetrics { prefix, registry, started: Instant::now(), http_requests, http_latency, errors }
+    }
+
+    /// `name` with this service's prefix, for its own series
+    pub fn name(&self, name: &str) -> String {
+        format!("{}_{}", self.prefix, name)
+    }
+
+    /// Adds one of the service's own series to what /metrics reports
+    pub fn register<C: Collector + Clone + 'static>(&self, collector: &C) {
+        self.registry.register(Box::new(collector.clone())).unwrap();
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Request and error totals since startup, read back from the Prometheus counters so
+    /// /health always agrees with /metrics
+    pub fn request_counts(&self) -> RequestCounts {
+        let mut counts = RequestCounts::default();
+        for metric in self.http_requests.collect().iter().flat_map(|family| family.get_metric()) {
+            let value = metric.get_counter().get_value() as u64;
+            counts.total += value;
+            *counts.by_endpoint.entry(label(metric, "path").to_string()).or_default() += value;
+            if label(metric, "status").starts_with(['4', '5']) {
+                counts.errors += value;
+            }
+        }
+        for metric in self.errors.collect().iter().flat_map(|family| family.get_metric()) {
+            counts.errors_by_kind.insert(label(metric, "kind").to_string(), metric.get_counter().get_value() as u64);
+        }
+        counts
+    }
+
+    /// Every registered series in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+#[derive(Default)]
+pub struct RequestCounts {
+    pub total: u64,
+    /// Keyed by route template, e.g. "/keys/{id}"
+    pub by_endpoint: BTreeMap<String, u64>,
+    /// Responses with a 4xx or 5xx status, including ones that never became an AppError
+    pub errors: u64,
+    pub errors_by_kind: BTreeMap<String, u64>,
+}
+
+fn label<'a>(metric: &'a proto::Metric, name: &str) -> &'a str {
+    metric
+        .get_label()
+        .iter()
+        .find(|pair| pair.name() == name)
+        .map_or("", |pair| pair.value())
+}
+
+/// Records request count, latency and error kind for every route, including ones added later
+pub async fn track(State(metrics): State<Arc<ServiceMetrics>>, request: Request, next: Next) -> Response {
+    // The route template rather than the raw URI keeps label cardinality bounded
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", |p| p.as_str())
+        .to_string();
+    let method = request.method().to_string();
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let status = response.status().as_u16().to_string();
+    metrics.http_requests.with_label_values(&[&method, &path, &status]).inc();
+    metrics.http_latency.with_label_values(&[&method, &path]).observe(elapsed);
+    if let Some(ErrorKind(kind)) = response.extensions().get::<ErrorKind>() {
+        metrics.errors.with_label_values(&[kind]).inc();
+    }
+    response
+}
+
+/// The text-format response /metrics serves
+pub fn exposition(metrics: &ServiceMetrics) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], metrics.render())
+}
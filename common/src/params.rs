@@ -0,0 +1,182 @@
+//! Param set and operation names as clients may spell them. Each has one canonical
+//! snake_case name, which is what responses echo and what the services dispatch on, plus
+//! aliases for the spellings other tools use.
+//!
+//! Matching ignores case and the separators `-`, `_`, `+`, `.` and spaces, so "ML-KEM-768",
+//! "mlkem768" and "ml_kem_768" are the same param set. On top of that:
+//!
+//! | canonical | also accepted |
+//! |---|---|
+//! | ml_kem_512 / 768 / 1024 | kyber512 / 768 / 1024 |
+//! | x_wing | x_wing_kem |
+//! | ml_dsa_44 / 65 / 87 | dilithium2 / 3 / 5 |
+//! | slh_dsa_sha2_128s / 128f | sphincs+_sha2_128s / 128f |
+//! | keygen | key_gen, keypair, generate |
+//! | encaps | encap, encapsulate |
+//! | decaps | decap, decapsulate |
+//! | full_handshake | handshake |
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// "ML-KEM-768" and "ml_kem_768" both become "mlkem768"
+fn compact(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '-' | '_' | '+' | '.' | ' '))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+macro_rules! names {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $what:literal, $example:literal {
+            $($variant:ident => $canonical:literal $(| $alias:literal)*,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum $name {
+            $($variant,)+
+        }
+
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant,)+];
+
+            /// The canonical snake_case name
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($name::$variant => $canonical,)+
+                }
+            }
+
+            fn aliases(self) -> &'static [&'static str] {
+                match self {
+                    $($name::$variant => &[$($alias),*],)+
+                }
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, String> {
+                let wanted = compact(s);
+                $name::ALL
+                    .iter()
+                    .copied()
+                    .find(|n| compact(n.name()) == wanted || n.aliases().iter().any(|a| compact(a) == wanted))
+                    .ok_or_else(|| {
+                        let names: Vec<&str> = $name::ALL.iter().map(|n| n.name()).collect();
+                        format!(
+                            "unknown {} '{}', expected one of {} (aliases such as {} are accepted too, in any case)",
+                            $what, s, names.join(", "), $example
+                        )
+                    })
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.name())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+names! {
+    /// Every param set either service benchmarks
+    ParamSet, "param_set", "ML-KEM-768 or kyber768" {
+        MlKem512 => "ml_kem_512" | "kyber512",
+        MlKem768 => "ml_kem_768" | "kyber768",
+        MlKem1024 => "ml_kem_1024" | "kyber1024",
+        XWing => "x_wing" | "x_wing_kem",
+        X25519 => "x25519",
+        Rsa2048 => "rsa_2048",
+        MlDsa44 => "ml_dsa_44" | "dilithium2",
+        MlDsa65 => "ml_dsa_65" | "dilithium3",
+        MlDsa87 => "ml_dsa_87" | "dilithium5",
+        SlhDsaSha2_128s => "slh_dsa_sha2_128s" | "sphincs+_sha2_128s",
+        SlhDsaSha2_128f => "slh_dsa_sha2_128f" | "sphincs+_sha2_128f",
+    }
+}
+
+names! {
+    /// Every benchmark operation; which ones a param set supports is up to the service
+    Operation, "operation", "encapsulate or Full-Handshake" {
+        Keygen => "keygen" | "key_gen" | "keypair" | "generate",
+        Encaps => "encaps" | "encap" | "encapsulate",
+        Decaps => "decaps" | "decap" | "decapsulate",
+        EncapsDecaps => "encaps_decaps",
+        FullHandshake => "full_handshake" | "handshake",
+        DecapsReject => "decaps_reject",
+        CtCompare => "ct_compare",
+        SerializeKeys => "serialize_keys",
+        DeserializeKeys => "deserialize_keys",
+        KemDemEncrypt => "kem_dem_encrypt",
+        KemDemDecrypt => "kem_dem_decrypt",
+        Mixed => "mixed",
+        Sign => "sign",
+        Verify => "verify",
+        SignVerify => "sign_verify",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spellings_from_other_tools_normalize_to_the_canonical_name() {
+        for spelling in ["ml_kem_768", "ML-KEM-768", "mlkem768", "Kyber768", "KYBER-768"] {
+            assert_eq!(spelling.parse::<ParamSet>(), Ok(ParamSet::MlKem768), "{}", spelling);
+        }
+        assert_eq!("SPHINCS+-SHA2-128f".parse::<ParamSet>(), Ok(ParamSet::SlhDsaSha2_128f));
+        assert_eq!("Dilithium3".parse::<ParamSet>(), Ok(ParamSet::MlDsa65));
+        assert_eq!("Encapsulate".parse::<Operation>(), Ok(Operation::Encaps));
+        assert_eq!("full-handshake".parse::<Operation>(), Ok(Operation::FullHandshake));
+    }
+
+    #[test]
+    fn canonical_names_round_trip_and_stay_unambiguous() {
+        for &p in ParamSet::ALL {
+            assert_eq!(p.name().parse::<ParamSet>(), Ok(p));
+            assert_eq!(serde_json::to_value(p).unwrap(), p.name());
+        }
+        for &o in Operation::ALL {
+            assert_eq!(serde_json::from_value::<Operation>(o.name().into()).unwrap(), o);
+        }
+        // No alias may collide with another variant's name or aliases
+        let compacted: Vec<String> = ParamSet::ALL
+            .iter()
+            .flat_map(|p| std::iter::once(p.name()).chain(p.aliases().iter().copied()))
+            .map(compact)
+            .collect();
+        let mut unique = compacted.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), compacted.len());
+    }
+
+    #[test]
+    fn unknown_names_list_the_canonical_ones() {
+        let err = "ml_kem_767".parse::<ParamSet>().unwrap_err();
+        assert!(err.starts_with("unknown param_set 'ml_kem_767', expected one of ml_kem_512, ml_kem_768"), "{}", err);
+        assert!(err.contains("aliases"), "{}", err);
+        assert!(serde_json::from_str::<Operation>("\"encrypt\"").is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! Field-level errors for request bodies that parse but don't match the request type, so
+//! a typo'd field name or a quoted number is reported against the field that caused it.
+
+use serde::{de::DeserializeOwned, Serialize};
+use utoipa::ToSchema;
+
+/// JSON body of a 422 for a request with an unknown, missing or wrongly-typed field
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct FieldError {
+    pub error: String,
+    /// Dotted path to the offending field, e.g. "iterations" or "weights.sign"
+    pub field: String,
+    /// What the field accepts: a type such as "u32", the allowed values, or for an unknown
+    /// field the valid field names. Null for a missing field.
+    pub expected: Option<String>,
+}
+
+/// Deserializes `T` from an already-parsed body, naming the field it failed on
+pub fn from_value<T: DeserializeOwned>(value: serde_json::Value) -> Result<T, FieldError> {
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let path = e.path().to_string();
+        let message = e.into_inner().to_string();
+        field_error(&path, message)
+    })
+}
+
+// A missing field is reported against the enclosing object, so its name comes from the
+// message; every other error's path already ends at the field itself
+fn field_error(path: &str, message: String) -> FieldError {
+    let missing = message.strip_prefix("missing field `").and_then(|rest| rest.split('`').next());
+    let field = match (missing, path) {
+        (Some(name), ".") => name.to_string(),
+        (Some(name), path) => format!("{}.{}", path, name),
+        (None, path) => path.to_string(),
+    };
+    let expected = message
+        .split_once(", expected ")
+        .map(|(_, expected)| expected.to_string());
+    FieldError { error: format!("Invalid field '{}': {}", field, message), field, expected }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Request {
+        operation: String,
+        #[serde(default)]
+        iterations: Option<u32>,
+        #[serde(default)]
+        inner: Option<Inner>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Inner {
+        #[serde(default)]
+        keygen: u32,
+    }
+
+    fn error(value: serde_json::Value) -> FieldError {
+        from_value::<Request>(value).unwrap_err()
+    }
+
+    #[test]
+    fn unknown_fields_name_the_field_and_the_valid_ones() {
+        let e = error(json!({ "operaton": "keygen" }));
+        assert_eq!(e.field, "operaton");
+        assert_eq!(e.expected.as_deref(), Some("one of `operation`, `iterations`, `inner`"));
+
+        let e = error(json!({ "operation": "keygen", "inner": { "sign": 1 } }));
+        assert_eq!(e.field, "inner.sign");
+    }
+
+    #[test]
+    fn missing_nested_fields_are_prefixed_with_their_parent() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Outer {
+            inner: Request,
+        }
+        let e = from_value::<Outer>(json!({ "inner": {} })).unwrap_err();
+        assert_eq!(e.field, "inner.operation");
+    }
+
+    #[test]
+    fn wrong_types_name_the_expected_type() {
+        let e = error(json!({ "operation": "keygen", "iterations": "100" }));
+        assert_eq!(e.field, "iterations");
+        assert_eq!(e.expected.as_deref(), Some("u32"));
+        assert!(e.error.contains("invalid type: string \"100\""), "{}", e.error);
+    }
+
+    #[test]
+    fn missing_fields_have_no_expected_value() {
+        let e = error(json!({ "iterations": 5 }));
+        assert_eq!(e.field, "operation");
+        assert_eq!(e.expected, None);
+        assert_eq!(e.error, "Invalid field 'operation': missing field `operation`");
+    }
+}
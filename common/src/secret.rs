@@ -0,0 +1,83 @@
+//! Secret byte buffers that are scrubbed when they go out of scope, and comparing them
+//! without leaking where they differ.
+
+use std::fmt;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+/// Owns secret bytes (shared secrets, encoded decapsulation keys) and zeroizes them on
+/// drop. Debug output is redacted so secrets can't leak through logs.
+pub struct SecretBox<T: AsMut<[u8]>>(T);
+
+impl<T: AsMut<[u8]>> SecretBox<T> {
+    pub fn new(secret: T) -> Self {
+        SecretBox(secret)
+    }
+
+    pub fn expose_secret(&self) -> &T {
+        &self.0
+    }
+
+    /// For filling the secret in place, so it never exists outside the box
+    pub fn expose_secret_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: AsMut<[u8]>> Drop for SecretBox<T> {
+    fn drop(&mut self) {
+        self.0.as_mut().zeroize();
+    }
+}
+
+impl<T: AsMut<[u8]>> fmt::Debug for SecretBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretBox([REDACTED])")
+    }
+}
+
+/// Whether two secrets are equal, in time that depends only on their lengths. Use this
+/// rather than `==`, which returns at the first differing byte.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Borrows the caller's buffer so the test can look at it after the box is dropped
+    struct Borrowed<'a>(&'a mut [u8]);
+
+    impl AsMut<[u8]> for Borrowed<'_> {
+        fn as_mut(&mut self) -> &mut [u8] {
+            self.0
+        }
+    }
+
+    #[test]
+    fn bytes_are_zeroized_on_drop() {
+        let mut buf = [0xAAu8; 32];
+        let secret = SecretBox::new(Borrowed(&mut buf));
+        assert!(secret.expose_secret().0.iter().all(|&b| b == 0xAA));
+        drop(secret);
+        assert_eq!(buf, [0u8; 32]);
+    }
+
+    #[test]
+    fn ct_eq_compares_contents_and_lengths() {
+        let secret = [0x5Cu8; 32];
+        assert!(ct_eq(&secret, &[0x5C; 32]));
+        let mut last_differs = secret;
+        last_differs[31] ^= 1;
+        assert!(!ct_eq(&secret, &last_differs));
+        assert!(!ct_eq(&secret, &secret[..31]));
+        assert!(ct_eq(&[], &[]));
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let secret = SecretBox::new(vec![1u8, 2, 3]);
+        assert_eq!(format!("{:?}", secret), "SecretBox([REDACTED])");
+    }
+}
@@ -0,0 +1,258 @@
+//! Measurement corrections and sample statistics shared by both services' benchmark loops.
+
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Empty timed regions measured by a startup calibration
+pub const TIMER_CALIBRATION_ITERATIONS: u32 = 5_000;
+
+/// What timing a region costs when there is nothing in it: the tail of `Instant::now()` and
+/// the head of `elapsed()`, which every benchmark sample carries on top of the operation.
+/// The median over `iterations` regions, so a preemption during calibration doesn't skew it.
+pub fn calibrate_timer_overhead(iterations: u32) -> Duration {
+    let mut samples: Vec<Duration> = (0..iterations.max(1))
+        .map(|_| {
+            let start = Instant::now();
+            black_box(());
+            start.elapsed()
+        })
+        .collect();
+    samples.sort_unstable();
+    samples[samples.len() / 2]
+}
+
+/// A sample with the timer overhead taken off, floored at zero since the overhead is a
+/// median and a fast sample can come in under it
+pub fn subtract_overhead(elapsed: Duration, overhead: Duration) -> Duration {
+    elapsed.saturating_sub(overhead)
+}
+
+// ============ Sample Statistics ============
+//
+// What both services report about a benchmark's samples. All values are in the unit of
+// the input timings, microseconds in both services.
+
+pub struct Stats {
+    pub avg: f64,
+    pub min: f64,
+    pub max: f64,
+    pub p95: f64,
+    pub stddev: f64,
+    pub median: f64,
+    pub throughput: f64,
+    /// 95% confidence interval for the mean
+    pub ci95_low: f64,
+    pub ci95_high: f64,
+}
+
+pub fn compute_stats(timings: &[u128]) -> Stats {
+    if timings.is_empty() {
+        return Stats {
+            avg: 0.0,
+            min: 0.0,
+            max: 0.0,
+            p95: 0.0,
+            stddev: 0.0,
+            median: 0.0,
+            throughput: 0.0,
+            ci95_low: 0.0,
+            ci95_high: 0.0,
+        };
+    }
+
+    let sum: u128 = timings.iter().sum();
+    let avg = sum as f64 / timings.len() as f64;
+    let min = *timings.iter().min().unwrap() as f64;
+    let max = *timings.iter().max().unwrap() as f64;
+    let p95 = compute_percentile(timings, 0.95);
+    let stddev = compute_stddev(timings, avg);
+    let median = compute_median(timings);
+    let throughput = if avg > 0.0 { 1_000_000.0 / avg } else { 0.0 };
+    let (ci95_low, ci95_high) = ci95(avg, stddev, timings.len());
+
+    Stats { avg, min, max, p95, stddev, median, throughput, ci95_low, ci95_high }
+}
+
+// Two-sided 97.5th percentile of Student's t for 1 to 30 degrees of freedom
+const T_975: [f64; 30] = [
+    12.7062, 4.3027, 3.1824, 2.7764, 2.5706, 2.4469, 2.3646, 2.3060, 2.2622, 2.2281,
+    2.2010, 2.1788, 2.1604, 2.1448, 2.1314, 2.1199, 2.1098, 2.1009, 2.0930, 2.0860,
+    2.0796, 2.0739, 2.0687, 2.0639, 2.0595, 2.0555, 2.0518, 2.0484, 2.0452, 2.0423,
+];
+const Z_975: f64 = 1.96;
+
+/// 95% confidence interval for the mean of `n` samples with the given sample stddev.
+/// Up to 31 samples use the t-distribution, beyond that the normal approximation; a
+/// single sample gives a zero-width interval at the mean, since there is no spread to go on.
+pub fn ci95(avg: f64, stddev: f64, n: usize) -> (f64, f64) {
+    if n < 2 {
+        return (avg, avg);
+    }
+    let critical = T_975.get(n - 2).copied().unwrap_or(Z_975);
+    let half_width = critical * stddev / (n as f64).sqrt();
+    (avg - half_width, avg + half_width)
+}
+
+// Nearest-rank percentile: the smallest sample with at least `percentile` of the data at or below it
+fn compute_percentile(timings: &[u128], percentile: f64) -> f64 {
+    let mut sorted = timings.to_vec();
+    sorted.sort_unstable();
+    nearest_rank(&sorted, percentile)
+}
+
+// Products like 0.07 * 100 land just above the integer they stand for; without the slack
+// they would round up a whole rank
+const RANK_EPSILON: f64 = 1e-9;
+
+fn nearest_rank(sorted: &[u128], percentile: f64) -> f64 {
+    let rank = (percentile * sorted.len() as f64 - RANK_EPSILON).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1] as f64
+}
+
+/// Percentiles for each requested value in (0, 1], keyed by the value itself
+pub fn compute_percentiles(timings: &[u128], percentiles: &[f64]) -> BTreeMap<String, f64> {
+    let mut sorted = timings.to_vec();
+    sorted.sort_unstable();
+
+    percentiles
+        .iter()
+        .map(|&p| {
+            let value = if sorted.is_empty() { 0.0 } else { nearest_rank(&sorted, p) };
+            (p.to_string(), value)
+        })
+        .collect()
+}
+
+// Sample standard deviation (n - 1); a single sample has no spread
+fn compute_stddev(timings: &[u128], avg: f64) -> f64 {
+    if timings.len() < 2 {
+        return 0.0;
+    }
+    let sum_sq: f64 = timings.iter().map(|&t| (t as f64 - avg).powi(2)).sum();
+    (sum_sq / (timings.len() - 1) as f64).sqrt()
+}
+
+// Middle value, or the mean of the two middle values for an even count
+fn compute_median(timings: &[u128]) -> f64 {
+    let mut sorted = timings.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) as f64 / 2.0
+    } else {
+        sorted[mid] as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer_overhead_is_positive_and_small() {
+        let overhead = calibrate_timer_overhead(TIMER_CALIBRATION_ITERATIONS);
+        assert!(overhead > Duration::ZERO);
+        assert!(overhead < Duration::from_micros(10), "{:?}", overhead);
+    }
+
+    #[test]
+    fn subtraction_never_goes_negative() {
+        let overhead = Duration::from_nanos(40);
+        assert_eq!(subtract_overhead(Duration::from_nanos(1_040), overhead), Duration::from_nanos(1_000));
+        assert_eq!(subtract_overhead(Duration::from_nanos(25), overhead), Duration::ZERO);
+        assert_eq!(subtract_overhead(Duration::ZERO, overhead), Duration::ZERO);
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn stats_match_hand_computed_fixture() {
+        // mean 5, squared deviations sum to 32, sample variance 32/7
+        let stats = compute_stats(&[2, 4, 4, 4, 5, 5, 7, 9]);
+        assert_close(stats.avg, 5.0);
+        assert_close(stats.min, 2.0);
+        assert_close(stats.max, 9.0);
+        assert_close(stats.median, 4.5);
+        assert_close(stats.stddev, (32.0f64 / 7.0).sqrt());
+        // t(7) = 2.3646, standard error sqrt(32/7)/sqrt(8) = sqrt(4/7)
+        let half_width = 2.3646 * (4.0f64 / 7.0).sqrt();
+        assert_close(stats.ci95_low, 5.0 - half_width);
+        assert_close(stats.ci95_high, 5.0 + half_width);
+    }
+
+    #[test]
+    fn ci95_switches_from_t_to_normal_after_thirty_degrees_of_freedom() {
+        // Standard error 10/sqrt(n) is 2 for n = 25 and 1 for n = 100
+        let (low, high) = ci95(100.0, 10.0, 25);
+        assert_close(low, 100.0 - 2.0639 * 2.0);
+        assert_close(high, 100.0 + 2.0639 * 2.0);
+        let (low, high) = ci95(100.0, 10.0, 100);
+        assert_close(low, 100.0 - 1.96);
+        assert_close(high, 100.0 + 1.96);
+
+        // 31 samples is the last t value, 32 the first normal one
+        assert_close(ci95(0.0, 1.0, 31).1, 2.0423 / 31f64.sqrt());
+        assert_close(ci95(0.0, 1.0, 32).1, 1.96 / 32f64.sqrt());
+        assert_eq!(ci95(42.0, 0.0, 1), (42.0, 42.0));
+    }
+
+    #[test]
+    fn single_sample_has_zero_spread() {
+        let stats = compute_stats(&[42]);
+        assert_close(stats.avg, 42.0);
+        assert_close(stats.median, 42.0);
+        assert_close(stats.stddev, 0.0);
+        assert_eq!((stats.ci95_low, stats.ci95_high), (42.0, 42.0));
+    }
+
+    #[test]
+    fn two_samples_use_the_midpoint_median() {
+        let stats = compute_stats(&[10, 20]);
+        assert_close(stats.median, 15.0);
+        assert_close(stats.stddev, 50.0f64.sqrt());
+        // One degree of freedom: t = 12.7062, standard error sqrt(50)/sqrt(2) = 5
+        assert_close(stats.ci95_high - stats.ci95_low, 2.0 * 12.7062 * 5.0);
+    }
+
+    #[test]
+    fn nearest_rank_percentiles_for_small_samples() {
+        let timings = [15, 20, 35, 40, 50];
+        assert_close(compute_percentile(&timings, 0.05), 15.0);
+        assert_close(compute_percentile(&timings, 0.3), 20.0);
+        assert_close(compute_percentile(&timings, 0.4), 20.0);
+        assert_close(compute_percentile(&timings, 0.5), 35.0);
+        assert_close(compute_percentile(&timings, 1.0), 50.0);
+
+        // With two samples p50 is the lower one and anything above is the upper one
+        assert_close(compute_percentile(&[10, 20], 0.5), 10.0);
+        assert_close(compute_percentile(&[10, 20], 0.95), 20.0);
+        assert_close(compute_percentile(&[7], 0.999), 7.0);
+
+        // p * n is 7.000000000000001, 14.000000000000002 and 28.000000000000004 in floating point
+        let hundred: Vec<u128> = (1..=100).collect();
+        assert_close(compute_percentile(&hundred, 0.07), 7.0);
+        assert_close(compute_percentile(&hundred, 0.14), 14.0);
+        assert_close(compute_percentile(&hundred, 0.28), 28.0);
+        assert_close(compute_percentile(&hundred, 0.071), 8.0);
+    }
+
+    #[test]
+    fn percentile_map_is_keyed_by_requested_value() {
+        let timings: Vec<u128> = (1..=1000).collect();
+        let map = compute_percentiles(&timings, &[0.5, 0.99, 0.999]);
+        assert_close(map["0.5"], 500.0);
+        assert_close(map["0.99"], 990.0);
+        assert_close(map["0.999"], 999.0);
+    }
+
+    #[test]
+    fn empty_input_is_all_zero() {
+        let stats = compute_stats(&[]);
+        assert_close(stats.avg, 0.0);
+        assert_close(stats.stddev, 0.0);
+        assert_close(stats.median, 0.0);
+    }
+}
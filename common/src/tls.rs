@@ -0,0 +1,119 @@
+//! HTTPS serving for both services, via axum-server and rustls.
+
+use axum::Router;
+use axum_server::{
+    tls_rustls::{RustlsAcceptor, RustlsConfig},
+    Handle,
+};
+use std::future::Future;
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::http::HttpTuning;
+use crate::limits::ConnectionLimit;
+
+/// Reads a PEM certificate chain and private key.
+pub async fn load(cert: &Path, key: &Path) -> std::io::Result<RustlsConfig> {
+    // Only ring is compiled in, but installing it explicitly avoids ambiguity if another
+    // dependency ever enables a second provider
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(cert, key).await
+}
+
+/// Re-reads the certificate and key on SIGHUP so rotated certificates take effect without a
+/// restart. A failed reload keeps serving the previous certificate.
+#[cfg(unix)]
+pub fn reload_on_sighup(config: RustlsConfig, cert: PathBuf, key: PathBuf) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let mut hangup = signal(SignalKind::hangup()).expect("failed to install SIGHUP handler");
+        while hangup.recv().await.is_some() {
+            match config.reload_from_pem_file(&cert, &key).await {
+                Ok(()) => tracing::info!(cert = %cert.display(), "reloaded TLS certificate"),
+                Err(e) => tracing::error!(error = %e, "TLS reload failed, keeping the previous certificate"),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn reload_on_sighup(_config: RustlsConfig, _cert: PathBuf, _key: PathBuf) {}
+
+/// HTTPS counterpart of `http::serve` on `listener`: once `shutdown` resolves, stop
+/// accepting connections and give in-flight requests `drain_timeout` to finish.
+pub async fn serve(
+    listener: TcpListener,
+    router: Router,
+    config: RustlsConfig,
+    tuning: HttpTuning,
+    connections: ConnectionLimit,
+    drain_timeout: Duration,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let (shutdown, _) = crate::http::announce(shutdown, drain_timeout);
+    let handle = Handle::new();
+    let draining = handle.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        draining.graceful_shutdown(Some(drain_timeout));
+    });
+
+    // The rustls config offers h2 and http/1.1 over ALPN
+    let mut server = axum_server::from_tcp(listener)
+        .acceptor(RustlsAcceptor::new(config).acceptor(connections))
+        .handle(handle);
+    tuning.configure(server.http_builder());
+    server.serve(router.into_make_service_with_connect_info::<SocketAddr>()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata").join(name)
+    }
+
+    #[tokio::test]
+    async fn missing_files_are_an_error() {
+        assert!(load(&fixture("absent.pem"), &fixture("server.key")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn serves_https_to_a_client_trusting_the_ca() {
+        let config = load(&fixture("server.pem"), &fixture("server.key")).await.unwrap();
+        let router = Router::new().route("/health", get(|| async { "ok" }));
+
+        let (listener, addr) = crate::listen::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(listener, router, config, HttpTuning::default(), ConnectionLimit::default(), Duration::from_secs(1), async {
+            let _ = stopped.await;
+        }));
+
+        let ca = reqwest::Certificate::from_pem(&std::fs::read(fixture("ca.pem")).unwrap()).unwrap();
+        let client = reqwest::Client::builder().add_root_certificate(ca).build().unwrap();
+        let url = format!("https://localhost:{}/health", addr.port());
+        let mut body = None;
+        for _ in 0..50 {
+            match client.get(&url).send().await {
+                Ok(resp) => {
+                    body = Some(resp.text().await.unwrap());
+                    break;
+                }
+                // The server task may not be listening yet
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        }
+        assert_eq!(body.as_deref(), Some("ok"));
+
+        // Plain HTTP to the same port doesn't get through
+        let plain = reqwest::get(format!("http://127.0.0.1:{}/health", addr.port())).await;
+        assert!(plain.map_or(true, |r| !r.status().is_success()));
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+//! Unix domain socket listening, for running a service as a sidecar without a TCP port.
+
+#[cfg(unix)]
+use std::{
+    io,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+/// Owner read/write only, so just the service's own user can connect
+pub const DEFAULT_MODE: u32 = 0o600;
+
+/// Parses an octal permission mode such as "600" or "0660", for use as a clap value parser
+pub fn parse_mode(s: &str) -> Result<u32, String> {
+    u32::from_str_radix(s, 8)
+        .ok()
+        .filter(|&mode| mode <= 0o777)
+        .ok_or_else(|| format!("'{}' is not an octal permission mode such as 600 or 660", s))
+}
+
+/// The socket file behind a listener; removed when dropped, i.e. once the server has shut
+/// down cleanly
+#[cfg(unix)]
+pub struct SocketFile {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::warn!(path = %self.path.display(), error = %e, "failed to remove socket file");
+        }
+    }
+}
+
+/// Listens on `path` with permissions `mode`. A socket left behind by an unclean exit is
+/// replaced; any other file at `path` is an error rather than something to delete.
+#[cfg(unix)]
+pub fn bind(path: &Path, mode: u32) -> io::Result<(UnixListener, SocketFile)> {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let listener = UnixListener::bind(path)?;
+    let socket = SocketFile { path: path.to_path_buf() };
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok((listener, socket))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn temp_socket(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("common-uds-{}-{}.sock", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn modes_are_octal() {
+        assert_eq!(parse_mode("600"), Ok(0o600));
+        assert_eq!(parse_mode("0660"), Ok(0o660));
+        assert!(parse_mode("800").is_err());
+        assert!(parse_mode("7777").is_err());
+    }
+
+    #[tokio::test]
+    async fn socket_gets_its_mode_and_is_removed_on_drop() {
+        let path = temp_socket("mode");
+        let (listener, socket) = bind(&path, DEFAULT_MODE).unwrap();
+        let meta = std::fs::metadata(&path).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+
+        drop((listener, socket));
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn stale_sockets_are_replaced_but_other_files_are_not() {
+        let path = temp_socket("stale");
+        // Leaking the guard leaves the socket behind, as a crash would
+        let (listener, socket) = bind(&path, DEFAULT_MODE).unwrap();
+        drop(listener);
+        std::mem::forget(socket);
+        let (_listener, _socket) = bind(&path, 0o660).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o660);
+
+        let file = temp_socket("regular");
+        std::fs::write(&file, b"keep me").unwrap();
+        assert_eq!(bind(&file, DEFAULT_MODE).err().unwrap().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read(&file).unwrap(), b"keep me");
+        std::fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+// Embeds build details for GET /version. Every lookup falls back to "unknown" so a build
+// outside a git checkout, or without a Cargo.lock, still succeeds.
+
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let workspace = Path::new(&manifest_dir).parent().unwrap();
+
+    println!("cargo:rustc-env=LATTICE_GIT_COMMIT={}", git_commit(workspace));
+    println!("cargo:rustc-env=LATTICE_BUILD_PROFILE={}", std::env::var("PROFILE").unwrap_or_else(|_| "unknown".into()));
+    println!("cargo:rustc-env=LATTICE_RUSTC_VERSION={}", rustc_version());
+    println!("cargo:rustc-env=LATTICE_ML_KEM_VERSION={}", locked_version(&workspace.join("Cargo.lock"), "ml-kem"));
+    println!("cargo:rustc-env=LATTICE_TARGET={}", std::env::var("TARGET").unwrap_or_else(|_| "unknown".into()));
+    println!("cargo:rustc-env=LATTICE_TARGET_CPU={}", target_cpu().unwrap_or_else(|| "unknown".into()));
+}
+
+// The -C target-cpu the crate is compiled with, from RUSTFLAGS or build.rustflags
+fn target_cpu() -> Option<String> {
+    let flags = std::env::var("CARGO_ENCODED_RUSTFLAGS").ok()?;
+    let mut flags = flags.split('\x1f');
+    let mut cpu = None;
+    while let Some(flag) = flags.next() {
+        let codegen = match flag {
+            "-C" | "--codegen" => flags.next(),
+            _ => flag.strip_prefix("-C").or_else(|| flag.strip_prefix("--codegen=")),
+        };
+        // A later flag overrides an earlier one, as it does for rustc
+        if let Some(value) = codegen.and_then(|c| c.strip_prefix("target-cpu=")) {
+            cpu = Some(value.to_string());
+        }
+    }
+    cpu
+}
+
+fn command_output(program: &str, args: &[&str], dir: &Path) -> Option<String> {
+    let output = Command::new(program).args(args).current_dir(dir).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn git_commit(workspace: &Path) -> String {
+    // Rebuild when HEAD moves: either the branch changes or the branch's ref is updated
+    let git_dir = workspace.join(".git");
+    println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+    if let Ok(head) = std::fs::read_to_string(git_dir.join("HEAD")) {
+        if let Some(reference) = head.trim().strip_prefix("ref: ") {
+            println!("cargo:rerun-if-changed={}", git_dir.join(reference).display());
+        }
+    }
+
+    match command_output("git", &["rev-parse", "HEAD"], workspace) {
+        Some(commit) => {
+            let dirty = command_output("git", &["status", "--porcelain", "--untracked-files=no"], workspace).is_some();
+            if dirty { format!("{}-dirty", commit) } else { commit }
+        }
+        None => "unknown".to_string(),
+    }
+}
+
+fn rustc_version() -> String {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    command_output(&rustc, &["--version"], Path::new(".")).unwrap_or_else(|| "unknown".to_string())
+}
+
+fn locked_version(lock_file: &Path, package: &str) -> String {
+    println!("cargo:rerun-if-changed={}", lock_file.display());
+    let Ok(lock) = std::fs::read_to_string(lock_file) else {
+        return "unknown".to_string();
+    };
+    let name_line = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line == name_line {
+            if let Some(version) = lines.next().and_then(|l| l.strip_prefix("version = ")) {
+                return version.trim_matches('"').to_string();
+            }
+        }
+    }
+    "unknown".to_string()
+}
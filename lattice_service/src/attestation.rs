@@ -0,0 +1,130 @@
+use axum::{extract::State, Json};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::{
+    attest::{self, ResponseSigner, SEED_BYTES},
+    secret::SecretBox,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+// ============ Response Signing ============
+//
+// Every kem_bench response carries a detached signature by this instance's key, so an audit
+// can show a results file came from the service under test and wasn't edited afterwards.
+// The signed bytes follow common::attest's canonical JSON rules. Without --signing-key the
+// key is new on every start, which still ties results to one process but not across restarts.
+
+pub struct Attestation {
+    signer: ResponseSigner,
+    public_key: Vec<u8>,
+}
+
+impl Attestation {
+    /// Derives the key from the hex seed in `seed_file`, or from a random seed without one
+    pub fn load(seed_file: Option<&Path>) -> Result<Self, String> {
+        let mut seed = SecretBox::new([0u8; SEED_BYTES]);
+        match seed_file {
+            Some(path) => {
+                let contents = SecretBox::new(
+                    std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?.into_bytes(),
+                );
+                let hex = std::str::from_utf8(contents.expose_secret()).map_err(|_| format!("{} is not hex", path.display()))?;
+                hex::decode_to_slice(hex.trim(), seed.expose_secret_mut())
+                    .map_err(|_| format!("{} must hold {} hex-encoded bytes", path.display(), SEED_BYTES))?;
+            }
+            None => OsRng.fill_bytes(seed.expose_secret_mut()),
+        }
+        let signer = ResponseSigner::from_seed(seed.expose_secret());
+        let public_key = signer.public_key();
+        let attestation = Attestation { signer, public_key };
+        tracing::info!(fingerprint = %attestation.fingerprint(), persistent = seed_file.is_some(), "response signing key ready");
+        Ok(attestation)
+    }
+
+    pub fn sign(&self, response: &impl Serialize) -> String {
+        self.signer.sign(response)
+    }
+
+    // What an audit records to pin the key; the key itself is nearly 2 KB
+    fn fingerprint(&self) -> String {
+        hex::encode(Sha256::digest(&self.public_key))
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SigningKeyResponse {
+    /// Always "ml_dsa_65"
+    algorithm: &'static str,
+    /// Base64 encoded verifying key
+    public_key: String,
+    /// Hex SHA-256 of the verifying key
+    fingerprint: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/signing_key",
+    responses((
+        status = 200,
+        description = "Public key for the signature field of benchmark responses. A signature covers the response's JSON without its signature field, with object keys sorted and no whitespace",
+        body = SigningKeyResponse,
+    ))
+)]
+pub async fn signing_key(State(attestation): State<Arc<Attestation>>) -> Json<SigningKeyResponse> {
+    Json(SigningKeyResponse {
+        algorithm: attest::ALGORITHM,
+        public_key: STANDARD.encode(&attestation.public_key),
+        fingerprint: attestation.fingerprint(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app, config::Config, AppState};
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    async fn get_json(router: &axum::Router, request: Request<Body>) -> serde_json::Value {
+        let resp = router.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn kem_bench_responses_verify_against_the_published_key() {
+        let router = app(AppState::new(Config::default()));
+        let key = get_json(&router, Request::get("/signing_key").body(Body::empty()).unwrap()).await;
+        assert_eq!(key["algorithm"], "ml_dsa_65");
+        let public_key = STANDARD.decode(key["public_key"].as_str().unwrap()).unwrap();
+
+        let bench = Request::post("/kem_bench")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"param_set":"ml_kem_512","operation":"keygen","iterations":5}"#))
+            .unwrap();
+        let mut resp = get_json(&router, bench).await;
+        assert!(resp["signature"].is_string());
+        assert_eq!(attest::verify(&public_key, &resp), Ok(()));
+
+        resp["avg_us"] = serde_json::json!(resp["avg_us"].as_f64().unwrap() / 2.0);
+        assert!(attest::verify(&public_key, &resp).is_err());
+    }
+
+    #[test]
+    fn seed_files_give_the_same_key_every_start() {
+        let path = std::env::temp_dir().join(format!("lattice-signing-seed-{}", std::process::id()));
+        std::fs::write(&path, format!("{}\n", "0f".repeat(SEED_BYTES))).unwrap();
+        let first = Attestation::load(Some(&path)).unwrap();
+        let second = Attestation::load(Some(&path)).unwrap();
+        assert_eq!(first.fingerprint(), second.fingerprint());
+        assert_ne!(Attestation::load(None).unwrap().fingerprint(), first.fingerprint());
+
+        std::fs::write(&path, "0f0f").unwrap();
+        assert!(Attestation::load(Some(&path)).err().unwrap().contains("32 hex-encoded bytes"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
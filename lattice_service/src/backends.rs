@@ -0,0 +1,146 @@
+use std::fmt::Debug;
+use std::time::Instant;
+use rand::{CryptoRng, RngCore};
+
+use crate::{budget::Budget, AppError, BenchOutput, HandshakePhases, KeyMode, SecretCheck, SharedSecretMismatch};
+
+// ============ Non-KemCore Backends ============
+//
+// Hybrid and classical baselines don't fit ml-kem's KemCore, so they implement this
+// smaller trait and share one set of generic benchmark loops.
+
+pub trait KemBackend {
+    type DecapsulationKey;
+    type EncapsulationKey;
+    type Ciphertext;
+    type SharedSecret: PartialEq + Debug + AsRef<[u8]>;
+
+    fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> (Self::DecapsulationKey, Self::EncapsulationKey);
+
+    fn encapsulate<R: RngCore + CryptoRng>(
+        ek: &Self::EncapsulationKey,
+        rng: &mut R,
+    ) -> (Self::Ciphertext, Self::SharedSecret);
+
+    fn decapsulate(dk: &Self::DecapsulationKey, ct: &Self::Ciphertext) -> Self::SharedSecret;
+
+    fn encapsulation_key_bytes(ek: &Self::EncapsulationKey) -> Vec<u8>;
+
+    fn decapsulation_key_bytes(dk: &Self::DecapsulationKey) -> Vec<u8>;
+
+    fn ciphertext_bytes(ct: &Self::Ciphertext) -> Vec<u8>;
+}
+
+fn bench_keygen<B: KemBackend, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128> {
+    budget.measure(|| {
+        let _ = B::generate(rng);
+    })
+}
+
+fn bench_encaps<B: KemBackend, R: RngCore + CryptoRng>(budget: Budget, key_mode: KeyMode, rng: &mut R) -> Vec<u128> {
+    match key_mode {
+        KeyMode::Single => {
+            let (_dk, ek) = B::generate(rng);
+            budget.measure(|| {
+                let _ = B::encapsulate(&ek, rng);
+            })
+        }
+        KeyMode::FreshPerIteration => {
+            let keys: Vec<_> = (0..budget.iterations).map(|_| B::generate(rng).1).collect();
+            let mut keys = keys.iter();
+            budget.measure(|| {
+                let _ = B::encapsulate(keys.next().unwrap(), rng);
+            })
+        }
+    }
+}
+
+fn bench_decaps<B: KemBackend, R: RngCore + CryptoRng>(budget: Budget, key_mode: KeyMode, rng: &mut R) -> Vec<u128> {
+    let mut key_and_ciphertext = || {
+        let (dk, ek) = B::generate(rng);
+        let (ct, _ss) = B::encapsulate(&ek, rng);
+        (dk, ct)
+    };
+
+    match key_mode {
+        KeyMode::Single => {
+            let (dk, ct) = key_and_ciphertext();
+            budget.measure(|| {
+                let _ = B::decapsulate(&dk, &ct);
+            })
+        }
+        KeyMode::FreshPerIteration => {
+            let pairs: Vec<_> = (0..budget.iterations).map(|_| key_and_ciphertext()).collect();
+            let mut pairs = pairs.iter();
+            budget.measure(|| {
+                let (dk, ct) = pairs.next().unwrap();
+                let _ = B::decapsulate(dk, ct);
+            })
+        }
+    }
+}
+
+fn bench_full_handshake<B: KemBackend, R: RngCore + CryptoRng>(
+    budget: Budget,
+    rng: &mut R,
+) -> Result<(Vec<u128>, HandshakePhases), SharedSecretMismatch> {
+    let mut check = SecretCheck::default();
+    let mut phases = HandshakePhases::default();
+    let timings = budget.try_measure(|| {
+        let start = Instant::now();
+        let (dk, ek) = B::generate(rng);
+        let generated = Instant::now();
+        let (ct, ss_sender) = B::encapsulate(&ek, rng);
+        let encapsulated = Instant::now();
+        let ss_receiver = B::decapsulate(&dk, &ct);
+        phases.record(budget.overhead, [start, generated, encapsulated, Instant::now()]);
+        check.check(ss_sender.as_ref(), ss_receiver.as_ref())
+    })?;
+    Ok((timings, phases))
+}
+
+fn bench_encaps_decaps<B: KemBackend, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Result<Vec<u128>, SharedSecretMismatch> {
+    let (dk, ek) = B::generate(rng);
+    let mut check = SecretCheck::default();
+    budget.try_measure(|| {
+        let (ct, ss_sender) = B::encapsulate(&ek, rng);
+        let ss_receiver = B::decapsulate(&dk, &ct);
+        check.check(ss_sender.as_ref(), ss_receiver.as_ref())
+    })
+}
+
+pub fn run_backend_operation<B: KemBackend, R: RngCore + CryptoRng>(
+    param_set: &str,
+    operation: &str,
+    budget: Budget,
+    key_mode: KeyMode,
+    rng: &mut R,
+) -> Result<BenchOutput, AppError> {
+    let mut phases = None;
+    let timings = match operation {
+        "keygen" => bench_keygen::<B, _>(budget, rng),
+        "encaps" => bench_encaps::<B, _>(budget, key_mode, rng),
+        "decaps" => bench_decaps::<B, _>(budget, key_mode, rng),
+        "encaps_decaps" => bench_encaps_decaps::<B, _>(budget, rng).map_err(|m| m.at(param_set, operation))?,
+        "full_handshake" => {
+            let (timings, handshake) = bench_full_handshake::<B, _>(budget, rng).map_err(|m| m.at(param_set, operation))?;
+            phases = Some(handshake);
+            timings
+        }
+        _ => return Err(AppError::InvalidOperation(operation.to_string())),
+    };
+
+    // Sizes come from a real serialized key and ciphertext rather than constants
+    let (dk, ek) = B::generate(rng);
+    let (ct, ss) = B::encapsulate(&ek, rng);
+
+    Ok(BenchOutput {
+        timings,
+        encapsulation_key_bytes: Some(B::encapsulation_key_bytes(&ek).len()),
+        decapsulation_key_bytes: Some(B::decapsulation_key_bytes(&dk).len()),
+        ciphertext_bytes: Some(B::ciphertext_bytes(&ct).len()),
+        shared_secret_bytes: Some(ss.as_ref().len()),
+        phases,
+        ..BenchOutput::default()
+    })
+}
@@ -0,0 +1,330 @@
+use common::{cpu_time::thread_cpu_time, stats::subtract_overhead};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::ops::Add;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+// ============ Iteration Budget ============
+
+/// How long a benchmark loop may run: a fixed iteration count, cut short once
+/// the deadline passes if there is one
+#[derive(Clone, Copy)]
+pub struct Budget<'a> {
+    pub iterations: u32,
+    pub deadline: Option<Instant>,
+    /// Told about every timed iteration, for streaming progress to the client
+    pub progress: Option<&'a Progress>,
+    /// Widened to cover this loop, for throughput over wall-clock time
+    pub window: Option<&'a LoopWindow>,
+    /// Told when each timed iteration started, for spotting drift over the run
+    pub timeline: Option<&'a Timeline>,
+    /// Taken off every sample; zero unless the request asked for the timer overhead
+    /// to be subtracted
+    pub overhead: Duration,
+}
+
+impl Budget<'_> {
+    #[cfg(test)]
+    pub fn fixed(iterations: u32) -> Self {
+        Budget { iterations, deadline: None, progress: None, window: None, timeline: None, overhead: Duration::ZERO }
+    }
+
+    /// Time `op` once per iteration. The deadline is only checked between iterations,
+    /// so at least one always runs and an overrun is bounded by a single operation.
+    pub fn measure(self, mut op: impl FnMut()) -> Vec<u128> {
+        self.try_measure(|| {
+            op();
+            Ok::<_, Infallible>(())
+        })
+        .unwrap_or_else(|never| match never {})
+    }
+
+    /// `measure`, stopping at the first iteration that fails. That iteration isn't timed.
+    pub fn try_measure<E>(self, mut op: impl FnMut() -> Result<(), E>) -> Result<Vec<u128>, E> {
+        let mut timings = Vec::with_capacity(self.iterations as usize);
+        let mut starts = Vec::with_capacity(if self.timeline.is_some() { self.iterations as usize } else { 0 });
+        let cpu_start = self.window.and_then(|_| thread_cpu_time());
+        let loop_start = Instant::now();
+        for _ in 0..self.iterations {
+            if !timings.is_empty() && self.deadline.is_some_and(|d| Instant::now() >= d) {
+                break;
+            }
+            let start = Instant::now();
+            op()?;
+            let elapsed = subtract_overhead(start.elapsed(), self.overhead).as_micros();
+            timings.push(elapsed);
+            if self.timeline.is_some() {
+                starts.push(start);
+            }
+            if let Some(progress) = self.progress {
+                progress.record(elapsed);
+            }
+        }
+        if let Some(window) = self.window {
+            let loop_end = Instant::now();
+            let cpu = cpu_start.zip(thread_cpu_time()).map(|(start, end)| end.saturating_sub(start));
+            window.cover(loop_start, loop_end, cpu);
+        }
+        if let Some(timeline) = self.timeline {
+            timeline.record(&starts, &timings);
+        }
+        Ok(timings)
+    }
+}
+
+/// Wall-clock span from the first measured loop starting to the last one ending, across
+/// every thread of a run. Unlike the per-iteration timings it includes the loop's own
+/// overhead between operations, and overlapping threads count once.
+#[derive(Default)]
+pub struct LoopWindow {
+    span: Mutex<Option<(Instant, Instant)>>,
+    /// One entry per loop; None where the thread's CPU clock couldn't be read
+    cpu: Mutex<Vec<Option<CpuTime>>>,
+}
+
+impl LoopWindow {
+    fn cover(&self, start: Instant, end: Instant, cpu: Option<Duration>) {
+        let mut span = self.span.lock().unwrap();
+        *span = Some(match *span {
+            Some((first, last)) => (first.min(start), last.max(end)),
+            None => (start, end),
+        });
+        self.cpu.lock().unwrap().push(cpu.map(|cpu| CpuTime { cpu, wall: end - start }));
+    }
+
+    /// Zero if no loop has run
+    pub fn elapsed(&self) -> Duration {
+        self.span.lock().unwrap().map_or(Duration::ZERO, |(first, last)| last - first)
+    }
+
+    /// Summed over every loop; None if no loop has run or any couldn't read its CPU clock
+    pub fn cpu_time(&self) -> Option<CpuTime> {
+        let loops = self.cpu.lock().unwrap();
+        if loops.is_empty() {
+            return None;
+        }
+        loops.iter().copied().sum()
+    }
+}
+
+/// CPU time the measuring threads spent in their loops, next to the wall-clock time of the
+/// same loops added up per thread. A thread descheduled mid-loop accrues wall time only, so
+/// a ratio well below 1.0 means the run was contended.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CpuTime {
+    pub cpu: Duration,
+    pub wall: Duration,
+}
+
+impl CpuTime {
+    pub fn ratio(self) -> f64 {
+        if self.wall.is_zero() { 1.0 } else { self.cpu.as_secs_f64() / self.wall.as_secs_f64() }
+    }
+}
+
+impl Add for CpuTime {
+    type Output = CpuTime;
+
+    fn add(self, other: CpuTime) -> CpuTime {
+        CpuTime { cpu: self.cpu + other.cpu, wall: self.wall + other.wall }
+    }
+}
+
+impl std::iter::Sum for CpuTime {
+    fn sum<I: Iterator<Item = CpuTime>>(iter: I) -> CpuTime {
+        iter.fold(CpuTime::default(), Add::add)
+    }
+}
+
+/// When each timed iteration started, in microseconds since the timeline was created, with
+/// its latency. Every loop of a request shares one, so samples from several threads or
+/// runs land on the same clock.
+pub struct Timeline {
+    origin: Instant,
+    samples: Mutex<Vec<(u64, u128)>>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Timeline { origin: Instant::now(), samples: Mutex::new(Vec::new()) }
+    }
+}
+
+impl Timeline {
+    // Once per loop rather than per iteration, so threads don't contend on the lock
+    fn record(&self, starts: &[Instant], timings: &[u128]) {
+        let offsets = starts.iter().map(|start| start.duration_since(self.origin).as_micros() as u64);
+        self.samples.lock().unwrap().extend(offsets.zip(timings.iter().copied()));
+    }
+
+    /// (start offset, latency) pairs in the order the iterations started
+    pub fn into_samples(self) -> Vec<(u64, u128)> {
+        let mut samples = self.samples.into_inner().unwrap();
+        samples.sort_by_key(|&(offset, _)| offset);
+        samples
+    }
+}
+
+// ============ Progress Reporting ============
+
+/// Running totals for a benchmark in flight, shared by every thread of the run.
+/// Every `every` completed iterations a snapshot is handed to `report`, and with
+/// `on_sample` every iteration's latency is handed over as well, on whichever benchmark
+/// thread completed it, so neither callback may block.
+pub struct Progress {
+    every: u32,
+    total_iterations: AtomicU32,
+    completed: AtomicU32,
+    total_us: AtomicU64,
+    started: Instant,
+    report: Box<dyn Fn(ProgressUpdate) + Send + Sync>,
+    sample: Option<Box<dyn Fn(Sample) + Send + Sync>>,
+}
+
+/// One measured iteration, numbered from 0 in the order iterations completed
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+pub struct Sample {
+    i: u32,
+    us: u128,
+}
+
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+pub struct ProgressUpdate {
+    completed_iterations: u32,
+    /// Iterations scheduled for the measured run; 0 until calibration and warmup are done
+    total_iterations: u32,
+    /// Mean over the iterations completed so far
+    avg_us: f64,
+    /// Since the request started, including any pilot and warmup
+    elapsed_ms: u64,
+}
+
+impl Progress {
+    pub fn new(every: u32, report: impl Fn(ProgressUpdate) + Send + Sync + 'static) -> Self {
+        Progress {
+            every: every.max(1),
+            total_iterations: AtomicU32::new(0),
+            completed: AtomicU32::new(0),
+            total_us: AtomicU64::new(0),
+            started: Instant::now(),
+            report: Box::new(report),
+            sample: None,
+        }
+    }
+
+    pub fn on_sample(self, sample: impl Fn(Sample) + Send + Sync + 'static) -> Self {
+        Progress { sample: Some(Box::new(sample)), ..self }
+    }
+
+    pub fn set_total(&self, iterations: u32) {
+        self.total_iterations.store(iterations, Ordering::Relaxed);
+    }
+
+    fn record(&self, micros: u128) {
+        let total_us = self.total_us.fetch_add(micros as u64, Ordering::Relaxed) + micros as u64;
+        let completed = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(sample) = &self.sample {
+            sample(Sample { i: completed - 1, us: micros });
+        }
+        if completed.is_multiple_of(self.every) {
+            (self.report)(ProgressUpdate {
+                completed_iterations: completed,
+                total_iterations: self.total_iterations.load(Ordering::Relaxed),
+                avg_us: total_us as f64 / completed as f64,
+                elapsed_ms: self.started.elapsed().as_millis() as u64,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_budget_runs_every_iteration() {
+        let mut calls = 0;
+        assert_eq!(Budget::fixed(5).measure(|| calls += 1).len(), 5);
+        assert_eq!(calls, 5);
+    }
+
+    #[test]
+    fn expired_deadline_still_runs_one_iteration() {
+        let budget = Budget { deadline: Some(Instant::now()), ..Budget::fixed(100) };
+        assert_eq!(budget.measure(|| {}).len(), 1);
+    }
+
+    #[test]
+    fn deadline_stops_the_loop_early() {
+        let budget = Budget { deadline: Some(Instant::now() + Duration::from_millis(20)), ..Budget::fixed(1000) };
+        let timings = budget.measure(|| std::thread::sleep(Duration::from_millis(5)));
+        assert!((2..1000).contains(&timings.len()), "{}", timings.len());
+    }
+
+    #[test]
+    fn overhead_larger_than_a_sample_floors_it_at_zero() {
+        let budget = Budget { overhead: Duration::from_secs(1), ..Budget::fixed(5) };
+        assert_eq!(budget.measure(|| std::thread::sleep(Duration::from_millis(1))), vec![0; 5]);
+    }
+
+    #[test]
+    fn window_spans_every_loop() {
+        let window = LoopWindow::default();
+        assert_eq!(window.elapsed(), Duration::ZERO);
+        let budget = Budget { window: Some(&window), ..Budget::fixed(2) };
+        budget.measure(|| std::thread::sleep(Duration::from_millis(5)));
+        let first = window.elapsed();
+        assert!(first >= Duration::from_millis(10), "{:?}", first);
+        budget.measure(|| {});
+        assert!(window.elapsed() >= first);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn sleeping_loops_show_a_low_cpu_ratio() {
+        let window = LoopWindow::default();
+        assert_eq!(window.cpu_time(), None);
+        let budget = Budget { window: Some(&window), ..Budget::fixed(3) };
+        budget.measure(|| std::thread::sleep(Duration::from_millis(5)));
+        let slept = window.cpu_time().expect("Linux has a thread CPU clock");
+        assert!(slept.wall >= Duration::from_millis(15), "{:?}", slept);
+        assert!(slept.ratio() < 0.5, "{:?}", slept);
+
+        let busy = LoopWindow::default();
+        Budget { window: Some(&busy), ..Budget::fixed(3) }.measure(|| {
+            let until = Instant::now() + Duration::from_millis(5);
+            while Instant::now() < until {}
+        });
+        assert!(busy.cpu_time().unwrap().ratio() > slept.ratio());
+    }
+
+    #[test]
+    fn timeline_orders_samples_from_every_loop_by_start() {
+        let timeline = Timeline::default();
+        let budget = Budget { timeline: Some(&timeline), ..Budget::fixed(3) };
+        let first = budget.measure(|| std::thread::sleep(Duration::from_millis(1)));
+        let second = budget.measure(|| {});
+        let samples = timeline.into_samples();
+        assert_eq!(samples.len(), 6);
+        assert!(samples.windows(2).all(|w| w[0].0 <= w[1].0));
+        // Each iteration starts at least one sleep after the previous one in the first loop
+        assert!(samples[2].0 - samples[0].0 >= 2000, "{:?}", samples);
+        let latencies: Vec<u128> = samples.iter().map(|&(_, latency)| latency).collect();
+        assert_eq!(latencies, [first, second].concat());
+    }
+
+    #[test]
+    fn progress_reports_every_n_iterations() {
+        let updates = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = updates.clone();
+        let progress = Progress::new(4, move |update| sink.lock().unwrap().push(update.completed_iterations));
+        progress.set_total(10);
+        let budget = Budget { progress: Some(&progress), ..Budget::fixed(10) };
+        budget.measure(|| {});
+        // Other iterations don't report, so a partial final stretch goes unreported
+        assert_eq!(*updates.lock().unwrap(), vec![4, 8]);
+    }
+}
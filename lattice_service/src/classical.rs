@@ -0,0 +1,131 @@
+use rand::{CryptoRng, RngCore};
+use rsa::{pkcs1::{EncodeRsaPrivateKey, EncodeRsaPublicKey}, Oaep, RsaPrivateKey, RsaPublicKey};
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::backends::KemBackend;
+
+// RSA keygen takes tens to hundreds of milliseconds, so rsa_2048 gets a much lower cap
+pub const RSA_MAX_ITERATIONS: u32 = 50;
+
+// ============ X25519 Baseline ============
+//
+// ECDH phrased as a KEM: encaps generates an ephemeral key and performs DH against the
+// recipient's public key (the ephemeral public key is the "ciphertext"), decaps is one DH.
+
+pub struct X25519;
+
+impl KemBackend for X25519 {
+    type DecapsulationKey = StaticSecret;
+    type EncapsulationKey = PublicKey;
+    type Ciphertext = PublicKey;
+    type SharedSecret = [u8; 32];
+
+    fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> (StaticSecret, PublicKey) {
+        let sk = StaticSecret::random_from_rng(rng);
+        let pk = PublicKey::from(&sk);
+        (sk, pk)
+    }
+
+    fn encapsulate<R: RngCore + CryptoRng>(ek: &PublicKey, rng: &mut R) -> (PublicKey, [u8; 32]) {
+        let esk = EphemeralSecret::random_from_rng(rng);
+        let ct = PublicKey::from(&esk);
+        (ct, esk.diffie_hellman(ek).to_bytes())
+    }
+
+    fn decapsulate(dk: &StaticSecret, ct: &PublicKey) -> [u8; 32] {
+        dk.diffie_hellman(ct).to_bytes()
+    }
+
+    fn encapsulation_key_bytes(ek: &PublicKey) -> Vec<u8> {
+        ek.as_bytes().to_vec()
+    }
+
+    fn decapsulation_key_bytes(dk: &StaticSecret) -> Vec<u8> {
+        dk.to_bytes().to_vec()
+    }
+
+    fn ciphertext_bytes(ct: &PublicKey) -> Vec<u8> {
+        ct.as_bytes().to_vec()
+    }
+}
+
+// ============ RSA-2048 Baseline ============
+//
+// RSA-OAEP (SHA-256) phrased as a KEM: encaps encrypts a fresh random 32-byte secret
+// to the public key, decaps decrypts it.
+
+pub struct Rsa2048;
+
+impl KemBackend for Rsa2048 {
+    type DecapsulationKey = RsaPrivateKey;
+    type EncapsulationKey = RsaPublicKey;
+    type Ciphertext = Vec<u8>;
+    type SharedSecret = Vec<u8>;
+
+    fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> (RsaPrivateKey, RsaPublicKey) {
+        let sk = RsaPrivateKey::new(rng, 2048).expect("RSA key generation failed");
+        let pk = sk.to_public_key();
+        (sk, pk)
+    }
+
+    fn encapsulate<R: RngCore + CryptoRng>(ek: &RsaPublicKey, rng: &mut R) -> (Vec<u8>, Vec<u8>) {
+        let mut secret = vec![0u8; 32];
+        rng.fill_bytes(&mut secret);
+        let ct = ek
+            .encrypt(rng, Oaep::new::<Sha256>(), &secret)
+            .expect("RSA-OAEP encryption failed");
+        (ct, secret)
+    }
+
+    fn decapsulate(dk: &RsaPrivateKey, ct: &Vec<u8>) -> Vec<u8> {
+        dk.decrypt(Oaep::new::<Sha256>(), ct).expect("RSA-OAEP decryption failed")
+    }
+
+    // PKCS#1 DER, i.e. what would actually go on the wire
+    fn encapsulation_key_bytes(ek: &RsaPublicKey) -> Vec<u8> {
+        ek.to_pkcs1_der().expect("RSA public key encoding failed").into_vec()
+    }
+
+    fn decapsulation_key_bytes(dk: &RsaPrivateKey) -> Vec<u8> {
+        dk.to_pkcs1_der().expect("RSA private key encoding failed").as_bytes().to_vec()
+    }
+
+    fn ciphertext_bytes(ct: &Vec<u8>) -> Vec<u8> {
+        ct.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::run_backend_operation;
+    use crate::budget::Budget;
+    use crate::KeyMode;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn both_sides_derive_the_same_secret() {
+        let (dk, ek) = X25519::generate(&mut OsRng);
+        let (ct, ss_sender) = X25519::encapsulate(&ek, &mut OsRng);
+        assert_eq!(ss_sender, X25519::decapsulate(&dk, &ct));
+    }
+
+    #[test]
+    fn reports_x25519_sizes() {
+        let output = run_backend_operation::<X25519, _>("x25519", "full_handshake", Budget::fixed(2), KeyMode::Single, &mut OsRng).unwrap();
+        assert_eq!(output.timings.len(), 2);
+        assert_eq!(output.encapsulation_key_bytes, Some(32));
+        assert_eq!(output.decapsulation_key_bytes, Some(32));
+        assert_eq!(output.ciphertext_bytes, Some(32));
+        assert_eq!(output.shared_secret_bytes, Some(32));
+    }
+
+    #[test]
+    fn rsa_oaep_round_trip() {
+        let (dk, ek) = Rsa2048::generate(&mut OsRng);
+        let (ct, ss_sender) = Rsa2048::encapsulate(&ek, &mut OsRng);
+        assert_eq!(ct.len(), 256);
+        assert_eq!(ss_sender, Rsa2048::decapsulate(&dk, &ct));
+    }
+}
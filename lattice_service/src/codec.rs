@@ -0,0 +1,193 @@
+use base64::{
+    alphabet,
+    engine::{general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD}, DecodePaddingMode},
+    Engine,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::ToSchema;
+
+use crate::AppError;
+
+// ============ Binary Field Encodings ============
+//
+// Keys, ciphertexts and shared secrets travel as text. A /keygen, /encaps or /decaps
+// request picks one encoding for all of them with its `encoding` field (base64 unless it
+// says otherwise), and the response is written in, and names, the same one. base64url is
+// written unpadded and read with or without padding. A field that only decodes to the size
+// it should have under some other encoding is a 422 naming both, so a client mixing
+// encodings finds out directly rather than through a length error.
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Base64,
+    Hex,
+    Base64url,
+}
+
+const ENCODINGS: [Encoding; 3] = [Encoding::Base64, Encoding::Hex, Encoding::Base64url];
+
+const BASE64URL: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Base64 => "base64",
+            Encoding::Hex => "hex",
+            Encoding::Base64url => "base64url",
+        }
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Base64 => STANDARD.encode(bytes),
+            Encoding::Hex => hex::encode(bytes),
+            Encoding::Base64url => BASE64URL.encode(bytes),
+        }
+    }
+
+    fn try_decode(self, value: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Encoding::Base64 => STANDARD.decode(value).map_err(|e| e.to_string()),
+            Encoding::Hex => hex::decode(value).map_err(|e| e.to_string()),
+            Encoding::Base64url => BASE64URL.decode(value).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn decode(self, field: &str, value: &str) -> Result<Vec<u8>, AppError> {
+        self.try_decode(value)
+            .map_err(|e| AppError::MalformedInput(format!("{} is not valid {}: {}", field, self, e)))
+    }
+
+    /// Decodes a field that must come out as exactly `len` bytes. One that doesn't, but
+    /// would under another encoding, is a 422; otherwise the result is as from `decode`,
+    /// leaving a wrong length for the caller to report.
+    pub fn decode_exact(self, field: &str, value: &str, len: usize) -> Result<Vec<u8>, AppError> {
+        let decoded = match self.try_decode(value) {
+            Ok(bytes) if bytes.len() == len => return Ok(bytes),
+            decoded => decoded,
+        };
+        let other = ENCODINGS
+            .into_iter()
+            .filter(|&other| other != self)
+            .find(|other| other.try_decode(value).is_ok_and(|bytes| bytes.len() == len));
+        if let Some(other) = other {
+            return Err(AppError::ValidationFailed(format!(
+                "{} is {}-encoded, but this request's encoding is {}; every field of a request must use the same encoding",
+                field, other, self
+            )));
+        }
+        decoded.map_err(|e| AppError::MalformedInput(format!("{} is not valid {}: {}", field, self, e)))
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::OsRng, RngCore};
+
+    fn is_malformed(result: Result<Vec<u8>, AppError>) -> bool {
+        matches!(result, Err(AppError::MalformedInput(_)))
+    }
+
+    #[test]
+    fn every_encoding_round_trips_every_length() {
+        for encoding in ENCODINGS {
+            for len in 0..=70 {
+                let mut bytes = vec![0u8; len];
+                OsRng.fill_bytes(&mut bytes);
+                let encoded = encoding.encode(&bytes);
+                assert_eq!(encoding.decode("field", &encoded).unwrap(), bytes, "{} {}", encoding, len);
+                assert_eq!(encoding.decode_exact("field", &encoded, len).unwrap(), bytes, "{} {}", encoding, len);
+            }
+            for bytes in [&[0u8; 32][..], &[0xff; 32], &[0xfb, 0xff, 0xbf]] {
+                assert_eq!(encoding.decode("field", &encoding.encode(bytes)).unwrap(), bytes, "{}", encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn encodings_write_their_own_alphabet() {
+        let bytes = [0xfb, 0xff, 0xbf, 0x00];
+        assert_eq!(Encoding::Base64.encode(&bytes), "+/+/AA==");
+        assert_eq!(Encoding::Hex.encode(&bytes), "fbffbf00");
+        assert_eq!(Encoding::Base64url.encode(&bytes), "-_-_AA");
+        // Hex is read in either case, base64url with or without padding
+        assert_eq!(Encoding::Hex.decode("field", "FBFFBF00").unwrap(), bytes);
+        assert_eq!(Encoding::Base64url.decode("field", "-_-_AA==").unwrap(), bytes);
+    }
+
+    #[test]
+    fn invalid_input_is_malformed_and_names_the_field() {
+        for (encoding, value) in [
+            (Encoding::Base64, "-_-_AA=="),
+            (Encoding::Base64, "+/+/AA"),
+            (Encoding::Base64, "AA=A"),
+            (Encoding::Base64, "not base64!"),
+            (Encoding::Hex, "abc"),
+            (Encoding::Hex, "zz"),
+            (Encoding::Hex, "0x00"),
+            (Encoding::Base64url, "+/+/AA"),
+            (Encoding::Base64url, "A"),
+            (Encoding::Base64url, "AA=A"),
+        ] {
+            let result = encoding.decode("ciphertext", value);
+            match &result {
+                Err(AppError::MalformedInput(message)) => {
+                    assert!(message.starts_with(&format!("ciphertext is not valid {}: ", encoding)), "{}", message)
+                }
+                _ => panic!("{} {:?} decoded", encoding, value),
+            }
+        }
+    }
+
+    #[test]
+    fn fields_in_another_encoding_are_422s() {
+        let bytes = [0xfbu8; 24];
+        for declared in ENCODINGS {
+            for used in ENCODINGS.into_iter().filter(|&used| used != declared) {
+                match declared.decode_exact("encapsulation_key", &used.encode(&bytes), bytes.len()) {
+                    Err(AppError::ValidationFailed(message)) => {
+                        assert!(message.contains(&format!("is {}-encoded", used)), "{}", message);
+                        assert!(message.contains(&format!("encoding is {}", declared)), "{}", message);
+                    }
+                    _ => panic!("{} read as {} was accepted", used, declared),
+                }
+            }
+        }
+        // Hex digits are valid base64 too; only the length gives them away
+        let hex = Encoding::Hex.encode(&[0x11; 24]);
+        assert!(Encoding::Base64.decode("field", &hex).is_ok());
+        assert!(matches!(Encoding::Base64.decode_exact("field", &hex, 24), Err(AppError::ValidationFailed(_))));
+    }
+
+    #[test]
+    fn wrong_lengths_without_an_alternative_pass_through() {
+        let short = Encoding::Base64.encode(&[1; 10]);
+        assert_eq!(Encoding::Base64.decode_exact("field", &short, 32).unwrap().len(), 10);
+        assert!(is_malformed(Encoding::Hex.decode_exact("field", "zz", 1)));
+    }
+
+    #[test]
+    fn names_are_what_requests_send() {
+        for encoding in ENCODINGS {
+            assert_eq!(serde_json::to_value(encoding).unwrap(), encoding.name());
+            assert_eq!(serde_json::from_value::<Encoding>(encoding.name().into()).unwrap(), encoding);
+        }
+        assert!(serde_json::from_value::<Encoding>("base32".into()).is_err());
+        assert!(serde_json::from_value::<Encoding>("Base64".into()).is_err());
+    }
+}
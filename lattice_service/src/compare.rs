@@ -0,0 +1,164 @@
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use common::params::{Operation, ParamSet};
+
+use crate::format::{Accepted, Formatted, Negotiated};
+use crate::results::Requester;
+use crate::{bench_in_slot, default_percentiles, default_threads, AppError, AppState, KemBenchRequest, KemBenchResponse, KeyMode};
+
+// ============ Param Set Comparison ============
+//
+// POST /kem_compare answers "how much slower is B than A for this operation" in one call.
+// Both legs run back to back in a single benchmark slot, with the same iterations,
+// warmup and seed, so nothing else on the server runs between or during them.
+
+#[derive(Deserialize, ToSchema)]
+pub struct KemCompareRequest {
+    /// Canonical names or aliases, as for kem_bench
+    #[schema(value_type = String)]
+    param_set_a: ParamSet,
+    #[schema(value_type = String)]
+    param_set_b: ParamSet,
+    #[schema(value_type = String)]
+    operation: Operation,
+    iterations: u32,
+    /// Untimed iterations run before each leg
+    #[serde(default)]
+    warmup_iterations: Option<u32>,
+    /// Seed both legs' ChaCha20 RNGs identically instead of using OsRng
+    #[serde(default)]
+    seed: Option<u64>,
+}
+
+/// Both legs in full, plus B relative to A: a ratio above 1 means B is slower for the
+/// latency ratios, and faster for throughput_ratio
+#[derive(Serialize, ToSchema)]
+pub struct KemCompareResponse {
+    a: KemBenchResponse,
+    b: KemBenchResponse,
+    /// b.avg_us / a.avg_us
+    avg_ratio: f64,
+    /// b.p95_us / a.p95_us
+    p95_ratio: f64,
+    /// b.throughput_ops_sec / a.throughput_ops_sec
+    throughput_ratio: f64,
+}
+
+impl KemCompareRequest {
+    fn leg(&self, param_set: ParamSet) -> KemBenchRequest {
+        KemBenchRequest {
+            param_set,
+            iterations: Some(self.iterations),
+            target_duration_ms: None,
+            operation: self.operation,
+            percentiles: default_percentiles(),
+            include_raw_timings: false,
+            threads: default_threads(),
+            warmup_iterations: self.warmup_iterations,
+            seed: self.seed,
+            timeout_ms: None,
+            key_mode: KeyMode::Single,
+            include_machine_info: false,
+            trim_percent: None,
+            cipher: None,
+            payload_bytes: None,
+            weights: None,
+            runs: None,
+            record_timestamps: false,
+            drift_threshold: None,
+            subtract_overhead: false,
+            warmup_factor: None,
+        }
+    }
+}
+
+fn ratio(b: f64, a: f64) -> f64 {
+    if a > 0.0 { b / a } else { 0.0 }
+}
+
+#[utoipa::path(
+    post,
+    path = "/kem_compare",
+    request_body = KemCompareRequest,
+    responses(
+        (status = 200, description = "Both legs' statistics and B-over-A ratios", body = KemCompareResponse),
+        (status = 400, description = "InvalidParamSet or InvalidOperation, naming the failing leg; or MalformedInput", body = crate::ErrorBody),
+        (status = 422, description = "ValidationFailed: iterations out of range, naming the failing leg; or InvalidField: an unknown, missing or wrongly-typed field", body = crate::ErrorBody),
+        (status = 429, description = "TooManyBenchmarks: every benchmark slot is busy, see Retry-After", body = crate::ErrorBody),
+        (status = 503, description = "SheddingLoad: memory or in-flight benchmarks are past a --shed-* limit, see Retry-After", body = crate::ErrorBody),
+    )
+)]
+pub async fn kem_compare(
+    State(state): State<AppState>,
+    Requester(requester): Requester,
+    Accepted(format): Accepted,
+    Negotiated(req): Negotiated<KemCompareRequest>,
+) -> Result<Formatted<KemCompareResponse>, AppError> {
+    let _permit = state
+        .benchmark_slots
+        .try_acquire()
+        .map_err(|_| AppError::TooManyBenchmarks(state.config.max_concurrent_benchmarks()))?;
+
+    let a = bench_in_slot(&state, req.leg(req.param_set_a), None)
+        .await
+        .map_err(|e| AppError::Leg(format!("param_set_a ({})", req.param_set_a), Box::new(e)))?;
+    let b = bench_in_slot(&state, req.leg(req.param_set_b), None)
+        .await
+        .map_err(|e| AppError::Leg(format!("param_set_b ({})", req.param_set_b), Box::new(e)))?;
+    state.record_result(requester, &a);
+    state.record_result(requester, &b);
+
+    Ok(Formatted(format, KemCompareResponse {
+        avg_ratio: ratio(b.avg_us, a.avg_us),
+        p95_ratio: ratio(b.p95_us, a.p95_us),
+        throughput_ratio: ratio(b.throughput_ops_sec, a.throughput_ops_sec),
+        a,
+        b,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn compare_request(a: &str, b: &str, operation: &str) -> KemCompareRequest {
+        KemCompareRequest {
+            param_set_a: a.parse().unwrap(),
+            param_set_b: b.parse().unwrap(),
+            operation: operation.parse().unwrap(),
+            iterations: 20,
+            warmup_iterations: Some(2),
+            seed: Some(7),
+        }
+    }
+
+    async fn compare(req: KemCompareRequest) -> Result<KemCompareResponse, AppError> {
+        let state = State(AppState::new(Config::default()));
+        let Formatted(_, resp) = kem_compare(state, Requester(None), Accepted(crate::format::Format::Json), Negotiated(req)).await?;
+        Ok(resp)
+    }
+
+    #[tokio::test]
+    async fn legs_share_settings_and_ratios_are_b_over_a() {
+        let resp = compare(compare_request("ml_kem_512", "ml_kem_1024", "keygen")).await.unwrap();
+        for leg in [&resp.a, &resp.b] {
+            assert_eq!((leg.completed_iterations, leg.warmup_iterations, leg.seed), (20, 2, Some(7)));
+        }
+        assert_eq!((resp.a.param_set.as_str(), resp.b.param_set.as_str()), ("ml_kem_512", "ml_kem_1024"));
+        assert_eq!(resp.avg_ratio, resp.b.avg_us / resp.a.avg_us);
+        assert_eq!(resp.throughput_ratio, resp.b.throughput_ops_sec / resp.a.throughput_ops_sec);
+    }
+
+    #[tokio::test]
+    async fn failing_leg_is_named_and_keeps_its_status() {
+        // Signatures have no decaps, so only leg B fails
+        let err = compare(compare_request("ml_kem_768", "ml_dsa_65", "decaps")).await.err().unwrap();
+        assert_eq!(err.kind(), "invalid_operation");
+        let (status, message) = err.status_and_message();
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+        assert!(message.starts_with("param_set_b (ml_dsa_65) failed: Invalid operation"), "{}", message);
+    }
+}
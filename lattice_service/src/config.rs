@@ -0,0 +1,181 @@
+use clap::Parser;
+use common::{http::{self, HttpTuning}, limits::{self, Limits}, listen, uds};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+// Iteration cap for param sets without a lower one of their own
+pub const DEFAULT_MAX_ITERATIONS: u32 = 10_000;
+
+pub const DEFAULT_PORT: u16 = 8000;
+
+/// Runtime configuration, from CLI flags or the matching environment variables
+#[derive(Parser, Debug, Clone)]
+#[command(name = "lattice_service")]
+#[command(about = "ML-KEM and signature benchmark service")]
+pub struct Config {
+    /// Most iterations a single kem_bench request will run; larger requests are capped
+    #[arg(long, env = "LATTICE_MAX_ITERATIONS", default_value_t = DEFAULT_MAX_ITERATIONS)]
+    pub max_iterations: u32,
+
+    /// Maximum number of raw per-iteration timings returned by kem_bench
+    #[arg(long, env = "LATTICE_MAX_RAW_TIMINGS", default_value_t = 10_000)]
+    pub max_raw_timings: usize,
+
+    /// Seconds to let in-flight requests finish after SIGTERM/Ctrl-C before exiting
+    #[arg(long, env = "LATTICE_SHUTDOWN_TIMEOUT_SECS", default_value_t = 30)]
+    pub shutdown_timeout_secs: u64,
+
+    /// Benchmarks allowed to run at once; more get a 429 (default: available parallelism)
+    #[arg(long, env = "LATTICE_MAX_CONCURRENT_BENCHMARKS")]
+    pub max_concurrent_benchmarks: Option<usize>,
+
+    /// Refuse new benchmarks with a 503, and report not ready, while resident memory is
+    /// above this many MiB (default: no limit)
+    #[arg(long, env = "LATTICE_SHED_RSS_MB")]
+    pub shed_rss_mb: Option<u64>,
+
+    /// Refuse new benchmarks with a 503, and report not ready, while this many are already
+    /// running (default: no limit beyond --max-concurrent-benchmarks)
+    #[arg(long, env = "LATTICE_SHED_IN_FLIGHT")]
+    pub shed_in_flight: Option<usize>,
+
+    /// Keep this many ML-KEM keypairs per param set generated in the background, for
+    /// fresh_per_iteration benchmarks without a seed to draw on (default: no pool)
+    #[arg(long, env = "LATTICE_KEYPAIR_POOL_SIZE", value_parser = clap::value_parser!(u16).range(1..=4096))]
+    pub keypair_pool_size: Option<u16>,
+
+    /// Threads in the pool every KEM benchmark runs on; benchmark work beyond it queues
+    /// (default: available parallelism)
+    #[arg(long, env = "LATTICE_BENCH_THREADS", value_parser = clap::value_parser!(u16).range(1..))]
+    pub bench_threads: Option<u16>,
+
+    /// Longest a single kem_bench run may take; runs past it return partial results
+    #[arg(long, env = "LATTICE_MAX_TIMEOUT_MS", default_value_t = 60_000)]
+    pub max_timeout_ms: u64,
+
+    /// Largest request body accepted; bigger ones get a 413
+    #[arg(long, env = "LATTICE_MAX_BODY_BYTES", default_value_t = limits::DEFAULT_MAX_BODY_BYTES)]
+    pub max_body_bytes: usize,
+
+    /// Seconds a handler may run before the request gets a 408. Benchmark endpoints keep to
+    /// --max-timeout-ms instead, and streams and /ws to their own lifetime
+    #[arg(long, env = "LATTICE_REQUEST_TIMEOUT_SECS", default_value_t = limits::DEFAULT_REQUEST_TIMEOUT_SECS, value_parser = clap::value_parser!(u64).range(1..))]
+    pub request_timeout_secs: u64,
+
+    /// Connections kept open at once; further clients wait to be accepted (default: no limit)
+    #[arg(long, env = "LATTICE_MAX_CONNECTIONS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_connections: Option<u32>,
+
+    /// Seconds an HTTP/1.1 connection may sit idle between requests before it's closed
+    #[arg(long, env = "LATTICE_IDLE_TIMEOUT_SECS", default_value_t = http::DEFAULT_IDLE_TIMEOUT_SECS, value_parser = clap::value_parser!(u64).range(1..))]
+    pub idle_timeout_secs: u64,
+
+    /// Requests a single HTTP/2 connection may have in flight at once
+    #[arg(long, env = "LATTICE_HTTP2_MAX_CONCURRENT_STREAMS", default_value_t = http::DEFAULT_HTTP2_MAX_CONCURRENT_STREAMS, value_parser = clap::value_parser!(u32).range(1..))]
+    pub http2_max_concurrent_streams: u32,
+
+    /// Seconds between keep-alive pings on an HTTP/2 connection
+    #[arg(long, env = "LATTICE_HTTP2_KEEP_ALIVE_INTERVAL_SECS", default_value_t = http::DEFAULT_HTTP2_KEEP_ALIVE_INTERVAL_SECS, value_parser = clap::value_parser!(u64).range(1..))]
+    pub http2_keep_alive_interval_secs: u64,
+
+    /// Comma-separated origins allowed to call the API from a browser, or "*" (default: CORS off)
+    #[arg(long, env = "LATTICE_CORS_ALLOWED_ORIGINS", value_delimiter = ',')]
+    pub cors_allowed_origins: Vec<String>,
+
+    /// Never gzip/brotli-compress responses, even when the client sends Accept-Encoding
+    #[arg(long, env = "LATTICE_DISABLE_COMPRESSION")]
+    pub disable_compression: bool,
+
+    /// Never attach hostname/CPU details to responses, even when a request sets include_machine_info
+    #[arg(long, env = "LATTICE_DISABLE_MACHINE_INFO")]
+    pub disable_machine_info: bool,
+
+    /// Require `Authorization: Bearer <token>` on every endpoint except /health, /live and /ready
+    #[arg(long, env = "LATTICE_AUTH_TOKEN", hide_env_values = true)]
+    pub auth_token: Option<String>,
+
+    /// PEM certificate chain; together with --tls-key, serve HTTPS instead of plain HTTP
+    #[arg(long, env = "LATTICE_TLS_CERT", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert. Both are re-read on SIGHUP
+    #[arg(long, env = "LATTICE_TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Address to listen on: host:port, a bare port for every interface, or a bare IP for
+    /// port 8000. Port 0 picks a free port, logged at startup
+    #[arg(long, env = "LATTICE_BIND", default_value = "0.0.0.0:8000", value_parser = |s: &str| listen::parse_bind(s, DEFAULT_PORT))]
+    pub bind: SocketAddr,
+
+    /// Listen on this Unix domain socket instead of --bind; the socket file is removed on
+    /// clean shutdown
+    #[arg(long, env = "LATTICE_UDS", conflicts_with = "tls_cert")]
+    pub uds: Option<PathBuf>,
+
+    /// Octal permissions for the --uds socket file
+    #[arg(long, env = "LATTICE_UDS_MODE", default_value = "600", value_parser = uds::parse_mode)]
+    pub uds_mode: u32,
+
+    /// File holding the hex-encoded 32-byte seed of the key that signs benchmark responses,
+    /// so signatures stay attributable across restarts (default: a fresh key every start)
+    #[arg(long, env = "LATTICE_SIGNING_KEY")]
+    pub signing_key: Option<PathBuf>,
+
+    /// Append every completed kem_bench result to this JSONL file, queryable via GET /results
+    #[arg(long, env = "LATTICE_RESULTS_LOG")]
+    pub results_log: Option<PathBuf>,
+
+    /// Seconds a finished async job's result stays available at GET /jobs/{id}
+    #[arg(long, env = "LATTICE_JOB_TTL_SECS", default_value_t = 600)]
+    pub job_ttl_secs: u64,
+
+    /// Most async jobs held at once; the oldest finished one is evicted to make room
+    #[arg(long, env = "LATTICE_MAX_JOBS", default_value_t = 100)]
+    pub max_jobs: usize,
+
+    /// Seconds a keypair created via POST /keys stays usable
+    #[arg(long, env = "LATTICE_KEY_TTL_SECS", default_value_t = 300)]
+    pub key_ttl_secs: u64,
+
+    /// Most keypairs held at once, at least 1; the least recently used one is evicted to make room
+    #[arg(long, env = "LATTICE_MAX_STORED_KEYS", default_value_t = 1000, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_stored_keys: usize,
+
+    /// Largest payload_bytes a kem_dem_encrypt/kem_dem_decrypt benchmark may request
+    #[arg(long, env = "LATTICE_MAX_PAYLOAD_BYTES", default_value_t = 1024 * 1024)]
+    pub max_payload_bytes: usize,
+}
+
+impl Config {
+    pub fn max_concurrent_benchmarks(&self) -> usize {
+        self.max_concurrent_benchmarks
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    pub fn limits(&self) -> Limits {
+        Limits {
+            max_body_bytes: self.max_body_bytes,
+            request_timeout_secs: self.request_timeout_secs,
+            max_connections: self.max_connections.map(|n| n as usize),
+        }
+    }
+
+    pub fn http_tuning(&self) -> HttpTuning {
+        HttpTuning {
+            idle_timeout_secs: self.idle_timeout_secs,
+            http2_max_concurrent_streams: self.http2_max_concurrent_streams,
+            http2_keep_alive_interval_secs: self.http2_keep_alive_interval_secs,
+        }
+    }
+
+    pub fn bench_threads(&self) -> usize {
+        self.bench_threads
+            .map_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()), usize::from)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::parse_from(["lattice_service"])
+    }
+}
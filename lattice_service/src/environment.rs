@@ -0,0 +1,133 @@
+use axum::Json;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+// ============ Environment Fingerprint ============
+//
+// What a benchmark number depends on besides the code: compiler and target, the CPU
+// features the crypto can use, frequency scaling and speculative-execution mitigations.
+// Anything that can't be determined is null rather than left out, so the schema is the
+// same on every host.
+
+#[derive(Serialize, ToSchema)]
+pub struct EnvironmentResponse {
+    rustc_version: Option<&'static str>,
+    /// Target triple the binary was built for
+    target_triple: Option<&'static str>,
+    release_build: bool,
+    /// -C target-cpu the service was compiled with; null when none was given
+    target_cpu: Option<&'static str>,
+    /// A release build with target-cpu=native, i.e. tuned for the build host
+    native_release_build: bool,
+    cpu_features: CpuFeatures,
+    /// cpufreq governor of cpu0, e.g. "performance" or "powersave"
+    scaling_governor: Option<String>,
+    /// Kernel status of each known CPU vulnerability, e.g. "Mitigation: PTI"
+    mitigations: Option<BTreeMap<String, String>>,
+    ml_kem_version: Option<&'static str>,
+}
+
+/// Detected at runtime; null on architectures where a feature doesn't exist
+#[derive(Serialize, ToSchema)]
+pub struct CpuFeatures {
+    avx2: Option<bool>,
+    avx512f: Option<bool>,
+    neon: Option<bool>,
+}
+
+impl CpuFeatures {
+    fn detect() -> Self {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        let (avx2, avx512f) = (
+            Some(std::arch::is_x86_feature_detected!("avx2")),
+            Some(std::arch::is_x86_feature_detected!("avx512f")),
+        );
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+        let (avx2, avx512f) = (None, None);
+        #[cfg(target_arch = "aarch64")]
+        let neon = Some(std::arch::is_aarch64_feature_detected!("neon"));
+        #[cfg(not(target_arch = "aarch64"))]
+        let neon = None;
+        CpuFeatures { avx2, avx512f, neon }
+    }
+}
+
+// build.rs writes "unknown" for anything it couldn't find out
+fn known(value: &'static str) -> Option<&'static str> {
+    (value != "unknown").then_some(value)
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    let value = std::fs::read_to_string(path).ok()?;
+    Some(value.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn mitigations() -> Option<BTreeMap<String, String>> {
+    let entries = std::fs::read_dir("/sys/devices/system/cpu/vulnerabilities").ok()?;
+    let mitigations: BTreeMap<String, String> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let status = read_trimmed(entry.path().to_str()?)?;
+            Some((entry.file_name().into_string().ok()?, status))
+        })
+        .collect();
+    (!mitigations.is_empty()).then_some(mitigations)
+}
+
+#[utoipa::path(
+    get,
+    path = "/environment",
+    responses((status = 200, description = "Build, CPU and kernel details that affect benchmark results", body = EnvironmentResponse))
+)]
+pub async fn environment() -> Json<EnvironmentResponse> {
+    let release_build = !cfg!(debug_assertions);
+    let target_cpu = known(env!("LATTICE_TARGET_CPU"));
+    Json(EnvironmentResponse {
+        rustc_version: known(env!("LATTICE_RUSTC_VERSION")),
+        target_triple: known(env!("LATTICE_TARGET")),
+        release_build,
+        target_cpu,
+        native_release_build: release_build && target_cpu == Some("native"),
+        cpu_features: CpuFeatures::detect(),
+        scaling_governor: read_trimmed("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor"),
+        mitigations: mitigations(),
+        ml_kem_version: known(env!("LATTICE_ML_KEM_VERSION")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn undetermined_fields_are_null_not_missing() {
+        let Json(resp) = environment().await;
+        let value = serde_json::to_value(&resp).unwrap();
+        for field in [
+            "rustc_version",
+            "target_triple",
+            "release_build",
+            "target_cpu",
+            "native_release_build",
+            "cpu_features",
+            "scaling_governor",
+            "mitigations",
+            "ml_kem_version",
+        ] {
+            assert!(value.get(field).is_some(), "missing {}", field);
+        }
+        for feature in ["avx2", "avx512f", "neon"] {
+            assert!(value["cpu_features"].get(feature).is_some(), "missing {}", feature);
+        }
+        assert_eq!(value["release_build"], !cfg!(debug_assertions));
+        assert!(resp.target_triple.is_some_and(|t| t.contains(std::env::consts::ARCH)), "{:?}", resp.target_triple);
+    }
+
+    #[test]
+    fn features_exist_only_on_their_architecture() {
+        let features = CpuFeatures::detect();
+        assert_eq!(features.avx2.is_some(), cfg!(any(target_arch = "x86", target_arch = "x86_64")));
+        assert_eq!(features.neon.is_some(), cfg!(target_arch = "aarch64"));
+    }
+}
@@ -0,0 +1,194 @@
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::AppError;
+use common::request;
+
+// ============ Wire Formats ============
+//
+// Benchmark endpoints speak JSON by default, and CBOR or MessagePack when asked via
+// Content-Type (request body) and Accept (response). Errors are always JSON.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Cbor,
+    MsgPack,
+}
+
+impl Format {
+    fn from_media_type(media_type: &str) -> Option<Format> {
+        match media_type {
+            "application/json" => Some(Format::Json),
+            "application/cbor" => Some(Format::Cbor),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => Some(Format::MsgPack),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Cbor => "application/cbor",
+            Format::MsgPack => "application/msgpack",
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(self, body: &[u8]) -> Result<T, String> {
+        match self {
+            Format::Json => serde_json::from_slice(body).map_err(|e| e.to_string()),
+            Format::Cbor => ciborium::from_reader(body).map_err(|e| e.to_string()),
+            Format::MsgPack => rmp_serde::from_slice(body).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            Format::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            Format::Cbor => {
+                let mut buffer = Vec::new();
+                ciborium::into_writer(value, &mut buffer).map_err(|e| e.to_string())?;
+                Ok(buffer)
+            }
+            // Named fields, so clients don't depend on struct field order
+            Format::MsgPack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+// Media type without parameters such as charset or q
+fn media_type(value: &str) -> String {
+    value.split(';').next().unwrap_or("").trim().to_ascii_lowercase()
+}
+
+/// First acceptable entry of the Accept header wins; wildcards and a missing header mean JSON
+fn negotiate(headers: &HeaderMap) -> Result<Format, AppError> {
+    let Some(accept) = headers.get(header::ACCEPT) else {
+        return Ok(Format::Json);
+    };
+    let accept = accept.to_str().unwrap_or("");
+    accept
+        .split(',')
+        .map(media_type)
+        .find_map(|m| match m.as_str() {
+            "*/*" | "application/*" => Some(Format::Json),
+            m => Format::from_media_type(m),
+        })
+        .ok_or_else(|| AppError::NotAcceptable(accept.to_string()))
+}
+
+/// Response format chosen from the Accept header; rejects with 406 if nothing supported is acceptable
+pub(crate) struct Accepted(pub Format);
+
+impl<S: Send + Sync> FromRequestParts<S> for Accepted {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, AppError> {
+        negotiate(&parts.headers).map(Accepted)
+    }
+}
+
+/// Request body decoded according to its Content-Type; fields that don't match T are a 422
+pub(crate) struct Negotiated<T>(pub T);
+
+impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for Negotiated<T> {
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, AppError> {
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(media_type)
+            .unwrap_or_default();
+        let format = Format::from_media_type(&content_type)
+            .ok_or(AppError::UnsupportedMediaType(content_type))?;
+        let body = Bytes::from_request(req, state).await.map_err(|e| match e.status() {
+            StatusCode::PAYLOAD_TOO_LARGE => AppError::PayloadTooLarge(e.body_text()),
+            _ => AppError::MalformedInput(e.body_text()),
+        })?;
+        // Parsed generically first, so a body that isn't valid at all stays a 400 and one
+        // that doesn't fit T becomes a 422 naming the field
+        let value: serde_json::Value = format
+            .decode(&body)
+            .map_err(|e| AppError::MalformedInput(format!("invalid {} body: {}", format.content_type(), e)))?;
+        request::from_value(value).map(Negotiated).map_err(AppError::InvalidField)
+    }
+}
+
+/// Response body serialized in the negotiated format
+pub struct Formatted<T>(pub Format, pub T);
+
+impl<T: Serialize> IntoResponse for Formatted<T> {
+    fn into_response(self) -> Response {
+        let Formatted(format, value) = self;
+        match format.encode(&value) {
+            Ok(body) => ([(header::CONTENT_TYPE, HeaderValue::from_static(format.content_type()))], body).into_response(),
+            Err(e) => AppError::Internal(format!("failed to encode response: {}", e)).into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app, config::Config, AppState};
+    use axum::{body::Body, http::StatusCode};
+    use tower::ServiceExt;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn accept_header_picks_first_supported_format() {
+        assert_eq!(negotiate(&HeaderMap::new()).unwrap(), Format::Json);
+        assert_eq!(negotiate(&accept("*/*")).unwrap(), Format::Json);
+        assert_eq!(negotiate(&accept("application/cbor")).unwrap(), Format::Cbor);
+        assert_eq!(negotiate(&accept("text/html, application/msgpack;q=0.9")).unwrap(), Format::MsgPack);
+        assert!(matches!(negotiate(&accept("text/html")), Err(AppError::NotAcceptable(_))));
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let value = serde_json::json!({ "param_set": "ml_kem_768", "timings_us": [1, 2, 3], "avg_us": 1.5 });
+        for format in [Format::Json, Format::Cbor, Format::MsgPack] {
+            let bytes = format.encode(&value).unwrap();
+            assert_eq!(format.decode::<serde_json::Value>(&bytes).unwrap(), value, "{:?}", format);
+        }
+    }
+
+    #[tokio::test]
+    async fn kem_bench_speaks_cbor_end_to_end() {
+        let router = app(AppState::new(Config::default()));
+        let request = serde_json::json!({ "param_set": "ml_kem_512", "operation": "keygen", "iterations": 3 });
+        let post = |content_type: &str, accept: &str, body: Vec<u8>| {
+            axum::http::Request::post("/kem_bench")
+                .header(header::CONTENT_TYPE, content_type)
+                .header(header::ACCEPT, accept)
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let cbor = Format::Cbor.encode(&request).unwrap();
+        let resp = router.clone().oneshot(post("application/cbor", "application/cbor", cbor.clone())).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "application/cbor");
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let decoded: serde_json::Value = Format::Cbor.decode(&body).unwrap();
+        assert_eq!(decoded["param_set"], "ml_kem_512");
+        assert_eq!(decoded["completed_iterations"], 3);
+
+        let resp = router.clone().oneshot(post("application/cbor", "text/html", cbor)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+        let resp = router.oneshot(post("text/plain", "application/json", b"hello".to_vec())).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
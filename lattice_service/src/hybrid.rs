@@ -0,0 +1,125 @@
+use ml_kem::{*, kem::{Encapsulate, Decapsulate}};
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Sha3_256};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::backends::KemBackend;
+
+// X-Wing combiner label from draft-connolly-cfrg-xwing-kem
+const XWING_LABEL: &[u8] = b"\\.//^\\";
+
+// ============ X-Wing Style Hybrid KEM ============
+//
+// ML-KEM-768 and X25519 run side by side; the two shared secrets are bound together
+// with SHA3-256 over both secrets, the X25519 ciphertext, and the X25519 public key.
+
+type MlKem768DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type MlKem768EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+pub struct XWingDecapsulationKey {
+    ml_kem: MlKem768DecapsulationKey,
+    x25519: StaticSecret,
+    x25519_public: PublicKey,
+}
+
+pub struct XWingEncapsulationKey {
+    ml_kem: MlKem768EncapsulationKey,
+    x25519: PublicKey,
+}
+
+pub struct XWingCiphertext {
+    ml_kem: Ciphertext<MlKem768>,
+    x25519: PublicKey,
+}
+
+fn combine(ss_m: &[u8], ss_x: &[u8], ct_x: &PublicKey, pk_x: &PublicKey) -> [u8; 32] {
+    Sha3_256::new()
+        .chain_update(ss_m)
+        .chain_update(ss_x)
+        .chain_update(ct_x.as_bytes())
+        .chain_update(pk_x.as_bytes())
+        .chain_update(XWING_LABEL)
+        .finalize()
+        .into()
+}
+
+pub struct XWing;
+
+impl KemBackend for XWing {
+    type DecapsulationKey = XWingDecapsulationKey;
+    type EncapsulationKey = XWingEncapsulationKey;
+    type Ciphertext = XWingCiphertext;
+    type SharedSecret = [u8; 32];
+
+    fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> (XWingDecapsulationKey, XWingEncapsulationKey) {
+        let (dk_m, ek_m) = MlKem768::generate(rng);
+        let sk_x = StaticSecret::random_from_rng(&mut *rng);
+        let pk_x = PublicKey::from(&sk_x);
+
+        (
+            XWingDecapsulationKey { ml_kem: dk_m, x25519: sk_x, x25519_public: pk_x },
+            XWingEncapsulationKey { ml_kem: ek_m, x25519: pk_x },
+        )
+    }
+
+    fn encapsulate<R: RngCore + CryptoRng>(ek: &XWingEncapsulationKey, rng: &mut R) -> (XWingCiphertext, [u8; 32]) {
+        let (ct_m, ss_m) = ek.ml_kem.encapsulate(rng).unwrap();
+        let ek_x = EphemeralSecret::random_from_rng(&mut *rng);
+        let ct_x = PublicKey::from(&ek_x);
+        let ss_x = ek_x.diffie_hellman(&ek.x25519);
+
+        let ss = combine(&ss_m, ss_x.as_bytes(), &ct_x, &ek.x25519);
+        (XWingCiphertext { ml_kem: ct_m, x25519: ct_x }, ss)
+    }
+
+    fn decapsulate(dk: &XWingDecapsulationKey, ct: &XWingCiphertext) -> [u8; 32] {
+        let ss_m = dk.ml_kem.decapsulate(&ct.ml_kem).unwrap();
+        let ss_x = dk.x25519.diffie_hellman(&ct.x25519);
+
+        combine(&ss_m, ss_x.as_bytes(), &ct.x25519, &dk.x25519_public)
+    }
+
+    fn encapsulation_key_bytes(ek: &XWingEncapsulationKey) -> Vec<u8> {
+        let mut bytes = ek.ml_kem.as_bytes().to_vec();
+        bytes.extend_from_slice(ek.x25519.as_bytes());
+        bytes
+    }
+
+    fn decapsulation_key_bytes(dk: &XWingDecapsulationKey) -> Vec<u8> {
+        let mut bytes = dk.ml_kem.as_bytes().to_vec();
+        bytes.extend_from_slice(dk.x25519.as_bytes());
+        bytes
+    }
+
+    fn ciphertext_bytes(ct: &XWingCiphertext) -> Vec<u8> {
+        let mut bytes = ct.ml_kem.to_vec();
+        bytes.extend_from_slice(ct.x25519.as_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::run_backend_operation;
+    use crate::budget::Budget;
+    use crate::KeyMode;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn both_sides_derive_the_same_secret() {
+        let (dk, ek) = XWing::generate(&mut OsRng);
+        let (ct, ss_sender) = XWing::encapsulate(&ek, &mut OsRng);
+        assert_eq!(ss_sender, XWing::decapsulate(&dk, &ct));
+    }
+
+    #[test]
+    fn reports_combined_sizes() {
+        let output = run_backend_operation::<XWing, _>("x_wing", "keygen", Budget::fixed(2), KeyMode::Single, &mut OsRng).unwrap();
+        assert_eq!(output.timings.len(), 2);
+        assert_eq!(output.encapsulation_key_bytes, Some(1184 + 32));
+        assert_eq!(output.decapsulation_key_bytes, Some(2400 + 32));
+        assert_eq!(output.ciphertext_bytes, Some(1088 + 32));
+        assert_eq!(output.shared_secret_bytes, Some(32));
+    }
+}
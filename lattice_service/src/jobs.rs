@@ -0,0 +1,173 @@
+use axum::extract::{Path, State};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+use crate::format::{Accepted, Formatted};
+use crate::{AppError, AppState, KemBenchResponse};
+
+// ============ Async Benchmark Jobs ============
+//
+// POST /kem_bench?async=true hands the run to a background task and returns a job id at
+// once, for clients behind proxies that time out long requests. Jobs live only in memory:
+// finished ones are dropped after the configured TTL, and the store holds at most
+// max_jobs entries, evicting the oldest finished job to make room.
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for a free benchmark slot
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+/// Current state of a job, as returned by POST /kem_bench?async=true and GET /jobs/{id}
+#[derive(Serialize, Clone, ToSchema)]
+pub struct JobView {
+    job_id: String,
+    status: JobStatus,
+    /// Present once status is done
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<KemBenchResponse>,
+    /// Present once status is failed; the error the synchronous request would have returned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl JobView {
+    pub fn job_id(&self) -> &str {
+        &self.job_id
+    }
+}
+
+struct Job {
+    view: JobView,
+    finished_at: Option<Instant>,
+}
+
+pub struct JobStore {
+    jobs: Mutex<HashMap<String, Job>>,
+    ttl: Duration,
+    max_jobs: usize,
+}
+
+impl JobStore {
+    pub fn new(ttl: Duration, max_jobs: usize) -> Self {
+        JobStore { jobs: Mutex::new(HashMap::new()), ttl, max_jobs }
+    }
+
+    /// Registers a queued job, or refuses when every slot holds an unfinished one
+    pub fn submit(&self) -> Result<JobView, AppError> {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, job| job.finished_at.is_none_or(|at| at.elapsed() < self.ttl));
+        if jobs.len() >= self.max_jobs {
+            let oldest_finished = jobs
+                .iter()
+                .filter_map(|(id, job)| Some((job.finished_at?, id)))
+                .min()
+                .map(|(_, id)| id.clone())
+                .ok_or(AppError::TooManyJobs(self.max_jobs))?;
+            jobs.remove(&oldest_finished);
+        }
+
+        let job_id = format!("{:032x}", rand::random::<u128>());
+        let view = JobView { job_id: job_id.clone(), status: JobStatus::Queued, result: None, error: None };
+        jobs.insert(job_id, Job { view: view.clone(), finished_at: None });
+        Ok(view)
+    }
+
+    pub fn start(&self, job_id: &str) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(job_id) {
+            job.view.status = JobStatus::Running;
+        }
+    }
+
+    pub fn finish(&self, job_id: &str, outcome: Result<KemBenchResponse, String>) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(job_id) {
+            match outcome {
+                Ok(resp) => {
+                    job.view.status = JobStatus::Done;
+                    job.view.result = Some(resp);
+                }
+                Err(error) => {
+                    job.view.status = JobStatus::Failed;
+                    job.view.error = Some(error);
+                }
+            }
+            job.finished_at = Some(Instant::now());
+        }
+    }
+
+    /// None for unknown ids and for finished jobs past their TTL
+    pub fn get(&self, job_id: &str) -> Option<JobView> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(job_id)?;
+        if job.finished_at.is_some_and(|at| at.elapsed() >= self.ttl) {
+            return None;
+        }
+        Some(job.view.clone())
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    params(("id" = String, Path, description = "job_id returned by POST /kem_bench?async=true")),
+    responses(
+        (status = 200, description = "Job status, with the benchmark result once done", body = JobView),
+        (status = 404, description = "NotFound: no such job, or it finished longer ago than the job TTL", body = crate::ErrorBody),
+    )
+)]
+pub async fn job(
+    State(state): State<AppState>,
+    Accepted(format): Accepted,
+    Path(job_id): Path<String>,
+) -> Result<Formatted<JobView>, AppError> {
+    state
+        .jobs
+        .get(&job_id)
+        .map(|view| Formatted(format, view))
+        .ok_or_else(|| AppError::NotFound(format!("No job '{}'; it may have expired", job_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finished(store: &JobStore) -> String {
+        let job_id = store.submit().unwrap().job_id;
+        store.finish(&job_id, Err("boom".to_string()));
+        job_id
+    }
+
+    #[test]
+    fn full_store_evicts_oldest_finished_job() {
+        let store = JobStore::new(Duration::from_secs(60), 2);
+        let first = finished(&store);
+        let second = finished(&store);
+
+        let third = store.submit().unwrap().job_id;
+        assert!(store.get(&first).is_none());
+        assert_eq!(store.get(&second).unwrap().status, JobStatus::Failed);
+        assert_eq!(store.get(&third).unwrap().status, JobStatus::Queued);
+
+        // Once every stored job is unfinished there is nothing to evict
+        store.submit().unwrap();
+        assert!(matches!(store.submit(), Err(AppError::TooManyJobs(2))));
+    }
+
+    #[test]
+    fn finished_jobs_expire_after_ttl() {
+        let store = JobStore::new(Duration::ZERO, 4);
+        let job_id = store.submit().unwrap().job_id;
+        store.start(&job_id);
+        assert_eq!(store.get(&job_id).unwrap().status, JobStatus::Running);
+
+        store.finish(&job_id, Err("boom".to_string()));
+        assert!(store.get(&job_id).is_none());
+    }
+}
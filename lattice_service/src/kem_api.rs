@@ -0,0 +1,786 @@
+use axum::Json;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ml_kem::{*, kem::{Encapsulate, Decapsulate}};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use std::time::Instant;
+use utoipa::ToSchema;
+
+use crate::codec::Encoding;
+use crate::kem_dem::{derive_key, Cipher, KEY_DERIVATION, NONCE_BYTES};
+use crate::{sizes_of, AppError, BenchmarkableKem, KemSizes};
+use common::{params::ParamSet, secret::SecretBox};
+
+// ============ KEM API Types ============
+//
+// These endpoints do real key exchange, so requests are strict: unknown fields (such as
+// the benchmark-only seed) are rejected rather than silently ignored.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeygenRequest {
+    param_set: ParamSet,
+    #[serde(default)]
+    encoding: Encoding,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct KeygenResponse {
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+    encoding: Encoding,
+    encapsulation_key: String,
+    decapsulation_key: String,
+}
+
+// Batch keygen hands load generators many distinct keys in one round trip
+
+/// Most keypairs one /keygen_batch request may ask for
+pub const MAX_KEYGEN_BATCH: u32 = 1000;
+// Keygen is cheap enough that a few threads saturate the response encoding anyway
+const MAX_KEYGEN_BATCH_THREADS: usize = 4;
+
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KeygenBatchRequest {
+    /// An ML-KEM param set, canonical name or alias
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+    #[schema(minimum = 1, maximum = 1000)]
+    count: u32,
+}
+
+/// base64 keys
+#[derive(Serialize, ToSchema)]
+pub struct Keypair {
+    encapsulation_key: String,
+    decapsulation_key: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct KeygenBatchResponse {
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+    keypairs: Vec<Keypair>,
+    /// Wall-clock time to generate every keypair, excluding encoding
+    generation_time_ms: f64,
+    /// Threads the keypairs were generated on
+    threads: usize,
+}
+
+// Deterministic keygen is for reproducible tests only: anyone who knows the seed knows the key
+
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DeterministicKeygenRequest {
+    /// An ML-KEM param set, canonical name or alias
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+    /// 32 bytes, hex-encoded
+    seed: String,
+}
+
+/// Keys are always base64
+#[derive(Serialize, ToSchema)]
+pub struct DeterministicKeygenResponse {
+    #[serde(flatten)]
+    keys: KeygenResponse,
+    /// Hex SHA-256 of the encapsulation key, to compare keys across runs at a glance
+    fingerprint: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EncapsRequest {
+    param_set: ParamSet,
+    encapsulation_key: String,
+    #[serde(default)]
+    encoding: Encoding,
+}
+
+#[derive(Serialize)]
+pub struct EncapsResponse {
+    param_set: ParamSet,
+    encoding: Encoding,
+    ciphertext: String,
+    shared_secret: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DecapsRequest {
+    param_set: ParamSet,
+    decapsulation_key: String,
+    ciphertext: String,
+    #[serde(default)]
+    encoding: Encoding,
+}
+
+#[derive(Serialize)]
+pub struct DecapsResponse {
+    param_set: ParamSet,
+    encoding: Encoding,
+    shared_secret: String,
+}
+
+// KEM-DEM encryption: the KEM shared secret never leaves the server, only the AEAD output
+
+// Every binary field is base64
+
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EncryptMessageRequest {
+    /// An ML-KEM param set, canonical name or alias
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+    encapsulation_key: String,
+    plaintext: String,
+    #[serde(default)]
+    cipher: Cipher,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct EncryptMessageResponse {
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+    cipher: Cipher,
+    kem_ciphertext: String,
+    nonce: String,
+    aead_ciphertext: String,
+    /// How the AEAD key was derived from the shared secret
+    key_derivation: &'static str,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DecryptMessageRequest {
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+    decapsulation_key: String,
+    kem_ciphertext: String,
+    nonce: String,
+    aead_ciphertext: String,
+    #[serde(default)]
+    cipher: Cipher,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DecryptMessageResponse {
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+    cipher: Cipher,
+    plaintext: String,
+    key_derivation: &'static str,
+}
+
+// ============ Encoding Helpers ============
+
+pub(crate) fn decode_base64(field: &str, value: &str) -> Result<Vec<u8>, AppError> {
+    Encoding::Base64.decode(field, value)
+}
+
+// Parse a fixed-size byte array, rejecting anything that isn't exactly the expected length
+fn fixed_length<N: ArraySize>(field: &str, bytes: &[u8]) -> Result<array::Array<u8, N>, AppError> {
+    array::Array::try_from(bytes).map_err(|_| {
+        AppError::MalformedInput(format!(
+            "{} must be {} bytes, got {}",
+            field,
+            N::USIZE,
+            bytes.len()
+        ))
+    })
+}
+
+/// Encoded sizes for one ML-KEM param set, so each field can be decoded to an exact length
+fn kem_sizes(param_set: ParamSet) -> Result<KemSizes, AppError> {
+    match param_set {
+        ParamSet::MlKem512 => Ok(sizes_of::<MlKem512>()),
+        ParamSet::MlKem768 => Ok(sizes_of::<MlKem768>()),
+        ParamSet::MlKem1024 => Ok(sizes_of::<MlKem1024>()),
+        _ => Err(AppError::InvalidParamSet(param_set.to_string())),
+    }
+}
+
+const SEED_BYTES: usize = 32;
+
+fn seed(value: &str) -> Result<[u8; SEED_BYTES], AppError> {
+    let bytes = hex::decode(value).map_err(|e| AppError::MalformedInput(format!("seed is not valid hex: {}", e)))?;
+    bytes.as_slice().try_into().map_err(|_| {
+        AppError::ValidationFailed(format!("seed must be {} bytes ({} hex digits), got {}", SEED_BYTES, SEED_BYTES * 2, bytes.len()))
+    })
+}
+
+fn nonce(value: &str) -> Result<[u8; NONCE_BYTES], AppError> {
+    let bytes = decode_base64("nonce", value)?;
+    bytes.as_slice().try_into().map_err(|_| {
+        AppError::MalformedInput(format!("nonce must be {} bytes, got {}", NONCE_BYTES, bytes.len()))
+    })
+}
+
+// ============ Generic KEM Operations ============
+//
+// Decapsulation keys zeroize themselves on drop (ml-kem's zeroize feature); shared secrets
+// and encoded decapsulation keys are held in SecretBox until they've been written out as text.
+
+pub(crate) fn keygen_with<K: BenchmarkableKem>() -> (Vec<u8>, SecretBox<Vec<u8>>) {
+    let (dk, ek) = K::generate(&mut OsRng);
+    (ek.as_bytes().to_vec(), SecretBox::new(dk.as_bytes().to_vec()))
+}
+
+// Encapsulation key and boxed decapsulation key, as keygen_with returns them
+type EncodedKeypair = (Vec<u8>, SecretBox<Vec<u8>>);
+
+// Splits the keypairs across threads as evenly as possible, the way threaded benchmarks do
+fn keygen_batch_with<K: BenchmarkableKem>(count: usize, threads: usize) -> Vec<EncodedKeypair> {
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|i| count / threads + usize::from(i < count % threads))
+            .map(|share| scope.spawn(move || (0..share).map(|_| keygen_with::<K>()).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("keygen thread panicked"))
+            .collect()
+    })
+}
+
+// FIPS 203 keygen takes two 32-byte seeds, d and z; both come from SHA-512 of the one seed
+fn keygen_from_seed<K: BenchmarkableKem>(seed: &[u8; SEED_BYTES]) -> (Vec<u8>, SecretBox<Vec<u8>>) {
+    let expanded = SecretBox::new(Sha512::digest(seed));
+    let (d, z) = expanded.expose_secret().split_at(32);
+    let d = SecretBox::new(B32::try_from(d).expect("SHA-512 halves are 32 bytes"));
+    let z = SecretBox::new(B32::try_from(z).expect("SHA-512 halves are 32 bytes"));
+    let (dk, ek) = K::generate_deterministic(d.expose_secret(), z.expose_secret());
+    (ek.as_bytes().to_vec(), SecretBox::new(dk.as_bytes().to_vec()))
+}
+
+fn encaps_with<K: BenchmarkableKem>(ek_bytes: &[u8]) -> Result<(Vec<u8>, SecretBox<Vec<u8>>), AppError> {
+    let encoded = fixed_length("encapsulation_key", ek_bytes)?;
+    let ek = K::EncapsulationKey::from_bytes(&encoded);
+    let (ct, ss) = ek
+        .encapsulate(&mut OsRng)
+        .map_err(|_| AppError::MalformedInput("encapsulation failed".to_string()))?;
+    let ss = SecretBox::new(ss);
+    Ok((ct.to_vec(), SecretBox::new(ss.expose_secret().to_vec())))
+}
+
+// ML-KEM decapsulation uses implicit rejection: any correctly sized ciphertext yields a
+// shared secret (a pseudorandom one if it was tampered with), so only length is validated here.
+pub(crate) fn decaps_with<K: BenchmarkableKem>(dk_bytes: &[u8], ct_bytes: &[u8]) -> Result<SecretBox<Vec<u8>>, AppError> {
+    let encoded = SecretBox::new(fixed_length("decapsulation_key", dk_bytes)?);
+    let dk = K::DecapsulationKey::from_bytes(encoded.expose_secret());
+    let ct: Ciphertext<K> = fixed_length("ciphertext", ct_bytes)?;
+    let ss = dk
+        .decapsulate(&ct)
+        .map_err(|_| AppError::MalformedInput("decapsulation failed".to_string()))?;
+    let ss = SecretBox::new(ss);
+    Ok(SecretBox::new(ss.expose_secret().to_vec()))
+}
+
+// ============ Handlers ============
+
+pub async fn keygen(Json(req): Json<KeygenRequest>) -> Result<Json<KeygenResponse>, AppError> {
+    let (ek, dk) = match req.param_set {
+        ParamSet::MlKem512 => keygen_with::<MlKem512>(),
+        ParamSet::MlKem768 => keygen_with::<MlKem768>(),
+        ParamSet::MlKem1024 => keygen_with::<MlKem1024>(),
+        _ => return Err(AppError::InvalidParamSet(req.param_set.to_string())),
+    };
+
+    Ok(Json(KeygenResponse {
+        param_set: req.param_set,
+        encoding: req.encoding,
+        encapsulation_key: req.encoding.encode(&ek),
+        decapsulation_key: req.encoding.encode(dk.expose_secret()),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/keygen_batch",
+    request_body = KeygenBatchRequest,
+    responses(
+        (status = 200, description = "count distinct keypairs", body = KeygenBatchResponse),
+        (status = 400, description = "InvalidParamSet: not an ML-KEM param set", body = crate::ErrorBody),
+        (status = 422, description = "ValidationFailed: count is outside 1..=1000", body = crate::ErrorBody),
+        (status = 500, description = "Internal: the keygen task failed", body = crate::ErrorBody),
+    )
+)]
+pub async fn keygen_batch(Json(req): Json<KeygenBatchRequest>) -> Result<Json<KeygenBatchResponse>, AppError> {
+    if req.count == 0 || req.count > MAX_KEYGEN_BATCH {
+        return Err(AppError::ValidationFailed(format!(
+            "count must be between 1 and {}, got {}", MAX_KEYGEN_BATCH, req.count
+        )));
+    }
+    let keygen: fn(usize, usize) -> Vec<EncodedKeypair> = match req.param_set {
+        ParamSet::MlKem512 => keygen_batch_with::<MlKem512>,
+        ParamSet::MlKem768 => keygen_batch_with::<MlKem768>,
+        ParamSet::MlKem1024 => keygen_batch_with::<MlKem1024>,
+        _ => return Err(AppError::InvalidParamSet(req.param_set.to_string())),
+    };
+    let count = req.count as usize;
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(MAX_KEYGEN_BATCH_THREADS)
+        .min(count);
+
+    let (keypairs, elapsed) = tokio::task::spawn_blocking(move || {
+        let start = Instant::now();
+        let keypairs = keygen(count, threads);
+        (keypairs, start.elapsed())
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("keygen task failed: {}", e)))?;
+
+    Ok(Json(KeygenBatchResponse {
+        param_set: req.param_set,
+        keypairs: keypairs
+            .into_iter()
+            .map(|(ek, dk)| Keypair {
+                encapsulation_key: STANDARD.encode(ek),
+                decapsulation_key: STANDARD.encode(dk.expose_secret()),
+            })
+            .collect(),
+        generation_time_ms: elapsed.as_secs_f64() * 1000.0,
+        threads,
+    }))
+}
+
+// Kept apart from /keygen so a seeded key can't be requested by accident
+#[utoipa::path(
+    post,
+    path = "/keygen_deterministic",
+    request_body = DeterministicKeygenRequest,
+    responses(
+        (status = 200, description = "The keypair the seed derives, the same on every call; for reproducible tests only", body = DeterministicKeygenResponse),
+        (status = 400, description = "InvalidParamSet: not an ML-KEM param set; or MalformedInput: seed isn't hex", body = crate::ErrorBody),
+        (status = 422, description = "ValidationFailed: seed isn't 32 bytes (64 hex digits)", body = crate::ErrorBody),
+    )
+)]
+pub async fn keygen_deterministic(
+    Json(req): Json<DeterministicKeygenRequest>,
+) -> Result<Json<DeterministicKeygenResponse>, AppError> {
+    let seed = SecretBox::new(seed(&req.seed)?);
+    let (ek, dk) = match req.param_set {
+        ParamSet::MlKem512 => keygen_from_seed::<MlKem512>(seed.expose_secret()),
+        ParamSet::MlKem768 => keygen_from_seed::<MlKem768>(seed.expose_secret()),
+        ParamSet::MlKem1024 => keygen_from_seed::<MlKem1024>(seed.expose_secret()),
+        _ => return Err(AppError::InvalidParamSet(req.param_set.to_string())),
+    };
+
+    Ok(Json(DeterministicKeygenResponse {
+        fingerprint: hex::encode(Sha256::digest(&ek)),
+        keys: KeygenResponse {
+            param_set: req.param_set,
+            encoding: Encoding::Base64,
+            encapsulation_key: STANDARD.encode(ek),
+            decapsulation_key: STANDARD.encode(dk.expose_secret()),
+        },
+    }))
+}
+
+pub async fn encaps(Json(req): Json<EncapsRequest>) -> Result<Json<EncapsResponse>, AppError> {
+    let sizes = kem_sizes(req.param_set)?;
+    let ek = req.encoding.decode_exact("encapsulation_key", &req.encapsulation_key, sizes.encapsulation_key)?;
+
+    let (ct, ss) = match req.param_set {
+        ParamSet::MlKem512 => encaps_with::<MlKem512>(&ek)?,
+        ParamSet::MlKem768 => encaps_with::<MlKem768>(&ek)?,
+        ParamSet::MlKem1024 => encaps_with::<MlKem1024>(&ek)?,
+        _ => return Err(AppError::InvalidParamSet(req.param_set.to_string())),
+    };
+
+    Ok(Json(EncapsResponse {
+        param_set: req.param_set,
+        encoding: req.encoding,
+        ciphertext: req.encoding.encode(&ct),
+        shared_secret: req.encoding.encode(ss.expose_secret()),
+    }))
+}
+
+pub async fn decaps(Json(req): Json<DecapsRequest>) -> Result<Json<DecapsResponse>, AppError> {
+    let sizes = kem_sizes(req.param_set)?;
+    let dk = SecretBox::new(req.encoding.decode_exact("decapsulation_key", &req.decapsulation_key, sizes.decapsulation_key)?);
+    let ct = req.encoding.decode_exact("ciphertext", &req.ciphertext, sizes.ciphertext)?;
+
+    let ss = match req.param_set {
+        ParamSet::MlKem512 => decaps_with::<MlKem512>(dk.expose_secret(), &ct)?,
+        ParamSet::MlKem768 => decaps_with::<MlKem768>(dk.expose_secret(), &ct)?,
+        ParamSet::MlKem1024 => decaps_with::<MlKem1024>(dk.expose_secret(), &ct)?,
+        _ => return Err(AppError::InvalidParamSet(req.param_set.to_string())),
+    };
+
+    Ok(Json(DecapsResponse {
+        param_set: req.param_set,
+        encoding: req.encoding,
+        shared_secret: req.encoding.encode(ss.expose_secret()),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/encrypt_message",
+    request_body = EncryptMessageRequest,
+    responses(
+        (status = 200, description = "The KEM ciphertext and the AEAD-sealed plaintext, with the nonce", body = EncryptMessageResponse),
+        (status = 400, description = "InvalidParamSet: not an ML-KEM param set; or MalformedInput: a field isn't base64, or the encapsulation key is the wrong length", body = crate::ErrorBody),
+        (status = 422, description = "The body isn't an encrypt request: an unknown param_set or cipher, or a missing or unknown field"),
+    )
+)]
+pub async fn encrypt_message(Json(req): Json<EncryptMessageRequest>) -> Result<Json<EncryptMessageResponse>, AppError> {
+    let ek = decode_base64("encapsulation_key", &req.encapsulation_key)?;
+    let plaintext = decode_base64("plaintext", &req.plaintext)?;
+
+    let (ct, ss) = match req.param_set {
+        ParamSet::MlKem512 => encaps_with::<MlKem512>(&ek)?,
+        ParamSet::MlKem768 => encaps_with::<MlKem768>(&ek)?,
+        ParamSet::MlKem1024 => encaps_with::<MlKem1024>(&ek)?,
+        _ => return Err(AppError::InvalidParamSet(req.param_set.to_string())),
+    };
+    let mut nonce = [0u8; NONCE_BYTES];
+    OsRng.fill_bytes(&mut nonce);
+    let aead_ciphertext = req.cipher.seal(derive_key(ss.expose_secret()).expose_secret(), &nonce, &plaintext);
+
+    Ok(Json(EncryptMessageResponse {
+        param_set: req.param_set,
+        cipher: req.cipher,
+        kem_ciphertext: STANDARD.encode(ct),
+        nonce: STANDARD.encode(nonce),
+        aead_ciphertext: STANDARD.encode(aead_ciphertext),
+        key_derivation: KEY_DERIVATION,
+    }))
+}
+
+// A tampered kem_ciphertext still decapsulates (implicit rejection), just to the wrong
+// key, so it surfaces here as an AEAD authentication failure like any other tampering
+#[utoipa::path(
+    post,
+    path = "/decrypt_message",
+    request_body = DecryptMessageRequest,
+    responses(
+        (status = 200, description = "The decrypted plaintext", body = DecryptMessageResponse),
+        (status = 400, description = "AuthenticationFailed: the AEAD tag didn't verify, because the key, KEM ciphertext, nonce or AEAD ciphertext doesn't match what was sealed; \
+            InvalidParamSet: not an ML-KEM param set; or MalformedInput: a field isn't base64, or is the wrong length", body = crate::ErrorBody),
+        (status = 422, description = "The body isn't a decrypt request: an unknown param_set or cipher, or a missing or unknown field"),
+    )
+)]
+pub async fn decrypt_message(Json(req): Json<DecryptMessageRequest>) -> Result<Json<DecryptMessageResponse>, AppError> {
+    let dk = SecretBox::new(decode_base64("decapsulation_key", &req.decapsulation_key)?);
+    let ct = decode_base64("kem_ciphertext", &req.kem_ciphertext)?;
+    let nonce = nonce(&req.nonce)?;
+    let aead_ciphertext = decode_base64("aead_ciphertext", &req.aead_ciphertext)?;
+
+    let ss = match req.param_set {
+        ParamSet::MlKem512 => decaps_with::<MlKem512>(dk.expose_secret(), &ct)?,
+        ParamSet::MlKem768 => decaps_with::<MlKem768>(dk.expose_secret(), &ct)?,
+        ParamSet::MlKem1024 => decaps_with::<MlKem1024>(dk.expose_secret(), &ct)?,
+        _ => return Err(AppError::InvalidParamSet(req.param_set.to_string())),
+    };
+    let plaintext = req
+        .cipher
+        .open(derive_key(ss.expose_secret()).expose_secret(), &nonce, &aead_ciphertext)
+        .ok_or(AppError::AuthenticationFailed)?;
+
+    Ok(Json(DecryptMessageResponse {
+        param_set: req.param_set,
+        cipher: req.cipher,
+        plaintext: STANDARD.encode(plaintext),
+        key_derivation: KEY_DERIVATION,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARAM_SETS: [&str; 3] = ["ml_kem_512", "ml_kem_768", "ml_kem_1024"];
+
+    async fn keygen_for(param_set: &str) -> KeygenResponse {
+        let Json(resp) = keygen(Json(KeygenRequest { param_set: param_set.parse().unwrap(), encoding: Encoding::Base64 }))
+            .await
+            .unwrap_or_else(|_| panic!("keygen failed for {}", param_set));
+        resp
+    }
+
+    async fn encaps_for(param_set: &str, ek: &str) -> EncapsResponse {
+        let Json(resp) = encaps(Json(EncapsRequest {
+            param_set: param_set.parse().unwrap(),
+            encapsulation_key: ek.to_string(),
+            encoding: Encoding::Base64,
+        }))
+        .await
+        .unwrap_or_else(|_| panic!("encaps failed for {}", param_set));
+        resp
+    }
+
+    #[tokio::test]
+    async fn keygen_encaps_decaps_round_trip() {
+        for param_set in PARAM_SETS {
+            let keys = keygen_for(param_set).await;
+            let sender = encaps_for(param_set, &keys.encapsulation_key).await;
+
+            let Json(receiver) = decaps(Json(DecapsRequest {
+                param_set: param_set.parse().unwrap(),
+                decapsulation_key: keys.decapsulation_key,
+                ciphertext: sender.ciphertext,
+                encoding: Encoding::Base64,
+            }))
+            .await
+            .unwrap_or_else(|_| panic!("decaps failed for {}", param_set));
+
+            assert_eq!(sender.shared_secret, receiver.shared_secret, "{}", param_set);
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_choose_the_encoding_of_every_binary_field() {
+        for encoding in [Encoding::Hex, Encoding::Base64url] {
+            let Json(keys) = keygen(Json(KeygenRequest { param_set: ParamSet::MlKem768, encoding })).await.unwrap();
+            assert_eq!(keys.encoding, encoding);
+            assert_eq!(encoding.decode("ek", &keys.encapsulation_key).unwrap().len(), 1184, "{}", encoding);
+
+            let encaps_request = EncapsRequest { param_set: ParamSet::MlKem768, encapsulation_key: keys.encapsulation_key.clone(), encoding };
+            let Json(sender) = encaps(Json(encaps_request)).await.unwrap();
+            assert_eq!(sender.encoding, encoding);
+            let Json(receiver) = decaps(Json(DecapsRequest {
+                param_set: ParamSet::MlKem768,
+                decapsulation_key: keys.decapsulation_key.clone(),
+                ciphertext: sender.ciphertext.clone(),
+                encoding,
+            }))
+            .await
+            .unwrap();
+            assert_eq!((receiver.encoding, &receiver.shared_secret), (encoding, &sender.shared_secret));
+
+            // A hex key with a base64 ciphertext, under either label
+            let base64_ct = STANDARD.encode(encoding.decode("ct", &sender.ciphertext).unwrap());
+            for declared in [encoding, Encoding::Base64] {
+                let decapsulation_key = if declared == encoding {
+                    keys.decapsulation_key.clone()
+                } else {
+                    STANDARD.encode(encoding.decode("dk", &keys.decapsulation_key).unwrap())
+                };
+                let ciphertext = if declared == encoding { base64_ct.clone() } else { sender.ciphertext.clone() };
+                let err = decaps(Json(DecapsRequest { param_set: ParamSet::MlKem768, decapsulation_key, ciphertext, encoding: declared }))
+                    .await
+                    .err()
+                    .unwrap();
+                let (status, message) = err.status_and_message();
+                assert_eq!(status, axum::http::StatusCode::UNPROCESSABLE_ENTITY, "{}", message);
+                assert!(message.starts_with("ciphertext is "), "{}", message);
+            }
+        }
+
+        let request: KeygenRequest = serde_json::from_value(serde_json::json!({ "param_set": "ml_kem_512" })).unwrap();
+        assert_eq!(request.encoding, Encoding::Base64);
+        let request = serde_json::json!({ "param_set": "ml_kem_512", "encoding": "base32" });
+        assert!(serde_json::from_value::<KeygenRequest>(request).is_err());
+    }
+
+    #[tokio::test]
+    async fn tampered_ciphertext_is_implicitly_rejected() {
+        let keys = keygen_for("ml_kem_768").await;
+        let sender = encaps_for("ml_kem_768", &keys.encapsulation_key).await;
+
+        let mut ct = STANDARD.decode(&sender.ciphertext).unwrap();
+        ct[0] ^= 0x01;
+
+        let Json(receiver) = decaps(Json(DecapsRequest {
+            param_set: ParamSet::MlKem768,
+            decapsulation_key: keys.decapsulation_key,
+            ciphertext: STANDARD.encode(ct),
+            encoding: Encoding::Base64,
+        }))
+        .await
+        .unwrap_or_else(|_| panic!("tampered ciphertext should still decapsulate"));
+
+        assert_ne!(sender.shared_secret, receiver.shared_secret);
+    }
+
+    #[tokio::test]
+    async fn wrong_length_ciphertext_is_rejected() {
+        let keys = keygen_for("ml_kem_512").await;
+        let sender = encaps_for("ml_kem_512", &keys.encapsulation_key).await;
+
+        let mut ct = STANDARD.decode(&sender.ciphertext).unwrap();
+        ct.pop();
+
+        let result = decaps(Json(DecapsRequest {
+            param_set: ParamSet::MlKem512,
+            decapsulation_key: keys.decapsulation_key,
+            ciphertext: STANDARD.encode(ct),
+            encoding: Encoding::Base64,
+        }))
+        .await;
+
+        assert!(matches!(result, Err(AppError::MalformedInput(_))));
+    }
+
+    #[tokio::test]
+    async fn keygen_batch_returns_distinct_usable_keypairs() {
+        let Json(batch) = keygen_batch(Json(KeygenBatchRequest { param_set: ParamSet::MlKem512, count: 9 }))
+            .await
+            .unwrap_or_else(|_| panic!("keygen_batch failed"));
+        assert_eq!(batch.keypairs.len(), 9);
+        assert!(batch.threads >= 1 && batch.threads <= MAX_KEYGEN_BATCH_THREADS);
+        let mut eks: Vec<&str> = batch.keypairs.iter().map(|k| k.encapsulation_key.as_str()).collect();
+        eks.sort();
+        eks.dedup();
+        assert_eq!(eks.len(), 9);
+
+        let last = batch.keypairs.last().unwrap();
+        let sender = encaps_for("ml_kem_512", &last.encapsulation_key).await;
+        let Json(receiver) = decaps(Json(DecapsRequest {
+            param_set: ParamSet::MlKem512,
+            decapsulation_key: last.decapsulation_key.clone(),
+            ciphertext: sender.ciphertext,
+            encoding: Encoding::Base64,
+        }))
+        .await
+        .unwrap_or_else(|_| panic!("batch key should decapsulate"));
+        assert_eq!(sender.shared_secret, receiver.shared_secret);
+
+        for count in [0, MAX_KEYGEN_BATCH + 1] {
+            let result = keygen_batch(Json(KeygenBatchRequest { param_set: ParamSet::MlKem512, count })).await;
+            assert!(matches!(result, Err(AppError::ValidationFailed(_))), "{}", count);
+        }
+    }
+
+    async fn keygen_from(param_set: &str, seed: &str) -> Result<DeterministicKeygenResponse, AppError> {
+        let req = DeterministicKeygenRequest { param_set: param_set.parse().unwrap(), seed: seed.to_string() };
+        keygen_deterministic(Json(req)).await.map(|Json(resp)| resp)
+    }
+
+    const SEED: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[tokio::test]
+    async fn seeded_keygen_is_reproducible_and_usable() {
+        let keys = keygen_from("ml_kem_768", SEED).await.unwrap();
+        // Pinned, so a change in how the seed is expanded (or in ml-kem) can't go unnoticed
+        assert_eq!(keys.fingerprint, "f03f15bcb8d26f4412a06280ebab459a84e65f34afcd7f8818bd01fcfcca723c");
+        assert_eq!(keys.fingerprint, hex::encode(Sha256::digest(STANDARD.decode(&keys.keys.encapsulation_key).unwrap())));
+        let again = keygen_from("ML-KEM-768", &SEED.to_uppercase()).await.unwrap();
+        assert_eq!(again.keys.decapsulation_key, keys.keys.decapsulation_key);
+
+        let other = keygen_from("ml_kem_768", &SEED.replace("1f", "20")).await.unwrap();
+        assert_ne!(other.fingerprint, keys.fingerprint);
+
+        let sender = encaps_for("ml_kem_768", &keys.keys.encapsulation_key).await;
+        let Json(receiver) = decaps(Json(DecapsRequest {
+            param_set: ParamSet::MlKem768,
+            decapsulation_key: keys.keys.decapsulation_key,
+            ciphertext: sender.ciphertext,
+            encoding: Encoding::Base64,
+        }))
+        .await
+        .unwrap_or_else(|_| panic!("seeded key should decapsulate"));
+        assert_eq!(sender.shared_secret, receiver.shared_secret);
+    }
+
+    #[tokio::test]
+    async fn seeds_of_the_wrong_length_are_422s() {
+        for seed in [&SEED[2..], &format!("{}00", SEED), ""] {
+            let err = keygen_from("ml_kem_512", seed).await.err().unwrap();
+            assert_eq!(err.status_and_message().0, axum::http::StatusCode::UNPROCESSABLE_ENTITY, "{}", seed);
+        }
+        assert!(matches!(keygen_from("ml_kem_512", &SEED.replace('0', "g")).await, Err(AppError::MalformedInput(_))));
+        assert!(matches!(keygen_from("x25519", SEED).await, Err(AppError::InvalidParamSet(_))));
+    }
+
+    async fn encrypt_for(param_set: &str, ek: &str, plaintext: &[u8], cipher: Cipher) -> EncryptMessageResponse {
+        let Json(resp) = encrypt_message(Json(EncryptMessageRequest {
+            param_set: param_set.parse().unwrap(),
+            encapsulation_key: ek.to_string(),
+            plaintext: STANDARD.encode(plaintext),
+            cipher,
+        }))
+        .await
+        .unwrap_or_else(|_| panic!("encrypt_message failed for {}", param_set));
+        resp
+    }
+
+    fn decrypt_request(param_set: &str, dk: &str, sealed: &EncryptMessageResponse) -> DecryptMessageRequest {
+        DecryptMessageRequest {
+            param_set: param_set.parse().unwrap(),
+            decapsulation_key: dk.to_string(),
+            kem_ciphertext: sealed.kem_ciphertext.clone(),
+            nonce: sealed.nonce.clone(),
+            aead_ciphertext: sealed.aead_ciphertext.clone(),
+            cipher: sealed.cipher,
+        }
+    }
+
+    #[tokio::test]
+    async fn encrypt_decrypt_message_round_trip() {
+        for (param_set, cipher) in [("ml_kem_512", Cipher::Aes256Gcm), ("ml_kem_1024", Cipher::ChaCha20Poly1305)] {
+            let keys = keygen_for(param_set).await;
+            let sealed = encrypt_for(param_set, &keys.encapsulation_key, b"attack at dawn", cipher).await;
+            assert_eq!(sealed.key_derivation, KEY_DERIVATION);
+
+            let Json(opened) = decrypt_message(Json(decrypt_request(param_set, &keys.decapsulation_key, &sealed)))
+                .await
+                .unwrap_or_else(|_| panic!("decrypt_message failed for {}", param_set));
+            assert_eq!(STANDARD.decode(opened.plaintext).unwrap(), b"attack at dawn");
+        }
+    }
+
+    #[tokio::test]
+    async fn tampered_messages_fail_authentication() {
+        let keys = keygen_for("ml_kem_768").await;
+        let sealed = encrypt_for("ml_kem_768", &keys.encapsulation_key, b"attack at dawn", Cipher::Aes256Gcm).await;
+
+        let flip = |value: &str| {
+            let mut bytes = STANDARD.decode(value).unwrap();
+            bytes[0] ^= 0x01;
+            STANDARD.encode(bytes)
+        };
+        let base = || decrypt_request("ml_kem_768", &keys.decapsulation_key, &sealed);
+        for tampered in [
+            DecryptMessageRequest { aead_ciphertext: flip(&sealed.aead_ciphertext), ..base() },
+            DecryptMessageRequest { kem_ciphertext: flip(&sealed.kem_ciphertext), ..base() },
+            DecryptMessageRequest { nonce: flip(&sealed.nonce), ..base() },
+            DecryptMessageRequest { cipher: Cipher::ChaCha20Poly1305, ..base() },
+        ] {
+            let err = decrypt_message(Json(tampered)).await.err().unwrap();
+            assert_eq!(err.kind(), "authentication_failed");
+            assert_eq!(err.status_and_message().0, axum::http::StatusCode::BAD_REQUEST);
+        }
+
+        let short_nonce = DecryptMessageRequest { nonce: STANDARD.encode([0u8; 8]), ..base() };
+        assert!(matches!(decrypt_message(Json(short_nonce)).await, Err(AppError::MalformedInput(_))));
+    }
+
+    // Compile-time checks: fails to build if ml-kem's zeroize feature is ever dropped
+    fn assert_zeroize_on_drop<T: zeroize::ZeroizeOnDrop>() {}
+
+    #[test]
+    fn decapsulation_keys_zeroize_on_drop() {
+        assert_zeroize_on_drop::<<MlKem512 as KemCore>::DecapsulationKey>();
+        assert_zeroize_on_drop::<<MlKem768 as KemCore>::DecapsulationKey>();
+        assert_zeroize_on_drop::<<MlKem1024 as KemCore>::DecapsulationKey>();
+    }
+
+    #[test]
+    fn secrets_leave_the_generic_path_boxed() {
+        let (ek, dk): (Vec<u8>, SecretBox<Vec<u8>>) = keygen_with::<MlKem768>();
+        let (ct, ss_sender): (Vec<u8>, SecretBox<Vec<u8>>) = encaps_with::<MlKem768>(&ek).unwrap();
+        let ss_receiver: SecretBox<Vec<u8>> = decaps_with::<MlKem768>(dk.expose_secret(), &ct).unwrap();
+        assert_eq!(ss_sender.expose_secret(), ss_receiver.expose_secret());
+        assert_eq!(format!("{:?}", ss_receiver), "SecretBox([REDACTED])");
+    }
+
+    #[test]
+    fn crypto_endpoints_never_accept_a_seed() {
+        let keygen = serde_json::json!({ "param_set": "ml_kem_768", "seed": 42 });
+        assert!(serde_json::from_value::<KeygenRequest>(keygen).is_err());
+
+        let encaps = serde_json::json!({ "param_set": "ml_kem_768", "encapsulation_key": "", "seed": 42 });
+        assert!(serde_json::from_value::<EncapsRequest>(encaps).is_err());
+
+        let decaps = serde_json::json!({
+            "param_set": "ml_kem_768", "decapsulation_key": "", "ciphertext": "", "seed": 42
+        });
+        assert!(serde_json::from_value::<DecapsRequest>(decaps).is_err());
+    }
+}
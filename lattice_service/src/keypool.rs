@@ -0,0 +1,209 @@
+use ml_kem::{KemCore, MlKem1024, MlKem512, MlKem768};
+use rand::rngs::OsRng;
+use serde::Serialize;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use utoipa::ToSchema;
+
+// ============ Keypair Pool ============
+//
+// key_mode fresh_per_iteration needs a new keypair for every iteration, and generating them
+// before the loop dominates the request's latency. With --keypair-pool-size, a background
+// thread keeps that many ML-KEM keypairs per param set ready; those benchmarks take what
+// they can from it and generate the rest on demand. Every take wakes the thread to top the
+// pool back up. Pooled keys come from OsRng, so seeded benchmarks never draw from it.
+
+pub type Keypair<K> = (<K as KemCore>::DecapsulationKey, <K as KemCore>::EncapsulationKey);
+
+/// Keypairs of one fresh_per_iteration benchmark, by where they came from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, ToSchema)]
+pub struct PoolUsage {
+    /// Taken ready-made from the pool
+    pub hits: u64,
+    /// Generated on demand because the pool ran short
+    pub generated: u64,
+}
+
+impl PoolUsage {
+    pub fn add(self, other: PoolUsage) -> PoolUsage {
+        PoolUsage { hits: self.hits + other.hits, generated: self.generated + other.generated }
+    }
+}
+
+pub struct KeypairPool {
+    ml_kem_512: Mutex<Vec<Keypair<MlKem512>>>,
+    ml_kem_768: Mutex<Vec<Keypair<MlKem768>>>,
+    ml_kem_1024: Mutex<Vec<Keypair<MlKem1024>>>,
+    /// Keypairs kept per param set
+    capacity: usize,
+    /// Wakes the refill thread; None for a pool nothing refills
+    refill: Option<SyncSender<()>>,
+}
+
+/// The ML-KEM param sets the pool holds keypairs for
+pub trait Pooled: KemCore {
+    fn slot(pool: &KeypairPool) -> &Mutex<Vec<Keypair<Self>>>;
+}
+
+impl Pooled for MlKem512 {
+    fn slot(pool: &KeypairPool) -> &Mutex<Vec<Keypair<Self>>> {
+        &pool.ml_kem_512
+    }
+}
+
+impl Pooled for MlKem768 {
+    fn slot(pool: &KeypairPool) -> &Mutex<Vec<Keypair<Self>>> {
+        &pool.ml_kem_768
+    }
+}
+
+impl Pooled for MlKem1024 {
+    fn slot(pool: &KeypairPool) -> &Mutex<Vec<Keypair<Self>>> {
+        &pool.ml_kem_1024
+    }
+}
+
+impl KeypairPool {
+    fn new(capacity: usize, refill: Option<SyncSender<()>>) -> Self {
+        KeypairPool {
+            ml_kem_512: Mutex::new(Vec::new()),
+            ml_kem_768: Mutex::new(Vec::new()),
+            ml_kem_1024: Mutex::new(Vec::new()),
+            capacity,
+            refill,
+        }
+    }
+
+    /// Starts the refill thread, which fills the pool right away. The thread exits once the
+    /// pool is dropped.
+    pub fn start(capacity: usize) -> Arc<Self> {
+        // One pending wake-up is enough, since a refill tops up every param set
+        let (tx, rx) = mpsc::sync_channel(1);
+        let pool = Arc::new(KeypairPool::new(capacity, Some(tx)));
+        let weak = Arc::downgrade(&pool);
+        std::thread::Builder::new()
+            .name("keypair-pool".to_string())
+            .spawn(move || {
+                while rx.recv().is_ok() {
+                    match weak.upgrade() {
+                        Some(pool) => pool.refill(),
+                        None => break,
+                    }
+                }
+            })
+            .expect("failed to start keypair pool thread");
+        pool.wake();
+        pool
+    }
+
+    fn wake(&self) {
+        if let Some(refill) = &self.refill {
+            // Full means a refill is already pending
+            let _ = refill.try_send(());
+        }
+    }
+
+    /// Keypairs ready for K right now
+    pub fn available<K: Pooled>(&self) -> usize {
+        K::slot(self).lock().unwrap().len()
+    }
+
+    /// Up to `n` keypairs for K, fewer once the pool runs out
+    pub fn take<K: Pooled>(&self, n: usize) -> Vec<Keypair<K>> {
+        let taken = {
+            let mut slot = K::slot(self).lock().unwrap();
+            let keep = slot.len().saturating_sub(n);
+            slot.split_off(keep)
+        };
+        if !taken.is_empty() {
+            self.wake();
+        }
+        taken
+    }
+
+    /// Tops every param set back up to capacity
+    pub fn refill(&self) {
+        self.top_up::<MlKem512>();
+        self.top_up::<MlKem768>();
+        self.top_up::<MlKem1024>();
+    }
+
+    // Keys are generated without the lock held, so benchmarks can keep taking meanwhile
+    fn top_up<K: Pooled>(&self) {
+        let missing = self.capacity.saturating_sub(self.available::<K>());
+        if missing == 0 {
+            return;
+        }
+        let fresh: Vec<_> = (0..missing).map(|_| K::generate(&mut OsRng)).collect();
+        let mut slot = K::slot(self).lock().unwrap();
+        slot.extend(fresh);
+        slot.truncate(self.capacity);
+    }
+}
+
+/// `n` keypairs for a fresh_per_iteration benchmark: from the pool while it lasts, then
+/// generated with `rng`. Without a pool nothing is counted.
+pub fn fresh_keypairs<K: Pooled, R: rand::RngCore + rand::CryptoRng>(
+    pool: Option<&KeypairPool>,
+    n: usize,
+    rng: &mut R,
+) -> (Vec<Keypair<K>>, Option<PoolUsage>) {
+    let mut keypairs = pool.map(|pool| pool.take::<K>(n)).unwrap_or_default();
+    let hits = keypairs.len();
+    keypairs.extend((hits..n).map(|_| K::generate(rng)));
+    let usage = pool.map(|_| PoolUsage { hits: hits as u64, generated: (n - hits) as u64 });
+    (keypairs, usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn draws_run_the_pool_dry_then_generate() {
+        let pool = KeypairPool::new(3, None);
+        pool.refill();
+        assert_eq!(pool.available::<MlKem512>(), 3);
+        assert_eq!(pool.available::<MlKem1024>(), 3);
+
+        let (keypairs, usage) = fresh_keypairs::<MlKem512, _>(Some(&pool), 2, &mut OsRng);
+        assert_eq!((keypairs.len(), usage), (2, Some(PoolUsage { hits: 2, generated: 0 })));
+        let (keypairs, usage) = fresh_keypairs::<MlKem512, _>(Some(&pool), 4, &mut OsRng);
+        assert_eq!((keypairs.len(), usage), (4, Some(PoolUsage { hits: 1, generated: 3 })));
+        // Exhausted, and nothing refills this pool
+        assert_eq!(pool.available::<MlKem512>(), 0);
+        assert!(pool.take::<MlKem512>(1).is_empty());
+        // Other param sets are untouched
+        assert_eq!(pool.available::<MlKem768>(), 3);
+
+        let (keypairs, usage) = fresh_keypairs::<MlKem768, _>(None, 2, &mut OsRng);
+        assert_eq!((keypairs.len(), usage), (2, None));
+        assert_eq!(pool.available::<MlKem768>(), 3);
+    }
+
+    #[test]
+    fn refills_stop_at_capacity() {
+        let pool = KeypairPool::new(2, None);
+        pool.refill();
+        pool.take::<MlKem768>(1);
+        pool.refill();
+        pool.refill();
+        assert_eq!(pool.available::<MlKem768>(), 2);
+    }
+
+    #[test]
+    fn the_background_thread_refills_after_a_take() {
+        let pool = KeypairPool::start(4);
+        let full = |pool: &KeypairPool| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while pool.available::<MlKem512>() < 4 || pool.available::<MlKem768>() < 4 || pool.available::<MlKem1024>() < 4 {
+                assert!(Instant::now() < deadline, "pool never filled");
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        };
+        full(&pool);
+        assert_eq!(pool.take::<MlKem1024>(10).len(), 4);
+        full(&pool);
+    }
+}
@@ -0,0 +1,289 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ml_kem::{MlKem1024, MlKem512, MlKem768};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+use crate::kem_api::{decaps_with, decode_base64, keygen_with};
+use crate::{AppError, AppState};
+use common::{params::ParamSet, secret::SecretBox};
+
+// ============ Server-Held Keypairs ============
+//
+// For protocols spread over several HTTP calls, POST /keys keeps the decapsulation key on
+// the server and hands out only an id and the encapsulation key. Keys expire a fixed TTL
+// after creation, and once the store is full the least recently used key is evicted.
+// Stored keys are zeroized when they expire, are evicted or deleted.
+
+struct StoredKey {
+    param_set: ParamSet,
+    decapsulation_key: SecretBox<Vec<u8>>,
+    created: Instant,
+    /// Tick of the last create or decaps, for LRU eviction
+    last_used: AtomicU64,
+}
+
+pub struct KeyStore {
+    keys: RwLock<HashMap<String, StoredKey>>,
+    /// Bumped on every use, so recency is exact even when two uses share a timestamp
+    clock: AtomicU64,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl KeyStore {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        KeyStore { keys: RwLock::new(HashMap::new()), clock: AtomicU64::new(0), ttl, capacity }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn insert(&self, param_set: ParamSet, decapsulation_key: SecretBox<Vec<u8>>) -> String {
+        let mut keys = self.keys.write().unwrap();
+        keys.retain(|_, key| key.created.elapsed() < self.ttl);
+        if keys.len() >= self.capacity {
+            let lru = keys
+                .iter()
+                .min_by_key(|(_, key)| key.last_used.load(Ordering::Relaxed))
+                .map(|(id, _)| id.clone());
+            if let Some(id) = lru {
+                keys.remove(&id);
+            }
+        }
+
+        let key_id = format!("{:032x}", rand::random::<u128>());
+        keys.insert(key_id.clone(), StoredKey {
+            param_set,
+            decapsulation_key,
+            created: Instant::now(),
+            last_used: AtomicU64::new(self.tick()),
+        });
+        key_id
+    }
+
+    /// Runs `f` on a live key, marking it used; None when the id is unknown or expired
+    fn with_key<T>(&self, key_id: &str, f: impl FnOnce(ParamSet, &[u8]) -> T) -> Option<T> {
+        let keys = self.keys.read().unwrap();
+        let key = keys.get(key_id).filter(|key| key.created.elapsed() < self.ttl)?;
+        key.last_used.store(self.tick(), Ordering::Relaxed);
+        Some(f(key.param_set, key.decapsulation_key.expose_secret()))
+    }
+
+    fn remove(&self, key_id: &str) -> bool {
+        self.keys.write().unwrap().remove(key_id).is_some_and(|key| key.created.elapsed() < self.ttl)
+    }
+}
+
+fn not_found(key_id: &str) -> AppError {
+    AppError::NotFound(format!("No key '{}'; it may have expired or been evicted", key_id))
+}
+
+// ============ Handlers ============
+
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CreateKeyRequest {
+    /// An ML-KEM param set, canonical name or alias
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreateKeyResponse {
+    key_id: String,
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+    /// base64
+    encapsulation_key: String,
+    /// Seconds until the server forgets the decapsulation key
+    expires_in_secs: u64,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KeyDecapsRequest {
+    /// base64, encapsulated to the stored key's encapsulation key
+    ciphertext: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct KeyDecapsResponse {
+    key_id: String,
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+    /// base64
+    shared_secret: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/keys",
+    request_body = CreateKeyRequest,
+    responses(
+        (status = 201, description = "A new keypair; the server keeps the decapsulation key until it expires, is evicted or deleted", body = CreateKeyResponse),
+        (status = 400, description = "InvalidParamSet: not an ML-KEM param set", body = crate::ErrorBody),
+        (status = 422, description = "The body isn't a create request: an unknown param_set, or a missing or unknown field"),
+    )
+)]
+pub async fn create_key(
+    State(state): State<AppState>,
+    Json(req): Json<CreateKeyRequest>,
+) -> Result<(StatusCode, Json<CreateKeyResponse>), AppError> {
+    let (ek, dk) = match req.param_set {
+        ParamSet::MlKem512 => keygen_with::<MlKem512>(),
+        ParamSet::MlKem768 => keygen_with::<MlKem768>(),
+        ParamSet::MlKem1024 => keygen_with::<MlKem1024>(),
+        _ => return Err(AppError::InvalidParamSet(req.param_set.to_string())),
+    };
+    let key_id = state.keys.insert(req.param_set, dk);
+
+    Ok((StatusCode::CREATED, Json(CreateKeyResponse {
+        key_id,
+        param_set: req.param_set,
+        encapsulation_key: STANDARD.encode(ek),
+        expires_in_secs: state.keys.ttl.as_secs(),
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/keys/{id}/decaps",
+    params(("id" = String, Path, description = "key_id from POST /keys")),
+    request_body = KeyDecapsRequest,
+    responses(
+        (status = 200, description = "The shared secret the stored key decapsulates the ciphertext to", body = KeyDecapsResponse),
+        (status = 400, description = "MalformedInput: ciphertext isn't base64 or is the wrong length for the key's param set", body = crate::ErrorBody),
+        (status = 404, description = "NotFound: no such key, or it has expired or been evicted", body = crate::ErrorBody),
+        (status = 422, description = "The body isn't a decaps request: a missing or unknown field"),
+    )
+)]
+pub async fn decaps_with_key(
+    State(state): State<AppState>,
+    Path(key_id): Path<String>,
+    Json(req): Json<KeyDecapsRequest>,
+) -> Result<Json<KeyDecapsResponse>, AppError> {
+    let ct = decode_base64("ciphertext", &req.ciphertext)?;
+    let (param_set, ss) = state
+        .keys
+        .with_key(&key_id, |param_set, dk| {
+            let ss = match param_set {
+                ParamSet::MlKem512 => decaps_with::<MlKem512>(dk, &ct),
+                ParamSet::MlKem768 => decaps_with::<MlKem768>(dk, &ct),
+                _ => decaps_with::<MlKem1024>(dk, &ct),
+            };
+            ss.map(|ss| (param_set, ss))
+        })
+        .ok_or_else(|| not_found(&key_id))??;
+
+    Ok(Json(KeyDecapsResponse {
+        key_id,
+        param_set,
+        shared_secret: STANDARD.encode(ss.expose_secret()),
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/keys/{id}",
+    params(("id" = String, Path, description = "key_id from POST /keys")),
+    responses(
+        (status = 204, description = "The decapsulation key was zeroized and forgotten"),
+        (status = 404, description = "NotFound: no such key, or it has already expired or been evicted", body = crate::ErrorBody),
+    )
+)]
+pub async fn delete_key(State(state): State<AppState>, Path(key_id): Path<String>) -> Result<StatusCode, AppError> {
+    if state.keys.remove(&key_id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(not_found(&key_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app, config::Config};
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    fn secret() -> SecretBox<Vec<u8>> {
+        SecretBox::new(vec![7; 4])
+    }
+
+    #[test]
+    fn full_store_evicts_least_recently_used() {
+        let store = KeyStore::new(Duration::from_secs(60), 2);
+        let first = store.insert(ParamSet::MlKem768, secret());
+        let second = store.insert(ParamSet::MlKem768, secret());
+        // Using the older key makes the newer one the eviction candidate
+        assert!(store.with_key(&first, |_, _| ()).is_some());
+
+        let third = store.insert(ParamSet::MlKem768, secret());
+        assert!(store.with_key(&first, |_, _| ()).is_some());
+        assert!(store.with_key(&second, |_, _| ()).is_none());
+        assert!(store.with_key(&third, |_, _| ()).is_some());
+    }
+
+    #[test]
+    fn keys_expire_after_ttl() {
+        let store = KeyStore::new(Duration::ZERO, 4);
+        let key_id = store.insert(ParamSet::MlKem768, secret());
+        assert!(store.with_key(&key_id, |_, _| ()).is_none());
+        assert!(!store.remove(&key_id));
+    }
+
+    async fn call(router: &axum::Router, method: &str, uri: &str, body: Option<serde_json::Value>) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn stored_key_decapsulates_client_ciphertexts() {
+        let router = app(AppState::new(Config::default()));
+        let (status, created) = call(&router, "POST", "/keys", Some(serde_json::json!({ "param_set": "ml_kem_512" }))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(created.get("decapsulation_key").is_none());
+        let key_id = created["key_id"].as_str().unwrap();
+
+        // The client side of the exchange, via the stateless endpoint
+        let encaps = serde_json::json!({ "param_set": "ml_kem_512", "encapsulation_key": created["encapsulation_key"] });
+        let (_, sender) = call(&router, "POST", "/encaps", Some(encaps)).await;
+
+        let decaps = serde_json::json!({ "ciphertext": sender["ciphertext"] });
+        let (status, receiver) = call(&router, "POST", &format!("/keys/{}/decaps", key_id), Some(decaps.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(receiver["shared_secret"], sender["shared_secret"]);
+
+        let (status, _) = call(&router, "DELETE", &format!("/keys/{}", key_id), None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = call(&router, "POST", &format!("/keys/{}/decaps", key_id), Some(decaps)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn an_empty_store_is_rejected_at_startup() {
+        use clap::Parser;
+
+        // Capacity 0 would leave insert evicting from an empty store and growing without bound
+        assert!(Config::try_parse_from(["lattice_service", "--max-stored-keys", "0"]).is_err());
+        assert_eq!(Config::parse_from(["lattice_service", "--max-stored-keys", "1"]).max_stored_keys, 1);
+    }
+}
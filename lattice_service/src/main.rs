@@ -1,35 +1,82 @@
 use axum::{
-    debug_handler,
     Router,
-    routing::{get, post},
+    routing::{delete, get, post},
     Json,
-    http::StatusCode,
+    extract::{rejection::QueryRejection, FromRef, Query, State},
+    middleware,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
 };
-use std::net::SocketAddr;
-use std::time::{SystemTime, UNIX_EPOCH, Instant};
-use tokio::net::TcpListener;
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH, Instant};
+use tokio::sync::Semaphore;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    compression::CompressionLayer,
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::Level;
+use tracing_subscriber::EnvFilter;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
 use serde::{Deserialize, Serialize};
-use ml_kem::{*, kem::{Encapsulate, Decapsulate}};
-use rand::rngs::OsRng;
-
-// === Health Check service ==
+use ml_kem::{*, array::typenum::Unsigned, kem::{Encapsulate, Decapsulate}};
+use ml_dsa::{MlDsa44, MlDsa65, MlDsa87};
+use slh_dsa::{Sha2_128f, Sha2_128s};
+use rand::{rngs::OsRng, CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 
-#[derive(serde::Serialize)]
-struct HealthResponse {
-    status: String,
-    service: String,
-    timestamp: u64,
-}
+use budget::{Budget, CpuTime, LoopWindow, Progress, Timeline};
+use clap::Parser;
+use common::{
+    auth::BearerAuthLayer,
+    cors,
+    health,
+    http,
+    listen,
+    machine::MachineInfo,
+    memory,
+    params::{Operation, ParamSet},
+    request::{self, FieldError},
+    secret::{self, SecretBox},
+    tls,
+};
+use config::Config;
+use format::{Accepted, Formatted, Negotiated};
+use results::Requester;
+use stats::{compute_histogram, compute_percentiles, compute_stats, run_spread, trim_outliers, Drift, HistogramBucket, RunSpread, Trend};
 
-#[debug_handler]
-async fn health() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "healthy".to_string(),
-        service: "lattice_service".to_string(),
-        timestamp: current_timestamp(),
-    })
-}
+mod attestation;
+mod backends;
+mod budget;
+mod classical;
+mod codec;
+mod compare;
+mod config;
+mod environment;
+mod format;
+mod hybrid;
+mod jobs;
+mod kem_api;
+mod kem_dem;
+mod keypool;
+mod keystore;
+mod matrix;
+mod metrics;
+mod mixed;
+mod openapi;
+mod params;
+mod pool;
+mod results;
+mod selftest;
+mod signatures;
+mod stats;
+mod stream;
+mod version;
+mod ws;
 
 fn current_timestamp() -> u64 {
     SystemTime::now()
@@ -44,45 +91,614 @@ fn current_timestamp() -> u64 {
 enum AppError {
     InvalidParamSet(String),
     InvalidOperation(String),
+    MalformedInput(String),
+    ValidationFailed(String),
+    /// The body parsed, but a field is unknown, missing or of the wrong type
+    InvalidField(FieldError),
+    NotAcceptable(String),
+    UnsupportedMediaType(String),
+    /// The body ran past --max-body-bytes without declaring its length up front
+    PayloadTooLarge(String),
+    TooManyBenchmarks(usize),
+    TooManyJobs(usize),
+    /// Memory or in-flight benchmarks are past a --shed-* limit
+    SheddingLoad(String),
+    NotFound(String),
+    /// An AEAD tag didn't verify: wrong key, or a tampered ciphertext or nonce
+    AuthenticationFailed,
+    /// One leg of a /kem_compare run failed; keeps that leg's kind and status
+    Leg(String, Box<AppError>),
+    Internal(String),
+    /// Benchmark code panicked, e.g. on an unwrap that should never fail
+    BenchmarkPanicked { param_set: String, operation: String, message: String },
+    /// Sender and receiver derived different shared secrets. Iterations count from 0 across
+    /// every measured run and thread of the request, each thread's share numbered after the
+    /// ones before it; a mismatch in the pilot or warmup counts within those.
+    SharedSecretMismatch { param_set: String, operation: String, iteration: u64 },
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, message) = match self {
+/// JSON body of every error response
+#[derive(Serialize, ToSchema)]
+struct ErrorBody {
+    error: String,
+    /// Offending request field, on invalid_field errors only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<String>,
+    /// What that field accepts; see FieldError
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<String>,
+    /// Param set of the benchmark that failed, on internal errors from a panic only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    param_set: Option<String>,
+    /// Operation of the benchmark that failed, alongside param_set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operation: Option<String>,
+    /// Iteration whose shared secrets differed, on shared_secret_mismatch errors only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iteration: Option<u64>,
+}
+
+// Suggested client backoff when every benchmark slot is busy or the server is shedding load
+const RETRY_AFTER_SECS: u64 = 1;
+
+impl AppError {
+    /// Renumbers a mismatch from a loop that started `earlier` iterations into the request
+    fn after_iterations(self, earlier: u64) -> AppError {
+        match self {
+            AppError::SharedSecretMismatch { param_set, operation, iteration } => {
+                AppError::SharedSecretMismatch { param_set, operation, iteration: iteration + earlier }
+            }
+            other => other,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            AppError::InvalidParamSet(_) => "invalid_param_set",
+            AppError::InvalidOperation(_) => "invalid_operation",
+            AppError::MalformedInput(_) => "malformed_input",
+            AppError::ValidationFailed(_) => "validation_failed",
+            AppError::InvalidField(_) => "invalid_field",
+            AppError::NotAcceptable(_) => "not_acceptable",
+            AppError::UnsupportedMediaType(_) => "unsupported_media_type",
+            AppError::PayloadTooLarge(_) => "payload_too_large",
+            AppError::TooManyBenchmarks(_) => "too_many_benchmarks",
+            AppError::TooManyJobs(_) => "too_many_jobs",
+            AppError::SheddingLoad(_) => "shedding_load",
+            AppError::NotFound(_) => "not_found",
+            AppError::AuthenticationFailed => "authentication_failed",
+            AppError::Leg(_, inner) => inner.kind(),
+            AppError::Internal(_) | AppError::BenchmarkPanicked { .. } => "internal",
+            AppError::SharedSecretMismatch { .. } => "shared_secret_mismatch",
+        }
+    }
+
+    fn status_and_message(self) -> (StatusCode, String) {
+        match self {
             AppError::InvalidParamSet(s) => (
                 StatusCode::BAD_REQUEST,
-                format!("Invalid param_set '{}'. Valid options: ml_kem_512, ml_kem_768, ml_kem_1024", s)
+                format!(
+                    "Invalid param_set '{}'. Valid options: {} (aliases such as ML-KEM-768 or kyber768 are accepted too)",
+                    s, params::param_set_names().join(", ")
+                )
             ),
             AppError::InvalidOperation(s) => (
                 StatusCode::BAD_REQUEST,
-                format!("Invalid operation '{}'. Valid options: keygen, encaps, decaps, full_handshake", s)
+                format!("Invalid operation '{}'. Valid options: keygen, encaps, decaps, encaps_decaps, full_handshake (ML-KEM, X-Wing, X25519, RSA), plus decaps_reject, ct_compare, serialize_keys, deserialize_keys, kem_dem_encrypt, kem_dem_decrypt, mixed (ML-KEM); keygen, sign, verify, sign_verify (ML-DSA); keygen, sign, verify (SLH-DSA)", s)
+            ),
+            AppError::MalformedInput(s) => (StatusCode::BAD_REQUEST, s),
+            AppError::ValidationFailed(s) => (StatusCode::UNPROCESSABLE_ENTITY, s),
+            AppError::InvalidField(e) => (StatusCode::UNPROCESSABLE_ENTITY, e.error),
+            AppError::NotAcceptable(s) => (
+                StatusCode::NOT_ACCEPTABLE,
+                format!("Cannot produce any of '{}'. Supported: application/json, application/cbor, application/msgpack", s)
+            ),
+            AppError::UnsupportedMediaType(s) => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("Unsupported Content-Type '{}'. Supported: application/json, application/cbor, application/msgpack", s)
+            ),
+            AppError::PayloadTooLarge(s) => (StatusCode::PAYLOAD_TOO_LARGE, s),
+            AppError::TooManyBenchmarks(limit) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                format!("Server is already running its limit of {} concurrent benchmarks; retry later", limit)
+            ),
+            AppError::TooManyJobs(limit) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                format!("Server is already holding its limit of {} unfinished async jobs; retry later", limit)
+            ),
+            AppError::SheddingLoad(reason) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("Server is shedding load ({}); retry later", reason)
+            ),
+            AppError::NotFound(s) => (StatusCode::NOT_FOUND, s),
+            AppError::AuthenticationFailed => (
+                StatusCode::BAD_REQUEST,
+                "Authentication failed: the ciphertext, nonce or key does not match".to_string()
+            ),
+            AppError::Leg(leg, inner) => {
+                let (status, message) = inner.status_and_message();
+                (status, format!("{} failed: {}", leg, message))
+            }
+            AppError::Internal(s) => (StatusCode::INTERNAL_SERVER_ERROR, s),
+            AppError::BenchmarkPanicked { param_set, operation, message } => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Benchmark {} {} panicked: {}", param_set, operation, message)
             ),
+            AppError::SharedSecretMismatch { param_set, operation, iteration } => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Benchmark {} {} derived mismatched shared secrets at iteration {}", param_set, operation, iteration)
+            ),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let kind = metrics::ErrorKind(self.kind());
+        let (field, expected) = match &self {
+            AppError::InvalidField(e) => (Some(e.field.clone()), e.expected.clone()),
+            _ => (None, None),
+        };
+        let (param_set, operation) = match &self {
+            AppError::BenchmarkPanicked { param_set, operation, .. } | AppError::SharedSecretMismatch { param_set, operation, .. } => {
+                (Some(param_set.clone()), Some(operation.clone()))
+            }
+            _ => (None, None),
         };
+        let iteration = match &self {
+            AppError::SharedSecretMismatch { iteration, .. } => Some(*iteration),
+            _ => None,
+        };
+        let (status, message) = self.status_and_message();
+        tracing::warn!(kind = kind.0, status = status.as_u16(), %message, "request failed");
+        let body = ErrorBody { error: message, field, expected, param_set, operation, iteration };
+        let mut response = (status, Json(body)).into_response();
+        response.extensions_mut().insert(kind);
+        if matches!(status, StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE) {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+        }
+        response
+    }
+}
+
+// ============ Shared State ============
+
+#[derive(Clone)]
+struct AppState {
+    config: Arc<Config>,
+    metrics: Arc<metrics::Metrics>,
+    readiness: Arc<health::Readiness>,
+    /// One permit per benchmark allowed to run at once
+    benchmark_slots: Arc<Semaphore>,
+    /// Fixed-size pool every KEM benchmark runs on
+    bench_pool: Arc<pool::BenchPool>,
+    /// Detected once at startup; None when the deployment has opted out
+    machine_info: Option<Arc<MachineInfo>>,
+    /// Set by --results-log; completed kem_bench results are appended here
+    results_log: Option<Arc<results::ResultsLog>>,
+    /// Benchmarks submitted with ?async=true
+    jobs: Arc<jobs::JobStore>,
+    /// Decapsulation keys held between requests for POST /keys/{id}/decaps
+    keys: Arc<keystore::KeyStore>,
+    /// Signs every kem_bench response
+    attestation: Arc<attestation::Attestation>,
+    /// Cost of an empty timed region, calibrated once at startup
+    timer_overhead: Duration,
+    /// Pre-generated ML-KEM keypairs, with --keypair-pool-size
+    keypairs: Option<Arc<keypool::KeypairPool>>,
+}
+
+impl AppState {
+    fn new(config: Config) -> Self {
+        let max_concurrent = config.max_concurrent_benchmarks();
+        let readiness = health::Readiness::new(env!("CARGO_PKG_NAME"), max_concurrent).with_selftest().with_shed_limits(health::ShedLimits {
+            max_rss_kb: config.shed_rss_mb.map(|mb| mb.saturating_mul(1024)),
+            max_in_flight: config.shed_in_flight,
+        });
+        // The self-test is the only startup work, and takes milliseconds
+        selftest::run_and_record(&readiness);
+        readiness.mark_initialized();
+        let machine_info = (!config.disable_machine_info).then(|| Arc::new(MachineInfo::detect()));
+        let results_log = config
+            .results_log
+            .as_deref()
+            .map(|path| Arc::new(results::ResultsLog::open(path).expect("failed to open results log")));
+        let jobs = jobs::JobStore::new(Duration::from_secs(config.job_ttl_secs), config.max_jobs);
+        let keys = keystore::KeyStore::new(Duration::from_secs(config.key_ttl_secs), config.max_stored_keys);
+        let bench_pool = pool::BenchPool::new(config.bench_threads());
+        let keypairs = config.keypair_pool_size.map(|size| keypool::KeypairPool::start(size.into()));
+        let attestation = attestation::Attestation::load(config.signing_key.as_deref())
+            .unwrap_or_else(|e| panic!("failed to load signing key: {}", e));
+        AppState {
+            config: Arc::new(config),
+            metrics: Arc::new(metrics::Metrics::new()),
+            readiness: Arc::new(readiness),
+            benchmark_slots: Arc::new(Semaphore::new(max_concurrent)),
+            bench_pool: Arc::new(bench_pool),
+            machine_info,
+            results_log,
+            jobs: Arc::new(jobs),
+            keys: Arc::new(keys),
+            attestation: Arc::new(attestation),
+            timer_overhead: common::stats::calibrate_timer_overhead(common::stats::TIMER_CALIBRATION_ITERATIONS),
+            keypairs,
+        }
+    }
+
+    fn machine_info(&self, requested: bool) -> Option<MachineInfo> {
+        if requested { self.machine_info.as_deref().cloned() } else { None }
+    }
 
-        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    fn record_result(&self, requester: Option<IpAddr>, resp: &KemBenchResponse) {
+        if let Some(log) = &self.results_log {
+            log.record(requester, resp);
+        }
+    }
+}
+
+impl FromRef<AppState> for Arc<health::Readiness> {
+    fn from_ref(state: &AppState) -> Self {
+        state.readiness.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<common::metrics::ServiceMetrics> {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.service.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<attestation::Attestation> {
+    fn from_ref(state: &AppState) -> Self {
+        state.attestation.clone()
     }
 }
 
 // ============ KEM Benchmark Types ============
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 struct KemBenchRequest {
-    param_set: String, 
-    iterations: u32,
-    operation: String,
+    /// Canonical name or an alias such as "ML-KEM-768" or "kyber768", in any case
+    #[schema(value_type = String)]
+    param_set: ParamSet,
+    /// Exactly one of iterations and target_duration_ms must be set
+    #[serde(default)]
+    iterations: Option<u32>,
+    /// Pick the iteration count so the measured run takes about this long, from a short pilot run
+    #[serde(default)]
+    target_duration_ms: Option<u64>,
+    /// Canonical name or an alias such as "encapsulate", in any case
+    #[schema(value_type = String)]
+    operation: Operation,
+    #[serde(default = "default_percentiles")]
+    percentiles: Vec<f64>,
+    #[serde(default)]
+    include_raw_timings: bool,
+    /// Split the iterations across this many OS threads
+    #[serde(default = "default_threads")]
+    threads: u32,
+    /// Untimed iterations run before the measured loop
+    #[serde(default)]
+    warmup_iterations: Option<u32>,
+    /// Seed a ChaCha20 RNG instead of using OsRng, for reproducible key material
+    #[serde(default)]
+    seed: Option<u64>,
+    /// Stop the run early once this much time has passed (capped by the server's max_timeout_ms)
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+    /// Only meaningful for encaps and decaps
+    #[serde(default)]
+    key_mode: KeyMode,
+    /// Attach hostname, CPU model and core count to the response, unless the server disables it
+    #[serde(default)]
+    include_machine_info: bool,
+    /// Drop this fraction of samples from each end (e.g. 0.01) before computing the summary stats
+    #[serde(default)]
+    trim_percent: Option<f64>,
+    /// AEAD for kem_dem_encrypt and kem_dem_decrypt (default aes_256_gcm)
+    #[serde(default)]
+    cipher: Option<kem_dem::Cipher>,
+    /// Plaintext size for kem_dem_encrypt and kem_dem_decrypt (default 1024, capped by the server's max_payload_bytes)
+    #[serde(default)]
+    payload_bytes: Option<usize>,
+    /// Required for operation "mixed": relative counts of keygen, encaps and decaps
+    #[serde(default)]
+    weights: Option<mixed::MixedWeights>,
+    /// Repeat the measured loop this many times back to back, for run-to-run variance (default 1)
+    #[serde(default)]
+    runs: Option<u32>,
+    /// Note when each iteration started and report drift, e.g. from thermal throttling, over the run
+    #[serde(default)]
+    record_timestamps: bool,
+    /// Relative change between the first and last decile that counts as drift (default 0.1);
+    /// needs record_timestamps
+    #[serde(default)]
+    drift_threshold: Option<f64>,
+    /// Take the calibrated timer overhead off every sample
+    #[serde(default)]
+    subtract_overhead: bool,
+    /// How many times the overall mean the first decile's mean must exceed for the trend
+    /// to flag warmup (default 1.2)
+    #[serde(default)]
+    warmup_factor: Option<f64>,
 }
 
-#[derive(Serialize)]
+fn default_threads() -> u32 {
+    1
+}
+
+/// Which keys encaps/decaps run against
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum KeyMode {
+    /// One key (and ciphertext, for decaps) reused for every iteration
+    #[default]
+    Single,
+    /// A distinct key per iteration, generated before the timed loop, to include cache effects
+    FreshPerIteration,
+}
+
+/// Per-request settings that only some operations read
+#[derive(Clone, Copy, Default)]
+struct OperationOptions<'a> {
+    key_mode: KeyMode,
+    dem: kem_dem::DemOptions,
+    weights: mixed::MixedWeights,
+    /// Where fresh_per_iteration keys come from first; None generates them all
+    keypairs: Option<&'a keypool::KeypairPool>,
+}
+
+// Most repetitions of the measured loop one request may ask for
+const MAX_RUNS: u32 = 20;
+
+// Last decile 10% slower or faster than the first
+const DEFAULT_DRIFT_THRESHOLD: f64 = 0.1;
+
+// First decile 20% slower than the run as a whole
+const DEFAULT_WARMUP_FACTOR: f64 = 1.2;
+
+// Bucket count for the latency histogram in every KEM response
+const HISTOGRAM_BUCKETS: usize = 20;
+
+fn default_percentiles() -> Vec<f64> {
+    vec![0.5, 0.95, 0.99]
+}
+
+#[derive(Serialize, Clone, ToSchema)]
 struct KemBenchResponse {
     operation: String,
     param_set: String,
+    /// Same as effective_iterations, kept for existing clients
     iterations: u32,
+    /// Iterations as sent, or as estimated from target_duration_ms, before capping
+    requested_iterations: u32,
+    /// Iterations actually scheduled after capping at iteration_cap
+    effective_iterations: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_duration_ms: Option<u64>,
     avg_us: f64,
     min_us: f64,
     max_us: f64,
     p95_us: f64,
+    stddev_us: f64,
+    median_us: f64,
+    /// 95% confidence interval for avg_us: t-distribution up to 31 samples, normal beyond
+    ci95_low_us: f64,
+    ci95_high_us: f64,
+    /// With trim_percent set, avg/min/max/p95/stddev/median/ci95 above cover only the samples kept
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trim_percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trimmed_avg_us: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    untrimmed_avg_us: Option<f64>,
+    /// Samples dropped across both ends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trimmed_samples: Option<usize>,
+    /// Requested percentiles, keyed by the value as sent (e.g. "0.99")
+    percentiles: BTreeMap<String, f64>,
     throughput_ops_sec: f64,
+    threads: u32,
+    /// Threads in the server's benchmark pool, which every concurrent benchmark shares
+    bench_pool_threads: usize,
+    warmup_iterations: u32,
+    key_mode: KeyMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    /// How many fresh_per_iteration keypairs came from the server's keypair pool and how many
+    /// were generated on demand; absent when the pool is off, or for seeded and single-key runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keypair_pool: Option<keypool::PoolUsage>,
+    /// Total operations per second across all threads, over the wall-clock time of the run
+    /// including per-thread setup such as key generation
+    wall_clock_throughput_ops_sec: f64,
+    /// Total operations per second over the wall-clock span of the measured loops only.
+    /// throughput_ops_sec is 1e6 / avg_us, so it ignores both threading and any per-iteration
+    /// overhead outside the timed region; this doesn't
+    measured_loop_ops_sec: f64,
+    /// Thread CPU time per iteration over the measured loops, including the loop's own
+    /// overhead; absent where the platform has no per-thread CPU clock
+    #[serde(skip_serializing_if = "Option::is_none")]
+    avg_cpu_us: Option<f64>,
+    /// CPU time over wall-clock time of the measured loops, per thread. Well below 1.0 means
+    /// the threads were descheduled, i.e. the host was contended
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_time_ratio: Option<f64>,
+    /// Cost of an empty timed region on this host, which every sample carries on top of
+    /// the operation
+    timer_overhead_ns: u64,
+    /// Whether timer_overhead_ns was taken off each sample before the stats were computed
+    overhead_subtracted: bool,
+    /// For operations that check both sides' shared secrets: runs of this param set and
+    /// operation that failed on a mismatch since the server started. A mismatch fails its
+    /// own request with a 500, so anything above zero points at an earlier run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shared_secret_mismatches: Option<u64>,
+    /// Log-scaled latency distribution between min_us and max_us
+    histogram: Vec<HistogramBucket>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encapsulation_key_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decapsulation_key_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ciphertext_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shared_secret_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature_bytes: Option<usize>,
+    /// Per-request iteration cap for this param set
+    iteration_cap: u32,
+    /// Per-iteration samples, only when include_raw_timings was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timings_us: Option<Vec<u64>>,
+    /// Set when timings_us was cut off at the server's max_raw_timings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timings_truncated: Option<bool>,
+    /// For decaps_reject: valid-ciphertext decaps with the same key, for a side-by-side comparison
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accept_path: Option<PathStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cipher: Option<kem_dem::Cipher>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload_bytes: Option<usize>,
+    /// For kem_dem_*: the encapsulate/decapsulate share of each iteration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kem_us: Option<PathStats>,
+    /// For kem_dem_*: the HKDF plus AEAD share of each iteration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dem_us: Option<PathStats>,
+    /// For full_handshake: the keygen share of each iteration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keygen_stats: Option<PathStats>,
+    /// For full_handshake: the encapsulate share of each iteration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encaps_stats: Option<PathStats>,
+    /// For full_handshake: the decapsulate share of each iteration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decaps_stats: Option<PathStats>,
+    /// For full_handshake: how much of avg_us the three phases don't account for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phase_discrepancy: Option<PhaseDiscrepancy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weights: Option<mixed::MixedWeights>,
+    /// For mixed: stats per operation, over the iterations that ran it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operation_breakdown: Option<BTreeMap<String, mixed::OperationBreakdown>>,
+    /// Times the measured loop ran; with more than one, the stats above pool every run's samples
+    runs: u32,
+    /// With runs above 1, each run's own stats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    per_run: Option<Vec<RunStats>>,
+    /// With runs above 1, how much the per-run averages vary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    across_runs: Option<RunSpread>,
+    /// With record_timestamps set, how latency moved from the start of the run to the end
+    #[serde(skip_serializing_if = "Option::is_none")]
+    drift: Option<Drift>,
+    /// Whether the early iterations ran slower than the rest; left out for a multi-threaded
+    /// run without record_timestamps, whose samples aren't in the order they ran
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trend: Option<Trend>,
+    /// With record_timestamps and include_raw_timings set, [started_us, latency_us] per
+    /// iteration in the order they started, started_us counting from the first measured loop.
+    /// Capped at max_raw_timings like timings_us.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeline_us: Option<Vec<(u64, u64)>>,
+    /// Iterations actually measured; the stats above cover only these
+    completed_iterations: u32,
+    /// Time limit applied to this run, after capping at the server's maximum
+    timeout_ms: u64,
+    /// Set when the run hit timeout_ms before finishing every iteration
+    timed_out: bool,
+    /// Resident set size in KiB around the benchmark run; null where the platform can't report it
+    rss_before_kb: Option<u64>,
+    rss_after_kb: Option<u64>,
+    /// Process-lifetime peak RSS in KiB, so it may predate this request
+    peak_rss_kb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    machine_info: Option<MachineInfo>,
     timestamp: u64,
+    /// Base64 ML-DSA-65 signature over this response's canonical JSON, without this field,
+    /// by the key at GET /signing_key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+struct PathStats {
+    avg_us: f64,
+    min_us: f64,
+    max_us: f64,
+    p95_us: f64,
+    stddev_us: f64,
+    median_us: f64,
+}
+
+impl PathStats {
+    fn from_timings(timings: &[u128]) -> Self {
+        let stats = compute_stats(timings);
+        PathStats {
+            avg_us: stats.avg,
+            min_us: stats.min,
+            max_us: stats.max,
+            p95_us: stats.p95,
+            stddev_us: stats.stddev,
+            median_us: stats.median,
+        }
+    }
+}
+
+/// The whole iteration against its phases. The gap is mostly the per-phase clock reads;
+/// each phase is also truncated to whole microseconds on its own, so on fast operations
+/// it can come out slightly negative.
+#[derive(Serialize, Clone, ToSchema)]
+struct PhaseDiscrepancy {
+    /// Phase averages added up
+    sum_of_phases_us: f64,
+    /// Untrimmed avg_us minus sum_of_phases_us
+    unaccounted_us: f64,
+    /// unaccounted_us as a percentage of the untrimmed avg_us
+    unaccounted_percent: f64,
+}
+
+impl PhaseDiscrepancy {
+    fn new(total_avg_us: f64, phases: &[PathStats]) -> Self {
+        let sum_of_phases_us = phases.iter().map(|phase| phase.avg_us).sum::<f64>();
+        let unaccounted_us = total_avg_us - sum_of_phases_us;
+        let unaccounted_percent = if total_avg_us > 0.0 { unaccounted_us / total_avg_us * 100.0 } else { 0.0 };
+        PhaseDiscrepancy { sum_of_phases_us, unaccounted_us, unaccounted_percent }
+    }
+}
+
+/// One repetition of the measured loop
+#[derive(Serialize, Clone, ToSchema)]
+struct RunStats {
+    completed_iterations: u32,
+    #[serde(flatten)]
+    stats: PathStats,
+}
+
+// Timings plus any artifact sizes the backend can report alongside them
+#[derive(Default)]
+struct BenchOutput {
+    timings: Vec<u128>,
+    /// Valid-ciphertext decaps timings measured alongside decaps_reject
+    accept_timings: Option<Vec<u128>>,
+    /// KEM and DEM halves of each kem_dem_* iteration
+    kem_timings: Option<Vec<u128>>,
+    dem_timings: Option<Vec<u128>>,
+    /// For mixed: the operation each of `timings` ran
+    mixed_ops: Option<Vec<mixed::MixedOp>>,
+    encapsulation_key_bytes: Option<usize>,
+    decapsulation_key_bytes: Option<usize>,
+    ciphertext_bytes: Option<usize>,
+    shared_secret_bytes: Option<usize>,
+    signature_bytes: Option<usize>,
+    /// For fresh_per_iteration drawing on the keypair pool: where the keys came from
+    keypair_pool: Option<keypool::PoolUsage>,
+    /// For full_handshake: each phase of every iteration
+    phases: Option<HandshakePhases>,
 }
 
 // ============ Generic KEM Constructs ============
@@ -102,162 +718,2175 @@ where
 {
 }
 
+/// Encoded sizes of a parameter set's keys, ciphertext and shared secret, fixed by its type
+struct KemSizes {
+    encapsulation_key: usize,
+    decapsulation_key: usize,
+    ciphertext: usize,
+    shared_secret: usize,
+}
+
+fn sizes_of<K: BenchmarkableKem>() -> KemSizes {
+    KemSizes {
+        encapsulation_key: <K::EncapsulationKey as EncodedSizeUser>::EncodedSize::USIZE,
+        decapsulation_key: <K::DecapsulationKey as EncodedSizeUser>::EncodedSize::USIZE,
+        ciphertext: K::CiphertextSize::USIZE,
+        shared_secret: K::SharedKeySize::USIZE,
+    }
+}
+
 // ============ KEM Operations ============
 
-fn bench_keygen<K: KemCore>(iterations: u32) -> Vec<u128> {
-    (0..iterations)
-        .map( |_| {
-            let start = Instant::now();
-            let _ = K::generate(&mut OsRng);
-            start.elapsed().as_micros()
-        })
-        .collect()
+fn bench_keygen<K: KemCore, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128> {
+    budget.measure(|| {
+        let _ = K::generate(rng);
+    })
+}
+
+fn bench_encaps<K, R: RngCore + CryptoRng>(
+    budget: Budget,
+    key_mode: KeyMode,
+    keypairs: Option<&keypool::KeypairPool>,
+    rng: &mut R,
+) -> (Vec<u128>, Option<keypool::PoolUsage>)
+where
+    K: keypool::Pooled,
+    K::EncapsulationKey: Encapsulate<Ciphertext<K>, SharedKey<K>>,
+{
+    match key_mode {
+        KeyMode::Single => {
+            let (_dk, ek) = K::generate(rng);
+            let timings = budget.measure(|| {
+                let _ = ek.encapsulate(rng);
+            });
+            (timings, None)
+        }
+        KeyMode::FreshPerIteration => {
+            let (keys, usage) = keypool::fresh_keypairs::<K, _>(keypairs, budget.iterations as usize, rng);
+            let mut keys = keys.iter().map(|(_, ek)| ek);
+            let timings = budget.measure(|| {
+                let _ = keys.next().unwrap().encapsulate(rng);
+            });
+            (timings, usage)
+        }
+    }
 }
 
-fn bench_encaps<K>(iterations: u32) -> Vec<u128>
+fn bench_decaps<K, R: RngCore + CryptoRng>(
+    budget: Budget,
+    key_mode: KeyMode,
+    keypairs: Option<&keypool::KeypairPool>,
+    rng: &mut R,
+) -> (Vec<u128>, Option<keypool::PoolUsage>)
+where
+    K: keypool::Pooled,
+    K::EncapsulationKey: Encapsulate<Ciphertext<K>, SharedKey<K>>,
+    K::DecapsulationKey: Decapsulate<Ciphertext<K>, SharedKey<K>>,
+{
+    let ciphertext_for = |(dk, ek): keypool::Keypair<K>, rng: &mut R| {
+        let (ct, ss) = ek.encapsulate(rng).unwrap();
+        drop(SecretBox::new(ss));
+        (dk, ct)
+    };
+
+    match key_mode {
+        KeyMode::Single => {
+            let (dk, ct) = ciphertext_for(K::generate(rng), rng);
+            let timings = budget.measure(|| {
+                let _ss = SecretBox::new(dk.decapsulate(&ct).unwrap());
+            });
+            (timings, None)
+        }
+        KeyMode::FreshPerIteration => {
+            let (keys, usage) = keypool::fresh_keypairs::<K, _>(keypairs, budget.iterations as usize, rng);
+            let pairs: Vec<_> = keys.into_iter().map(|keypair| ciphertext_for(keypair, rng)).collect();
+            let mut pairs = pairs.iter();
+            let timings = budget.measure(|| {
+                let (dk, ct) = pairs.next().unwrap();
+                let _ss = SecretBox::new(dk.decapsulate(ct).unwrap());
+            });
+            (timings, usage)
+        }
+    }
+}
+
+// Wire encoding of what a TLS-like handshake sends: the encapsulation key one way and the
+// ciphertext back. black_box keeps the otherwise-unused encodings from being optimized out.
+fn bench_serialize_keys<K, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128>
 where
     K: KemCore,
     K::EncapsulationKey: Encapsulate<Ciphertext<K>, SharedKey<K>>,
 {
-    let (_dk, ek) = K::generate(&mut OsRng);
+    let (_dk, ek) = K::generate(rng);
+    let (ct, _ss) = ek.encapsulate(rng).unwrap();
+    budget.measure(|| {
+        black_box(ek.as_bytes());
+        black_box(ct.to_vec());
+    })
+}
 
-    (0..iterations)
-        .map(|_| {
-            let start = Instant::now();
-            let _ = ek.encapsulate(&mut OsRng);
-            start.elapsed().as_micros()
-        })
-        .collect()
+fn bench_deserialize_keys<K, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128>
+where
+    K: KemCore,
+    K::EncapsulationKey: Encapsulate<Ciphertext<K>, SharedKey<K>>,
+{
+    let (_dk, ek) = K::generate(rng);
+    let (ct, _ss) = ek.encapsulate(rng).unwrap();
+    let (ek_bytes, ct_bytes) = (ek.as_bytes(), ct.to_vec());
+    budget.measure(|| {
+        black_box(K::EncapsulationKey::from_bytes(&ek_bytes));
+        black_box(Ciphertext::<K>::try_from(ct_bytes.as_slice()).unwrap());
+    })
 }
 
-fn bench_decaps<K>(iterations: u32) -> Vec<u128>
+// Decapsulates a ciphertext with one bit flipped, which ML-KEM answers through implicit
+// rejection (re-encryption mismatch) rather than an error. The same key's valid ciphertext
+// is then timed for as many iterations, giving the accept path to compare against.
+fn bench_decaps_reject<K, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> (Vec<u128>, Vec<u128>)
 where
     K: KemCore,
     K::EncapsulationKey: Encapsulate<Ciphertext<K>, SharedKey<K>>,
     K::DecapsulationKey: Decapsulate<Ciphertext<K>, SharedKey<K>>,
 {
-    let (dk, ek) = K::generate(&mut OsRng);
-    let (ct, _ss) = ek.encapsulate(&mut OsRng).unwrap();
+    let (dk, ek) = K::generate(rng);
+    let (ct, ss) = ek.encapsulate(rng).unwrap();
+    let mut corrupted = ct.clone();
+    corrupted[0] ^= 1;
+    assert!(!secret::ct_eq(&dk.decapsulate(&corrupted).unwrap(), &ss), "corrupted ciphertext decapsulated to the real shared secret");
 
-    (0..iterations)
-        .map(|_| {
-            let start = Instant::now();
-            let _ = dk.decapsulate(&ct);
-            start.elapsed().as_micros()
-        })
-        .collect()
+    let reject = budget.measure(|| {
+        let _ = dk.decapsulate(&corrupted);
+    });
+    let accept = Budget { iterations: reject.len() as u32, deadline: None, progress: None, window: None, timeline: None, overhead: budget.overhead }.measure(|| {
+        let _ = dk.decapsulate(&ct);
+    });
+    (reject, accept)
 }
 
-fn bench_full_handshake<K>(iterations: u32) -> Vec<u128>
+fn bench_full_handshake<K, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Result<(Vec<u128>, HandshakePhases), SharedSecretMismatch>
 where
     K: KemCore,
     K::EncapsulationKey: Encapsulate<Ciphertext<K>, SharedKey<K>>,
     K::DecapsulationKey: Decapsulate<Ciphertext<K>, SharedKey<K>>,
 {
-    (0..iterations)
-        .map(|_| {
-            let start = Instant::now();
-            let (dk, ek) = K::generate(&mut OsRng);
-            let (ct, ss_sender) = ek.encapsulate(&mut OsRng).unwrap();
-            let ss_receiver = dk.decapsulate(&ct).unwrap();
-            debug_assert_eq!(ss_sender, ss_receiver);
-            start.elapsed().as_micros()
-        })
-        .collect()
+    let mut check = SecretCheck::default();
+    let mut phases = HandshakePhases::default();
+    let timings = budget.try_measure(|| {
+        let start = Instant::now();
+        let (dk, ek) = K::generate(rng);
+        let generated = Instant::now();
+        let (ct, ss_sender) = ek.encapsulate(rng).unwrap();
+        let encapsulated = Instant::now();
+        let ss_receiver = dk.decapsulate(&ct).unwrap();
+        phases.record(budget.overhead, [start, generated, encapsulated, Instant::now()]);
+        let (ss_sender, ss_receiver) = (SecretBox::new(ss_sender), SecretBox::new(ss_receiver));
+        check.check(ss_sender.expose_secret(), ss_receiver.expose_secret())
+    })?;
+    Ok((timings, phases))
 }
 
-// Dispatch to the right generic function based on param_set
-fn run_benchmark(param_set: &str, operation: &str, iterations: u32) -> Result<Vec<u128>, AppError> {
-    match param_set {
-        "ml_kem_512" => run_operation::<MlKem512>(operation, iterations),
-        "ml_kem_768" => run_operation::<MlKem768>(operation, iterations),
-        "ml_kem_1024" => run_operation::<MlKem1024>(operation, iterations),
-        _ => Err(AppError::InvalidParamSet(param_set.to_string())),
-    }
+// Steady-state session cost: the keypair is long-lived, so only encaps+decaps are timed.
+fn bench_encaps_decaps<K, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Result<Vec<u128>, SharedSecretMismatch>
+where
+    K: KemCore,
+    K::EncapsulationKey: Encapsulate<Ciphertext<K>, SharedKey<K>>,
+    K::DecapsulationKey: Decapsulate<Ciphertext<K>, SharedKey<K>>,
+{
+    let (dk, ek) = K::generate(rng);
+    let mut check = SecretCheck::default();
+    budget.try_measure(|| {
+        let (ct, ss_sender) = ek.encapsulate(rng).unwrap();
+        let (ss_sender, ss_receiver) = (SecretBox::new(ss_sender), SecretBox::new(dk.decapsulate(&ct).unwrap()));
+        check.check(ss_sender.expose_secret(), ss_receiver.expose_secret())
+    })
 }
 
-fn run_operation<K>(operation: &str, iterations: u32) -> Result<Vec<u128>, AppError>
+// The comparison that ends every handshake, on its own, over one pair of real shared secrets
+fn bench_ct_compare<K, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128>
 where
     K: KemCore,
     K::EncapsulationKey: Encapsulate<Ciphertext<K>, SharedKey<K>>,
     K::DecapsulationKey: Decapsulate<Ciphertext<K>, SharedKey<K>>,
 {
-    match operation {
-        "keygen" => Ok(bench_keygen::<K>(iterations)),
-        "encaps" => Ok(bench_encaps::<K>(iterations)),
-        "decaps" => Ok(bench_decaps::<K>(iterations)),
-        "full_handshake" => Ok(bench_full_handshake::<K>(iterations)),
-        _ => Err(AppError::InvalidOperation(operation.to_string())),
+    let (dk, ek) = K::generate(rng);
+    let (ct, ss_sender) = ek.encapsulate(rng).unwrap();
+    let (ss_sender, ss_receiver) = (SecretBox::new(ss_sender), SecretBox::new(dk.decapsulate(&ct).unwrap()));
+    budget.measure(|| {
+        black_box(secret::ct_eq(black_box(ss_sender.expose_secret()), black_box(ss_receiver.expose_secret())));
+    })
+}
+
+// Dispatch to the right generic function based on param_set
+fn run_benchmark_with<R: RngCore + CryptoRng>(
+    param_set: &str,
+    operation: &str,
+    budget: Budget,
+    options: OperationOptions,
+    rng: &mut R,
+) -> Result<BenchOutput, AppError> {
+    let key_mode = options.key_mode;
+    match param_set {
+        "ml_kem_512" => run_operation::<MlKem512, _>(param_set, operation, budget, options, rng),
+        "ml_kem_768" => run_operation::<MlKem768, _>(param_set, operation, budget, options, rng),
+        "ml_kem_1024" => run_operation::<MlKem1024, _>(param_set, operation, budget, options, rng),
+        "x_wing" => backends::run_backend_operation::<hybrid::XWing, _>(param_set, operation, budget, key_mode, rng),
+        "x25519" => backends::run_backend_operation::<classical::X25519, _>(param_set, operation, budget, key_mode, rng),
+        "rsa_2048" => backends::run_backend_operation::<classical::Rsa2048, _>(param_set, operation, budget, key_mode, rng),
+        "ml_dsa_44" => signatures::run_ml_dsa_operation::<MlDsa44, _>(operation, budget, rng),
+        "ml_dsa_65" => signatures::run_ml_dsa_operation::<MlDsa65, _>(operation, budget, rng),
+        "ml_dsa_87" => signatures::run_ml_dsa_operation::<MlDsa87, _>(operation, budget, rng),
+        "slh_dsa_sha2_128s" => signatures::run_slh_dsa_operation::<Sha2_128s, _>(operation, budget, rng),
+        "slh_dsa_sha2_128f" => signatures::run_slh_dsa_operation::<Sha2_128f, _>(operation, budget, rng),
+        _ => Err(AppError::InvalidParamSet(param_set.to_string())),
+    }
+}
+
+// A seed swaps OsRng for ChaCha20 so repeated runs see identical key material
+fn run_benchmark(
+    param_set: &str,
+    operation: &str,
+    budget: Budget,
+    options: OperationOptions,
+    seed: Option<u64>,
+) -> Result<BenchOutput, AppError> {
+    match seed {
+        Some(seed) => run_benchmark_with(param_set, operation, budget, options, &mut ChaCha20Rng::seed_from_u64(seed)),
+        None => run_benchmark_with(param_set, operation, budget, options, &mut OsRng),
+    }
+}
+
+// Split iterations as evenly as possible across threads, each of which runs the full
+// benchmark (setup included) independently. Returns the merged output and wall time.
+// With a seed, thread i uses seed + i so the threads don't all repeat the same keys.
+// Every thread shares the budget's deadline.
+fn run_threaded_benchmark(
+    param_set: &str,
+    operation: &str,
+    budget: Budget,
+    options: OperationOptions,
+    threads: u32,
+    seed: Option<u64>,
+) -> Result<(BenchOutput, Duration), AppError> {
+    let start = Instant::now();
+    if threads <= 1 {
+        let output = run_benchmark(param_set, operation, budget, options, seed)?;
+        return Ok((output, start.elapsed()));
+    }
+
+    let iterations = budget.iterations;
+    let shares = (0..threads)
+        .map(|i| iterations / threads + u32::from(i < iterations % threads))
+        .filter(|&share| share > 0)
+        .map(|share| Budget { iterations: share, ..budget })
+        .collect::<Vec<_>>();
+
+    // Called from a pool thread, the shares go to the same pool; a panic in one is re-raised
+    // here once the rest finish, for catch_benchmark_panic to report
+    let mut outputs: Vec<Option<Result<BenchOutput, AppError>>> = shares.iter().map(|_| None).collect();
+    rayon::scope(|scope| {
+        let mut earlier = 0;
+        for (i, (slot, share)) in outputs.iter_mut().zip(shares).enumerate() {
+            let seed = seed.map(|s| s.wrapping_add(i as u64));
+            let first = earlier;
+            earlier += u64::from(share.iterations);
+            scope.spawn(move |_| *slot = Some(run_benchmark(param_set, operation, share, options, seed).map_err(|e| e.after_iterations(first))));
+        }
+    });
+    let outputs = outputs.into_iter().flatten().collect::<Result<Vec<_>, _>>()?;
+    let wall_time = start.elapsed();
+
+    Ok((merge_outputs(outputs), wall_time))
+}
+
+// The backends unwrap and expect on results that should never be errors. If one is, the
+// panic is caught here and becomes a 500 naming the benchmark, instead of the empty 500
+// axum gives for a panicked handler.
+fn catch_benchmark_panic<T>(
+    param_set: &str,
+    operation: &str,
+    f: impl FnOnce() -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))
+        .unwrap_or_else(|payload| Err(panicked(param_set, operation, payload)))
+}
+
+fn panicked(param_set: &str, operation: &str, payload: Box<dyn std::any::Any + Send>) -> AppError {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    AppError::BenchmarkPanicked { param_set: param_set.to_string(), operation: operation.to_string(), message }
+}
+
+/// The first iteration of one benchmark loop whose shared secrets differed, before the
+/// loop's param set and operation are attached
+#[derive(Debug)]
+struct SharedSecretMismatch {
+    iteration: u64,
+}
+
+impl SharedSecretMismatch {
+    fn at(self, param_set: &str, operation: &str) -> AppError {
+        AppError::SharedSecretMismatch { param_set: param_set.to_string(), operation: operation.to_string(), iteration: self.iteration }
+    }
+}
+
+/// Compares the two sides' shared secrets in every iteration of a loop that derives both.
+/// The first mismatch ends the loop (see Budget::try_measure), so the run fails instead of
+/// reporting timings for a broken handshake.
+#[derive(Default)]
+struct SecretCheck {
+    iteration: u64,
+}
+
+impl SecretCheck {
+    fn check(&mut self, sender: &[u8], receiver: &[u8]) -> Result<(), SharedSecretMismatch> {
+        if !secret::ct_eq(sender, receiver) {
+            tracing::error!(iteration = self.iteration, "sender and receiver derived different shared secrets");
+            return Err(SharedSecretMismatch { iteration: self.iteration });
+        }
+        self.iteration += 1;
+        Ok(())
+    }
+}
+
+/// keygen, encaps and decaps of each full_handshake iteration, timed inside the loop so they
+/// share its cache state. The extra clock reads land in the iteration's total but in no
+/// phase, which is what the response's phase_discrepancy reports.
+#[derive(Default)]
+struct HandshakePhases {
+    keygen: Vec<u128>,
+    encaps: Vec<u128>,
+    decaps: Vec<u128>,
+}
+
+impl HandshakePhases {
+    /// `marks` are the instants before keygen, after keygen, after encaps and after decaps
+    fn record(&mut self, overhead: Duration, marks: [Instant; 4]) {
+        let phase = |from: Instant, to: Instant| common::stats::subtract_overhead(to.duration_since(from), overhead).as_micros();
+        self.keygen.push(phase(marks[0], marks[1]));
+        self.encaps.push(phase(marks[1], marks[2]));
+        self.decaps.push(phase(marks[2], marks[3]));
+    }
+
+    fn extend(&mut self, other: HandshakePhases) {
+        self.keygen.extend(other.keygen);
+        self.encaps.extend(other.encaps);
+        self.decaps.extend(other.decaps);
+    }
+}
+
+// Concatenates every output's samples; artifact sizes come from the first
+fn merge_outputs(outputs: Vec<BenchOutput>) -> BenchOutput {
+    let mut outputs = outputs.into_iter();
+    let mut merged = outputs.next().unwrap_or_default();
+    for output in outputs {
+        merged.timings.extend(output.timings);
+        for (merged, timings) in [
+            (&mut merged.accept_timings, output.accept_timings),
+            (&mut merged.kem_timings, output.kem_timings),
+            (&mut merged.dem_timings, output.dem_timings),
+        ] {
+            if let (Some(merged), Some(timings)) = (merged.as_mut(), timings) {
+                merged.extend(timings);
+            }
+        }
+        if let (Some(merged), Some(ops)) = (merged.mixed_ops.as_mut(), output.mixed_ops) {
+            merged.extend(ops);
+        }
+        if let (Some(merged), Some(usage)) = (merged.keypair_pool.as_mut(), output.keypair_pool) {
+            *merged = merged.add(usage);
+        }
+        if let (Some(merged), Some(phases)) = (merged.phases.as_mut(), output.phases) {
+            merged.extend(phases);
+        }
+    }
+    merged
+}
+
+fn ops_per_sec(ops: usize, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() { 0.0 } else { ops as f64 / elapsed.as_secs_f64() }
+}
+
+fn validate_threads(threads: u32, pool_size: usize) -> Result<(), AppError> {
+    if threads == 0 || threads as usize > pool_size {
+        return Err(AppError::ValidationFailed(format!(
+            "threads must be between 1 and {} (benchmark pool size), got {}", pool_size, threads
+        )));
+    }
+    Ok(())
+}
+
+fn run_operation<K: BenchmarkableKem + keypool::Pooled, R: RngCore + CryptoRng>(
+    param_set: &str,
+    operation: &str,
+    budget: Budget,
+    options: OperationOptions,
+    rng: &mut R,
+) -> Result<BenchOutput, AppError> {
+    let mut accept_timings = None;
+    let mut split = None;
+    let mut mixed_ops = None;
+    let mut keypair_pool = None;
+    let mut phases = None;
+    let timings = match operation {
+        "keygen" => bench_keygen::<K, _>(budget, rng),
+        "encaps" | "decaps" => {
+            let (timings, usage) = if operation == "encaps" {
+                bench_encaps::<K, _>(budget, options.key_mode, options.keypairs, rng)
+            } else {
+                bench_decaps::<K, _>(budget, options.key_mode, options.keypairs, rng)
+            };
+            keypair_pool = usage;
+            timings
+        }
+        "decaps_reject" => {
+            let (reject, accept) = bench_decaps_reject::<K, _>(budget, rng);
+            accept_timings = Some(accept);
+            reject
+        }
+        "encaps_decaps" => bench_encaps_decaps::<K, _>(budget, rng).map_err(|m| m.at(param_set, operation))?,
+        "full_handshake" => {
+            let (timings, handshake) = bench_full_handshake::<K, _>(budget, rng).map_err(|m| m.at(param_set, operation))?;
+            phases = Some(handshake);
+            timings
+        }
+        "ct_compare" => bench_ct_compare::<K, _>(budget, rng),
+        "serialize_keys" => bench_serialize_keys::<K, _>(budget, rng),
+        "deserialize_keys" => bench_deserialize_keys::<K, _>(budget, rng),
+        "kem_dem_encrypt" | "kem_dem_decrypt" => {
+            let timings = if operation == "kem_dem_encrypt" {
+                kem_dem::bench_encrypt::<K, _>(budget, options.dem, rng)
+            } else {
+                kem_dem::bench_decrypt::<K, _>(budget, options.dem, rng)
+            };
+            split = Some((timings.kem, timings.dem));
+            timings.total
+        }
+        "mixed" => {
+            let (timings, ops) = mixed::bench_mixed::<K, _>(budget, options.weights, rng);
+            mixed_ops = Some(ops);
+            timings
+        }
+        _ => return Err(AppError::InvalidOperation(operation.to_string())),
+    };
+    let (kem_timings, dem_timings) = split.unzip();
+    let sizes = sizes_of::<K>();
+
+    Ok(BenchOutput {
+        timings,
+        accept_timings,
+        kem_timings,
+        dem_timings,
+        mixed_ops,
+        keypair_pool,
+        phases,
+        encapsulation_key_bytes: Some(sizes.encapsulation_key),
+        decapsulation_key_bytes: Some(sizes.decapsulation_key),
+        ciphertext_bytes: Some(sizes.ciphertext),
+        shared_secret_bytes: Some(sizes.shared_secret),
+        ..BenchOutput::default()
+    })
+}
+
+
+
+fn validate_percentiles(percentiles: &[f64]) -> Result<(), AppError> {
+    match percentiles.iter().find(|&&p| !(p > 0.0 && p <= 1.0)) {
+        Some(p) => Err(AppError::ValidationFailed(format!(
+            "percentiles must be in (0, 1], got {}", p
+        ))),
+        None => Ok(()),
+    }
+}
+
+// The DEM settings with defaults filled in, for kem_dem_* operations only; elsewhere
+// they'd be silently ignored, so setting them is an error
+fn validate_dem_options(req: &KemBenchRequest, max_payload_bytes: usize) -> Result<Option<kem_dem::DemOptions>, AppError> {
+    if !kem_dem::is_kem_dem(req.operation) {
+        if req.cipher.is_some() || req.payload_bytes.is_some() {
+            return Err(AppError::ValidationFailed(format!(
+                "cipher and payload_bytes only apply to kem_dem_encrypt and kem_dem_decrypt, not {}", req.operation
+            )));
+        }
+        return Ok(None);
+    }
+    let payload_bytes = req.payload_bytes.unwrap_or(kem_dem::DEFAULT_PAYLOAD_BYTES);
+    if payload_bytes > max_payload_bytes {
+        return Err(AppError::ValidationFailed(format!(
+            "payload_bytes must be at most {}, got {}", max_payload_bytes, payload_bytes
+        )));
+    }
+    Ok(Some(kem_dem::DemOptions { cipher: req.cipher.unwrap_or_default(), payload_bytes }))
+}
+
+// The threshold to judge drift against, when timestamps are being recorded at all
+fn validate_drift(req: &KemBenchRequest) -> Result<Option<f64>, AppError> {
+    match (req.record_timestamps, req.drift_threshold) {
+        (false, Some(_)) => Err(AppError::ValidationFailed("drift_threshold needs record_timestamps".to_string())),
+        (false, None) => Ok(None),
+        (true, None) => Ok(Some(DEFAULT_DRIFT_THRESHOLD)),
+        (true, Some(t)) if t.is_finite() && t > 0.0 => Ok(Some(t)),
+        (true, Some(t)) => Err(AppError::ValidationFailed(format!("drift_threshold must be positive, got {}", t))),
+    }
+}
+
+// Below 1 even a flat run would count as warming up
+fn validate_warmup_factor(factor: Option<f64>) -> Result<f64, AppError> {
+    match factor.unwrap_or(DEFAULT_WARMUP_FACTOR) {
+        f if f.is_finite() && f >= 1.0 => Ok(f),
+        f => Err(AppError::ValidationFailed(format!("warmup_factor must be at least 1, got {}", f))),
+    }
+}
+
+fn validate_runs(runs: Option<u32>) -> Result<u32, AppError> {
+    match runs.unwrap_or(1) {
+        runs @ 1..=MAX_RUNS => Ok(runs),
+        runs => Err(AppError::ValidationFailed(format!("runs must be between 1 and {}, got {}", MAX_RUNS, runs))),
+    }
+}
+
+// Weights are required for mixed and meaningless for everything else
+fn validate_weights(req: &KemBenchRequest) -> Result<Option<mixed::MixedWeights>, AppError> {
+    match (req.operation, req.weights) {
+        (Operation::Mixed, Some(weights)) => weights.validate().map(|()| Some(weights)),
+        (Operation::Mixed, None) => Err(AppError::ValidationFailed("operation mixed requires weights".to_string())),
+        (_, Some(_)) => Err(AppError::ValidationFailed(format!(
+            "weights only apply to operation mixed, not {}", req.operation
+        ))),
+        (_, None) => Ok(None),
     }
 }
 
+// Untimed iterations used to estimate per-op cost for target_duration_ms
+const PILOT_ITERATIONS: u32 = 10;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum IterationCount {
+    Fixed(u32),
+    TargetDuration(u64),
+}
 
-// ============ Other operations ============
-struct Stats {
-    avg: f64,
-    min: f64,
-    max: f64,
-    p95: f64,
-    throughput: f64,
+fn validate_iteration_count(
+    iterations: Option<u32>,
+    target_duration_ms: Option<u64>,
+    iteration_cap: u32,
+    param_set: &str,
+) -> Result<IterationCount, AppError> {
+    match (iterations, target_duration_ms) {
+        (Some(0), None) => Err(AppError::ValidationFailed(format!(
+            "iterations must be between 1 and {} for {}, got 0", iteration_cap, param_set
+        ))),
+        (Some(n), None) => Ok(IterationCount::Fixed(n)),
+        (None, Some(0)) => Err(AppError::ValidationFailed("target_duration_ms must be at least 1".to_string())),
+        (None, Some(ms)) => Ok(IterationCount::TargetDuration(ms)),
+        _ => Err(AppError::ValidationFailed(
+            "exactly one of iterations and target_duration_ms must be set".to_string(),
+        )),
+    }
 }
 
-fn compute_stats(timings: &[u128]) -> Stats {
-    if timings.is_empty() {
-        return Stats { avg: 0.0, min: 0.0, max: 0.0, p95: 0.0, throughput: 0.0 };
+// Threads split the iterations between them, so each thread only has to fit its share in the target.
+// decaps_reject times the accept path alongside every measured iteration, so both count toward the cost.
+fn calibrate_iterations(pilot: &BenchOutput, target_ms: u64, threads: u32) -> u32 {
+    let per_op_us = compute_stats(&pilot.timings).avg
+        + pilot.accept_timings.as_deref().map_or(0.0, |t| compute_stats(t).avg);
+    // Sub-microsecond ops round down to 0us; treat them as 1us rather than dividing by zero
+    let estimate = target_ms as f64 * 1000.0 / per_op_us.max(1.0) * threads as f64;
+    estimate.clamp(1.0, u32::MAX as f64) as u32
+}
+
+// Trimming half or more from each end would leave nothing to summarize
+fn validate_trim(trim_percent: Option<f64>) -> Result<(), AppError> {
+    match trim_percent {
+        Some(t) if !(0.0..0.5).contains(&t) => Err(AppError::ValidationFailed(format!(
+            "trim_percent must be in [0, 0.5), got {}", t
+        ))),
+        _ => Ok(()),
     }
+}
 
-    let sum: u128 = timings.iter().sum();
-    let avg = sum as f64 / timings.len() as f64;
-    let min = *timings.iter().min().unwrap() as f64;
-    let max = *timings.iter().max().unwrap() as f64;
-    let p95 = compute_percentile(timings, 0.95);
-    let throughput = if avg > 0.0 { 1_000_000.0 / avg } else { 0.0 };
+// A missing timeout means the server maximum; anything above it is capped rather than rejected
+fn validate_timeout(timeout_ms: Option<u64>, max_timeout_ms: u64) -> Result<u64, AppError> {
+    match timeout_ms {
+        Some(0) => Err(AppError::ValidationFailed("timeout_ms must be at least 1".to_string())),
+        Some(t) => Ok(t.min(max_timeout_ms)),
+        None => Ok(max_timeout_ms),
+    }
+}
 
-    Stats { avg, min, max, p95, throughput }
+// The server-wide cap, lowered further for slow param sets
+fn iteration_cap(param_set: &str, max_iterations: u32) -> u32 {
+    slow_param_set_cap(param_set).map_or(max_iterations, |cap| cap.min(max_iterations))
 }
 
-fn compute_percentile(timings: &[u128], percentile: f64) -> f64 {
-    let mut sorted = timings.to_vec();
-    sorted.sort_unstable();
-    let idx = ((sorted.len() as f64) * percentile) as usize;
-    sorted[idx.min(sorted.len() - 1)] as f64
+// Param sets slow enough to need their own iteration cap
+fn slow_param_set_cap(param_set: &str) -> Option<u32> {
+    match param_set {
+        "rsa_2048" => Some(classical::RSA_MAX_ITERATIONS),
+        p if signatures::is_slh_dsa(p) => Some(signatures::SLH_DSA_MAX_ITERATIONS),
+        _ => None,
+    }
 }
 
+#[utoipa::path(
+    post,
+    path = "/kem_bench",
+    params(KemBenchMode),
+    request_body = KemBenchRequest,
+    responses(
+        (status = 200, description = "Benchmark statistics", body = KemBenchResponse),
+        (status = 202, description = "With async=true: the job was queued; poll GET /jobs/{id}", body = jobs::JobView),
+        (status = 400, description = "InvalidParamSet, InvalidOperation or MalformedInput", body = ErrorBody),
+        (status = 406, description = "NotAcceptable: Accept names no supported format", body = ErrorBody),
+        (status = 415, description = "UnsupportedMediaType: body is not JSON, CBOR or MessagePack", body = ErrorBody),
+        (status = 422, description = "ValidationFailed: percentiles, threads, iterations or timeout_ms out of range; or InvalidField: an unknown, missing or wrongly-typed field, named in `field`", body = ErrorBody),
+        (status = 429, description = "TooManyBenchmarks: every benchmark slot is busy, or TooManyJobs: the async job store is full; see Retry-After", body = ErrorBody),
+        (status = 503, description = "SheddingLoad: memory or in-flight benchmarks are past a --shed-* limit, see Retry-After", body = ErrorBody),
+        (status = 500, description = "Internal: the benchmark task failed or panicked; a panic names the param_set and operation", body = ErrorBody),
+    )
+)]
 async fn kem_bench(
-    Json(req): Json<KemBenchRequest>,
-) -> Result<Json<KemBenchResponse>, AppError> {
-    let iterations = req.iterations.clamp(1, 10000);
+    State(state): State<AppState>,
+    Requester(requester): Requester,
+    mode: Result<Query<KemBenchMode>, QueryRejection>,
+    Accepted(format): Accepted,
+    Negotiated(req): Negotiated<KemBenchRequest>,
+) -> Result<Response, AppError> {
+    let Query(mode) = mode.map_err(|e| AppError::MalformedInput(e.body_text()))?;
+    if mode.run_async {
+        let job = spawn_kem_job(state, requester, req)?;
+        let location = HeaderValue::from_str(&format!("/jobs/{}", job.job_id())).expect("job ids are hex");
+        return Ok((StatusCode::ACCEPTED, [(header::LOCATION, location)], Formatted(format, job)).into_response());
+    }
+    let resp = run_kem_bench(&state, req).await?;
+    state.record_result(requester, &resp);
+    Ok(Formatted(format, resp).into_response())
+}
+
+#[derive(Deserialize, IntoParams)]
+struct KemBenchMode {
+    /// Return 202 with a job_id straight away and run the benchmark in the background
+    #[serde(default, rename = "async")]
+    run_async: bool,
+}
+
+fn spawn_kem_job(state: AppState, requester: Option<IpAddr>, req: KemBenchRequest) -> Result<jobs::JobView, AppError> {
+    let job = state.jobs.submit()?;
+    let job_id = job.job_id().to_string();
+    tokio::spawn(async move {
+        // Unlike a synchronous request, a job waits for a free slot instead of getting a 429
+        let _permit = state.benchmark_slots.acquire().await.expect("benchmark semaphore is never closed");
+        state.jobs.start(&job_id);
+        let outcome = match bench_in_slot(&state, req, None).await {
+            Ok(resp) => {
+                state.record_result(requester, &resp);
+                Ok(resp)
+            }
+            Err(e) => {
+                state.metrics.service.errors.with_label_values(&[e.kind()]).inc();
+                Err(e.status_and_message().1)
+            }
+        };
+        state.jobs.finish(&job_id, outcome);
+    });
+    Ok(job)
+}
+
+async fn run_kem_bench(state: &AppState, req: KemBenchRequest) -> Result<KemBenchResponse, AppError> {
+    // Concurrent benchmarks contend for cores and skew each other, so excess ones are turned away
+    let _permit = state
+        .benchmark_slots
+        .try_acquire()
+        .map_err(|_| AppError::TooManyBenchmarks(state.config.max_concurrent_benchmarks()))?;
+    bench_in_slot(state, req, None).await
+}
+
+/// Runs a benchmark; the caller already holds one of the benchmark slots
+async fn bench_in_slot(
+    state: &AppState,
+    req: KemBenchRequest,
+    progress: Option<Arc<Progress>>,
+) -> Result<KemBenchResponse, AppError> {
+    // Checked before this benchmark counts as in flight, so the limit is on the ones already running
+    if let Some(reason) = state.readiness.pressure() {
+        return Err(AppError::SheddingLoad(reason));
+    }
+    let _in_flight = state.readiness.track();
+    validate_percentiles(&req.percentiles)?;
+    validate_threads(req.threads, state.bench_pool.size())?;
+    validate_trim(req.trim_percent)?;
+
+    let iteration_cap = iteration_cap(req.param_set.name(), state.config.max_iterations);
+    let iteration_count = validate_iteration_count(req.iterations, req.target_duration_ms, iteration_cap, req.param_set.name())?;
+    let warmup_iterations = req.warmup_iterations.unwrap_or(0).min(iteration_cap);
+    let timeout_ms = validate_timeout(req.timeout_ms, state.config.max_timeout_ms)?;
+
+    if req.key_mode != KeyMode::Single && !matches!(req.operation, Operation::Encaps | Operation::Decaps) {
+        return Err(AppError::ValidationFailed(format!(
+            "key_mode only applies to encaps and decaps, not {}", req.operation
+        )));
+    }
+    let dem = validate_dem_options(&req, state.config.max_payload_bytes)?;
+    let weights = validate_weights(&req)?;
+    let runs = validate_runs(req.runs)?;
+    let drift_threshold = validate_drift(&req)?;
+    let warmup_factor = validate_warmup_factor(req.warmup_factor)?;
+
+    let (param_set, operation, threads, seed) = (req.param_set.name(), req.operation.name(), req.threads, req.seed);
+    let key_mode = req.key_mode;
+    let overhead = if req.subtract_overhead { state.timer_overhead } else { Duration::ZERO };
+    let (dem_options, mixed_weights) = (dem.unwrap_or_default(), weights.unwrap_or_default());
+    // Pooled keys come from OsRng, so a seeded run generates its own to stay reproducible
+    let keypairs = state.keypairs.clone().filter(|_| key_mode == KeyMode::FreshPerIteration && seed.is_none());
+    let rss_before_kb = memory::rss_kb();
+    // Benchmark loops can run for seconds, so keep them off the async workers
+    // to leave /health and other requests responsive
+    let result = state.bench_pool.run(move || catch_benchmark_panic(param_set, operation, || {
+        let options = OperationOptions { key_mode, dem: dem_options, weights: mixed_weights, keypairs: keypairs.as_deref() };
+        // The pilot and warmup generate their own keys, leaving the pool to the measured runs
+        let unpooled = OperationOptions { keypairs: None, ..options };
+        // The deadline covers the pilot and warmup too, since the point is to bound how long the request holds a slot
+        let deadline = Some(Instant::now() + Duration::from_millis(timeout_ms));
+        let requested = match iteration_count {
+            IterationCount::Fixed(n) => n,
+            IterationCount::TargetDuration(target_ms) => {
+                let pilot_budget = Budget { iterations: PILOT_ITERATIONS.min(iteration_cap), deadline, progress: None, window: None, timeline: None, overhead };
+                let pilot = run_benchmark(param_set, operation, pilot_budget, unpooled, seed)?;
+                calibrate_iterations(&pilot, target_ms, threads)
+            }
+        };
+        // Over-cap requests still run, but the response carries both counts so clients can tell
+        let iterations = requested.min(iteration_cap);
+        if iterations < requested {
+            tracing::warn!(requested, effective = iterations, "iterations capped");
+        }
+        if warmup_iterations > 0 {
+            run_benchmark(param_set, operation, Budget { iterations: warmup_iterations, deadline, progress: None, window: None, timeline: None, overhead }, unpooled, seed)?;
+        }
+        let progress = progress.as_deref();
+        if let Some(progress) = progress {
+            progress.set_total(iterations.saturating_mul(runs));
+        }
+        let (mut outputs, mut wall_time, mut loop_time, mut per_run) = (Vec::new(), Duration::ZERO, Duration::ZERO, Vec::new());
+        let mut cpu_time = Some(CpuTime::default());
+        // One clock across every run, so drift between runs shows up too
+        let timeline = drift_threshold.map(|_| Timeline::default());
+        for run in 0..runs {
+            // Past the deadline each further run would still measure one iteration; skip them
+            if run > 0 && deadline.is_some_and(|d| Instant::now() >= d) {
+                break;
+            }
+            // A window per run, so the setup between runs isn't counted as loop time
+            let window = LoopWindow::default();
+            let budget = Budget { iterations, deadline, progress, window: Some(&window), timeline: timeline.as_ref(), overhead };
+            // Only the last run can stop short of `iterations`, at the deadline
+            let (output, run_wall_time) = run_threaded_benchmark(param_set, operation, budget, options, threads, seed)
+                .map_err(|e| e.after_iterations(u64::from(run) * u64::from(iterations)))?;
+            per_run.push(RunStats {
+                completed_iterations: output.timings.len() as u32,
+                stats: PathStats::from_timings(&output.timings),
+            });
+            outputs.push(output);
+            wall_time += run_wall_time;
+            loop_time += window.elapsed();
+            cpu_time = cpu_time.zip(window.cpu_time()).map(|(total, run)| total + run);
+        }
+        let timeline = timeline.map(Timeline::into_samples);
+        Ok((requested, iterations, (merge_outputs(outputs), wall_time), loop_time, cpu_time, per_run, timeline))
+    }))
+    .await?;
+    let mismatches = state.metrics.shared_secret_mismatches.with_label_values(&[param_set, operation]);
+    if let Err(AppError::SharedSecretMismatch { .. }) = &result {
+        mismatches.inc();
+    }
+    let (requested_iterations, iterations, (output, wall_time), loop_time, cpu_time, per_run, timeline) = result?;
+    let rss_after_kb = memory::rss_kb();
+    state.metrics.observe_benchmark(req.param_set.name(), req.operation.name(), &output.timings);
+    let trimmed = req.trim_percent.map(|t| trim_outliers(&output.timings, t));
+    let stats = compute_stats(trimmed.as_deref().unwrap_or(&output.timings));
+    let completed_iterations = output.timings.len() as u32;
+    let timed_out = completed_iterations < iterations.saturating_mul(runs);
+    tracing::info!(
+        param_set = %req.param_set,
+        operation = %req.operation,
+        iterations,
+        completed_iterations,
+        timed_out,
+        threads = req.threads,
+        avg_us = stats.avg,
+        "kem benchmark complete"
+    );
 
-    let timings = run_benchmark(&req.param_set, &req.operation, iterations)?;
-    let stats = compute_stats(&timings);
+    let (timings_us, timings_truncated) = if req.include_raw_timings {
+        let cap = state.config.max_raw_timings;
+        let raw = output.timings.iter().take(cap).map(|&t| t as u64).collect();
+        (Some(raw), Some(output.timings.len() > cap))
+    } else {
+        (None, None)
+    };
+    // Samples in the order they ran: from the timeline if there is one, otherwise straight
+    // from a single thread, whose runs follow one another. Several threads' samples are
+    // only concatenated, so without a timeline they have no order to fit a trend to.
+    let ordered: Option<Vec<u128>> = match &timeline {
+        Some(timeline) => Some(timeline.iter().map(|&(_, latency)| latency).collect()),
+        None => (req.threads == 1).then(|| output.timings.clone()),
+    };
+    let trend = ordered.as_deref().map(|latencies| stats::trend(latencies, warmup_factor));
+    // A drift threshold means there is a timeline
+    let drift = drift_threshold.zip(ordered.as_deref()).map(|(threshold, latencies)| stats::drift(latencies, threshold));
+    let phase_stats = output.phases.as_ref().map(|phases| [&phases.keygen, &phases.encaps, &phases.decaps].map(|t| PathStats::from_timings(t)));
+    let phase_discrepancy = phase_stats.as_ref().map(|stats| PhaseDiscrepancy::new(compute_stats(&output.timings).avg, stats));
+    let [keygen_stats, encaps_stats, decaps_stats] = phase_stats.map_or([None, None, None], |stats| stats.map(Some));
+    let timeline_us = timeline.filter(|_| req.include_raw_timings).map(|timeline| {
+        timeline.into_iter().take(state.config.max_raw_timings).map(|(started, latency)| (started, latency as u64)).collect()
+    });
 
-    Ok(Json(KemBenchResponse {
-        operation: req.operation,
-        param_set: req.param_set,
+    let mut resp = KemBenchResponse {
+        operation: req.operation.to_string(),
+        param_set: req.param_set.to_string(),
         iterations,
+        requested_iterations,
+        effective_iterations: iterations,
+        target_duration_ms: req.target_duration_ms,
         avg_us: stats.avg,
         min_us: stats.min,
         max_us: stats.max,
         p95_us: stats.p95,
+        stddev_us: stats.stddev,
+        median_us: stats.median,
+        ci95_low_us: stats.ci95_low,
+        ci95_high_us: stats.ci95_high,
+        trim_percent: req.trim_percent,
+        trimmed_avg_us: trimmed.is_some().then_some(stats.avg),
+        untrimmed_avg_us: trimmed.is_some().then(|| compute_stats(&output.timings).avg),
+        trimmed_samples: trimmed.as_ref().map(|t| output.timings.len() - t.len()),
+        percentiles: compute_percentiles(&output.timings, &req.percentiles),
         throughput_ops_sec: stats.throughput,
+        threads: req.threads,
+        bench_pool_threads: state.bench_pool.size(),
+        warmup_iterations,
+        key_mode,
+        seed: req.seed,
+        keypair_pool: output.keypair_pool,
+        wall_clock_throughput_ops_sec: ops_per_sec(output.timings.len(), wall_time),
+        measured_loop_ops_sec: ops_per_sec(output.timings.len(), loop_time),
+        avg_cpu_us: cpu_time.filter(|_| completed_iterations > 0).map(|t| t.cpu.as_secs_f64() * 1e6 / completed_iterations as f64),
+        cpu_time_ratio: cpu_time.map(CpuTime::ratio),
+        timer_overhead_ns: state.timer_overhead.as_nanos() as u64,
+        overhead_subtracted: req.subtract_overhead,
+        shared_secret_mismatches: matches!(req.operation, Operation::EncapsDecaps | Operation::FullHandshake).then(|| mismatches.get()),
+        histogram: compute_histogram(&output.timings, HISTOGRAM_BUCKETS),
+        encapsulation_key_bytes: output.encapsulation_key_bytes,
+        decapsulation_key_bytes: output.decapsulation_key_bytes,
+        ciphertext_bytes: output.ciphertext_bytes,
+        shared_secret_bytes: output.shared_secret_bytes,
+        signature_bytes: output.signature_bytes,
+        iteration_cap,
+        timings_us,
+        timings_truncated,
+        accept_path: output.accept_timings.as_deref().map(PathStats::from_timings),
+        cipher: dem.map(|dem| dem.cipher),
+        payload_bytes: dem.map(|dem| dem.payload_bytes),
+        kem_us: output.kem_timings.as_deref().map(PathStats::from_timings),
+        dem_us: output.dem_timings.as_deref().map(PathStats::from_timings),
+        phase_discrepancy,
+        keygen_stats,
+        encaps_stats,
+        decaps_stats,
+        weights,
+        operation_breakdown: output.mixed_ops.as_deref().map(|ops| mixed::breakdown(&output.timings, ops)),
+        runs,
+        across_runs: (runs > 1).then(|| run_spread(&per_run.iter().map(|run| run.stats.avg_us).collect::<Vec<_>>())),
+        per_run: (runs > 1).then_some(per_run),
+        drift,
+        trend,
+        timeline_us,
+        completed_iterations,
+        timeout_ms,
+        timed_out,
+        rss_before_kb,
+        rss_after_kb,
+        peak_rss_kb: memory::peak_rss_kb(),
+        machine_info: state.machine_info(req.include_machine_info),
         timestamp: current_timestamp(),
-    }))
+        signature: None,
+    };
+    resp.signature = Some(state.attestation.sign(&resp));
+    Ok(resp)
+}
+
+// ============ Batch Benchmarks ============
+
+// Each job either succeeds with a full response or carries its own error, so one bad
+// param_set doesn't abort the rest of the batch. Jobs are decoded one at a time for the
+// same reason: a misspelled field fails only its own entry.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum KemBatchEntry {
+    Ok(Box<KemBenchResponse>),
+    Err { error: String, status: u16 },
 }
 
+// Jobs run one after another so they don't compete for CPU and skew each other's timings
+async fn kem_bench_batch(
+    State(state): State<AppState>,
+    Requester(requester): Requester,
+    Accepted(format): Accepted,
+    Negotiated(jobs): Negotiated<Vec<serde_json::Value>>,
+) -> Formatted<Vec<KemBatchEntry>> {
+    let mut entries = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        let result = match request::from_value(job) {
+            Ok(job) => run_kem_bench(&state, job).await,
+            Err(e) => Err(AppError::InvalidField(e)),
+        };
+        entries.push(match result {
+            Ok(resp) => {
+                state.record_result(requester, &resp);
+                KemBatchEntry::Ok(Box::new(resp))
+            }
+            Err(e) => {
+                state.metrics.service.errors.with_label_values(&[e.kind()]).inc();
+                let (status, error) = e.status_and_message();
+                KemBatchEntry::Err { error, status: status.as_u16() }
+            }
+        });
+    }
+    Formatted(format, entries)
+}
+
+fn app(state: AppState) -> Router {
+    let limits = state.config.limits();
+
+    // Benchmarks keep to their own deadline (timeout_ms, capped by --max-timeout-ms) per run,
+    // and streams and sockets last as long as the client wants, so only the rest time out
+    let long_running = Router::new()
+        .route("/kem_bench", post(kem_bench))
+        .route("/kem_bench/stream", post(stream::kem_bench_stream))
+        .route("/kem_bench/stream_ndjson", post(stream::kem_bench_ndjson))
+        .route("/kem_bench_batch", post(kem_bench_batch))
+        .route("/kem_bench_matrix", post(matrix::kem_bench_matrix))
+        .route("/kem_compare", post(compare::kem_compare))
+        .route("/ws", get(ws::ws));
+
+    let router = Router::new()
+        .route("/health", get(health::health))
+        .route("/live", get(health::live))
+        .route("/ready", get(health::ready))
+        .route("/params", get(params::params))
+        .route("/environment", get(environment::environment))
+        .route("/signing_key", get(attestation::signing_key))
+        .route("/keygen", post(kem_api::keygen))
+        .route("/keygen_batch", post(kem_api::keygen_batch))
+        .route("/keygen_deterministic", post(kem_api::keygen_deterministic))
+        .route("/encaps", post(kem_api::encaps))
+        .route("/decaps", post(kem_api::decaps))
+        .route("/encrypt_message", post(kem_api::encrypt_message))
+        .route("/decrypt_message", post(kem_api::decrypt_message))
+        .route("/keys", post(keystore::create_key))
+        .route("/keys/{id}", delete(keystore::delete_key))
+        .route("/keys/{id}/decaps", post(keystore::decaps_with_key))
+        .route("/metrics", get(metrics::metrics))
+        .route("/version", get(version::version))
+        .route("/selftest", get(selftest::selftest))
+        .route("/results", get(results::results))
+        .route("/jobs/{id}", get(jobs::job))
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", openapi::ApiDoc::openapi()));
+    let router = limits.time_out(router).merge(long_running);
+
+    let router = limits
+        .limit_body(router)
+        .layer(middleware::from_fn_with_state(state.metrics.service.clone(), common::metrics::track));
+
+    // Rejected requests never reach the metrics above, but are still traced below
+    let router = match &state.config.auth_token {
+        Some(token) => router.layer(BearerAuthLayer::new(token)),
+        None => router,
+    };
 
+    let router = router
+        // Outermost first: assign an x-request-id, log the request under it, echo it back
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(|req: &axum::extract::Request| {
+                    let request_id = req
+                        .headers()
+                        .get("x-request-id")
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or("-");
+                    // HTTP/1.1 or HTTP/2.0, as negotiated via ALPN or h2c prior knowledge
+                    let version = req.version();
+                    tracing::info_span!("request", method = %req.method(), path = %req.uri().path(), ?version, request_id)
+                })
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
+
+    // Raw timings for large runs make responses big enough that compression matters over a WAN
+    let router = if state.config.disable_compression {
+        router
+    } else {
+        router.layer(CompressionLayer::new())
+    };
+
+    // Outermost, so preflight requests are answered before anything else runs
+    let router = match cors::layer(&state.config.cors_allowed_origins) {
+        Some(cors) => router.layer(cors),
+        None => router,
+    };
+    router.with_state(state)
+}
 
 #[tokio::main]
 async fn main() {
-    let router = Router::new()
-        .route("/health", get(health))
-        .route("/kem_bench", post(kem_bench));
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .init();
+
+    let config = Config::parse();
+    let drain_timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    let tls_files = config.tls_cert.clone().zip(config.tls_key.clone());
+    let uds = config.uds.clone().map(|path| (path, config.uds_mode));
+    let connections = config.limits().connections();
+    let tuning = config.http_tuning();
+    let bind = config.bind;
+    let router = app(AppState::new(config));
+
+    #[cfg(unix)]
+    if let Some((path, mode)) = uds {
+        let (listener, _socket) = common::uds::bind(&path, mode).expect("failed to bind Unix domain socket");
+        tracing::info!(path = %path.display(), mode = format!("{:o}", mode), "lattice_service listening");
+        http::serve_with_drain(connections.listener(listener), router, drain_timeout, http::signal()).await.unwrap();
+        tracing::info!("lattice_service stopped");
+        return;
+    }
+    #[cfg(not(unix))]
+    if uds.is_some() {
+        panic!("--uds is only supported on Unix");
+    }
+    match tls_files {
+        Some((cert, key)) => {
+            let tls_config = tls::load(&cert, &key).await.expect("failed to load TLS certificate and key");
+            tls::reload_on_sighup(tls_config.clone(), cert, key);
+            let (listener, addr) = listen::bind(bind).expect("failed to bind --bind address");
+            tracing::info!(%addr, "lattice_service listening (https)");
+            tls::serve(listener, router, tls_config, tuning, connections, drain_timeout, http::signal()).await.unwrap();
+        }
+        None => {
+            let (listener, addr) = listen::bind(bind).expect("failed to bind --bind address");
+            tracing::info!(%addr, "lattice_service listening");
+            // HTTP/1.1, or HTTP/2 for clients that speak it with prior knowledge (h2c)
+            http::serve(listener, router, tuning, connections, drain_timeout, http::signal()).await.unwrap();
+        }
+    }
+    tracing::info!("lattice_service stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    fn test_state(config: Config) -> State<AppState> {
+        State(AppState::new(config))
+    }
+
+    fn bench_request(param_set: &str, operation: &str, iterations: u32) -> KemBenchRequest {
+        KemBenchRequest {
+            param_set: param_set.parse().unwrap(),
+            iterations: Some(iterations),
+            target_duration_ms: None,
+            operation: operation.parse().unwrap(),
+            percentiles: default_percentiles(),
+            include_raw_timings: false,
+            threads: 1,
+            warmup_iterations: None,
+            seed: None,
+            timeout_ms: None,
+            key_mode: KeyMode::Single,
+            include_machine_info: false,
+            trim_percent: None,
+            cipher: None,
+            payload_bytes: None,
+            weights: None,
+            runs: None,
+            record_timestamps: false,
+            drift_threshold: None,
+            subtract_overhead: false,
+            warmup_factor: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn raw_timings_are_only_returned_on_request_and_capped() {
+        let config = Config::parse_from(["lattice_service", "--max-raw-timings", "5"]);
+
+        let resp = run_kem_bench(&AppState::new(config.clone()), bench_request("ml_kem_512", "keygen", 10))
+            .await
+            .unwrap();
+        assert!(resp.timings_us.is_none());
+        assert!(resp.timings_truncated.is_none());
+
+        let req = KemBenchRequest { include_raw_timings: true, ..bench_request("ml_kem_512", "keygen", 10) };
+        let resp = run_kem_bench(&AppState::new(config), req).await.unwrap();
+        assert_eq!(resp.timings_us.map(|t| t.len()), Some(5));
+        assert_eq!(resp.timings_truncated, Some(true));
+    }
+
+    #[tokio::test]
+    async fn warmup_iterations_are_not_measured() {
+        let req = KemBenchRequest {
+            warmup_iterations: Some(5),
+            include_raw_timings: true,
+            ..bench_request("ml_kem_768", "encaps", 8)
+        };
+        let resp = run_kem_bench(&AppState::new(Config::default()), req).await.unwrap();
+        assert_eq!(resp.warmup_iterations, 5);
+        assert_eq!(resp.iterations, 8);
+        assert_eq!(resp.timings_us.map(|t| t.len()), Some(8));
+        assert_eq!(resp.histogram.iter().map(|b| b.count).sum::<u64>(), 8);
+    }
+
+    #[tokio::test]
+    async fn memory_samples_are_reported() {
+        let resp = run_kem_bench(&AppState::new(Config::default()), bench_request("ml_kem_512", "keygen", 5))
+            .await
+            .unwrap();
+        let json = serde_json::to_value(&resp).unwrap();
+        for field in ["rss_before_kb", "rss_after_kb", "peak_rss_kb"] {
+            // Present as null on platforms without the counters, never omitted
+            assert!(json.get(field).is_some(), "{} missing", field);
+        }
+        if cfg!(target_os = "linux") {
+            let peak = resp.peak_rss_kb.unwrap();
+            assert!(peak >= resp.rss_after_kb.unwrap());
+            assert!(resp.rss_before_kb.unwrap() > 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn machine_info_needs_request_flag_and_server_consent() {
+        let with_flag = || KemBenchRequest { include_machine_info: true, ..bench_request("ml_kem_512", "keygen", 2) };
+
+        let state = AppState::new(Config::default());
+        let resp = run_kem_bench(&state, bench_request("ml_kem_512", "keygen", 2)).await.unwrap();
+        assert!(resp.machine_info.is_none());
+        let resp = run_kem_bench(&state, with_flag()).await.unwrap();
+        let info = resp.machine_info.expect("requested machine_info");
+        assert!(info.logical_cores >= 1);
+
+        let config = Config::parse_from(["lattice_service", "--disable-machine-info"]);
+        let resp = run_kem_bench(&AppState::new(config), with_flag()).await.unwrap();
+        assert!(resp.machine_info.is_none());
+    }
+
+    #[tokio::test]
+    async fn timer_overhead_is_reported_and_only_subtracted_on_request() {
+        let state = AppState::new(Config::default());
+        let resp = run_kem_bench(&state, bench_request("ml_kem_512", "keygen", 2)).await.unwrap();
+        assert!(resp.timer_overhead_ns > 0);
+        assert!(!resp.overhead_subtracted);
+
+        let req = KemBenchRequest { subtract_overhead: true, ..bench_request("ml_kem_512", "keygen", 2) };
+        let resp = run_kem_bench(&state, req).await.unwrap();
+        assert!(resp.overhead_subtracted);
+        assert_eq!(resp.timer_overhead_ns, state.timer_overhead.as_nanos() as u64);
+    }
+
+    #[tokio::test]
+    async fn trim_percent_reports_both_averages() {
+        let req = KemBenchRequest { trim_percent: Some(0.1), ..bench_request("ml_kem_512", "keygen", 20) };
+        let resp = run_kem_bench(&AppState::new(Config::default()), req).await.unwrap();
+        assert_eq!(resp.trimmed_samples, Some(4));
+        assert_eq!(resp.trimmed_avg_us, Some(resp.avg_us));
+        assert!(resp.untrimmed_avg_us.is_some());
+
+        let resp = run_kem_bench(&AppState::new(Config::default()), bench_request("ml_kem_512", "keygen", 5))
+            .await
+            .unwrap();
+        assert!(resp.trimmed_avg_us.is_none() && resp.trimmed_samples.is_none());
+    }
+
+    #[test]
+    fn trim_percent_of_half_or_more_is_rejected() {
+        for trim in [0.5, 0.9, -0.1, f64::NAN] {
+            assert!(matches!(validate_trim(Some(trim)), Err(AppError::ValidationFailed(_))), "{} was accepted", trim);
+        }
+        assert!(validate_trim(Some(0.0)).is_ok());
+        assert!(validate_trim(Some(0.49)).is_ok());
+        assert!(validate_trim(None).is_ok());
+    }
+
+    #[test]
+    fn exactly_one_iteration_source_is_required() {
+        let check = |iterations, target| validate_iteration_count(iterations, target, 100, "ml_kem_512");
+        assert_eq!(check(Some(5), None).unwrap(), IterationCount::Fixed(5));
+        assert_eq!(check(None, Some(250)).unwrap(), IterationCount::TargetDuration(250));
+        for (iterations, target) in [(Some(5), Some(250)), (None, None), (Some(0), None), (None, Some(0))] {
+            assert!(
+                matches!(check(iterations, target), Err(AppError::ValidationFailed(_))),
+                "{:?}/{:?} was accepted", iterations, target
+            );
+        }
+    }
+
+    #[test]
+    fn calibration_scales_pilot_cost_to_the_target() {
+        let pilot = BenchOutput { timings: vec![100; 10], ..BenchOutput::default() };
+        // 10ms at 100us per op is 100 ops per thread
+        assert_eq!(calibrate_iterations(&pilot, 10, 1), 100);
+        assert_eq!(calibrate_iterations(&pilot, 10, 4), 400);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8000));
-    let tcp = TcpListener::bind(&addr).await.unwrap();
-    
-    println!("lattice_service listening on {}", addr);
-    axum::serve(tcp, router).await.unwrap();
-   
+        let reject_pilot = BenchOutput { accept_timings: Some(vec![100; 10]), ..pilot };
+        assert_eq!(calibrate_iterations(&reject_pilot, 10, 1), 50);
+
+        let too_fast = BenchOutput { timings: vec![0; 10], ..BenchOutput::default() };
+        assert_eq!(calibrate_iterations(&too_fast, 10, 1), 10_000);
+        let too_slow = BenchOutput { timings: vec![1_000_000; 10], ..BenchOutput::default() };
+        assert_eq!(calibrate_iterations(&too_slow, 10, 1), 1);
+    }
+
+    #[tokio::test]
+    async fn target_duration_picks_and_reports_an_iteration_count() {
+        let req = KemBenchRequest {
+            iterations: None,
+            target_duration_ms: Some(50),
+            ..bench_request("ml_kem_512", "keygen", 0)
+        };
+        let resp = run_kem_bench(&AppState::new(Config::default()), req).await.unwrap();
+        assert_eq!(resp.target_duration_ms, Some(50));
+        assert!(resp.effective_iterations > PILOT_ITERATIONS, "only {} iterations", resp.effective_iterations);
+        assert_eq!(resp.effective_iterations, resp.requested_iterations.min(resp.iteration_cap));
+        assert_eq!(resp.completed_iterations, resp.effective_iterations);
+
+        // A long target still respects the iteration cap
+        let config = Config::parse_from(["lattice_service", "--max-iterations", "30"]);
+        let req = KemBenchRequest {
+            iterations: None,
+            target_duration_ms: Some(60_000),
+            ..bench_request("ml_kem_512", "keygen", 0)
+        };
+        let resp = run_kem_bench(&AppState::new(config), req).await.unwrap();
+        assert_eq!(resp.effective_iterations, 30);
+        assert!(resp.requested_iterations > 30);
+    }
+
+    #[tokio::test]
+    async fn timeout_returns_partial_results_over_clamped_iterations() {
+        // 50,000 is capped at max_iterations, still far more full handshakes than fit in 50ms
+        let max_iterations = Config::default().max_iterations;
+        let req = KemBenchRequest { timeout_ms: Some(50), ..bench_request("ml_kem_1024", "full_handshake", 50_000) };
+        let resp = run_kem_bench(&AppState::new(Config::default()), req).await.unwrap();
+        assert_eq!((resp.requested_iterations, resp.effective_iterations), (50_000, max_iterations));
+        assert!(resp.timed_out);
+        assert!((1..max_iterations).contains(&resp.completed_iterations), "{}", resp.completed_iterations);
+        assert_eq!(resp.histogram.iter().map(|b| b.count).sum::<u64>(), resp.completed_iterations as u64);
+
+        let resp = run_kem_bench(&AppState::new(Config::default()), bench_request("ml_kem_512", "keygen", 5))
+            .await
+            .unwrap();
+        assert!(!resp.timed_out);
+        assert_eq!(resp.completed_iterations, 5);
+        assert_eq!(resp.timeout_ms, Config::default().max_timeout_ms);
+    }
+
+    #[tokio::test]
+    async fn requested_timeout_is_capped_at_server_maximum() {
+        let config = Config::parse_from(["lattice_service", "--max-timeout-ms", "20"]);
+        let req = KemBenchRequest { timeout_ms: Some(3_600_000), ..bench_request("ml_kem_1024", "full_handshake", 10_000) };
+        let resp = run_kem_bench(&AppState::new(config), req).await.unwrap();
+        assert_eq!(resp.timeout_ms, 20);
+        assert!(resp.timed_out);
+
+        assert!(matches!(validate_timeout(Some(0), 20), Err(AppError::ValidationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn iterations_over_the_configured_cap_are_reported() {
+        let config = Config::parse_from(["lattice_service", "--max-iterations", "20"]);
+        let resp = run_kem_bench(&AppState::new(config.clone()), bench_request("ml_kem_512", "keygen", 50))
+            .await
+            .unwrap();
+        assert_eq!((resp.requested_iterations, resp.effective_iterations, resp.iteration_cap), (50, 20, 20));
+        assert_eq!(resp.completed_iterations, 20);
+
+        // Slow param sets keep their own lower cap, unless the server cap is lower still
+        assert_eq!(iteration_cap("rsa_2048", 10_000), classical::RSA_MAX_ITERATIONS);
+        assert_eq!(iteration_cap("rsa_2048", 20), 20);
+
+        let err = run_kem_bench(&AppState::new(config), bench_request("ml_kem_512", "keygen", 0)).await;
+        assert!(matches!(err, Err(AppError::ValidationFailed(msg)) if msg.contains("between 1 and 20")));
+    }
+
+    #[tokio::test]
+    async fn fresh_keys_exclude_keygen_but_are_not_faster() {
+        let state = AppState::new(Config::default());
+        let run = |operation: &str, key_mode| {
+            let req = KemBenchRequest { key_mode, ..bench_request("ml_kem_1024", operation, 30) };
+            run_kem_bench(&state, req)
+        };
+        for operation in ["encaps", "decaps"] {
+            // Alternate rounds and keep each mode's best, so a burst of load from tests running
+            // in parallel can't land on just one of them
+            let (mut single_min, mut fresh_min) = (f64::MAX, f64::MAX);
+            for _ in 0..5 {
+                single_min = single_min.min(run(operation, KeyMode::Single).await.unwrap().min_us);
+                let fresh = run(operation, KeyMode::FreshPerIteration).await.unwrap();
+                assert_eq!((fresh.key_mode, fresh.completed_iterations), (KeyMode::FreshPerIteration, 30));
+                fresh_min = fresh_min.min(fresh.min_us);
+            }
+            // Cold keys can only cost more (less the noise floor on a busy box), but keygen inside the
+            // timed section would cost far more
+            assert!(fresh_min >= single_min * 0.75, "{} {} vs {}", operation, fresh_min, single_min);
+            assert!(fresh_min < single_min * 1.5, "{} {} vs {}", operation, fresh_min, single_min);
+        }
+
+        let err = run("keygen", KeyMode::FreshPerIteration).await;
+        assert!(matches!(err, Err(AppError::ValidationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn fresh_keys_draw_on_the_pool_unless_seeded() {
+        let state = AppState::new(Config::parse_from(["lattice_service", "--keypair-pool-size", "8"]));
+        let pool = state.keypairs.clone().unwrap();
+        let fresh = |operation: &str, seed| KemBenchRequest {
+            key_mode: KeyMode::FreshPerIteration,
+            seed,
+            ..bench_request("ml_kem_512", operation, 20)
+        };
+        for operation in ["encaps", "decaps"] {
+            // Filled here rather than waiting on the background thread
+            pool.refill();
+            let resp = run_kem_bench(&state, fresh(operation, None)).await.unwrap();
+            assert_eq!(resp.keypair_pool, Some(keypool::PoolUsage { hits: 8, generated: 12 }), "{}", operation);
+            assert_eq!(resp.completed_iterations, 20);
+        }
+
+        pool.refill();
+        let seeded = run_kem_bench(&state, fresh("encaps", Some(7))).await.unwrap();
+        assert_eq!(seeded.keypair_pool, None);
+        let single = run_kem_bench(&state, bench_request("ml_kem_512", "encaps", 20)).await.unwrap();
+        assert_eq!(single.keypair_pool, None);
+        assert_eq!(pool.available::<MlKem512>(), 8);
+
+        let unpooled = run_kem_bench(&AppState::new(Config::default()), fresh("encaps", None)).await.unwrap();
+        assert_eq!(unpooled.keypair_pool, None);
+    }
+
+    #[tokio::test]
+    async fn key_serialization_reports_encoded_sizes() {
+        for operation in ["serialize_keys", "deserialize_keys"] {
+            let resp = run_kem_bench(&AppState::new(Config::default()), bench_request("ml_kem_768", operation, 10))
+                .await
+                .unwrap();
+            assert_eq!(resp.completed_iterations, 10);
+            assert_eq!(resp.encapsulation_key_bytes, Some(1184));
+            assert_eq!(resp.ciphertext_bytes, Some(1088));
+        }
+    }
+
+    #[tokio::test]
+    async fn kem_dem_reports_kem_and_dem_halves() {
+        let req = KemBenchRequest {
+            cipher: Some(kem_dem::Cipher::ChaCha20Poly1305),
+            payload_bytes: Some(4096),
+            threads: 2.min(std::thread::available_parallelism().map_or(1, |n| n.get()) as u32),
+            ..bench_request("ml_kem_768", "kem_dem_decrypt", 10)
+        };
+        let resp = run_kem_bench(&AppState::new(Config::default()), req).await.unwrap();
+        assert_eq!((resp.cipher, resp.payload_bytes), (Some(kem_dem::Cipher::ChaCha20Poly1305), Some(4096)));
+        let (kem, dem) = (resp.kem_us.expect("kem stats"), resp.dem_us.expect("dem stats"));
+        assert!(kem.min_us <= kem.max_us && dem.min_us <= dem.max_us);
+
+        let resp = run_kem_bench(&AppState::new(Config::default()), bench_request("ml_kem_512", "kem_dem_encrypt", 3))
+            .await
+            .unwrap();
+        assert_eq!((resp.cipher, resp.payload_bytes), (Some(kem_dem::Cipher::Aes256Gcm), Some(kem_dem::DEFAULT_PAYLOAD_BYTES)));
+        assert!(resp.kem_us.is_some() && resp.dem_us.is_some());
+    }
+
+    #[tokio::test]
+    async fn dem_options_are_validated() {
+        let config = Config::parse_from(["lattice_service", "--max-payload-bytes", "100"]);
+        let req = KemBenchRequest { payload_bytes: Some(101), ..bench_request("ml_kem_512", "kem_dem_encrypt", 1) };
+        let err = run_kem_bench(&AppState::new(config), req).await.err().unwrap();
+        assert_eq!(err.status_and_message().1, "payload_bytes must be at most 100, got 101");
+
+        let req = KemBenchRequest { cipher: Some(kem_dem::Cipher::Aes256Gcm), ..bench_request("ml_kem_512", "encaps", 1) };
+        let err = run_kem_bench(&AppState::new(Config::default()), req).await;
+        assert!(matches!(err, Err(AppError::ValidationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn mixed_workload_reports_per_operation_breakdown() {
+        let weights: mixed::MixedWeights = serde_json::from_str(r#"{"keygen": 1, "encaps": 10, "decaps": 10}"#).unwrap();
+        let req = KemBenchRequest { weights: Some(weights), seed: Some(5), ..bench_request("ml_kem_768", "mixed", 42) };
+        let resp = run_kem_bench(&AppState::new(Config::default()), req).await.unwrap();
+        assert_eq!(resp.completed_iterations, 42);
+        let breakdown = resp.operation_breakdown.expect("breakdown");
+        let counts: Vec<_> = breakdown.iter().map(|(op, b)| (op.as_str(), serde_json::to_value(b).unwrap()["iterations"].clone())).collect();
+        assert_eq!(counts, [("decaps", 20.into()), ("encaps", 20.into()), ("keygen", 2.into())]);
+
+        let zero = serde_json::from_str(r#"{"keygen": 0}"#).unwrap();
+        for weights in [None, Some(zero)] {
+            let req = KemBenchRequest { weights, ..bench_request("ml_kem_768", "mixed", 10) };
+            let err = run_kem_bench(&AppState::new(Config::default()), req).await;
+            assert!(matches!(err, Err(AppError::ValidationFailed(_))));
+        }
+        let req = KemBenchRequest { weights: Some(weights), ..bench_request("ml_kem_768", "encaps", 10) };
+        assert!(matches!(run_kem_bench(&AppState::new(Config::default()), req).await, Err(AppError::ValidationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn repeated_runs_report_per_run_stats_and_spread() {
+        let req = KemBenchRequest { runs: Some(3), ..bench_request("ml_kem_512", "encaps", 10) };
+        let resp = run_kem_bench(&AppState::new(Config::default()), req).await.unwrap();
+        assert_eq!((resp.runs, resp.completed_iterations), (3, 30));
+        let per_run = resp.per_run.expect("per-run stats");
+        assert_eq!(per_run.iter().map(|run| run.completed_iterations).collect::<Vec<_>>(), [10, 10, 10]);
+        let across = resp.across_runs.expect("cross-run spread");
+        let avgs: Vec<f64> = per_run.iter().map(|run| run.stats.avg_us).collect();
+        assert_eq!(across, run_spread(&avgs));
+
+        let resp = run_kem_bench(&AppState::new(Config::default()), bench_request("ml_kem_512", "encaps", 2)).await.unwrap();
+        assert_eq!(resp.runs, 1);
+        assert!(resp.per_run.is_none() && resp.across_runs.is_none());
+
+        for runs in [0, MAX_RUNS + 1] {
+            let req = KemBenchRequest { runs: Some(runs), ..bench_request("ml_kem_512", "encaps", 2) };
+            assert!(matches!(run_kem_bench(&AppState::new(Config::default()), req).await, Err(AppError::ValidationFailed(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn timestamps_give_a_drift_summary_and_optionally_the_timeline() {
+        let state = AppState::new(Config::default());
+        let req = KemBenchRequest { record_timestamps: true, runs: Some(2), ..bench_request("ml_kem_512", "keygen", 20) };
+        let resp = run_kem_bench(&state, req).await.unwrap();
+        let drift = resp.drift.expect("drift summary");
+        assert_eq!(drift.threshold, DEFAULT_DRIFT_THRESHOLD);
+        assert!(drift.first_decile_avg_us > 0.0 && drift.last_decile_avg_us > 0.0);
+        assert!(resp.timeline_us.is_none());
+
+        let req = KemBenchRequest {
+            record_timestamps: true,
+            drift_threshold: Some(0.5),
+            include_raw_timings: true,
+            ..bench_request("ml_kem_512", "keygen", 20)
+        };
+        let resp = run_kem_bench(&state, req).await.unwrap();
+        assert_eq!(resp.drift.unwrap().threshold, 0.5);
+        let timeline = resp.timeline_us.expect("timeline");
+        assert_eq!(timeline.len(), 20);
+        assert!(timeline.windows(2).all(|w| w[0].0 <= w[1].0));
+
+        let resp = run_kem_bench(&state, bench_request("ml_kem_512", "keygen", 2)).await.unwrap();
+        assert!(resp.drift.is_none());
+        for (record_timestamps, drift_threshold) in [(false, Some(0.5)), (true, Some(0.0)), (true, Some(f64::NAN))] {
+            let req = KemBenchRequest { record_timestamps, drift_threshold, ..bench_request("ml_kem_512", "keygen", 2) };
+            assert!(matches!(run_kem_bench(&state, req).await, Err(AppError::ValidationFailed(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn trend_needs_samples_in_the_order_they_ran() {
+        let state = AppState::new(Config::parse_from(["lattice_service", "--bench-threads", "2"]));
+        let resp = run_kem_bench(&state, bench_request("ml_kem_512", "keygen", 20)).await.unwrap();
+        let trend = resp.trend.expect("single-threaded runs have a trend");
+        assert_eq!(trend.warmup_factor, DEFAULT_WARMUP_FACTOR);
+
+        let threaded = KemBenchRequest { threads: 2, ..bench_request("ml_kem_512", "encaps", 20) };
+        let resp = run_kem_bench(&state, threaded).await.unwrap();
+        assert!(resp.trend.is_none());
+        let req = KemBenchRequest { threads: 2, record_timestamps: true, warmup_factor: Some(3.0), ..bench_request("ml_kem_512", "encaps", 20) };
+        let resp = run_kem_bench(&state, req).await.unwrap();
+        assert_eq!(resp.trend.expect("a timeline orders the samples").warmup_factor, 3.0);
+
+        for factor in [0.5, f64::INFINITY] {
+            let req = KemBenchRequest { warmup_factor: Some(factor), ..bench_request("ml_kem_512", "keygen", 2) };
+            assert!(matches!(run_kem_bench(&state, req).await, Err(AppError::ValidationFailed(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn decaps_reject_reports_both_paths() {
+        let resp = run_kem_bench(&AppState::new(Config::default()), bench_request("ml_kem_768", "decaps_reject", 20))
+            .await
+            .unwrap();
+        assert_eq!(resp.completed_iterations, 20);
+        let accept = resp.accept_path.expect("accept path stats");
+        assert!(accept.min_us <= accept.median_us && accept.median_us <= accept.max_us);
+
+        let resp = run_kem_bench(&AppState::new(Config::default()), bench_request("ml_kem_768", "decaps", 2))
+            .await
+            .unwrap();
+        assert!(resp.accept_path.is_none());
+        assert!(matches!(
+            run_benchmark("x25519", "decaps_reject", Budget::fixed(1), OperationOptions::default(), None),
+            Err(AppError::InvalidOperation(_))
+        ));
+    }
 
+    #[test]
+    fn corrupted_ciphertext_is_implicitly_rejected() {
+        let (dk, ek) = MlKem768::generate(&mut OsRng);
+        let (ct, ss) = ek.encapsulate(&mut OsRng).unwrap();
+        let mut corrupted = ct;
+        corrupted[0] ^= 1;
+        // Same length, so decapsulation succeeds, just with an unrelated shared secret
+        let rejected = dk.decapsulate(&corrupted).unwrap();
+        assert_ne!(rejected, ss);
+        assert_eq!(dk.decapsulate(&ct).unwrap(), ss);
+    }
+
+    #[tokio::test]
+    async fn seeded_benchmark_echoes_seed() {
+        let req = KemBenchRequest { seed: Some(42), ..bench_request("ml_kem_512", "full_handshake", 3) };
+        let resp = run_kem_bench(&AppState::new(Config::default()), req).await.unwrap();
+        assert_eq!(resp.seed, Some(42));
+        assert_eq!(resp.histogram.iter().map(|b| b.count).sum::<u64>(), 3);
+
+        let resp = run_kem_bench(&AppState::new(Config::default()), bench_request("ml_kem_512", "keygen", 1))
+            .await
+            .unwrap();
+        assert_eq!(resp.seed, None);
+    }
+
+    #[tokio::test]
+    async fn batch_reports_per_job_errors_without_aborting() {
+        let jobs = vec![
+            serde_json::json!({ "param_set": "ml_kem_512", "operation": "keygen", "iterations": 2 }),
+            serde_json::json!({ "param_set": "ml_kem_767", "operation": "keygen", "iterations": 2 }),
+            serde_json::json!({ "param_set": "x25519", "operation": "encaps", "iterations": 3 }),
+            serde_json::json!({ "param_set": "x25519", "operation": "sign", "iterations": 3 }),
+        ];
+        let Formatted(_, entries) = kem_bench_batch(test_state(Config::default()), Requester(None), Accepted(format::Format::Json), Negotiated(jobs)).await;
+
+        assert_eq!(entries.len(), 4);
+        assert!(matches!(&entries[0], KemBatchEntry::Ok(r) if r.param_set == "ml_kem_512"));
+        assert!(matches!(&entries[1], KemBatchEntry::Err { status: 422, error } if error.contains("ml_kem_767")));
+        assert!(matches!(&entries[2], KemBatchEntry::Ok(r) if r.param_set == "x25519" && r.iterations == 3));
+        assert!(matches!(&entries[3], KemBatchEntry::Err { status: 400, error } if error.contains("'sign'")));
+    }
+
+    #[tokio::test]
+    async fn kem_bench_updates_metrics() {
+        let state = AppState::new(Config::default());
+        let router = app(state.clone());
+        let post = |body: serde_json::Value| {
+            Request::post("/kem_bench")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let ok = post(serde_json::json!({ "param_set": "ml_kem_512", "operation": "keygen", "iterations": 4 }));
+        assert_eq!(router.clone().oneshot(ok).await.unwrap().status(), StatusCode::OK);
+        let bad = post(serde_json::json!({ "param_set": "x25519", "operation": "sign", "iterations": 4 }));
+        assert_eq!(router.clone().oneshot(bad).await.unwrap().status(), StatusCode::BAD_REQUEST);
+
+        let m = &state.metrics;
+        assert_eq!(m.bench_requests.with_label_values(&["ml_kem_512", "keygen"]).get(), 1);
+        assert_eq!(m.operation_latency.with_label_values(&["ml_kem_512", "keygen"]).get_sample_count(), 4);
+        assert_eq!(m.service.http_requests.with_label_values(&["POST", "/kem_bench", "200"]).get(), 1);
+        assert_eq!(m.service.http_requests.with_label_values(&["POST", "/kem_bench", "400"]).get(), 1);
+        assert_eq!(m.service.errors.with_label_values(&["invalid_operation"]).get(), 1);
+
+        let scrape = router.oneshot(Request::get("/metrics").body(Body::empty()).unwrap()).await.unwrap();
+        let body = axum::body::to_bytes(scrape.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(text.contains(r#"lattice_bench_requests_total{operation="keygen",param_set="ml_kem_512"} 1"#), "{}", text);
+    }
+
+    #[tokio::test]
+    async fn health_counts_requests_since_startup() {
+        let router = app(AppState::new(Config::default()));
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+        let health = |router: Router| async move {
+            let resp = router.oneshot(Request::get("/health").body(Body::empty()).unwrap()).await.unwrap();
+            let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+        };
+
+        let fresh = health(router.clone()).await;
+        assert_eq!((fresh["requests_total"].as_u64(), fresh["errors_total"].as_u64()), (Some(0), Some(0)));
+        assert!(fresh["uptime_seconds"].is_u64());
+
+        router.clone().oneshot(get("/params")).await.unwrap();
+        router.clone().oneshot(get("/params")).await.unwrap();
+        assert_eq!(router.clone().oneshot(get("/jobs/nope")).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        // The first /health call is counted by the time of the second
+        let counts = health(router).await;
+        assert_eq!(counts["requests_total"], 4);
+        assert_eq!(counts["requests_by_endpoint"], serde_json::json!({ "/health": 1, "/params": 2, "/jobs/{id}": 1 }));
+        assert_eq!(counts["errors_total"], 1);
+        assert_eq!(counts["errors_by_kind"], serde_json::json!({ "not_found": 1 }));
+    }
+
+    #[tokio::test]
+    async fn request_field_errors_are_structured_422s() {
+        let router = app(AppState::new(Config::default()));
+        let post = |body: String| {
+            Request::post("/kem_bench")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let cases = [
+            (serde_json::json!({ "param_set": "ml_kem_512", "operaton": "keygen", "iterations": 4 }), "operaton"),
+            (serde_json::json!({ "param_set": "ml_kem_512", "operation": "keygen", "iterations": "100" }), "iterations"),
+            (serde_json::json!({ "operation": "keygen", "iterations": 4 }), "param_set"),
+            (serde_json::json!({ "param_set": "ml_kem_767", "operation": "keygen", "iterations": 4 }), "param_set"),
+        ];
+        for (body, field) in cases {
+            let resp = router.clone().oneshot(post(body.to_string())).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+            let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+            let error: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(error["field"], field);
+            if field == "iterations" {
+                assert_eq!(error["expected"], "u32");
+            }
+            if field == "operaton" {
+                assert!(error["expected"].as_str().unwrap().contains("`operation`"), "{}", error);
+            }
+            if body["param_set"] == "ml_kem_767" {
+                assert!(error["expected"].as_str().unwrap().starts_with("one of ml_kem_512, ml_kem_768"), "{}", error);
+            }
+        }
+
+        // A body that isn't JSON at all is still a plain 400
+        let resp = router.oneshot(post("{".to_string())).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn benchmark_panics_are_structured_500s() {
+        let err = catch_benchmark_panic::<()>("ml_kem_768", "decaps", || panic!("decapsulation failed")).unwrap_err();
+        let resp = err.into_response();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(error["param_set"], "ml_kem_768");
+        assert_eq!(error["operation"], "decaps");
+        assert_eq!(error["error"], "Benchmark ml_kem_768 decaps panicked: decapsulation failed");
+
+        // Formatted panic messages are Strings rather than &str
+        let err = catch_benchmark_panic::<()>("x25519", "keygen", || panic!("bad key {}", 7)).unwrap_err();
+        assert!(matches!(err, AppError::BenchmarkPanicked { message, .. } if message == "bad key 7"));
+        assert_eq!(catch_benchmark_panic("x25519", "keygen", || Ok(3)).unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn full_handshake_breaks_down_into_phases() {
+        let state = AppState::new(Config::default());
+        for param_set in ["ml_kem_768", "x25519"] {
+            let resp = run_kem_bench(&state, bench_request(param_set, "full_handshake", 20)).await.unwrap();
+            let phases = [&resp.keygen_stats, &resp.encaps_stats, &resp.decaps_stats].map(|s| s.clone().expect("phase stats"));
+            let discrepancy = resp.phase_discrepancy.expect("phase discrepancy");
+            let sum: f64 = phases.iter().map(|p| p.avg_us).sum();
+            assert!((discrepancy.sum_of_phases_us - sum).abs() < 1e-9);
+            assert!((discrepancy.unaccounted_us - (resp.avg_us - sum)).abs() < 1e-9);
+            // Three phases each truncated to whole microseconds lose under 3us against the total
+            assert!(discrepancy.unaccounted_us > -3.0, "{} {}", param_set, discrepancy.unaccounted_us);
+            assert!(phases.iter().all(|p| p.max_us <= resp.max_us), "{}", param_set);
+        }
+
+        let resp = run_kem_bench(&state, bench_request("ml_kem_768", "encaps_decaps", 5)).await.unwrap();
+        assert!(resp.keygen_stats.is_none() && resp.phase_discrepancy.is_none());
+    }
+
+    #[tokio::test]
+    async fn shared_secret_mismatches_fail_naming_the_iteration() {
+        let mut check = SecretCheck::default();
+        let timings = Budget::fixed(5).try_measure(|| check.check(&[1; 32], &[1; 32])).unwrap();
+        assert_eq!(timings.len(), 5);
+
+        let mut check = SecretCheck::default();
+        let mut secrets = [[1; 32], [1; 32], [2; 32], [1; 32]].into_iter();
+        let mut iterations = 0;
+        let mismatch = Budget::fixed(4)
+            .try_measure(|| {
+                iterations += 1;
+                check.check(&[1; 32], &secrets.next().unwrap())
+            })
+            .unwrap_err();
+        assert_eq!(iterations, 3, "the loop stops at the mismatch");
+        let err = mismatch.at("ml_kem_768", "full_handshake");
+        assert!(matches!(err, AppError::SharedSecretMismatch { iteration: 2, .. }));
+        // The second thread's share, or run, starts 10 iterations in
+        assert!(matches!(err.after_iterations(10), AppError::SharedSecretMismatch { iteration: 12, .. }));
+
+        let err = SharedSecretMismatch { iteration: 2 }.at("ml_kem_768", "full_handshake");
+        let resp = err.into_response();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(error["iteration"], 2);
+        assert_eq!(error["operation"], "full_handshake");
+        assert_eq!(error["error"], "Benchmark ml_kem_768 full_handshake derived mismatched shared secrets at iteration 2");
+    }
+
+    #[tokio::test]
+    async fn handshakes_report_the_mismatch_counter_and_ct_compare_runs() {
+        let state = AppState::new(Config::default());
+        for operation in ["full_handshake", "encaps_decaps"] {
+            let resp = run_kem_bench(&state, bench_request("ml_kem_512", operation, 3)).await.unwrap();
+            assert_eq!(resp.shared_secret_mismatches, Some(0), "{}", operation);
+        }
+        let resp = run_kem_bench(&state, bench_request("ml_kem_512", "ct_compare", 5)).await.unwrap();
+        assert_eq!(resp.completed_iterations, 5);
+        assert_eq!(resp.shared_secret_bytes, Some(32));
+        assert!(resp.shared_secret_mismatches.is_none());
+        assert!(matches!(
+            run_benchmark("x25519", "ct_compare", Budget::fixed(1), OperationOptions::default(), None),
+            Err(AppError::InvalidOperation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn pressure_past_the_shed_limits_is_a_503_and_not_ready() {
+        let config = Config::parse_from(["lattice_service", "--shed-rss-mb", "1"]);
+        let router = app(AppState::new(config));
+        let bench = Request::post("/kem_bench")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"param_set":"ml_kem_512","operation":"keygen","iterations":2}"#))
+            .unwrap();
+        let resp = router.clone().oneshot(bench).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.headers()[header::RETRY_AFTER], RETRY_AFTER_SECS.to_string());
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(error["error"].as_str().unwrap().contains("resident memory is"), "{}", error);
+
+        let resp = router.oneshot(Request::get("/ready").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // Zero in flight allowed turns away even the first benchmark
+        let state = AppState::new(Config::parse_from(["lattice_service", "--shed-in-flight", "0"]));
+        let result = run_kem_bench(&state, bench_request("ml_kem_512", "keygen", 2)).await;
+        assert!(matches!(result, Err(AppError::SheddingLoad(_))));
+        let state = AppState::new(Config::parse_from(["lattice_service", "--shed-in-flight", "1"]));
+        assert!(run_kem_bench(&state, bench_request("ml_kem_512", "keygen", 2)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn async_kem_bench_is_polled_until_done() {
+        let router = app(AppState::new(Config::default()));
+        let body = serde_json::json!({ "param_set": "ml_kem_768", "operation": "encaps", "iterations": 5 });
+        let submit = Request::post("/kem_bench?async=true")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let resp = router.clone().oneshot(submit).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let location = resp.headers()[header::LOCATION].to_str().unwrap().to_string();
+        let job: serde_json::Value =
+            serde_json::from_slice(&axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(location, format!("/jobs/{}", job["job_id"].as_str().unwrap()));
+
+        let mut job = job;
+        for _ in 0..500 {
+            if job["status"] == "done" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            let resp = router.clone().oneshot(Request::get(&location).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            job = serde_json::from_slice(&axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap()).unwrap();
+        }
+        assert_eq!(job["status"], "done", "{}", job);
+        assert_eq!(job["result"]["completed_iterations"], 5);
+
+        let resp = router.oneshot(Request::get("/jobs/0123").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn failed_async_job_reports_the_error() {
+        let state = AppState::new(Config::default());
+        let job = spawn_kem_job(state.clone(), None, bench_request("x25519", "sign", 2)).unwrap();
+        let view = loop {
+            let view = serde_json::to_value(state.jobs.get(job.job_id()).unwrap()).unwrap();
+            if view["status"] != "queued" && view["status"] != "running" {
+                break view;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        };
+        assert_eq!(view["status"], "failed");
+        assert!(view["error"].as_str().unwrap().contains("'sign'"), "{}", view);
+    }
+
+    #[tokio::test]
+    async fn responses_carry_a_request_id() {
+        let router = app(AppState::new(Config::default()));
+
+        let resp = router.clone().oneshot(Request::get("/health").body(Body::empty()).unwrap()).await.unwrap();
+        let generated = resp.headers().get("x-request-id").expect("missing x-request-id");
+        assert!(!generated.is_empty());
+
+        let req = Request::get("/health").header("x-request-id", "bench-42").body(Body::empty()).unwrap();
+        let resp = router.oneshot(req).await.unwrap();
+        assert_eq!(resp.headers().get("x-request-id").unwrap(), "bench-42");
+    }
+
+    #[tokio::test]
+    async fn auth_token_guards_everything_but_health() {
+        let request = |path: &str, token: Option<&str>| {
+            let mut req = Request::get(path);
+            if let Some(token) = token {
+                req = req.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            req.body(Body::empty()).unwrap()
+        };
+        let config = Config::parse_from(["lattice_service", "--auth-token", "s3cret"]);
+        let router = app(AppState::new(config));
+
+        for (path, token, expected) in [
+            ("/health", None, StatusCode::OK),
+            ("/params", None, StatusCode::UNAUTHORIZED),
+            ("/params", Some("wrong"), StatusCode::UNAUTHORIZED),
+            ("/params", Some("s3cret"), StatusCode::OK),
+            ("/metrics", None, StatusCode::UNAUTHORIZED),
+        ] {
+            let resp = router.clone().oneshot(request(path, token)).await.unwrap();
+            assert_eq!(resp.status(), expected, "{} with {:?}", path, token);
+        }
+
+        // Without a configured token nothing changes
+        let resp = app(AppState::new(Config::default())).oneshot(request("/params", None)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn large_responses_are_compressed_only_when_requested() {
+        let body = serde_json::json!({
+            "param_set": "x25519",
+            "operation": "keygen",
+            "iterations": 2000,
+            "include_raw_timings": true,
+        });
+        let request = |accept_encoding: Option<&str>| {
+            let mut builder = Request::post("/kem_bench").header("content-type", "application/json");
+            if let Some(encoding) = accept_encoding {
+                builder = builder.header(header::ACCEPT_ENCODING, encoding);
+            }
+            builder.body(Body::from(body.to_string())).unwrap()
+        };
+
+        let router = app(AppState::new(Config::default()));
+        let resp = router.clone().oneshot(request(Some("gzip"))).await.unwrap();
+        assert_eq!(resp.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+        let resp = router.oneshot(request(None)).await.unwrap();
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+        let plain = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert!(serde_json::from_slice::<serde_json::Value>(&plain).is_ok());
+
+        let config = Config::parse_from(["lattice_service", "--disable-compression"]);
+        let resp = app(AppState::new(config)).oneshot(request(Some("gzip"))).await.unwrap();
+        assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+
+    #[tokio::test]
+    async fn concurrent_benchmarks_beyond_the_limit_get_429() {
+        let config = Config::parse_from(["lattice_service", "--max-concurrent-benchmarks", "1"]);
+        let router = app(AppState::new(config));
+        let post = |iterations: u32| {
+            let body = serde_json::json!({ "param_set": "ml_kem_1024", "operation": "full_handshake", "iterations": iterations });
+            Request::post("/kem_bench")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let long = tokio::spawn(router.clone().oneshot(post(100)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let throttled = router.clone().oneshot(post(1)).await.unwrap();
+        assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(throttled.headers().get(header::RETRY_AFTER).unwrap(), "1");
+        let body = axum::body::to_bytes(throttled.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["error"].as_str().unwrap().contains("limit of 1"));
+
+        assert_eq!(long.await.unwrap().unwrap().status(), StatusCode::OK);
+        assert_eq!(router.oneshot(post(1)).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn ml_kem_sizes_match_fips_203() {
+        let cases = [
+            ("ml_kem_512", 800, 1632, 768),
+            ("ml_kem_768", 1184, 2400, 1088),
+            ("ml_kem_1024", 1568, 3168, 1568),
+        ];
+        for (param_set, ek, dk, ct) in cases {
+            let output = run_benchmark(param_set, "keygen", Budget::fixed(1), OperationOptions::default(), None).unwrap();
+            assert_eq!(output.encapsulation_key_bytes, Some(ek), "{}", param_set);
+            assert_eq!(output.decapsulation_key_bytes, Some(dk), "{}", param_set);
+            assert_eq!(output.ciphertext_bytes, Some(ct), "{}", param_set);
+            assert_eq!(output.shared_secret_bytes, Some(32), "{}", param_set);
+        }
+    }
+
+    #[test]
+    fn same_seed_produces_same_key_material() {
+        let keygen = |seed| {
+            let (_dk, ek) = MlKem768::generate(&mut ChaCha20Rng::seed_from_u64(seed));
+            ek.as_bytes().to_vec()
+        };
+        assert_eq!(keygen(7), keygen(7));
+        assert_ne!(keygen(7), keygen(8));
+    }
+
+    #[test]
+    fn percentiles_outside_unit_interval_are_rejected() {
+        assert!(validate_percentiles(&[0.5, 0.99, 1.0]).is_ok());
+        for bad in [0.0, -0.1, 1.5, f64::NAN] {
+            assert!(matches!(validate_percentiles(&[0.5, bad]), Err(AppError::ValidationFailed(_))), "{}", bad);
+        }
+    }
+
+    // A single-threaded runtime makes this strict: a benchmark running inline would
+    // hold the only worker and /health could not be polled until it finished
+    #[tokio::test(flavor = "current_thread")]
+    async fn health_stays_responsive_during_long_benchmark() {
+        let router = app(AppState::new(Config::default()));
+        let body = serde_json::json!({
+            "param_set": "ml_kem_1024",
+            "operation": "full_handshake",
+            "iterations": 200,
+        });
+        let bench = tokio::spawn(router.clone().oneshot(
+            Request::post("/kem_bench")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        ));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let start = Instant::now();
+        let health = tokio::time::timeout(
+            Duration::from_millis(100),
+            router.oneshot(Request::get("/health").body(Body::empty()).unwrap()),
+        )
+        .await
+        .expect("/health did not respond within 100ms")
+        .unwrap();
+
+        assert_eq!(health.status(), StatusCode::OK);
+        assert!(start.elapsed() < Duration::from_millis(100));
+        assert!(!bench.is_finished(), "benchmark finished too quickly to prove anything");
+        assert_eq!(bench.await.unwrap().unwrap().status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn threaded_benchmark_collects_every_iteration() {
+        let (output, wall_time) = run_threaded_benchmark("ml_kem_512", "keygen", Budget::fixed(10), OperationOptions::default(), 3, None).unwrap();
+        assert_eq!(output.timings.len(), 10);
+        assert!(wall_time > Duration::ZERO);
+
+        // More threads than iterations leaves the extra threads idle
+        let (output, _) = run_threaded_benchmark("x25519", "encaps", Budget::fixed(2), OperationOptions::default(), 4, Some(7)).unwrap();
+        assert_eq!(output.timings.len(), 2);
+        assert!(output.ciphertext_bytes.is_some());
+    }
+
+    #[tokio::test]
+    async fn wall_clock_throughput_is_reported_next_to_the_derived_figure() {
+        let threads = 4.min(std::thread::available_parallelism().map_or(1, |n| n.get()) as u32);
+        let req = KemBenchRequest { threads, ..bench_request("ml_kem_512", "encaps", 40) };
+        let resp = run_kem_bench(&AppState::new(Config::default()), req).await.unwrap();
+        assert!(resp.throughput_ops_sec > 0.0 && resp.measured_loop_ops_sec > 0.0);
+        // Threads overlap in wall-clock time and the loop's own overhead isn't in avg_us,
+        // so the two figures never agree
+        assert_ne!(resp.measured_loop_ops_sec, resp.throughput_ops_sec);
+        // The measured loops are a subset of the whole run
+        assert!(resp.measured_loop_ops_sec >= resp.wall_clock_throughput_ops_sec);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn cpu_time_is_reported_next_to_wall_clock_time() {
+        let resp = run_kem_bench(&AppState::new(Config::default()), bench_request("ml_kem_512", "encaps", 40)).await.unwrap();
+        let ratio = resp.cpu_time_ratio.expect("Linux has a thread CPU clock");
+        // The clocks tick at different granularities, so allow a little over 1.0
+        assert!(ratio > 0.0 && ratio < 1.5, "{}", ratio);
+        assert!(resp.avg_cpu_us.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn threads_beyond_the_pool_size_are_rejected() {
+        let pool_size = Config::default().bench_threads();
+        assert!(validate_threads(1, pool_size).is_ok());
+        assert!(validate_threads(pool_size as u32, pool_size).is_ok());
+        for bad in [0, pool_size as u32 + 1] {
+            assert!(matches!(validate_threads(bad, pool_size), Err(AppError::ValidationFailed(_))), "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn benchmarks_beyond_the_pool_size_queue_for_it() {
+        let config = Config::parse_from(["lattice_service", "--bench-threads", "1", "--max-concurrent-benchmarks", "4"]);
+        let state = AppState::new(config);
+        let runs: Vec<_> = (0..4)
+            .map(|_| {
+                let state = state.clone();
+                tokio::spawn(async move { run_kem_bench(&state, bench_request("ml_kem_512", "encaps", 20)).await })
+            })
+            .collect();
+        // Four slots admit every request; the single pool thread then runs them one at a time
+        for run in runs {
+            let resp = run.await.unwrap().unwrap();
+            assert_eq!(resp.bench_pool_threads, 1);
+            assert_eq!(resp.iterations, 20);
+        }
+
+        let req = KemBenchRequest { threads: 2, ..bench_request("ml_kem_512", "encaps", 20) };
+        let err = run_kem_bench(&state, req).await.err().unwrap();
+        assert!(matches!(err, AppError::ValidationFailed(ref m) if m.contains("benchmark pool size")), "{:?}", err);
+    }
+
+    // Serving through common::http as main does, so a shutdown signal drains in-flight work
+    mod drain {
+        use super::*;
+        use common::{http::HttpTuning, limits::ConnectionLimit};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpStream;
+        use tokio::sync::oneshot;
+
+        async fn post(addr: std::net::SocketAddr, path: &str, body: &str) -> String {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let request = format!(
+                "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                path, body.len(), body
+            );
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        }
+
+        #[tokio::test]
+        async fn in_flight_benchmark_completes_after_shutdown_signal() {
+            let (listener, addr) = listen::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+            let router = app(AppState::new(Config::default()));
+            let (stop, stopped) = oneshot::channel::<()>();
+            let server = tokio::spawn(http::serve(listener, router, HttpTuning::default(), ConnectionLimit::default(), Duration::from_secs(30), async {
+                let _ = stopped.await;
+            }));
+
+            let body = r#"{"param_set":"ml_kem_1024","operation":"full_handshake","iterations":100}"#;
+            let bench = tokio::spawn(post(addr, "/kem_bench", body));
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            stop.send(()).unwrap();
+
+            let response = bench.await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+            assert!(response.contains("\"param_set\":\"ml_kem_1024\""));
+            server.await.unwrap().unwrap();
+        }
+
+        #[cfg(unix)]
+        #[tokio::test]
+        async fn serves_over_a_unix_socket() {
+            let path = std::env::temp_dir().join(format!("lattice-uds-{}.sock", std::process::id()));
+            let (listener, socket) = common::uds::bind(&path, common::uds::DEFAULT_MODE).unwrap();
+            let router = app(AppState::new(Config::default()));
+            let (stop, stopped) = oneshot::channel::<()>();
+            let server = tokio::spawn(http::serve_with_drain(listener, router, Duration::from_secs(1), async {
+                let _ = stopped.await;
+            }));
+
+            let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+            let body = r#"{"param_set":"ml_kem_512","operation":"keygen","iterations":3}"#;
+            let request = format!(
+                "POST /kem_bench HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(), body
+            );
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+            stop.send(()).unwrap();
+            server.await.unwrap().unwrap();
+            drop(socket);
+            assert!(!path.exists());
+        }
+
+        #[tokio::test]
+        async fn drain_timeout_bounds_shutdown() {
+            let (listener, addr) = listen::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+            let router = app(AppState::new(Config::default()));
+            let (stop, stopped) = oneshot::channel::<()>();
+            let server = tokio::spawn(http::serve(listener, router, HttpTuning::default(), ConnectionLimit::default(), Duration::from_millis(50), async {
+                let _ = stopped.await;
+            }));
+
+            let body = r#"{"param_set":"ml_kem_1024","operation":"full_handshake","iterations":100}"#;
+            let _bench = tokio::spawn(post(addr, "/kem_bench", body));
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            stop.send(()).unwrap();
+
+            tokio::time::timeout(Duration::from_millis(500), server)
+                .await
+                .expect("server outlived its drain timeout")
+                .unwrap()
+                .unwrap();
+        }
+    }
+
+    // The layer is common::cors; these check where app puts it
+    mod cors {
+        use super::*;
+        use axum::http::header;
+        use clap::Parser;
+
+        fn preflight() -> Request<Body> {
+            Request::options("/kem_bench")
+                .header(header::ORIGIN, "http://dash.local")
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+                .body(Body::empty())
+                .unwrap()
+        }
+
+        #[tokio::test]
+        async fn preflight_is_answered_without_a_token() {
+            let config = Config::parse_from([
+                "lattice_service", "--cors-allowed-origins", "*", "--auth-token", "s3cret",
+            ]);
+            let resp = app(AppState::new(config)).oneshot(preflight()).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        #[tokio::test]
+        async fn cors_is_off_by_default() {
+            let resp = app(AppState::new(Config::default())).oneshot(preflight()).await.unwrap();
+            assert!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        }
+
+        #[tokio::test]
+        async fn configured_origins_pass_preflight() {
+            let origins = "http://other.local,http://dash.local";
+            let config = Config::parse_from(["lattice_service", "--cors-allowed-origins", origins]);
+            let resp = app(AppState::new(config)).oneshot(preflight()).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "http://dash.local");
+            let methods = resp.headers().get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap().to_str().unwrap();
+            assert!(methods.contains("POST"), "{}", methods);
+            let headers = resp.headers().get(header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap().to_str().unwrap();
+            assert!(headers.contains("authorization"), "{}", headers);
+
+            let config = Config::parse_from(["lattice_service", "--cors-allowed-origins", "*"]);
+            let resp = app(AppState::new(config)).oneshot(preflight()).await.unwrap();
+            assert_eq!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "*");
+        }
+    }
 }
@@ -0,0 +1,188 @@
+use axum::extract::State;
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use common::params::{Operation, ParamSet};
+use common::request::{self, FieldError};
+
+use crate::format::{Accepted, Formatted, Negotiated};
+use crate::params::PARAM_SETS;
+use crate::results::Requester;
+use crate::{run_kem_bench, AppError, AppState, KemBenchResponse};
+
+// ============ Benchmark Matrix ============
+//
+// POST /kem_bench_matrix takes a kem_bench body whose param_set and operation may each be
+// "all" or a list of names, and runs every combination one after another. It is its own
+// endpoint so /kem_bench keeps answering with a single response. "all" only expands to
+// the pairs GET /params lists as supported; pairs named explicitly always run, so an
+// unsupported one shows up as that cell's error. As in /kem_bench_batch, a failing cell
+// doesn't abort the rest.
+
+const EXPECTED_SELECTION: &str = "a name, a list of names, or \"all\"";
+
+/// A full kem_bench response, or the error for that combination
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum KemMatrixCell {
+    Ok(Box<KemBenchResponse>),
+    Err { param_set: String, operation: String, error: String, status: u16 },
+}
+
+// Canonical names, and whether they came from "all"
+fn selection<T: std::str::FromStr<Err = String>>(
+    body: &Value,
+    field: &str,
+    all: impl Fn() -> Vec<&'static str>,
+    name: impl Fn(T) -> &'static str,
+) -> Result<(Vec<&'static str>, bool), FieldError> {
+    let invalid = |error: String| FieldError { error: format!("Invalid field '{}': {}", field, error), field: field.to_string(), expected: Some(EXPECTED_SELECTION.to_string()) };
+    let names = match body.get(field) {
+        None => return Err(FieldError { error: format!("Invalid field '{}': missing field `{}`", field, field), field: field.to_string(), expected: None }),
+        Some(Value::String(s)) if s.eq_ignore_ascii_case("all") => return Ok((all(), true)),
+        Some(Value::String(s)) => vec![s.as_str()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| item.as_str().ok_or_else(|| invalid(format!("expected {}, got {}", EXPECTED_SELECTION, item))))
+            .collect::<Result<_, _>>()?,
+        Some(other) => return Err(invalid(format!("expected {}, got {}", EXPECTED_SELECTION, other))),
+    };
+    let names = names.into_iter().map(|s| s.parse().map(&name).map_err(&invalid)).collect::<Result<Vec<_>, _>>()?;
+    if names.is_empty() {
+        return Err(invalid("the list is empty".to_string()));
+    }
+    Ok((names, false))
+}
+
+fn supported(param_set: &str, operation: &str) -> bool {
+    PARAM_SETS.iter().any(|&(p, operations)| p == param_set && operations.contains(&operation))
+}
+
+/// The (param_set, operation) pairs a matrix request covers, param set by param set
+fn expand(body: &Value) -> Result<Vec<(&'static str, &'static str)>, AppError> {
+    let (param_sets, all_param_sets) =
+        selection(body, "param_set", || PARAM_SETS.iter().map(|&(p, _)| p).collect(), ParamSet::name).map_err(AppError::InvalidField)?;
+    let (operations, all_operations) =
+        selection(body, "operation", || Operation::ALL.iter().map(|o| o.name()).collect(), Operation::name).map_err(AppError::InvalidField)?;
+    let expanded = all_param_sets || all_operations;
+    let cells: Vec<_> = param_sets
+        .iter()
+        .flat_map(|&p| operations.iter().map(move |&o| (p, o)))
+        .filter(|&(p, o)| !expanded || supported(p, o))
+        .collect();
+    if cells.is_empty() {
+        return Err(AppError::ValidationFailed("no supported param_set and operation combination was selected".to_string()));
+    }
+    Ok(cells)
+}
+
+// Cells run one after another, each taking a benchmark slot for its own run
+#[utoipa::path(
+    post,
+    path = "/kem_bench_matrix",
+    request_body(content = Object, description = "A kem_bench request whose param_set and operation may each be a name, a list of names, or \"all\""),
+    responses(
+        (status = 200, description = "One cell per param_set and operation pair, param set by param set: a kem_bench response, or that pair's error and status", body = [KemMatrixCell]),
+        (status = 400, description = "MalformedInput: the body isn't a request object", body = crate::ErrorBody),
+        (status = 406, description = "NotAcceptable: Accept names no supported format", body = crate::ErrorBody),
+        (status = 415, description = "UnsupportedMediaType: body is not JSON, CBOR or MessagePack", body = crate::ErrorBody),
+        (status = 422, description = "InvalidField: param_set or operation is missing or names nothing; or ValidationFailed: \"all\" left no supported pair", body = crate::ErrorBody),
+    )
+)]
+pub async fn kem_bench_matrix(
+    State(state): State<AppState>,
+    Requester(requester): Requester,
+    Accepted(format): Accepted,
+    Negotiated(body): Negotiated<Value>,
+) -> Result<Formatted<Vec<KemMatrixCell>>, AppError> {
+    if !body.is_object() {
+        return Err(AppError::MalformedInput("kem_bench_matrix expects a kem_bench request object".to_string()));
+    }
+    let cells = expand(&body)?;
+    let mut results = Vec::with_capacity(cells.len());
+    for (param_set, operation) in cells {
+        let mut job = body.clone();
+        job["param_set"] = param_set.into();
+        job["operation"] = operation.into();
+        let result = match request::from_value(job) {
+            Ok(job) => run_kem_bench(&state, job).await,
+            Err(e) => Err(AppError::InvalidField(e)),
+        };
+        results.push(match result {
+            Ok(resp) => {
+                state.record_result(requester, &resp);
+                KemMatrixCell::Ok(Box::new(resp))
+            }
+            Err(e) => {
+                state.metrics.service.errors.with_label_values(&[e.kind()]).inc();
+                let (status, error) = e.status_and_message();
+                KemMatrixCell::Err { param_set: param_set.to_string(), operation: operation.to_string(), error, status: status.as_u16() }
+            }
+        });
+    }
+    Ok(Formatted(format, results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::format::Format;
+    use serde_json::json;
+
+    async fn matrix(body: Value) -> Result<Vec<Value>, AppError> {
+        let state = State(AppState::new(Config::default()));
+        let Formatted(_, cells) = kem_bench_matrix(state, Requester(None), Accepted(Format::Json), Negotiated(body)).await?;
+        Ok(cells.iter().map(|cell| serde_json::to_value(cell).unwrap()).collect())
+    }
+
+    #[test]
+    fn all_expands_to_supported_pairs_only() {
+        let cells = expand(&json!({ "param_set": "all", "operation": "decaps_reject" })).unwrap();
+        assert_eq!(cells, [("ml_kem_512", "decaps_reject"), ("ml_kem_768", "decaps_reject"), ("ml_kem_1024", "decaps_reject")]);
+
+        let cells = expand(&json!({ "param_set": "ML-DSA-65", "operation": "ALL" })).unwrap();
+        assert_eq!(cells.iter().map(|&(_, o)| o).collect::<Vec<_>>(), ["keygen", "sign", "verify", "sign_verify"]);
+
+        assert_eq!(expand(&json!({ "param_set": "all", "operation": "sign" })).unwrap().len(), 5);
+
+        // Named on both sides, unsupported pairs stay in and fail on their own
+        let cells = expand(&json!({ "param_set": ["x25519", "kyber512"], "operation": ["decaps_reject"] })).unwrap();
+        assert_eq!(cells, [("x25519", "decaps_reject"), ("ml_kem_512", "decaps_reject")]);
+    }
+
+    #[test]
+    fn bad_selections_name_the_field() {
+        let err = |body| match expand(&body) {
+            Err(AppError::InvalidField(e)) => e,
+            _ => panic!("expected an invalid field"),
+        };
+        assert_eq!(err(json!({ "operation": "all" })).field, "param_set");
+        let e = err(json!({ "param_set": "all", "operation": ["keygen", 3] }));
+        assert_eq!(e.field, "operation");
+        assert_eq!(e.expected.as_deref(), Some(EXPECTED_SELECTION));
+        assert!(err(json!({ "param_set": "kyber9000", "operation": "all" })).error.contains("unknown param_set 'kyber9000'"));
+        assert!(err(json!({ "param_set": [], "operation": "all" })).error.contains("empty"));
+    }
+
+    #[tokio::test]
+    async fn failing_cells_are_reported_inline() {
+        let cells = matrix(json!({ "param_set": ["ml_kem_512", "x25519"], "operation": ["keygen", "decaps_reject"], "iterations": 2 }))
+            .await
+            .unwrap();
+        let summary: Vec<_> = cells.iter().map(|c| (c["param_set"].clone(), c["operation"].clone(), c.get("error").is_some())).collect();
+        assert_eq!(summary, [
+            (json!("ml_kem_512"), json!("keygen"), false),
+            (json!("ml_kem_512"), json!("decaps_reject"), false),
+            (json!("x25519"), json!("keygen"), false),
+            (json!("x25519"), json!("decaps_reject"), true),
+        ]);
+        assert_eq!(cells[3]["status"], 400);
+        assert_eq!(cells[0]["completed_iterations"], 2);
+
+        // Fields other than the selections apply to every cell, and are checked per cell
+        let cells = matrix(json!({ "param_set": "ml_kem_512", "operation": ["keygen"], "iterations": 0 })).await.unwrap();
+        assert_eq!(cells[0]["status"], 422);
+    }
+}
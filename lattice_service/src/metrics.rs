@@ -0,0 +1,70 @@
+use axum::{extract::State, response::IntoResponse};
+use common::metrics::{self as service, ServiceMetrics};
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts};
+use std::sync::Arc;
+
+use crate::AppState;
+
+pub use common::metrics::ErrorKind;
+
+// ============ Prometheus Metrics ============
+//
+// The HTTP series, and the middleware recording them, are common::metrics, shared with
+// zk_service; this adds the KEM benchmark series.
+
+pub struct Metrics {
+    pub service: Arc<ServiceMetrics>,
+    pub bench_requests: IntCounterVec,
+    pub operation_latency: HistogramVec,
+    pub shared_secret_mismatches: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let service = Arc::new(ServiceMetrics::new("lattice"));
+        let bench_requests = IntCounterVec::new(
+            Opts::new(service.name("bench_requests_total"), "Benchmark runs by param set and operation"),
+            &["param_set", "operation"],
+        )
+        .unwrap();
+        // Crypto operations run from a few microseconds (X25519) to seconds (SLH-DSA signing)
+        let operation_latency = HistogramVec::new(
+            HistogramOpts::new(
+                service.name("crypto_operation_duration_seconds"),
+                "Measured latency of individual benchmarked crypto operations",
+            )
+            .buckets(prometheus::exponential_buckets(1e-6, 4.0, 12).unwrap()),
+            &["param_set", "operation"],
+        )
+        .unwrap();
+
+        let shared_secret_mismatches = IntCounterVec::new(
+            Opts::new(service.name("shared_secret_mismatches_total"), "Benchmark runs failed by sender and receiver deriving different shared secrets"),
+            &["param_set", "operation"],
+        )
+        .unwrap();
+
+        service.register(&bench_requests);
+        service.register(&operation_latency);
+        service.register(&shared_secret_mismatches);
+
+        Metrics {
+            service,
+            bench_requests,
+            operation_latency,
+            shared_secret_mismatches,
+        }
+    }
+
+    pub fn observe_benchmark(&self, param_set: &str, operation: &str, timings_us: &[u128]) {
+        self.bench_requests.with_label_values(&[param_set, operation]).inc();
+        let histogram = self.operation_latency.with_label_values(&[param_set, operation]);
+        for &t in timings_us {
+            histogram.observe(t as f64 / 1_000_000.0);
+        }
+    }
+}
+
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    service::exposition(&state.metrics.service)
+}
@@ -0,0 +1,199 @@
+use ml_kem::kem::{Decapsulate, Encapsulate};
+use rand::{seq::SliceRandom, CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use crate::budget::Budget;
+use crate::{AppError, BenchmarkableKem, PathStats};
+use common::secret::SecretBox;
+
+// ============ Mixed Workloads ============
+//
+// operation "mixed" interleaves keygen, encaps and decaps in proportion to the request's
+// weights, in a shuffled order, to show how the operations behave when they share caches
+// and branch predictors the way they do in a real server. encaps and decaps run against one
+// long-lived keypair, as in encaps_decaps. The shuffle draws from the run's RNG, so a seed
+// reproduces the schedule as well as the keys.
+
+/// Relative share of each operation in a mixed run, e.g. {"keygen": 1, "encaps": 10, "decaps": 10}
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MixedWeights {
+    #[serde(default)]
+    keygen: u32,
+    #[serde(default)]
+    encaps: u32,
+    #[serde(default)]
+    decaps: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MixedOp {
+    Keygen,
+    Encaps,
+    Decaps,
+}
+
+impl MixedOp {
+    fn name(self) -> &'static str {
+        match self {
+            MixedOp::Keygen => "keygen",
+            MixedOp::Encaps => "encaps",
+            MixedOp::Decaps => "decaps",
+        }
+    }
+}
+
+// An even mix; requests always name their weights, so this only matters to callers that
+// run every operation with default options
+impl Default for MixedWeights {
+    fn default() -> Self {
+        MixedWeights { keygen: 1, encaps: 1, decaps: 1 }
+    }
+}
+
+impl MixedWeights {
+    fn entries(self) -> [(MixedOp, u32); 3] {
+        [(MixedOp::Keygen, self.keygen), (MixedOp::Encaps, self.encaps), (MixedOp::Decaps, self.decaps)]
+    }
+
+    pub fn validate(self) -> Result<(), AppError> {
+        if self.entries().iter().all(|&(_, weight)| weight == 0) {
+            return Err(AppError::ValidationFailed(
+                "weights must give at least one of keygen, encaps and decaps a non-zero weight".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Stats for one operation's share of a mixed run
+#[derive(Serialize, Clone, ToSchema)]
+pub struct OperationBreakdown {
+    iterations: usize,
+    #[serde(flatten)]
+    stats: PathStats,
+}
+
+// Each operation gets its exact proportional share, rounded down, and the iterations left
+// over go to the largest remainders, so the counts always add up to `iterations`
+fn schedule<R: RngCore + CryptoRng>(weights: MixedWeights, iterations: u32, rng: &mut R) -> Vec<MixedOp> {
+    let entries = weights.entries();
+    let total: u64 = entries.iter().map(|&(_, w)| u64::from(w)).sum();
+    let shares = entries.map(|(op, w)| {
+        let exact = u64::from(iterations) * u64::from(w);
+        (op, exact / total, exact % total)
+    });
+    let mut counts = shares.map(|(op, count, _)| (op, count));
+    let assigned: u64 = counts.iter().map(|&(_, count)| count).sum();
+    let mut by_remainder: Vec<usize> = (0..shares.len()).collect();
+    by_remainder.sort_by_key(|&i| std::cmp::Reverse(shares[i].2));
+    for &i in by_remainder.iter().take((u64::from(iterations) - assigned) as usize) {
+        counts[i].1 += 1;
+    }
+
+    let mut schedule: Vec<MixedOp> = counts
+        .iter()
+        .flat_map(|&(op, count)| std::iter::repeat_n(op, count as usize))
+        .collect();
+    schedule.shuffle(rng);
+    schedule
+}
+
+/// Per-iteration timings and which operation each one ran
+pub fn bench_mixed<K: BenchmarkableKem, R: RngCore + CryptoRng>(
+    budget: Budget,
+    weights: MixedWeights,
+    rng: &mut R,
+) -> (Vec<u128>, Vec<MixedOp>) {
+    let schedule = schedule(weights, budget.iterations, rng);
+    let (dk, ek) = K::generate(rng);
+    let (ct, ss) = ek.encapsulate(rng).unwrap();
+    drop(SecretBox::new(ss));
+
+    let mut ops = schedule.iter();
+    let timings = budget.measure(|| match ops.next().unwrap() {
+        MixedOp::Keygen => {
+            let _ = K::generate(rng);
+        }
+        MixedOp::Encaps => {
+            let _ = ek.encapsulate(rng);
+        }
+        MixedOp::Decaps => {
+            let _ss = SecretBox::new(dk.decapsulate(&ct).unwrap());
+        }
+    });
+    // A deadline can stop the loop early; only the operations that ran are kept
+    let ran = schedule[..timings.len()].to_vec();
+    (timings, ran)
+}
+
+pub fn breakdown(timings: &[u128], ops: &[MixedOp]) -> BTreeMap<String, OperationBreakdown> {
+    let mut by_op: BTreeMap<&str, Vec<u128>> = BTreeMap::new();
+    for (&timing, op) in timings.iter().zip(ops) {
+        by_op.entry(op.name()).or_default().push(timing);
+    }
+    by_op
+        .into_iter()
+        .map(|(name, timings)| {
+            let stats = PathStats::from_timings(&timings);
+            (name.to_string(), OperationBreakdown { iterations: timings.len(), stats })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ml_kem::MlKem512;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn weights(keygen: u32, encaps: u32, decaps: u32) -> MixedWeights {
+        MixedWeights { keygen, encaps, decaps }
+    }
+
+    fn count(schedule: &[MixedOp], op: MixedOp) -> usize {
+        schedule.iter().filter(|&&o| o == op).count()
+    }
+
+    #[test]
+    fn schedule_follows_weights_and_seed() {
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let schedule = schedule(weights(1, 10, 10), 105, &mut rng);
+        assert_eq!(schedule.len(), 105);
+        assert_eq!(
+            (count(&schedule, MixedOp::Keygen), count(&schedule, MixedOp::Encaps), count(&schedule, MixedOp::Decaps)),
+            (5, 50, 50)
+        );
+        // Shuffled rather than run in blocks
+        assert_ne!(schedule[..5], [MixedOp::Keygen; 5]);
+
+        let again = super::schedule(weights(1, 10, 10), 105, &mut ChaCha20Rng::seed_from_u64(7));
+        assert_eq!(schedule, again);
+    }
+
+    #[test]
+    fn leftover_iterations_go_to_the_largest_remainders() {
+        let schedule = schedule(weights(1, 1, 1), 4, &mut ChaCha20Rng::seed_from_u64(1));
+        assert_eq!(schedule.len(), 4);
+        let schedule = super::schedule(weights(0, 3, 1), 2, &mut ChaCha20Rng::seed_from_u64(1));
+        assert_eq!((count(&schedule, MixedOp::Encaps), count(&schedule, MixedOp::Decaps)), (2, 0));
+    }
+
+    #[test]
+    fn empty_or_zero_weights_are_rejected() {
+        assert!(matches!(weights(0, 0, 0).validate(), Err(AppError::ValidationFailed(_))));
+        assert!(weights(0, 0, 1).validate().is_ok());
+        assert!(serde_json::from_str::<MixedWeights>(r#"{"sign": 1}"#).is_err());
+    }
+
+    #[test]
+    fn breakdown_covers_only_weighted_operations() {
+        let (timings, ops) = bench_mixed::<MlKem512, _>(Budget::fixed(12), weights(0, 1, 2), &mut ChaCha20Rng::seed_from_u64(3));
+        let breakdown = breakdown(&timings, &ops);
+        assert_eq!(breakdown.keys().collect::<Vec<_>>(), ["decaps", "encaps"]);
+        assert_eq!((breakdown["encaps"].iterations, breakdown["decaps"].iterations), (4, 8));
+    }
+}
@@ -0,0 +1,69 @@
+use utoipa::OpenApi;
+
+// ============ OpenAPI Document ============
+//
+// Served at /api-docs/openapi.json with Swagger UI at /swagger-ui. Schemas are collected
+// from the annotated handlers, so a new endpoint only needs adding to `paths` here.
+
+#[derive(OpenApi)]
+#[openapi(paths(crate::kem_bench, crate::stream::kem_bench_stream, crate::stream::kem_bench_ndjson, crate::compare::kem_compare, crate::matrix::kem_bench_matrix, common::health::health, crate::jobs::job, crate::results::results, crate::selftest::selftest, crate::attestation::signing_key, crate::version::version, crate::environment::environment, crate::keystore::create_key, crate::keystore::decaps_with_key, crate::keystore::delete_key, crate::kem_api::encrypt_message, crate::kem_api::decrypt_message, crate::kem_api::keygen_deterministic, crate::kem_api::keygen_batch))]
+pub struct ApiDoc;
+
+#[cfg(test)]
+mod tests {
+    use crate::{app, config::Config, kem_api, AppState};
+    use axum::{body::Body, http::{Request, StatusCode}};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn openapi_document_describes_kem_bench() {
+        let router = app(AppState::new(Config::default()));
+        let resp = router
+            .oneshot(Request::get("/api-docs/openapi.json").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let responses = &spec["paths"]["/kem_bench"]["post"]["responses"];
+        for status in ["200", "400", "422", "429"] {
+            assert!(responses[status].is_object(), "missing {} response", status);
+        }
+        let schemas = &spec["components"]["schemas"];
+        for schema in ["KemBenchRequest", "KemBenchResponse", "HealthResponse", "ErrorBody"] {
+            assert!(schemas[schema].is_object(), "missing {} schema", schema);
+        }
+    }
+
+    #[tokio::test]
+    async fn openapi_document_lists_each_endpoints_error_statuses() {
+        let router = app(AppState::new(Config::default()));
+        let resp = router
+            .oneshot(Request::get("/api-docs/openapi.json").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        for (method, path, statuses) in [
+            ("post", "/kem_bench_matrix", &["200", "400", "422"][..]),
+            ("post", "/keys", &["201", "400", "422"]),
+            ("post", "/keys/{id}/decaps", &["200", "400", "404"]),
+            ("delete", "/keys/{id}", &["204", "404"]),
+            ("post", "/encrypt_message", &["200", "400", "422"]),
+            ("post", "/decrypt_message", &["200", "400", "422"]),
+            ("post", "/keygen_deterministic", &["200", "400", "422"]),
+            ("post", "/keygen_batch", &["200", "400", "422"]),
+        ] {
+            let responses = &spec["paths"][path][method]["responses"];
+            for status in statuses {
+                assert!(responses[status].is_object(), "missing {} response for {} {}", status, method, path);
+            }
+        }
+        assert!(spec["components"]["schemas"]["KemMatrixCell"].is_object());
+        let authentication_failure = spec["paths"]["/decrypt_message"]["post"]["responses"]["400"]["description"].as_str().unwrap();
+        assert!(authentication_failure.contains("AuthenticationFailed"), "{}", authentication_failure);
+        let count = &spec["components"]["schemas"]["KeygenBatchRequest"]["properties"]["count"];
+        assert_eq!((count["minimum"].as_f64(), count["maximum"].as_f64()), (Some(1.0), Some(kem_api::MAX_KEYGEN_BATCH as f64)));
+    }
+}
@@ -0,0 +1,116 @@
+use axum::{extract::State, Json};
+use common::{http::HttpTuning, limits::Limits};
+use serde::Serialize;
+
+use crate::{iteration_cap, AppState};
+
+// ============ Supported Param Sets ============
+//
+// Single list of what run_benchmark dispatches, advertised via GET /params so clients
+// can check their inputs instead of finding out from a 400.
+
+const KEM_OPERATIONS: &[&str] = &["keygen", "encaps", "decaps", "encaps_decaps", "full_handshake"];
+const ML_KEM_OPERATIONS: &[&str] = &[
+    "keygen",
+    "encaps",
+    "decaps",
+    "encaps_decaps",
+    "full_handshake",
+    "decaps_reject",
+    "ct_compare",
+    "serialize_keys",
+    "deserialize_keys",
+    "kem_dem_encrypt",
+    "kem_dem_decrypt",
+    "mixed",
+];
+const ML_DSA_OPERATIONS: &[&str] = &["keygen", "sign", "verify", "sign_verify"];
+const SLH_DSA_OPERATIONS: &[&str] = &["keygen", "sign", "verify"];
+
+pub const PARAM_SETS: &[(&str, &[&str])] = &[
+    ("ml_kem_512", ML_KEM_OPERATIONS),
+    ("ml_kem_768", ML_KEM_OPERATIONS),
+    ("ml_kem_1024", ML_KEM_OPERATIONS),
+    ("x_wing", KEM_OPERATIONS),
+    ("x25519", KEM_OPERATIONS),
+    ("rsa_2048", KEM_OPERATIONS),
+    ("ml_dsa_44", ML_DSA_OPERATIONS),
+    ("ml_dsa_65", ML_DSA_OPERATIONS),
+    ("ml_dsa_87", ML_DSA_OPERATIONS),
+    ("slh_dsa_sha2_128s", SLH_DSA_OPERATIONS),
+    ("slh_dsa_sha2_128f", SLH_DSA_OPERATIONS),
+];
+
+pub fn param_set_names() -> Vec<&'static str> {
+    PARAM_SETS.iter().map(|(name, _)| *name).collect()
+}
+
+#[derive(Serialize)]
+pub struct ParamSetInfo {
+    name: &'static str,
+    operations: &'static [&'static str],
+    /// Highest iteration count a single request will run
+    iteration_cap: u32,
+}
+
+#[derive(Serialize)]
+pub struct ParamsResponse {
+    param_sets: Vec<ParamSetInfo>,
+    /// Body size, handler timeout and connection limits the server was started with
+    limits: Limits,
+    /// HTTP/1.1 keep-alive and HTTP/2 settings
+    http: HttpTuning,
+}
+
+pub async fn params(State(state): State<AppState>) -> Json<ParamsResponse> {
+    let param_sets = PARAM_SETS
+        .iter()
+        .map(|&(name, operations)| ParamSetInfo {
+            name,
+            operations,
+            iteration_cap: iteration_cap(name, state.config.max_iterations),
+        })
+        .collect();
+
+    Json(ParamsResponse { param_sets, limits: state.config.limits(), http: state.config.http_tuning() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{budget::Budget, config::Config, run_benchmark, slow_param_set_cap, AppError, OperationOptions};
+
+    #[test]
+    fn every_advertised_operation_is_dispatched() {
+        for &(param_set, operations) in PARAM_SETS {
+            // Slow param sets still need keygen/sign for setup, which takes far too long
+            // in a debug build; the unknown-operation check below covers their dispatch
+            let slow = slow_param_set_cap(param_set).is_some();
+            for &operation in operations.iter().filter(|_| !slow) {
+                assert!(
+                    run_benchmark(param_set, operation, Budget::fixed(1), OperationOptions::default(), Some(0)).is_ok(),
+                    "{} {}", param_set, operation
+                );
+            }
+            assert!(matches!(
+                run_benchmark(param_set, "bogus", Budget::fixed(1), OperationOptions::default(), Some(0)),
+                Err(AppError::InvalidOperation(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn params_report_iteration_caps() {
+        let config = Config::default();
+        let max_iterations = config.max_iterations;
+        let Json(resp) = params(State(AppState::new(config))).await;
+        let cap = |name| resp.param_sets.iter().find(|p| p.name == name).unwrap().iteration_cap;
+        assert_eq!(resp.param_sets.len(), PARAM_SETS.len());
+        assert_eq!(cap("ml_kem_768"), max_iterations);
+        assert_eq!(cap("rsa_2048"), crate::classical::RSA_MAX_ITERATIONS);
+        assert_eq!(cap("slh_dsa_sha2_128s"), crate::signatures::SLH_DSA_MAX_ITERATIONS);
+        assert_eq!(resp.limits.max_body_bytes, common::limits::DEFAULT_MAX_BODY_BYTES);
+        assert_eq!(resp.limits.max_connections, None);
+        assert_eq!(resp.http.idle_timeout_secs, common::http::DEFAULT_IDLE_TIMEOUT_SECS);
+    }
+}
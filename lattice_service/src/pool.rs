@@ -0,0 +1,75 @@
+use tokio::sync::oneshot;
+
+use crate::AppError;
+
+// ============ Benchmark Thread Pool ============
+//
+// Benchmarks are CPU-bound, so they run on a fixed-size rayon pool rather than tokio's
+// blocking pool, which grows to 512 threads and would let two concurrent requests each
+// believe they have the whole machine. Work beyond the pool's size queues instead of
+// oversubscribing cores; a threaded benchmark's shares are spawned onto the same pool.
+
+pub struct BenchPool {
+    pool: rayon::ThreadPool,
+}
+
+impl BenchPool {
+    pub fn new(threads: usize) -> Self {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("bench-{}", i))
+            .build()
+            .expect("failed to start benchmark thread pool");
+        BenchPool { pool }
+    }
+
+    /// Threads in the pool, i.e. the most benchmark work that runs at once
+    pub fn size(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    /// Runs `f` on the pool once a thread is free
+    pub async fn run<T: Send + 'static>(&self, f: impl FnOnce() -> T + Send + 'static) -> Result<T, AppError> {
+        let (tx, rx) = oneshot::channel();
+        self.pool.spawn(move || {
+            let _ = tx.send(f());
+        });
+        rx.await
+            .map_err(|_| AppError::Internal("benchmark task failed: pool thread exited without a result".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn work_beyond_the_pool_size_queues() {
+        let pool = Arc::new(BenchPool::new(2));
+        let (running, peak) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let tasks: Vec<_> = (0..6)
+            .map(|i| {
+                let (pool, running, peak) = (pool.clone(), running.clone(), peak.clone());
+                tokio::spawn(async move {
+                    pool.run(move || {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(Duration::from_millis(20));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        i
+                    })
+                    .await
+                })
+            })
+            .collect();
+        let mut done = Vec::new();
+        for task in tasks {
+            done.push(task.await.unwrap().unwrap());
+        }
+        assert_eq!(done, (0..6).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= 2, "{} ran at once on a pool of 2", peak.load(Ordering::SeqCst));
+    }
+}
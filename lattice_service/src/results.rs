@@ -0,0 +1,289 @@
+use axum::{
+    extract::{rejection::QueryRejection, ConnectInfo, FromRequestParts, Query, State},
+    http::request::Parts,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{current_timestamp, AppError, AppState};
+use common::params::ParamSet;
+
+// ============ Results Log ============
+//
+// With --results-log set, every completed kem_bench result is appended to a JSONL file
+// so runs survive the client that made them. Handlers only hand a serialized line to a
+// channel; a dedicated writer thread does the file I/O, so a slow disk never holds up a
+// benchmark. GET /results reads the file back in the order entries were written.
+
+/// Lines allowed to queue for the writer before new results are dropped
+const QUEUE_CAPACITY: usize = 1024;
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// One line of the results log
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ResultEntry {
+    /// Unix seconds when the benchmark completed
+    timestamp: u64,
+    /// Address of the client that asked for the run, when known
+    requester_ip: Option<String>,
+    /// The kem_bench response body, exactly as returned
+    #[schema(value_type = Object)]
+    response: serde_json::Value,
+}
+
+pub struct ResultsLog {
+    path: PathBuf,
+    tx: SyncSender<String>,
+}
+
+impl ResultsLog {
+    /// Opens (or creates) the log for appending and starts its writer thread
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (tx, rx) = mpsc::sync_channel::<String>(QUEUE_CAPACITY);
+        std::thread::Builder::new()
+            .name("results-log".to_string())
+            .spawn(move || write_lines(file, rx))?;
+        Ok(ResultsLog { path: path.to_path_buf(), tx })
+    }
+
+    /// Queues a completed result for writing without waiting on the file
+    pub fn record(&self, requester_ip: Option<IpAddr>, response: &impl Serialize) {
+        let entry = ResultEntry {
+            timestamp: current_timestamp(),
+            requester_ip: requester_ip.map(|ip| ip.to_string()),
+            response: match serde_json::to_value(response) {
+                Ok(value) => value,
+                Err(e) => {
+                    tracing::error!(error = %e, "failed to serialize benchmark result for the results log");
+                    return;
+                }
+            },
+        };
+        let line = serde_json::to_string(&entry).expect("a JSON value always serializes");
+        match self.tx.try_send(line) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => tracing::warn!("results log writer is behind; dropping a result"),
+            Err(TrySendError::Disconnected(_)) => tracing::error!("results log writer has stopped; dropping a result"),
+        }
+    }
+
+    fn query(&self, query: &ResultsQuery) -> io::Result<ResultsPage> {
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+        let reader = BufReader::new(File::open(&self.path)?);
+
+        let mut total = 0;
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            // A line the writer is still appending won't parse yet; it shows up next time
+            let Ok(entry) = serde_json::from_str::<ResultEntry>(&line?) else { continue };
+            if !query.matches(&entry) {
+                continue;
+            }
+            if total >= query.offset && entries.len() < limit {
+                entries.push(entry);
+            }
+            total += 1;
+        }
+
+        let next_offset = query.offset + entries.len();
+        Ok(ResultsPage {
+            entries,
+            total,
+            next_offset: (next_offset < total).then_some(next_offset),
+        })
+    }
+}
+
+/// Client address for the results log; None when the router runs without a listener, as in tests
+pub(crate) struct Requester(pub Option<IpAddr>);
+
+impl<S: Send + Sync> FromRequestParts<S> for Requester {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Infallible> {
+        let connect_info = ConnectInfo::<SocketAddr>::from_request_parts(parts, state).await;
+        Ok(Requester(connect_info.ok().map(|ConnectInfo(addr)| addr.ip())))
+    }
+}
+
+fn write_lines(mut file: File, rx: mpsc::Receiver<String>) {
+    // Ends once every sender, i.e. every AppState clone, is gone
+    for mut line in rx {
+        line.push('\n');
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            tracing::error!(error = %e, "failed to append to the results log");
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ResultsQuery {
+    /// Only entries recorded at or after this Unix timestamp (seconds)
+    since: Option<u64>,
+    /// Only entries for this param set; aliases are accepted, as for kem_bench
+    #[param(value_type = Option<String>)]
+    param_set: Option<ParamSet>,
+    /// Matching entries to skip, for paging
+    #[serde(default)]
+    offset: usize,
+    /// Entries to return (default 100, at most 1000)
+    limit: Option<usize>,
+}
+
+impl ResultsQuery {
+    fn matches(&self, entry: &ResultEntry) -> bool {
+        self.since.is_none_or(|since| entry.timestamp >= since)
+            && self
+                .param_set
+                .map(ParamSet::name)
+                .is_none_or(|wanted| entry.response.get("param_set").and_then(|v| v.as_str()) == Some(wanted))
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ResultsPage {
+    /// Oldest first
+    entries: Vec<ResultEntry>,
+    /// Entries matching the filters, across all pages
+    total: usize,
+    /// Pass as offset to fetch the next page; absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_offset: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/results",
+    params(ResultsQuery),
+    responses(
+        (status = 200, description = "Logged kem_bench results matching the filters", body = ResultsPage),
+        (status = 400, description = "MalformedInput: unparseable query string", body = crate::ErrorBody),
+        (status = 404, description = "NotFound: the service was started without --results-log", body = crate::ErrorBody),
+        (status = 500, description = "Internal: the results log could not be read", body = crate::ErrorBody),
+    )
+)]
+pub async fn results(
+    State(state): State<AppState>,
+    query: Result<Query<ResultsQuery>, QueryRejection>,
+) -> Result<Json<ResultsPage>, AppError> {
+    let Query(query) = query.map_err(|e| AppError::MalformedInput(e.body_text()))?;
+    let log = state
+        .results_log
+        .clone()
+        .ok_or_else(|| AppError::NotFound("Results log is disabled; start the service with --results-log".to_string()))?;
+    let page = tokio::task::spawn_blocking(move || log.query(&query))
+        .await
+        .map_err(|e| AppError::Internal(format!("results query task failed: {}", e)))?
+        .map_err(|e| AppError::Internal(format!("failed to read results log: {}", e)))?;
+    Ok(Json(page))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app, config::Config};
+    use axum::{body::Body, extract::connect_info::MockConnectInfo, http::{Request, StatusCode}};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn temp_log(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("lattice-results-{}-{}.jsonl", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn query(since: Option<u64>, param_set: Option<&str>, offset: usize, limit: Option<usize>) -> ResultsQuery {
+        ResultsQuery { since, param_set: param_set.map(|p| p.parse().unwrap()), offset, limit }
+    }
+
+    /// Polls until the writer thread has flushed `expected` matching entries
+    async fn wait_for(log: &ResultsLog, expected: usize) -> ResultsPage {
+        for _ in 0..200 {
+            let page = log.query(&query(None, None, 0, None)).unwrap();
+            if page.total >= expected {
+                return page;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("results log never reached {} entries", expected);
+    }
+
+    #[tokio::test]
+    async fn filters_by_param_set_and_pages() {
+        let path = temp_log("filters");
+        let log = ResultsLog::open(&path).unwrap();
+        for param_set in ["ml_kem_512", "ml_kem_768", "ml_kem_512", "ml_kem_512"] {
+            log.record(None, &serde_json::json!({ "param_set": param_set }));
+        }
+        wait_for(&log, 4).await;
+
+        let first = log.query(&query(None, Some("ml_kem_512"), 0, Some(2))).unwrap();
+        assert_eq!(first.total, 3);
+        assert_eq!(first.entries.len(), 2);
+        assert_eq!(first.next_offset, Some(2));
+
+        let last = log.query(&query(None, Some("ml_kem_512"), 2, Some(2))).unwrap();
+        assert_eq!(last.entries.len(), 1);
+        assert_eq!(last.next_offset, None);
+
+        let future = log.query(&query(Some(current_timestamp() + 60), None, 0, None)).unwrap();
+        assert_eq!(future.total, 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn kem_bench_results_are_logged_with_requester_ip() {
+        let path = temp_log("kem_bench");
+        let config = Config { results_log: Some(path.clone()), ..Config::default() };
+        let router = app(AppState::new(config)).layer(MockConnectInfo(SocketAddr::from(([192, 0, 2, 7], 4242))));
+
+        // Logged under the canonical name, whatever alias the request used
+        let body = r#"{"param_set":"Kyber768","iterations":3,"operation":"KeyGen"}"#;
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post("/kem_bench")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let log = ResultsLog::open(&path).unwrap();
+        let page = wait_for(&log, 1).await;
+        assert_eq!(page.entries[0].requester_ip.as_deref(), Some("192.0.2.7"));
+        assert_eq!(page.entries[0].response["param_set"], "ml_kem_768");
+        assert_eq!(page.entries[0].response["iterations"], 3);
+
+        let response = router
+            .oneshot(Request::get("/results?param_set=ML-KEM-768").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(page["total"], 1);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn results_endpoint_is_404_without_a_log() {
+        let response = app(AppState::new(Config::default()))
+            .oneshot(Request::get("/results").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,149 @@
+use axum::{extract::State, http::StatusCode, Json};
+use ml_kem::{kem::{Decapsulate, Encapsulate}, MlKem1024, MlKem512, MlKem768};
+use rand::rngs::OsRng;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Instant;
+use utoipa::ToSchema;
+
+use common::secret;
+
+use common::health::Readiness;
+use crate::{current_timestamp, AppError, BenchmarkableKem};
+
+// ============ KEM Self-Test ============
+//
+// A round trip per ML-KEM param set, run once at startup and again on every GET /selftest.
+// The latest result feeds /ready, so a build that computes wrong shared secrets never
+// takes benchmark traffic.
+
+const PARAM_SETS: [&str; 3] = ["ml_kem_512", "ml_kem_768", "ml_kem_1024"];
+
+#[derive(Serialize, ToSchema)]
+pub struct SelfTestResult {
+    param_set: &'static str,
+    passed: bool,
+    /// Encapsulated and decapsulated shared secrets are equal
+    shared_secrets_match: bool,
+    /// Decapsulating a ciphertext with one bit flipped yields a different secret
+    corrupted_ciphertext_rejected: bool,
+    keygen_us: f64,
+    encaps_us: f64,
+    decaps_us: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SelfTestReport {
+    passed: bool,
+    results: Vec<SelfTestResult>,
+    timestamp: u64,
+}
+
+fn micros_since(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1_000_000.0
+}
+
+fn check<K: BenchmarkableKem>(param_set: &'static str) -> SelfTestResult {
+    let mut rng = OsRng;
+
+    let start = Instant::now();
+    let (dk, ek) = K::generate(&mut rng);
+    let keygen_us = micros_since(start);
+
+    let start = Instant::now();
+    let (ct, ss_sender) = ek.encapsulate(&mut rng).unwrap();
+    let encaps_us = micros_since(start);
+
+    let start = Instant::now();
+    let ss_receiver = dk.decapsulate(&ct).unwrap();
+    let decaps_us = micros_since(start);
+
+    let mut corrupted = ct.clone();
+    corrupted[0] ^= 1;
+    let ss_corrupted = dk.decapsulate(&corrupted).unwrap();
+
+    let shared_secrets_match = secret::ct_eq(&ss_sender, &ss_receiver);
+    let corrupted_ciphertext_rejected = !secret::ct_eq(&ss_corrupted, &ss_sender);
+    SelfTestResult {
+        param_set,
+        passed: shared_secrets_match && corrupted_ciphertext_rejected,
+        shared_secrets_match,
+        corrupted_ciphertext_rejected,
+        keygen_us,
+        encaps_us,
+        decaps_us,
+    }
+}
+
+pub fn run() -> SelfTestReport {
+    let results: Vec<SelfTestResult> = PARAM_SETS
+        .iter()
+        .map(|&param_set| match param_set {
+            "ml_kem_512" => check::<MlKem512>(param_set),
+            "ml_kem_768" => check::<MlKem768>(param_set),
+            _ => check::<MlKem1024>(param_set),
+        })
+        .collect();
+    let passed = results.iter().all(|r| r.passed);
+    SelfTestReport { passed, results, timestamp: current_timestamp() }
+}
+
+/// Runs the self-test now and records the outcome for /ready
+pub fn run_and_record(readiness: &Readiness) -> SelfTestReport {
+    let report = run();
+    if !report.passed {
+        tracing::error!("KEM self-test failed; /ready will report not ready");
+    }
+    readiness.record_selftest(report.passed);
+    report
+}
+
+#[utoipa::path(
+    get,
+    path = "/selftest",
+    responses(
+        (status = 200, description = "Every param set round-tripped correctly", body = SelfTestReport),
+        (status = 500, description = "At least one param set failed; see results", body = SelfTestReport),
+    )
+)]
+pub async fn selftest(
+    State(readiness): State<Arc<Readiness>>,
+) -> Result<(StatusCode, Json<SelfTestReport>), AppError> {
+    let report = tokio::task::spawn_blocking(move || run_and_record(&readiness))
+        .await
+        .map_err(|e| AppError::Internal(format!("self-test task failed: {}", e)))?;
+    let code = if report.passed { StatusCode::OK } else { StatusCode::INTERNAL_SERVER_ERROR };
+    Ok((code, Json(report)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_param_set_round_trips() {
+        let report = run();
+        assert!(report.passed);
+        let names: Vec<&str> = report.results.iter().map(|r| r.param_set).collect();
+        assert_eq!(names, PARAM_SETS);
+        for r in &report.results {
+            assert!(r.shared_secrets_match && r.corrupted_ciphertext_rejected, "{} failed", r.param_set);
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_selftest_marks_service_not_ready() {
+        let readiness = Arc::new(Readiness::new("lattice_service", 4).with_selftest());
+        readiness.mark_initialized();
+        readiness.record_selftest(false);
+        let (code, _) = common::health::ready(State(readiness.clone())).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+
+        // A passing re-run restores readiness
+        let (code, Json(report)) = selftest(State(readiness.clone())).await.unwrap();
+        assert_eq!(code, StatusCode::OK);
+        assert!(report.passed);
+        let (code, _) = common::health::ready(State(readiness)).await;
+        assert_eq!(code, StatusCode::OK);
+    }
+}
@@ -0,0 +1,146 @@
+use ml_dsa::{EncodedSignature, KeyGen, MlDsaParams, signature::{Keypair, Signer, Verifier}};
+use ml_kem::array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+use slh_dsa::SigningKey;
+
+use crate::{budget::Budget, AppError, BenchOutput};
+
+// SLH-DSA signing takes hundreds of milliseconds, so these param sets get a much lower cap
+pub const SLH_DSA_MAX_ITERATIONS: u32 = 25;
+
+// Fixed message signed by every benchmark iteration
+const MESSAGE: &[u8] = b"lattice_service signature benchmark message";
+
+// ============ ML-DSA Operations ============
+
+fn bench_ml_dsa_keygen<P: MlDsaParams, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128> {
+    budget.measure(|| {
+        let _ = P::key_gen(rng);
+    })
+}
+
+fn bench_ml_dsa_sign<P: MlDsaParams, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128> {
+    let kp = P::key_gen(rng);
+
+    budget.measure(|| {
+        let _ = kp.signing_key().sign(MESSAGE);
+    })
+}
+
+fn bench_ml_dsa_verify<P: MlDsaParams, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128> {
+    let kp = P::key_gen(rng);
+    let sig = kp.signing_key().sign(MESSAGE);
+
+    budget.measure(|| {
+        let _ = kp.verifying_key().verify(MESSAGE, &sig);
+    })
+}
+
+fn bench_ml_dsa_sign_verify<P: MlDsaParams, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128> {
+    let kp = P::key_gen(rng);
+
+    budget.measure(|| {
+        let sig = kp.signing_key().sign(MESSAGE);
+        let valid = kp.verifying_key().verify(MESSAGE, &sig).is_ok();
+        debug_assert!(valid);
+    })
+}
+
+pub fn run_ml_dsa_operation<P: MlDsaParams, R: RngCore + CryptoRng>(
+    operation: &str,
+    budget: Budget,
+    rng: &mut R,
+) -> Result<BenchOutput, AppError> {
+    let timings = match operation {
+        "keygen" => bench_ml_dsa_keygen::<P, _>(budget, rng),
+        "sign" => bench_ml_dsa_sign::<P, _>(budget, rng),
+        "verify" => bench_ml_dsa_verify::<P, _>(budget, rng),
+        "sign_verify" => bench_ml_dsa_sign_verify::<P, _>(budget, rng),
+        _ => return Err(AppError::InvalidOperation(operation.to_string())),
+    };
+
+    Ok(BenchOutput {
+        timings,
+        signature_bytes: Some(EncodedSignature::<P>::default().len()),
+        ..BenchOutput::default()
+    })
+}
+
+// ============ SLH-DSA Operations ============
+
+pub fn is_slh_dsa(param_set: &str) -> bool {
+    param_set.starts_with("slh_dsa_")
+}
+
+fn bench_slh_dsa_keygen<P: slh_dsa::ParameterSet, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128> {
+    budget.measure(|| {
+        let _ = SigningKey::<P>::new(rng);
+    })
+}
+
+fn bench_slh_dsa_sign<P: slh_dsa::ParameterSet, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128> {
+    let sk = SigningKey::<P>::new(rng);
+
+    budget.measure(|| {
+        let _ = sk.sign(MESSAGE);
+    })
+}
+
+fn bench_slh_dsa_verify<P: slh_dsa::ParameterSet, R: RngCore + CryptoRng>(budget: Budget, rng: &mut R) -> Vec<u128> {
+    let sk = SigningKey::<P>::new(rng);
+    let vk = sk.verifying_key();
+    let sig = sk.sign(MESSAGE);
+
+    budget.measure(|| {
+        let _ = vk.verify(MESSAGE, &sig);
+    })
+}
+
+pub fn run_slh_dsa_operation<P: slh_dsa::ParameterSet, R: RngCore + CryptoRng>(
+    operation: &str,
+    budget: Budget,
+    rng: &mut R,
+) -> Result<BenchOutput, AppError> {
+    let timings = match operation {
+        "keygen" => bench_slh_dsa_keygen::<P, _>(budget, rng),
+        "sign" => bench_slh_dsa_sign::<P, _>(budget, rng),
+        "verify" => bench_slh_dsa_verify::<P, _>(budget, rng),
+        _ => return Err(AppError::InvalidOperation(operation.to_string())),
+    };
+
+    Ok(BenchOutput {
+        timings,
+        signature_bytes: Some(P::SigLen::USIZE),
+        ..BenchOutput::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+    use ml_dsa::MlDsa44;
+
+    #[test]
+    fn ml_dsa_operations_produce_one_timing_per_iteration() {
+        for operation in ["keygen", "sign", "verify", "sign_verify"] {
+            let output = run_ml_dsa_operation::<MlDsa44, _>(operation, Budget::fixed(3), &mut OsRng)
+                .unwrap_or_else(|_| panic!("{} failed", operation));
+            assert_eq!(output.timings.len(), 3, "{}", operation);
+            assert_eq!(output.signature_bytes, Some(2420));
+        }
+    }
+
+    #[test]
+    fn slh_dsa_reports_fips_205_signature_size() {
+        let output = run_slh_dsa_operation::<slh_dsa::Sha2_128f, _>("verify", Budget::fixed(1), &mut OsRng).unwrap();
+        assert_eq!(output.timings.len(), 1);
+        assert_eq!(output.signature_bytes, Some(17088));
+    }
+
+    #[test]
+    fn kem_operations_are_rejected_for_ml_dsa() {
+        let result = run_ml_dsa_operation::<MlDsa44, _>("encaps", Budget::fixed(1), &mut OsRng);
+        assert!(matches!(result, Err(AppError::InvalidOperation(_))));
+    }
+}
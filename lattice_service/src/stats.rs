@@ -0,0 +1,323 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+// ============ Benchmark Statistics ============
+//
+// All values are in the unit of the input timings (microseconds for the KEM benchmarks).
+// The summary statistics themselves live in common::stats, shared with zk_service; these
+// are the analyses only the KEM benchmarks run.
+
+pub use common::stats::{compute_percentiles, compute_stats};
+
+/// Sorted samples with the lowest and highest `trim_percent` of them dropped, e.g. 0.01
+/// drops the bottom and top 1%. `trim_percent` must be below 0.5, so at least one sample survives.
+pub fn trim_outliers(timings: &[u128], trim_percent: f64) -> Vec<u128> {
+    let mut sorted = timings.to_vec();
+    sorted.sort_unstable();
+    let per_side = (sorted.len() as f64 * trim_percent).floor() as usize;
+    sorted[per_side..sorted.len() - per_side].to_vec()
+}
+
+/// How much repeated runs of the same benchmark disagree, judged by their averages
+#[derive(Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct RunSpread {
+    pub mean_avg_us: f64,
+    /// Slowest run's average minus the fastest's
+    pub max_spread_us: f64,
+    /// Sample standard deviation of the averages over their mean
+    pub coefficient_of_variation: f64,
+}
+
+pub fn run_spread(avgs: &[f64]) -> RunSpread {
+    if avgs.is_empty() {
+        return RunSpread { mean_avg_us: 0.0, max_spread_us: 0.0, coefficient_of_variation: 0.0 };
+    }
+    let mean = avgs.iter().sum::<f64>() / avgs.len() as f64;
+    let max = avgs.iter().copied().fold(f64::MIN, f64::max);
+    let min = avgs.iter().copied().fold(f64::MAX, f64::min);
+    let stddev = if avgs.len() < 2 {
+        0.0
+    } else {
+        (avgs.iter().map(|a| (a - mean).powi(2)).sum::<f64>() / (avgs.len() - 1) as f64).sqrt()
+    };
+    RunSpread {
+        mean_avg_us: mean,
+        max_spread_us: max - min,
+        coefficient_of_variation: if mean > 0.0 { stddev / mean } else { 0.0 },
+    }
+}
+
+/// Whether latency moved over the course of a run, e.g. as a laptop CPU starts throttling
+#[derive(Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Drift {
+    /// Mean of the first and last tenth of the samples, in the order they ran
+    pub first_decile_avg_us: f64,
+    pub last_decile_avg_us: f64,
+    /// last_decile_avg_us / first_decile_avg_us - 1, so 0.25 means the end ran 25% slower
+    pub relative_change: f64,
+    /// Least-squares slope of latency over iteration index
+    pub slope_us_per_iteration: f64,
+    pub threshold: f64,
+    /// Whether relative_change, either way, exceeds threshold
+    pub drift_detected: bool,
+}
+
+fn mean(samples: &[u128]) -> f64 {
+    if samples.is_empty() { 0.0 } else { samples.iter().sum::<u128>() as f64 / samples.len() as f64 }
+}
+
+// Samples in the first tenth of a run, at least one
+fn decile_len(n: usize) -> usize {
+    (n / 10).max(1).min(n)
+}
+
+// Least-squares slope of latency over iteration index; flat for fewer than two samples
+fn slope(latencies: &[u128]) -> f64 {
+    let n = latencies.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let mean_x = (n - 1) as f64 / 2.0;
+    let mean_y = mean(latencies);
+    let (covariance, variance) = latencies.iter().enumerate().fold((0.0, 0.0), |(cov, var), (i, &y)| {
+        let dx = i as f64 - mean_x;
+        (cov + dx * (y as f64 - mean_y), var + dx * dx)
+    });
+    covariance / variance
+}
+
+/// Drift across `latencies`, which must be in the order the iterations ran
+pub fn drift(latencies: &[u128], threshold: f64) -> Drift {
+    let n = latencies.len();
+    let decile = decile_len(n);
+    let first = mean(&latencies[..decile]);
+    let last = mean(&latencies[n - decile..]);
+    let relative_change = if first > 0.0 { last / first - 1.0 } else { 0.0 };
+
+    Drift {
+        first_decile_avg_us: first,
+        last_decile_avg_us: last,
+        relative_change,
+        slope_us_per_iteration: slope(latencies),
+        threshold,
+        drift_detected: relative_change.abs() > threshold,
+    }
+}
+
+/// Whether a run's early iterations ran slower than the rest, as they do when caches and
+/// branch predictors are still cold, so the average may not describe steady state
+#[derive(Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct Trend {
+    /// Least-squares fit of latency over iteration index, as the fitted change from the
+    /// first iteration to the last in percent of the mean. Negative means the run sped up
+    pub slope_percent: f64,
+    /// Mean of the iterations in the first and second half of the run
+    pub first_half_avg_us: f64,
+    pub second_half_avg_us: f64,
+    pub first_decile_avg_us: f64,
+    pub warmup_factor: f64,
+    /// Whether first_decile_avg_us exceeds the overall mean by more than warmup_factor
+    pub warmup_suspected: bool,
+}
+
+/// Trend across `latencies`, which must be in the order the iterations ran
+pub fn trend(latencies: &[u128], warmup_factor: f64) -> Trend {
+    let n = latencies.len();
+    let overall = mean(latencies);
+    let first_decile = mean(&latencies[..decile_len(n)]);
+    let slope_percent = if overall > 0.0 { slope(latencies) * n.saturating_sub(1) as f64 / overall * 100.0 } else { 0.0 };
+    Trend {
+        slope_percent,
+        first_half_avg_us: mean(&latencies[..n / 2]),
+        second_half_avg_us: mean(&latencies[n / 2..]),
+        first_decile_avg_us: first_decile,
+        warmup_factor,
+        warmup_suspected: overall > 0.0 && first_decile > overall * warmup_factor,
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct HistogramBucket {
+    pub upper_bound_us: f64,
+    pub count: u64,
+}
+
+/// Log-scaled histogram between min and max; each bucket counts samples at or below its
+/// upper bound and above the previous one. Constant input collapses to a single bucket.
+pub fn compute_histogram(timings: &[u128], buckets: usize) -> Vec<HistogramBucket> {
+    let (Some(&min), Some(&max)) = (timings.iter().min(), timings.iter().max()) else {
+        return Vec::new();
+    };
+    if min == max || buckets <= 1 {
+        return vec![HistogramBucket { upper_bound_us: max as f64, count: timings.len() as u64 }];
+    }
+
+    // Sub-microsecond samples round to 0, which has no logarithm; start the scale at 1
+    let lo = (min as f64).max(1.0);
+    let hi = max as f64;
+    let step = (hi / lo).ln() / buckets as f64;
+
+    let mut histogram: Vec<HistogramBucket> = (1..=buckets)
+        .map(|i| HistogramBucket { upper_bound_us: lo * (step * i as f64).exp(), count: 0 })
+        .collect();
+    // Pin the last bound so rounding never leaves max outside every bucket
+    histogram[buckets - 1].upper_bound_us = hi;
+
+    for &t in timings {
+        let t = t as f64;
+        let index = histogram
+            .iter()
+            .position(|b| t <= b.upper_bound_us)
+            .unwrap_or(buckets - 1);
+        histogram[index].count += 1;
+    }
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn trimming_drops_injected_outliers() {
+        // 96 samples of 100us plus two scheduler hiccups and two suspiciously fast samples
+        let mut timings = vec![100u128; 96];
+        timings.extend([5000, 4000, 1, 2]);
+        assert_close(compute_stats(&timings).avg, (9600.0 + 9003.0) / 100.0);
+
+        // 2% of 100 samples is two from each end, which removes exactly the injected ones
+        let trimmed = trim_outliers(&timings, 0.02);
+        assert_eq!(trimmed.len(), 96);
+        let stats = compute_stats(&trimmed);
+        assert_close(stats.avg, 100.0);
+        assert_close(stats.min, 100.0);
+        assert_close(stats.max, 100.0);
+        assert_close(stats.stddev, 0.0);
+    }
+
+    #[test]
+    fn trimming_rounds_the_per_side_count_down() {
+        let timings: Vec<u128> = (1..=10).rev().collect();
+        // 0.15 of 10 samples is 1.5, so one sample goes from each end
+        assert_eq!(trim_outliers(&timings, 0.15), (2..=9).collect::<Vec<u128>>());
+        assert_eq!(trim_outliers(&timings, 0.0).len(), 10);
+        assert_eq!(trim_outliers(&timings, 0.49), vec![5, 6]);
+        assert_eq!(trim_outliers(&[7], 0.49), vec![7]);
+    }
+
+    #[test]
+    fn run_spread_matches_hand_computed_fixture() {
+        // mean 20, deviations -2/0/+2, sample variance 8/2 = 4
+        let spread = run_spread(&[18.0, 20.0, 22.0]);
+        assert_close(spread.mean_avg_us, 20.0);
+        assert_close(spread.max_spread_us, 4.0);
+        assert_close(spread.coefficient_of_variation, 0.1);
+
+        let single = run_spread(&[7.0]);
+        assert_eq!((single.max_spread_us, single.coefficient_of_variation), (0.0, 0.0));
+    }
+
+    #[test]
+    fn drift_compares_deciles_and_fits_a_slope() {
+        // Two regimes of ten samples: deciles are two samples each, and the slope is
+        // sum((i - 9.5)(y - 15)) / sum((i - 9.5)^2) = 500 / 665
+        let timings: Vec<u128> = [vec![10; 10], vec![20; 10]].concat();
+        let drift = drift(&timings, 0.1);
+        assert_close(drift.first_decile_avg_us, 10.0);
+        assert_close(drift.last_decile_avg_us, 20.0);
+        assert_close(drift.relative_change, 1.0);
+        assert_close(drift.slope_us_per_iteration, 500.0 / 665.0);
+        assert!(drift.drift_detected);
+    }
+
+    #[test]
+    fn steady_latency_has_no_drift() {
+        let linear: Vec<u128> = (0..50).map(|i| 100 + 2 * i).collect();
+        assert_close(drift(&linear, 10.0).slope_us_per_iteration, 2.0);
+
+        let steady = drift(&[100; 30], 0.1);
+        assert_close(steady.relative_change, 0.0);
+        assert_close(steady.slope_us_per_iteration, 0.0);
+        assert!(!steady.drift_detected);
+
+        // A faster end counts too
+        assert!(drift(&[100, 100, 50, 50], 0.1).drift_detected);
+        assert!(!drift(&[], 0.1).drift_detected);
+        assert!(!drift(&[7], 0.1).drift_detected);
+    }
+
+    #[test]
+    fn decreasing_series_suspects_warmup() {
+        // Ten slow iterations, then ninety at steady state: overall mean 109
+        let timings: Vec<u128> = [vec![190; 10], vec![100; 90]].concat();
+        let trend = trend(&timings, 1.2);
+        assert_close(trend.first_decile_avg_us, 190.0);
+        assert_close(trend.first_half_avg_us, 118.0);
+        assert_close(trend.second_half_avg_us, 100.0);
+        assert!(trend.slope_percent < 0.0);
+        assert!(trend.warmup_suspected);
+        assert!(!super::trend(&timings, 2.0).warmup_suspected);
+    }
+
+    #[test]
+    fn increasing_series_slopes_up_without_warmup() {
+        // From 100 to 199 in steps of 1: the fit is exact, 99us over a mean of 149.5
+        let timings: Vec<u128> = (100..200).collect();
+        let trend = trend(&timings, 1.2);
+        assert_close(trend.slope_percent, 99.0 / 149.5 * 100.0);
+        assert_close(trend.first_half_avg_us, 124.5);
+        assert_close(trend.second_half_avg_us, 174.5);
+        assert!(!trend.warmup_suspected);
+    }
+
+    #[test]
+    fn flat_series_has_no_trend() {
+        let flat = trend(&[250; 40], 1.2);
+        assert_close(flat.slope_percent, 0.0);
+        assert_close(flat.first_half_avg_us, flat.second_half_avg_us);
+        assert!(!flat.warmup_suspected);
+
+        for short in [&[][..], &[7][..]] {
+            let trend = trend(short, 1.2);
+            assert_close(trend.slope_percent, 0.0);
+            assert!(!trend.warmup_suspected);
+        }
+    }
+
+    #[test]
+    fn histogram_of_empty_input_has_no_buckets() {
+        assert!(compute_histogram(&[], 20).is_empty());
+    }
+
+    #[test]
+    fn histogram_of_constant_input_is_one_bucket() {
+        let histogram = compute_histogram(&[7, 7, 7], 20);
+        assert_eq!(histogram, vec![HistogramBucket { upper_bound_us: 7.0, count: 3 }]);
+    }
+
+    #[test]
+    fn histogram_buckets_are_log_scaled() {
+        // Three buckets over 1..1000 have upper bounds of ~10, ~100 and 1000
+        let histogram = compute_histogram(&[1, 2, 5, 20, 50, 200, 500, 1000], 3);
+        let bounds: Vec<f64> = histogram.iter().map(|b| b.upper_bound_us).collect();
+        let counts: Vec<u64> = histogram.iter().map(|b| b.count).collect();
+
+        assert_close(bounds[0], 10.0);
+        assert_close(bounds[1], 100.0);
+        assert_close(bounds[2], 1000.0);
+        assert_eq!(counts, vec![3, 2, 3]);
+    }
+
+    #[test]
+    fn histogram_counts_every_sample() {
+        let timings: Vec<u128> = (0..=500).collect();
+        let histogram = compute_histogram(&timings, 20);
+        assert_eq!(histogram.len(), 20);
+        assert_eq!(histogram.iter().map(|b| b.count).sum::<u64>(), 501);
+        assert!(histogram.windows(2).all(|w| w[0].upper_bound_us < w[1].upper_bound_us));
+    }
+}
@@ -0,0 +1,250 @@
+use axum::{
+    body::Body,
+    extract::{rejection::QueryRejection, Query, State},
+    http::header,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::{
+    wrappers::{ReceiverStream, UnboundedReceiverStream},
+    Stream, StreamExt,
+};
+use utoipa::IntoParams;
+
+use crate::budget::Progress;
+use crate::format::Negotiated;
+use crate::results::Requester;
+use crate::{bench_in_slot, AppError, AppState, KemBenchRequest};
+
+// ============ Progress Stream ============
+//
+// POST /kem_bench/stream runs the same benchmark as /kem_bench but answers with
+// Server-Sent Events: a `progress` event every `every` completed iterations, then one
+// `result` event carrying the full KemBenchResponse, or an `error` event. It's a POST
+// because the request is the usual KemBenchRequest body, so browsers need fetch() rather
+// than EventSource. Errors found before the stream starts are ordinary JSON responses.
+//
+// POST /kem_bench/stream_ndjson is for keeping every sample of a long run without one huge
+// array: newline-delimited JSON with a {"i", "us"} line per iteration as it completes,
+// then a {"summary": KemBenchResponse} line, or an {"error", "status"} line.
+
+const DEFAULT_EVERY: u32 = 100;
+
+// Progress events beyond this are dropped for a client that isn't reading; the final
+// event always waits for room
+const CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Deserialize, IntoParams)]
+pub struct StreamParams {
+    /// Completed iterations between progress events (default 100)
+    every: Option<u32>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/kem_bench/stream",
+    params(StreamParams),
+    request_body = KemBenchRequest,
+    responses(
+        (status = 200, description = "text/event-stream of `progress` events (ProgressUpdate), then one `result` (KemBenchResponse) or `error` event", body = crate::budget::ProgressUpdate),
+        (status = 400, description = "MalformedInput: unparseable query string or body", body = crate::ErrorBody),
+        (status = 422, description = "ValidationFailed: every is 0; or InvalidField: an unknown, missing or wrongly-typed field", body = crate::ErrorBody),
+        (status = 429, description = "TooManyBenchmarks: every benchmark slot is busy, see Retry-After", body = crate::ErrorBody),
+        (status = 503, description = "SheddingLoad: memory or in-flight benchmarks are past a --shed-* limit, see Retry-After", body = crate::ErrorBody),
+    )
+)]
+pub async fn kem_bench_stream(
+    State(state): State<AppState>,
+    Requester(requester): Requester,
+    params: Result<Query<StreamParams>, QueryRejection>,
+    Negotiated(req): Negotiated<KemBenchRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let Query(params) = params.map_err(|e| AppError::MalformedInput(e.body_text()))?;
+    let every = params.every.unwrap_or(DEFAULT_EVERY);
+    if every == 0 {
+        return Err(AppError::ValidationFailed("every must be at least 1".to_string()));
+    }
+    let permit = state
+        .benchmark_slots
+        .clone()
+        .try_acquire_owned()
+        .map_err(|_| AppError::TooManyBenchmarks(state.config.max_concurrent_benchmarks()))?;
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let progress_tx = tx.clone();
+    let progress = Arc::new(Progress::new(every, move |update| {
+        let _ = progress_tx.try_send(json_event("progress", &update));
+    }));
+
+    tokio::spawn(async move {
+        let _permit = permit;
+        let event = match bench_in_slot(&state, req, Some(progress)).await {
+            Ok(resp) => {
+                state.record_result(requester, &resp);
+                json_event("result", &resp)
+            }
+            Err(e) => {
+                state.metrics.service.errors.with_label_values(&[e.kind()]).inc();
+                let (status, error) = e.status_and_message();
+                json_event("error", &serde_json::json!({ "error": error, "status": status.as_u16() }))
+            }
+        };
+        // Fails only if the client has gone away, in which case nobody needs the result
+        let _ = tx.send(event).await;
+    });
+
+    Ok(Sse::new(ReceiverStream::new(rx).map(Ok)).keep_alive(KeepAlive::default()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/kem_bench/stream_ndjson",
+    request_body = KemBenchRequest,
+    responses(
+        (status = 200, description = "application/x-ndjson: one Sample line per iteration, then {\"summary\": KemBenchResponse} or {\"error\", \"status\"}", body = crate::budget::Sample, content_type = "application/x-ndjson"),
+        (status = 400, description = "MalformedInput: unparseable body", body = crate::ErrorBody),
+        (status = 422, description = "InvalidField: an unknown, missing or wrongly-typed field", body = crate::ErrorBody),
+        (status = 429, description = "TooManyBenchmarks: every benchmark slot is busy, see Retry-After", body = crate::ErrorBody),
+        (status = 503, description = "SheddingLoad: memory or in-flight benchmarks are past a --shed-* limit, see Retry-After", body = crate::ErrorBody),
+    )
+)]
+pub async fn kem_bench_ndjson(
+    State(state): State<AppState>,
+    Requester(requester): Requester,
+    Negotiated(req): Negotiated<KemBenchRequest>,
+) -> Result<Response, AppError> {
+    let permit = state
+        .benchmark_slots
+        .clone()
+        .try_acquire_owned()
+        .map_err(|_| AppError::TooManyBenchmarks(state.config.max_concurrent_benchmarks()))?;
+
+    // Unbounded, since dropping samples would defeat the point; the iteration cap bounds
+    // how far a slow reader can fall behind
+    let (tx, rx) = mpsc::unbounded_channel();
+    let sample_tx = tx.clone();
+    // No progress snapshots, just the samples
+    let progress = Arc::new(Progress::new(u32::MAX, |_| {}).on_sample(move |sample| {
+        let _ = sample_tx.send(json_line(&sample));
+    }));
+
+    tokio::spawn(async move {
+        let _permit = permit;
+        let line = match bench_in_slot(&state, req, Some(progress)).await {
+            Ok(resp) => {
+                state.record_result(requester, &resp);
+                json_line(&serde_json::json!({ "summary": resp }))
+            }
+            Err(e) => {
+                state.metrics.service.errors.with_label_values(&[e.kind()]).inc();
+                let (status, error) = e.status_and_message();
+                json_line(&serde_json::json!({ "error": error, "status": status.as_u16() }))
+            }
+        };
+        let _ = tx.send(line);
+    });
+
+    let body = Body::from_stream(UnboundedReceiverStream::new(rx).map(Ok::<_, Infallible>));
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], body).into_response())
+}
+
+fn json_line(value: &impl serde::Serialize) -> String {
+    let mut line = serde_json::to_string(value).expect("benchmark types always serialize");
+    line.push('\n');
+    line
+}
+
+fn json_event(name: &str, data: &impl serde::Serialize) -> Event {
+    Event::default().event(name).json_data(data).expect("benchmark types always serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{app, config::Config, AppState};
+    use axum::{body::Body, http::{Request, StatusCode}};
+    use tower::ServiceExt;
+
+    async fn stream(query: &str, body: serde_json::Value) -> (StatusCode, String) {
+        let request = Request::post(format!("/kem_bench/stream{}", query))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app(AppState::new(Config::default())).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn events(body: &str) -> Vec<(&str, serde_json::Value)> {
+        body.split("\n\n")
+            .filter_map(|event| {
+                let name = event.lines().find_map(|l| l.strip_prefix("event: "))?;
+                let data = event.lines().find_map(|l| l.strip_prefix("data: "))?;
+                Some((name, serde_json::from_str(data).unwrap()))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn streams_progress_then_the_result() {
+        let body = serde_json::json!({ "param_set": "ml_kem_512", "operation": "keygen", "iterations": 20 });
+        let (status, text) = stream("?every=5", body).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let events = events(&text);
+        let (last, progress) = events.split_last().unwrap();
+        assert_eq!(last.0, "result", "{}", text);
+        assert_eq!(last.1["completed_iterations"], 20);
+        let completed: Vec<_> = progress.iter().map(|(_, data)| data["completed_iterations"].as_u64().unwrap()).collect();
+        assert_eq!(completed, [5, 10, 15, 20]);
+        assert!(progress.iter().all(|(name, data)| *name == "progress" && data["total_iterations"] == 20));
+    }
+
+    #[tokio::test]
+    async fn late_failures_arrive_as_an_error_event() {
+        let body = serde_json::json!({ "param_set": "x25519", "operation": "sign", "iterations": 5 });
+        let (status, text) = stream("", body).await;
+        assert_eq!(status, StatusCode::OK);
+        let events = events(&text);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, "error");
+        assert_eq!(events[0].1["status"], 400);
+    }
+
+    #[tokio::test]
+    async fn ndjson_streams_every_sample_then_the_summary() {
+        let request = Request::post("/kem_bench/stream_ndjson")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::json!({ "param_set": "ml_kem_512", "operation": "keygen", "iterations": 12 }).to_string()))
+            .unwrap();
+        let response = app(AppState::new(Config::default())).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let lines: Vec<serde_json::Value> = String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let (summary, samples) = lines.split_last().unwrap();
+        assert_eq!(summary["summary"]["completed_iterations"], 12, "{}", summary);
+        let mut indices: Vec<u64> = samples.iter().map(|s| s["i"].as_u64().unwrap()).collect();
+        indices.sort();
+        assert_eq!(indices, (0..12).collect::<Vec<_>>());
+        assert!(samples.iter().all(|s| s["us"].is_u64()));
+    }
+
+    #[tokio::test]
+    async fn zero_interval_is_rejected_up_front() {
+        let body = serde_json::json!({ "param_set": "ml_kem_512", "operation": "keygen", "iterations": 5 });
+        let (status, _) = stream("?every=0", body).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
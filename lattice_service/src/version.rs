@@ -0,0 +1,45 @@
+use axum::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+// ============ Build Information ============
+//
+// Values are embedded by build.rs; any that couldn't be determined read "unknown",
+// so this endpoint never fails.
+
+#[derive(Serialize, ToSchema)]
+pub struct VersionResponse {
+    service: &'static str,
+    version: &'static str,
+    /// Commit hash, suffixed with -dirty if tracked files had uncommitted changes
+    git_commit: &'static str,
+    build_profile: &'static str,
+    rustc_version: &'static str,
+    ml_kem_version: &'static str,
+}
+
+#[utoipa::path(get, path = "/version", responses((status = 200, description = "Build information", body = VersionResponse)))]
+pub async fn version() -> Json<VersionResponse> {
+    Json(VersionResponse {
+        service: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: env!("LATTICE_GIT_COMMIT"),
+        build_profile: env!("LATTICE_BUILD_PROFILE"),
+        rustc_version: env!("LATTICE_RUSTC_VERSION"),
+        ml_kem_version: env!("LATTICE_ML_KEM_VERSION"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn version_reports_build_details() {
+        let Json(resp) = version().await;
+        assert_eq!(resp.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(resp.build_profile, if cfg!(debug_assertions) { "debug" } else { "release" });
+        assert!(resp.rustc_version.starts_with("rustc "), "{}", resp.rustc_version);
+        assert!(resp.ml_kem_version.starts_with("0.2."), "{}", resp.ml_kem_version);
+    }
+}
@@ -0,0 +1,107 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use std::net::IpAddr;
+
+use crate::results::Requester;
+use crate::{run_kem_bench, AppError, AppState, KemBenchRequest};
+
+// ============ WebSocket Benchmarks ============
+//
+// GET /ws upgrades to a WebSocket for clients that re-run benchmarks on a timer and don't
+// want per-request HTTP overhead in the numbers. Each text (or binary) message is a JSON
+// KemBenchRequest and gets one JSON KemBenchResponse back. A connection runs its requests
+// one at a time, in order. A bad message gets an error frame, {error, status}, and the
+// socket stays open.
+
+pub async fn ws(State(state): State<AppState>, Requester(requester): Requester, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| serve_socket(socket, state, requester))
+}
+
+async fn serve_socket(mut socket: WebSocket, state: AppState, requester: Option<IpAddr>) {
+    while let Some(Ok(message)) = socket.recv().await {
+        let reply = match message {
+            Message::Text(text) => handle_message(&state, requester, text.as_bytes()).await,
+            Message::Binary(bytes) => handle_message(&state, requester, &bytes).await,
+            Message::Close(_) => break,
+            // Pings are answered by the socket itself
+            Message::Ping(_) | Message::Pong(_) => continue,
+        };
+        if socket.send(Message::Text(reply.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn handle_message(state: &AppState, requester: Option<IpAddr>, message: &[u8]) -> String {
+    let outcome = match serde_json::from_slice::<KemBenchRequest>(message) {
+        Ok(req) => run_kem_bench(state, req).await,
+        Err(e) => Err(AppError::MalformedInput(format!("Invalid request message: {}", e))),
+    };
+    match outcome {
+        Ok(resp) => {
+            state.record_result(requester, &resp);
+            serde_json::to_string(&resp).expect("benchmark responses always serialize")
+        }
+        Err(e) => {
+            state.metrics.service.errors.with_label_values(&[e.kind()]).inc();
+            let (status, error) = e.status_and_message();
+            serde_json::json!({ "error": error, "status": status.as_u16() }).to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[tokio::test]
+    async fn bad_messages_get_an_error_frame() {
+        let state = AppState::new(Config::default());
+        let reply: serde_json::Value =
+            serde_json::from_str(&handle_message(&state, None, b"{\"param_set\":").await).unwrap();
+        assert_eq!(reply["status"], 400);
+        assert!(reply["error"].as_str().unwrap().starts_with("Invalid request message"), "{}", reply);
+
+        let reply: serde_json::Value = serde_json::from_str(
+            &handle_message(&state, None, br#"{"param_set":"ml_kem_512","operation":"keygen","iterations":3}"#).await,
+        )
+        .unwrap();
+        assert_eq!(reply["completed_iterations"], 3);
+    }
+
+    #[tokio::test]
+    async fn socket_serves_requests_in_order_and_survives_errors() {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = crate::app(AppState::new(Config::default()));
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr)).await.unwrap();
+        for message in [
+            r#"{"param_set":"ml_kem_512","operation":"keygen","iterations":2}"#,
+            "not json",
+            r#"{"param_set":"ml_kem_768","operation":"encaps","iterations":2}"#,
+        ] {
+            socket.send(ClientMessage::text(message)).await.unwrap();
+        }
+
+        let mut replies = Vec::new();
+        while replies.len() < 3 {
+            if let ClientMessage::Text(text) = socket.next().await.unwrap().unwrap() {
+                replies.push(serde_json::from_str::<serde_json::Value>(&text).unwrap());
+            }
+        }
+        assert_eq!(replies[0]["param_set"], "ml_kem_512");
+        assert_eq!(replies[1]["status"], 400);
+        assert_eq!(replies[2]["param_set"], "ml_kem_768");
+    }
+}
@@ -0,0 +1,31 @@
+#!/bin/bash
+# Groth16 proving time against circuit size: runs the chain circuit at each power-of-two
+# length from 2^MIN_LOG to 2^MAX_LOG against a running zk_service and prints one row per
+# length. The first request at a length runs its trusted setup, reported as setup_ms and
+# kept out of the prove timings. Lengths above the server's ZK_MAX_CHAIN_LENGTH are rejected.
+
+set -e
+
+URL=${URL:-"http://localhost:8001"}
+MIN_LOG=${MIN_LOG:-10}
+MAX_LOG=${MAX_LOG:-16}
+ITERATIONS=${ITERATIONS:-5}
+BENCH_CLIENT=${BENCH_CLIENT:-"cargo run --quiet --release -p bench_client --"}
+
+TMP_DIR=$(mktemp -d)
+trap 'rm -rf "$TMP_DIR"' EXIT
+
+echo "=== Chain Circuit Scaling Sweep ==="
+echo "chain_length 2^$MIN_LOG..2^$MAX_LOG, $ITERATIONS proofs each, against $URL"
+echo ""
+printf "%-12s %12s %12s %12s\n" "chain_length" "constraints" "setup_ms" "avg_prove_ms"
+
+for LOG in $(seq "$MIN_LOG" "$MAX_LOG"); do
+  N=$((1 << LOG))
+  FILE="$TMP_DIR/chain_$N.json"
+  $BENCH_CLIENT zk-prove --url "$URL" --circuit-id chain --chain-length "$N" \
+    --iterations "$ITERATIONS" --label "chain_$N" --file "$FILE" > /dev/null
+
+  field() { grep -m1 "\"$1\"" "$FILE" | sed 's/.*: *//; s/,$//'; }
+  printf "%-12s %12s %12s %12s\n" "$N" "$(field constraints)" "$(field setup_ms)" "$(field avg_latency_ms)"
+done
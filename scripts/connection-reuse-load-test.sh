@@ -0,0 +1,49 @@
+#!/bin/bash
+# Load test comparing a fresh TCP connection per request, HTTP/1.1 keep-alive and h2c
+# against a running lattice_service. Each request is a single cheap iteration, so the
+# differences are down to connection setup. Start the server with
+# LATTICE_MAX_CONCURRENT_BENCHMARKS at or above CONCURRENCY, or most requests get a 429.
+
+set -e
+
+URL=${URL:-"http://localhost:8000"}
+REQUESTS=${REQUESTS:-2000}
+CONCURRENCY=${CONCURRENCY:-32}
+PARAM_SET=${PARAM_SET:-"ml_kem_768"}
+OPERATION=${OPERATION:-"encaps"}
+BENCH_CLIENT=${BENCH_CLIENT:-"cargo run --quiet --release -p bench_client --"}
+
+TMP_DIR=$(mktemp -d)
+trap 'rm -rf "$TMP_DIR"' EXIT
+
+echo "=== Connection Reuse Load Test ==="
+echo "$REQUESTS x $PARAM_SET $OPERATION (1 iteration each), concurrency $CONCURRENCY, against $URL"
+echo ""
+printf "%-14s %12s %12s\n" "mode" "total_ms" "requests/s"
+
+run_mode() {
+  local MODE=$1
+  local FLAGS=$2
+  local FILE="$TMP_DIR/$MODE.json"
+
+  $BENCH_CLIENT kem --url "$URL" --param-set "$PARAM_SET" --operation "$OPERATION" \
+    --iterations 1 --requests "$REQUESTS" --concurrency "$CONCURRENCY" \
+    --label "$MODE" --file "$FILE" $FLAGS > /dev/null
+
+  local TOTAL_MS
+  TOTAL_MS=$(grep -m1 '"client_total_time_ms"' "$FILE" | sed 's/[^0-9.]//g')
+  local ERRORS
+  ERRORS=$(grep -m1 '"error_count"' "$FILE" | sed 's/[^0-9]//g')
+  printf "%-14s %12s %12s" "$MODE" "$TOTAL_MS" "$(awk "BEGIN { printf \"%.0f\", $REQUESTS * 1000 / $TOTAL_MS }")"
+  if [ "$ERRORS" != "0" ]; then
+    printf "  (%s errors)" "$ERRORS"
+  fi
+  echo ""
+}
+
+run_mode "no-keep-alive" "--no-keep-alive"
+run_mode "keep-alive" ""
+run_mode "h2c" "--http2"
+
+echo ""
+echo "Server logs show the protocol each request arrived over in the request span's version field."
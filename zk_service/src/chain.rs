@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use ark_ff::PrimeField;
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+use crate::curve::Engine;
+use crate::circuits::{self, Assignments, BenchCircuit, CircuitCounts, KeySizes};
+use crate::keystore::{self, KeySource};
+
+// ============ Chain Circuit ============
+//
+// A circuit of chosen size, for plotting proving time against constraint count:
+// x_{i+1} = x_i^2 + c_i for chain_length steps, from a private x_0 to a public result.
+// Each step costs one constraint. Keys are set up once per length and curve and kept.
+
+/// Longest chain a request may ask for, overridable via ZK_MAX_CHAIN_LENGTH
+pub const DEFAULT_MAX_CHAIN_LENGTH: u32 = 1 << 20;
+
+// Round constants, fixed so every setup for a length describes the same circuit
+fn round_constant<F: PrimeField>(i: u32) -> F {
+    F::from(u64::from(i) + 1)
+}
+
+/// x_0 squared and offset chain_length times
+pub fn evaluate<F: PrimeField>(x0: F, chain_length: u32) -> F {
+    (0..chain_length).fold(x0, |x, i| x * x + round_constant::<F>(i))
+}
+
+#[derive(Clone)]
+pub struct ChainCircuit<F: PrimeField> {
+    pub chain_length: u32,
+    pub x0: Option<F>,
+    pub result: Option<F>,
+    pub constants: fn(u32) -> F,
+}
+
+impl<F: PrimeField> ChainCircuit<F> {
+    pub fn new(chain_length: u32, x0: Option<F>) -> Self {
+        let result = x0.map(|x0| evaluate(x0, chain_length));
+        ChainCircuit { chain_length, x0, result, constants: round_constant }
+    }
+}
+
+impl<F: PrimeField> ConstraintSynthesizer<F> for ChainCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let mut x = FpVar::new_witness(cs.clone(), || self.x0.ok_or(SynthesisError::AssignmentMissing))?;
+        let result = FpVar::new_input(cs.clone(), || self.result.ok_or(SynthesisError::AssignmentMissing))?;
+        for i in 0..self.chain_length {
+            // Adding a constant is free; only the square is a constraint
+            x = x.square()? + (self.constants)(i);
+        }
+        x.enforce_equal(&result)?;
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> BenchCircuit<F> for ChainCircuit<F> {
+    fn instance(&self, i: u32) -> Self {
+        ChainCircuit::new(self.chain_length, Some(F::from(u64::from(i) + 3)))
+    }
+}
+
+/// As registry::CircuitKeys, for one chain length
+pub struct ChainKeys<E: Engine> {
+    pub pk: ProvingKey<E>,
+    pub vk: VerifyingKey<E>,
+    pub counts: CircuitCounts,
+    pub key_sizes: KeySizes,
+    pub matrices: ConstraintMatrices<E::ScalarField>,
+    pub assignments: Assignments<E::ScalarField>,
+}
+
+type Slot<E> = OnceLock<Arc<ChainKeys<E>>>;
+
+/// Keys per chain length on curve E, set up on first use. Concurrent first requests for
+/// one length share a single setup.
+pub struct ChainKeyCache<E: Engine> {
+    keys: Mutex<HashMap<u32, Arc<Slot<E>>>>,
+    /// Saved as chain_<length>_<curve>.keys, as the fixed circuits' keys are
+    source: KeySource,
+}
+
+impl<E: Engine> Default for ChainKeyCache<E> {
+    fn default() -> Self {
+        ChainKeyCache::new(KeySource::default())
+    }
+}
+
+impl<E: Engine> ChainKeyCache<E> {
+    pub fn new(source: KeySource) -> Self {
+        ChainKeyCache { keys: Mutex::default(), source }
+    }
+
+    /// Keys for `chain_length`, with how long setup (or loading them from the keys
+    /// directory) took if this call did it
+    pub fn get_or_setup(&self, chain_length: u32) -> (Arc<ChainKeys<E>>, Option<Duration>) {
+        let slot = self.keys.lock().unwrap().entry(chain_length).or_default().clone();
+        let mut setup_time = None;
+        let keys = slot.get_or_init(|| {
+            let start = Instant::now();
+            let id = format!("chain_{}", chain_length);
+            let (pk, vk) = keystore::load_or_setup(&self.source, &id, ChainCircuit::new(chain_length, None));
+            setup_time = Some(start.elapsed());
+            let blank = ChainCircuit::<E::ScalarField>::new(chain_length, None);
+            Arc::new(ChainKeys {
+                key_sizes: KeySizes::of(&pk, &vk),
+                pk,
+                vk,
+                counts: CircuitCounts::of(blank.clone()),
+                matrices: circuits::matrices(blank.clone()),
+                assignments: circuits::assignments(blank),
+            })
+        });
+        (keys.clone(), setup_time)
+    }
+
+    /// Chain lengths with keys ready, in order, with their key sizes
+    pub fn ready(&self) -> Vec<(u32, KeySizes)> {
+        let keys = self.keys.lock().unwrap();
+        let mut ready: Vec<(u32, KeySizes)> =
+            keys.iter().filter_map(|(&n, slot)| slot.get().map(|keys| (n, keys.key_sizes))).collect();
+        ready.sort_unstable_by_key(|&(n, _)| n);
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::budget;
+    use ark_bls12_381::Bls12_381;
+    use ark_bn254::Bn254;
+
+    #[test]
+    fn keys_are_set_up_once_per_length() {
+        let cache = ChainKeyCache::<Bn254>::default();
+        let (first, setup) = cache.get_or_setup(8);
+        assert!(setup.is_some());
+        // One constraint per step, plus the final equality
+        assert_eq!(first.counts.num_constraints, 9);
+        assert_eq!((first.counts.num_witness_variables, first.counts.num_public_inputs), (9, 1));
+
+        let (again, setup) = cache.get_or_setup(8);
+        assert!(setup.is_none());
+        assert!(Arc::ptr_eq(&first, &again));
+        let (shorter, _) = cache.get_or_setup(4);
+        // Key sizes are per length: the proving key grows with the chain, the verifying key doesn't
+        assert_eq!(cache.ready(), [(4, shorter.key_sizes), (8, first.key_sizes)]);
+        assert!(first.key_sizes.proving_key_bytes > shorter.key_sizes.proving_key_bytes);
+        assert_eq!(first.key_sizes.verifying_key_bytes, shorter.key_sizes.verifying_key_bytes);
+
+        let (timings, encoding, _) = crate::bench_prove(&first.pk, &first.matrices, &first.assignments, budget(2), false);
+        assert_eq!((timings.prove.len(), encoding.compressed_size()), (2, 128));
+        assert_eq!(crate::bench_verify(&first.pk, &first.vk, &first.matrices, &first.assignments, budget(3), 1, None).unwrap().0.accept.len(), 3);
+    }
+
+    #[test]
+    fn bls12_381_proofs_are_larger() {
+        let (keys, _) = ChainKeyCache::<Bls12_381>::default().get_or_setup(4);
+        let (_, encoding, _) = crate::bench_prove(&keys.pk, &keys.matrices, &keys.assignments, budget(1), false);
+        assert_eq!(encoding.compressed_size(), 192);
+        assert_eq!(crate::bench_verify(&keys.pk, &keys.vk, &keys.matrices, &keys.assignments, budget(1), 1, None).unwrap().0.accept.len(), 1);
+    }
+}
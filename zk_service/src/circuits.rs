@@ -0,0 +1,308 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use ark_ff::PrimeField;
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, OptimizationGoal, SynthesisMode};
+use ark_serialize::CanonicalSerialize;
+use sha2::{Digest, Sha256};
+
+use crate::chain::ChainKeyCache;
+use crate::curve::{Curve, Engine};
+use crate::registry::{SetupStatus, Status};
+use crate::AppState;
+
+// ============ Circuit Catalogue ============
+//
+// What GET /circuits reports. Sizes are worked out once, during each circuit's setup on
+// each curve, so listing is free. Clients use it to check a circuit_id before sending a
+// benchmark.
+
+#[derive(Serialize, ToSchema)]
+pub struct CircuitInfo {
+    id: &'static str,
+    description: &'static str,
+    /// The circuit's keys and sizes on each curve it can be benchmarked on
+    curves: Vec<CurveSetup>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CurveSetup {
+    curve: Curve,
+    /// Setup runs on the first request for a circuit on a curve, so until then its sizes
+    /// there are unknown
+    setup: SetupStatus,
+    /// How long that setup, or loading its keys from ZK_KEYS_DIR, took
+    #[serde(skip_serializing_if = "Option::is_none")]
+    setup_ms: Option<f64>,
+    /// Once set up; see vk_fingerprint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vk_fingerprint: Option<String>,
+    #[serde(flatten)]
+    sizes: Option<CircuitSizes>,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+pub struct CircuitSizes {
+    constraints: usize,
+    /// Private (witness) variables the prover assigns
+    witness_variables: usize,
+    /// Public inputs a proof is checked against, not counting the constant one
+    public_inputs: usize,
+    #[serde(flatten)]
+    pub keys: KeySizes,
+}
+
+/// Serialized sizes of a circuit's keys. Groth16's proving key grows with the circuit and
+/// dominates what a deployment has to store and load
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ToSchema)]
+pub struct KeySizes {
+    /// Compressed, as /circuits/{circuit_id}/vk serves the verifying key
+    pub proving_key_bytes: usize,
+    pub verifying_key_bytes: usize,
+    /// Uncompressed, as ZK_KEYS_DIR stores the proving key
+    pub proving_key_uncompressed_bytes: usize,
+    pub verifying_key_uncompressed_bytes: usize,
+}
+
+/// Hex SHA-256 of the compressed verifying key, as /circuits/{circuit_id}/vk serves it.
+/// Identifies the keys a proof will verify against without fetching them
+pub fn vk_fingerprint<E: Engine>(vk: &VerifyingKey<E>) -> String {
+    let mut bytes = Vec::new();
+    vk.serialize_compressed(&mut bytes).expect("serializing to a Vec cannot fail");
+    hex::encode(Sha256::digest(&bytes))
+}
+
+impl KeySizes {
+    pub fn of<E: Engine>(pk: &ProvingKey<E>, vk: &VerifyingKey<E>) -> KeySizes {
+        KeySizes {
+            proving_key_bytes: pk.compressed_size(),
+            verifying_key_bytes: vk.compressed_size(),
+            proving_key_uncompressed_bytes: pk.uncompressed_size(),
+            verifying_key_uncompressed_bytes: vk.uncompressed_size(),
+        }
+    }
+}
+
+/// A circuit's size as benchmark responses report it, so timings can be normalized per
+/// constraint. Counted from the synthesized constraint system, never written down by hand
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ToSchema)]
+pub struct CircuitCounts {
+    pub num_constraints: usize,
+    /// Private (witness) variables the prover assigns
+    pub num_witness_variables: usize,
+    /// Public inputs a proof is checked against, not counting the constant one
+    pub num_public_inputs: usize,
+}
+
+impl CircuitCounts {
+    pub fn of<F: PrimeField, C: ConstraintSynthesizer<F>>(circuit: C) -> CircuitCounts {
+        let cs = synthesize_for_setup(circuit);
+        CircuitCounts {
+            num_constraints: cs.num_constraints(),
+            num_witness_variables: cs.num_witness_variables(),
+            // The constant one is always the first instance variable
+            num_public_inputs: cs.num_instance_variables() - 1,
+        }
+    }
+}
+
+/// Synthesizes `circuit` in setup mode, where no witness is needed, to count its
+/// constraints and variables. Configured as Groth16's own setup does, so the counts
+/// match the proving key
+pub fn synthesize_for_setup<F: PrimeField, C: ConstraintSynthesizer<F>>(circuit: C) -> ConstraintSystemRef<F> {
+    let cs = ConstraintSystem::<F>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit.generate_constraints(cs.clone()).expect("circuit synthesis failed in setup mode");
+    cs.finalize();
+    cs
+}
+
+/// The A, B and C matrices of `blank`, numbered as `assignment` orders its values
+pub fn matrices<F: PrimeField, C: ConstraintSynthesizer<F>>(blank: C) -> ConstraintMatrices<F> {
+    synthesize_for_setup(blank).to_matrices().expect("setup mode always builds matrices")
+}
+
+/// Every variable's value for `circuit`, in column order (the constant one, then the
+/// public inputs, then the witness), synthesized as Groth16's prover does but without
+/// building the matrices it already has from setup
+pub fn assignment<F: PrimeField, C: ConstraintSynthesizer<F>>(circuit: C) -> Vec<F> {
+    let cs = ConstraintSystem::<F>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Prove { construct_matrices: false });
+    circuit.generate_constraints(cs.clone()).expect("circuit synthesis failed");
+    cs.finalize();
+    let cs = cs.borrow().expect("the constraint system is still shared");
+    cs.instance_assignment.iter().chain(&cs.witness_assignment).copied().collect()
+}
+
+/// What the registries need of a circuit to benchmark it. The blank instance, without a
+/// witness, is what setup takes; each benchmark iteration builds a full one from it, so
+/// witness generation can be timed apart from proving
+pub trait BenchCircuit<F: PrimeField>: ConstraintSynthesizer<F> + Clone + Send + Sync + 'static {
+    /// The instance proven on iteration `i`, witness and public inputs included. Iteration
+    /// 0's is the statement the verify benchmarks check
+    fn instance(&self, i: u32) -> Self;
+}
+
+/// A circuit's assignment for each iteration, with the circuit's type erased so one
+/// registry can hold them all
+pub type Assignments<F> = Arc<dyn Fn(u32) -> Vec<F> + Send + Sync>;
+
+pub fn assignments<F: PrimeField, C: BenchCircuit<F>>(blank: C) -> Assignments<F> {
+    Arc::new(move |i| assignment(blank.instance(i)))
+}
+
+impl CircuitSizes {
+    pub fn measure<E: Engine, C: ConstraintSynthesizer<E::ScalarField>>(
+        circuit: C,
+        pk: &ProvingKey<E>,
+        vk: &VerifyingKey<E>,
+    ) -> CircuitSizes {
+        let counts = CircuitCounts::of(circuit);
+        CircuitSizes {
+            constraints: counts.num_constraints,
+            witness_variables: counts.num_witness_variables,
+            public_inputs: counts.num_public_inputs,
+            keys: KeySizes::of(pk, vk),
+        }
+    }
+
+    pub fn counts(&self) -> CircuitCounts {
+        CircuitCounts {
+            num_constraints: self.constraints,
+            num_witness_variables: self.witness_variables,
+            num_public_inputs: self.public_inputs,
+        }
+    }
+}
+
+/// A circuit whose size is chosen per request, so it has no fixed counts or keys
+#[derive(Serialize, ToSchema)]
+pub struct ParametricCircuitInfo {
+    id: &'static str,
+    description: &'static str,
+    /// Largest chain_length a benchmark may ask for
+    max_chain_length: u32,
+    /// Lengths whose keys are already set up on each curve, so benchmarking them again
+    /// skips setup
+    ready_chain_lengths: Vec<ChainLengths>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ChainLengths {
+    curve: Curve,
+    lengths: Vec<u32>,
+    /// Each ready length's key sizes, which grow with it
+    key_sizes: Vec<ChainKeySizes>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ChainKeySizes {
+    chain_length: u32,
+    #[serde(flatten)]
+    sizes: KeySizes,
+}
+
+impl ChainLengths {
+    fn new<E: Engine>(chains: &ChainKeyCache<E>) -> ChainLengths {
+        let ready = chains.ready();
+        ChainLengths {
+            curve: E::CURVE,
+            lengths: ready.iter().map(|&(chain_length, _)| chain_length).collect(),
+            key_sizes: ready.into_iter().map(|(chain_length, sizes)| ChainKeySizes { chain_length, sizes }).collect(),
+        }
+    }
+}
+
+impl CurveSetup {
+    fn new(status: Status) -> CurveSetup {
+        CurveSetup {
+            curve: status.curve,
+            setup: status.setup,
+            setup_ms: status.setup_time.map(|t| t.as_secs_f64() * 1000.0),
+            vk_fingerprint: status.vk_fingerprint,
+            sizes: status.sizes,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CircuitsResponse {
+    circuits: Vec<CircuitInfo>,
+    /// Benchmark-only circuits taking a size in the request; /prove, /verify and the
+    /// verifying key export don't serve them
+    parametric_circuits: Vec<ParametricCircuitInfo>,
+    /// Whether Groth16 setup ran from ZK_SETUP_SEED, so the fingerprints are the same on
+    /// every run. Test-only: anyone who knows the seed can forge proofs
+    seeded_setup: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/circuits",
+    responses(
+        (status = 200, description = "Circuits this service can prove and verify, with their setup status and, once set up, their sizes", body = CircuitsResponse),
+    )
+)]
+pub async fn circuits(State(state): State<AppState>) -> Json<CircuitsResponse> {
+    // Both curves register the same circuits in the same order
+    let mut circuits: Vec<CircuitInfo> = state
+        .bn254
+        .circuits
+        .statuses()
+        .map(|status| CircuitInfo { id: status.id, description: status.description, curves: vec![CurveSetup::new(status)] })
+        .collect();
+    for (info, status) in circuits.iter_mut().zip(state.bls12_381.circuits.statuses()) {
+        info.curves.push(CurveSetup::new(status));
+    }
+    let chain = ParametricCircuitInfo {
+        id: "chain",
+        description: "x_{i+1} = x_i^2 + c_i for chain_length steps, for public x_n; one constraint per step",
+        max_chain_length: state.max_chain_length,
+        ready_chain_lengths: vec![ChainLengths::new(&state.bn254.chains), ChainLengths::new(&state.bls12_381.chains)],
+    };
+    Json(CircuitsResponse { circuits, parametric_circuits: vec![chain], seeded_setup: state.seeded_setup })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::ready_state;
+
+    #[tokio::test]
+    async fn circuits_are_listed_with_their_sizes_once_set_up() {
+        let state = ready_state();
+        state.bn254.circuits.get("multiply").await.unwrap();
+        state.bls12_381.circuits.get("multiply").await.unwrap();
+        let Json(resp) = circuits(State(state.clone())).await;
+        let ids: Vec<&str> = resp.circuits.iter().map(|c| c.id).collect();
+        assert_eq!(ids, ["multiply", "cube_root"]);
+
+        // a*b = c: the product gets a variable of its own, then equals c
+        let [bn254, bls12_381] = &resp.circuits[0].curves[..] else { panic!("expected two curves") };
+        assert_eq!((bn254.curve, bn254.setup), (Curve::Bn254, SetupStatus::Ready));
+        assert!(bn254.setup_ms.is_some());
+        // As /circuits/multiply/vk reports it
+        let (keys, _) = state.bn254.circuits.get("multiply").await.unwrap();
+        assert_eq!(bn254.vk_fingerprint, Some(vk_fingerprint(&keys.vk)));
+        assert!(!resp.seeded_setup);
+        let sizes = bn254.sizes.as_ref().unwrap();
+        assert_eq!((sizes.constraints, sizes.witness_variables, sizes.public_inputs), (2, 3, 1));
+        assert!(sizes.keys.proving_key_bytes > sizes.keys.verifying_key_bytes);
+        assert!(sizes.keys.proving_key_uncompressed_bytes > sizes.keys.proving_key_bytes);
+        // Same circuit, bigger group elements
+        let bls_sizes = bls12_381.sizes.as_ref().unwrap();
+        assert_eq!(bls_sizes.constraints, 2);
+        assert!(bls_sizes.keys.verifying_key_bytes > sizes.keys.verifying_key_bytes);
+
+        // Never requested, so never set up
+        let cube_root = &resp.circuits[1];
+        assert!(cube_root.curves.iter().all(|c| c.setup == SetupStatus::NotSetUp && c.sizes.is_none() && c.vk_fingerprint.is_none()));
+        assert_eq!(resp.parametric_circuits[0].id, "chain");
+    }
+}
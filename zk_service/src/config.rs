@@ -0,0 +1,161 @@
+use clap::Parser;
+use common::{http::{self, HttpTuning}, limits::{self, Limits}, listen, uds};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::{chain, parallelism};
+
+// Port when ZK_BIND / --bind doesn't name one
+pub const DEFAULT_PORT: u16 = 8001;
+
+/// Runtime configuration, from CLI flags or the matching environment variables
+#[derive(Parser, Debug, Clone)]
+#[command(name = "zk_service")]
+#[command(about = "Groth16 and Marlin proof benchmark service")]
+pub struct Config {
+    /// Most iterations a single prove benchmark will run; proving is much slower than
+    /// verifying, hence the lower cap
+    #[arg(long, env = "ZK_MAX_PROVE_ITERATIONS", default_value_t = crate::DEFAULT_MAX_PROVE_ITERATIONS)]
+    pub max_prove_iterations: u32,
+
+    /// Most iterations a single verify benchmark will run
+    #[arg(long, env = "ZK_MAX_VERIFY_ITERATIONS", default_value_t = crate::DEFAULT_MAX_VERIFY_ITERATIONS)]
+    pub max_verify_iterations: u32,
+
+    /// Most raw per-iteration timings a response carries
+    #[arg(long, env = "ZK_MAX_RAW_TIMINGS", default_value_t = crate::DEFAULT_MAX_RAW_TIMINGS)]
+    pub max_raw_timings: usize,
+
+    /// Most proofs a fresh-mode verify benchmark makes up front; past it, iterations cycle
+    /// through them
+    #[arg(long, env = "ZK_MAX_FRESH_STATEMENTS", default_value_t = crate::DEFAULT_MAX_FRESH_STATEMENTS, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_fresh_statements: u32,
+
+    /// Longest chain circuit a benchmark may ask for
+    #[arg(long, env = "ZK_MAX_CHAIN_LENGTH", default_value_t = chain::DEFAULT_MAX_CHAIN_LENGTH)]
+    pub max_chain_length: u32,
+
+    /// Threads a prove benchmark runs on without prover_threads, at most available
+    /// parallelism (default: available parallelism)
+    #[arg(long, env = "ZK_PROVER_THREADS", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub prover_threads: Option<usize>,
+
+    /// Seconds to let in-flight requests finish after SIGTERM/Ctrl-C before exiting
+    #[arg(long, env = "ZK_SHUTDOWN_TIMEOUT_SECS", default_value_t = crate::DEFAULT_SHUTDOWN_TIMEOUT_SECS)]
+    pub shutdown_timeout_secs: u64,
+
+    /// Benchmarks allowed to run at once before /ready reports overloaded (default:
+    /// available parallelism)
+    #[arg(long, env = "ZK_MAX_CONCURRENT_BENCHMARKS", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_concurrent_benchmarks: Option<usize>,
+
+    /// Seed the Groth16 setup from this integer, so every run sets up the same keys.
+    /// Refused without --allow-insecure-setup
+    #[arg(long, env = "ZK_SETUP_SEED")]
+    pub setup_seed: Option<u64>,
+
+    /// Save keys here after setup and load them from here on the next start; created if
+    /// missing (default: set up from scratch on every start)
+    #[arg(long, env = "ZK_KEYS_DIR")]
+    pub keys_dir: Option<PathBuf>,
+
+    /// Accept --setup-seed. Anyone who knows the seed can forge proofs, so only for tests
+    #[arg(long)]
+    pub allow_insecure_setup: bool,
+
+    /// Largest request body accepted; bigger ones get a 413
+    #[arg(long, env = "ZK_MAX_BODY_BYTES", default_value_t = limits::DEFAULT_MAX_BODY_BYTES)]
+    pub max_body_bytes: usize,
+
+    /// Seconds a handler may run before the request gets a 408. Benchmark endpoints keep
+    /// to their own iteration caps instead
+    #[arg(long, env = "ZK_REQUEST_TIMEOUT_SECS", default_value_t = limits::DEFAULT_REQUEST_TIMEOUT_SECS, value_parser = clap::value_parser!(u64).range(1..))]
+    pub request_timeout_secs: u64,
+
+    /// Connections kept open at once; further clients wait to be accepted (default: no limit)
+    #[arg(long, env = "ZK_MAX_CONNECTIONS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_connections: Option<u32>,
+
+    /// Seconds an HTTP/1.1 connection may sit idle between requests before it's closed
+    #[arg(long, env = "ZK_IDLE_TIMEOUT_SECS", default_value_t = http::DEFAULT_IDLE_TIMEOUT_SECS, value_parser = clap::value_parser!(u64).range(1..))]
+    pub idle_timeout_secs: u64,
+
+    /// Requests a single HTTP/2 connection may have in flight at once
+    #[arg(long, env = "ZK_HTTP2_MAX_CONCURRENT_STREAMS", default_value_t = http::DEFAULT_HTTP2_MAX_CONCURRENT_STREAMS, value_parser = clap::value_parser!(u32).range(1..))]
+    pub http2_max_concurrent_streams: u32,
+
+    /// Seconds between keep-alive pings on an HTTP/2 connection
+    #[arg(long, env = "ZK_HTTP2_KEEP_ALIVE_INTERVAL_SECS", default_value_t = http::DEFAULT_HTTP2_KEEP_ALIVE_INTERVAL_SECS, value_parser = clap::value_parser!(u64).range(1..))]
+    pub http2_keep_alive_interval_secs: u64,
+
+    /// Comma-separated origins allowed to call the API from a browser, or "*" (default: CORS off)
+    #[arg(long, env = "ZK_CORS_ALLOWED_ORIGINS", value_delimiter = ',')]
+    pub cors_allowed_origins: Vec<String>,
+
+    /// Never gzip/brotli-compress responses, even when the client sends Accept-Encoding
+    #[arg(long, env = "ZK_DISABLE_COMPRESSION")]
+    pub disable_compression: bool,
+
+    /// Never attach hostname/CPU details to responses, even when a request sets include_machine_info
+    #[arg(long, env = "ZK_DISABLE_MACHINE_INFO")]
+    pub disable_machine_info: bool,
+
+    /// Require `Authorization: Bearer <token>` on every endpoint except /health, /live and /ready
+    #[arg(long, env = "ZK_AUTH_TOKEN", hide_env_values = true)]
+    pub auth_token: Option<String>,
+
+    /// PEM certificate chain; together with --tls-key, serve HTTPS instead of plain HTTP
+    #[arg(long, env = "ZK_TLS_CERT", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert. Both are re-read on SIGHUP
+    #[arg(long, env = "ZK_TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Address to listen on: host:port, a bare port for every interface, or a bare IP for
+    /// port 8001. Port 0 picks a free port, logged at startup
+    #[arg(long, env = "ZK_BIND", default_value = "0.0.0.0:8001", value_parser = |s: &str| listen::parse_bind(s, DEFAULT_PORT))]
+    pub bind: SocketAddr,
+
+    /// Listen on this Unix domain socket instead of --bind; the socket file is removed on
+    /// clean shutdown
+    #[arg(long, env = "ZK_UDS", conflicts_with = "tls_cert")]
+    pub uds: Option<PathBuf>,
+
+    /// Octal permissions for the --uds socket file
+    #[arg(long, env = "ZK_UDS_MODE", default_value = "600", value_parser = uds::parse_mode)]
+    pub uds_mode: u32,
+}
+
+impl Config {
+    pub fn max_concurrent_benchmarks(&self) -> usize {
+        self.max_concurrent_benchmarks
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    pub fn prover_threads(&self) -> usize {
+        self.prover_threads.unwrap_or_else(parallelism::available).min(parallelism::available())
+    }
+
+    pub fn limits(&self) -> Limits {
+        Limits {
+            max_body_bytes: self.max_body_bytes,
+            request_timeout_secs: self.request_timeout_secs,
+            max_connections: self.max_connections.map(|n| n as usize),
+        }
+    }
+
+    pub fn http_tuning(&self) -> HttpTuning {
+        HttpTuning {
+            idle_timeout_secs: self.idle_timeout_secs,
+            http2_max_concurrent_streams: self.http2_max_concurrent_streams,
+            http2_keep_alive_interval_secs: self.http2_keep_alive_interval_secs,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::parse_from(["zk_service"])
+    }
+}
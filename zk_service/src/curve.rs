@@ -0,0 +1,57 @@
+use ark_bls12_381::Bls12_381;
+use ark_bn254::Bn254;
+use ark_ec::pairing::Pairing;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// ============ Pairing Curves ============
+//
+// Circuits are generic over the scalar field and the benchmarks over the pairing engine;
+// these are the engines a request can pick. Keys are set up per curve, lazily, so BN254
+// alone costs what it always did.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Curve {
+    #[default]
+    Bn254,
+    Bls12_381,
+}
+
+impl Curve {
+    pub fn name(self) -> &'static str {
+        match self {
+            Curve::Bn254 => "bn254",
+            Curve::Bls12_381 => "bls12_381",
+        }
+    }
+}
+
+/// A pairing engine a request can name
+pub trait Engine: Pairing {
+    const CURVE: Curve;
+    /// The same curve in arkworks 0.3, which the Marlin benchmarks run on
+    type Marlin: ark_ec_03::PairingEngine;
+}
+
+impl Engine for Bn254 {
+    const CURVE: Curve = Curve::Bn254;
+    type Marlin = ark_bn254_03::Bn254;
+}
+
+impl Engine for Bls12_381 {
+    const CURVE: Curve = Curve::Bls12_381;
+    type Marlin = ark_bls12_381_03::Bls12_381;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_match_the_wire_format() {
+        for curve in [Curve::Bn254, Curve::Bls12_381] {
+            assert_eq!(serde_json::to_value(curve).unwrap(), curve.name());
+        }
+    }
+}
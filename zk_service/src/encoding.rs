@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+use ark_ec::pairing::Pairing;
+use ark_groth16::Proof;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use common::stats;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{compute_stats, PhaseStats};
+
+// ============ Proof Encoding ============
+//
+// What a proof costs on the wire, compressed and uncompressed. Compression halves each
+// curve point but a verifier pays a square root per point to read it back, which some
+// verifier environments can't afford. The prove benchmarks measure both sizes on every
+// proof and, when asked, time writing and reading each one back in both modes.
+
+/// A proof's two encodings. Groth16 and Marlin proofs come from different ark-serialize
+/// versions, so each gets an impl of this rather than sharing one trait bound
+pub trait ProofCodec: Sized {
+    fn encode(&self, compress: bool) -> Vec<u8>;
+    /// Reads the proof back with the checks a verifier would make
+    fn decode(bytes: &[u8], compress: bool) -> Self;
+}
+
+fn compress_mode(compress: bool) -> Compress {
+    if compress { Compress::Yes } else { Compress::No }
+}
+
+impl<E: Pairing> ProofCodec for Proof<E> {
+    fn encode(&self, compress: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.serialize_with_mode(&mut bytes, compress_mode(compress)).expect("serializing to a Vec cannot fail");
+        bytes
+    }
+
+    fn decode(bytes: &[u8], compress: bool) -> Self {
+        Proof::deserialize_with_mode(bytes, compress_mode(compress), Validate::Yes).expect("a proof just serialized reads back")
+    }
+}
+
+/// Per-iteration serialization timings in microseconds
+#[derive(Default)]
+pub struct CodecTimings {
+    pub serialize_compressed: Vec<u128>,
+    pub deserialize_compressed: Vec<u128>,
+    pub serialize_uncompressed: Vec<u128>,
+    pub deserialize_uncompressed: Vec<u128>,
+}
+
+/// Every proof's encoded sizes, in the order they were proven, and their serialization
+/// timings when they were taken
+pub struct ProofEncoding {
+    pub compressed_bytes: Vec<usize>,
+    pub uncompressed_bytes: Vec<usize>,
+    pub timings: Option<CodecTimings>,
+}
+
+impl ProofEncoding {
+    pub fn new(timed: bool) -> Self {
+        ProofEncoding { compressed_bytes: Vec::new(), uncompressed_bytes: Vec::new(), timings: timed.then(CodecTimings::default) }
+    }
+
+    /// Encodes `proof` both ways, timing each encoding and its decoding if this is timed
+    pub fn record<P: ProofCodec>(&mut self, proof: &P, overhead: Duration) {
+        for compress in [true, false] {
+            let start = Instant::now();
+            let bytes = proof.encode(compress);
+            let serialize_time = stats::subtract_overhead(start.elapsed(), overhead);
+            let sizes = if compress { &mut self.compressed_bytes } else { &mut self.uncompressed_bytes };
+            sizes.push(bytes.len());
+
+            let Some(timings) = &mut self.timings else { continue };
+            let start = Instant::now();
+            P::decode(&bytes, compress);
+            let deserialize_time = stats::subtract_overhead(start.elapsed(), overhead);
+            let (serialize, deserialize) = if compress {
+                (&mut timings.serialize_compressed, &mut timings.deserialize_compressed)
+            } else {
+                (&mut timings.serialize_uncompressed, &mut timings.deserialize_uncompressed)
+            };
+            serialize.push(serialize_time.as_micros());
+            deserialize.push(deserialize_time.as_micros());
+        }
+    }
+
+    /// The compressed size every proof had. Neither proof system's proofs vary in size for
+    /// a circuit, but should they, this is the largest
+    pub fn compressed_size(&self) -> usize {
+        self.compressed_bytes.iter().copied().max().unwrap_or(0)
+    }
+
+    pub fn uncompressed_size(&self) -> usize {
+        self.uncompressed_bytes.iter().copied().max().unwrap_or(0)
+    }
+}
+
+/// Writing a proof out and reading it back, in each mode
+#[derive(Serialize, ToSchema)]
+pub struct SerializationStats {
+    serialize_compressed: PhaseStats,
+    /// Includes decompressing every point and checking it's in the right subgroup
+    deserialize_compressed: PhaseStats,
+    serialize_uncompressed: PhaseStats,
+    /// Includes the subgroup checks, but no decompression
+    deserialize_uncompressed: PhaseStats,
+}
+
+impl From<&CodecTimings> for SerializationStats {
+    fn from(timings: &CodecTimings) -> Self {
+        SerializationStats {
+            serialize_compressed: compute_stats(&timings.serialize_compressed).into(),
+            deserialize_compressed: compute_stats(&timings.deserialize_compressed).into(),
+            serialize_uncompressed: compute_stats(&timings.serialize_uncompressed).into(),
+            deserialize_uncompressed: compute_stats(&timings.deserialize_uncompressed).into(),
+        }
+    }
+}
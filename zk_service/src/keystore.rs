@@ -0,0 +1,340 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ark_ff::PrimeField;
+use ark_groth16::{Groth16, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_snark::SNARK;
+use rand::rngs::OsRng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+
+use crate::circuits;
+use crate::curve::{Curve, Engine};
+
+// ============ Key Persistence ============
+//
+// With ZK_KEYS_DIR set, each circuit's proving key (the verifying key is part of it) is
+// written out after setup and read back on later starts, so a restart skips setup and
+// proofs issued before it still verify. A file is only used if its header matches: the
+// format version, a hash of the circuit's constraint matrices, and a checksum of the key
+// bytes. Anything else is set up afresh and overwritten. Files are named
+// <circuit>_<curve>.keys.
+// The header also marks how setup ran: from OsRng, or from a test-only ZK_SETUP_SEED and
+// which one. A server only loads keys set up the way it would set them up itself, so a
+// seeded test run never hands its forgeable keys to a normal start, nor the reverse.
+
+const MAGIC: &[u8; 8] = b"ZKGROTH\0";
+const FORMAT_VERSION: u32 = 2;
+// Whether setup was seeded, then the seed
+const SETUP_MARKER_LEN: usize = 1 + 8;
+// Magic, version, circuit hash, setup marker, payload checksum
+const HEADER_LEN: usize = 8 + 4 + 32 + SETUP_MARKER_LEN + 32;
+// Version 1 had no setup marker: every key was set up from OsRng
+const V1_HEADER_LEN: usize = 8 + 4 + 32 + 32;
+
+/// Where a curve's keys come from: the directory they're saved to and loaded from, and the
+/// seed setup runs from, if any
+#[derive(Clone, Debug, Default)]
+pub struct KeySource {
+    pub dir: Option<PathBuf>,
+    /// Test-only, so that keys come out the same on every run; anyone who knows it can
+    /// forge proofs. See setup_seed in main
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Missing,
+    Io(io::Error),
+    BadHeader,
+    UnsupportedVersion(u32),
+    /// Saved for a circuit whose constraints have since changed
+    CircuitChanged,
+    /// Set up from OsRng where this server would use a seed, from another seed, or from
+    /// a seed where it would use OsRng
+    SetupMismatch { saved_seed: Option<u64> },
+    ChecksumMismatch,
+    Corrupt(SerializationError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Missing => write!(f, "no saved keys"),
+            LoadError::Io(e) => write!(f, "unreadable: {}", e),
+            LoadError::BadHeader => write!(f, "not a key file"),
+            LoadError::UnsupportedVersion(v) => write!(f, "format version {}, expected {}", v, FORMAT_VERSION),
+            LoadError::CircuitChanged => write!(f, "saved for a different version of the circuit"),
+            LoadError::SetupMismatch { saved_seed: Some(seed) } => write!(f, "set up from test seed {}, which this server doesn't use", seed),
+            LoadError::SetupMismatch { saved_seed: None } => write!(f, "set up from OsRng, not ZK_SETUP_SEED"),
+            LoadError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            LoadError::Corrupt(e) => write!(f, "corrupt key data: {}", e),
+        }
+    }
+}
+
+/// Hash of the circuit's R1CS over `curve`: its variable counts and every coefficient of
+/// A, B and C. Keys set up for one circuit are useless for any other, so this decides
+/// whether a saved file still applies
+pub fn circuit_hash<F: PrimeField, C: ConstraintSynthesizer<F>>(curve: Curve, circuit: C) -> [u8; 32] {
+    let matrices = circuits::matrices(circuit);
+
+    let mut hasher = Sha256::new();
+    hasher.update(b"groth16-");
+    hasher.update(curve.name().as_bytes());
+    for n in [matrices.num_instance_variables, matrices.num_witness_variables, matrices.num_constraints] {
+        hasher.update((n as u64).to_le_bytes());
+    }
+    let mut coefficient = Vec::new();
+    for matrix in [&matrices.a, &matrices.b, &matrices.c] {
+        for row in matrix {
+            hasher.update((row.len() as u64).to_le_bytes());
+            for (value, variable) in row {
+                coefficient.clear();
+                value.serialize_uncompressed(&mut coefficient).expect("serializing to a Vec cannot fail");
+                hasher.update(&coefficient);
+                hasher.update((*variable as u64).to_le_bytes());
+            }
+        }
+    }
+    hasher.finalize().into()
+}
+
+fn setup_marker(seed: Option<u64>) -> [u8; SETUP_MARKER_LEN] {
+    let mut marker = [0; SETUP_MARKER_LEN];
+    if let Some(seed) = seed {
+        marker[0] = 1;
+        marker[1..].copy_from_slice(&seed.to_le_bytes());
+    }
+    marker
+}
+
+pub fn save<E: Engine>(path: &Path, circuit_hash: &[u8; 32], seed: Option<u64>, pk: &ProvingKey<E>) -> io::Result<()> {
+    let mut payload = Vec::new();
+    pk.serialize_uncompressed(&mut payload).expect("serializing to a Vec cannot fail");
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(circuit_hash);
+    bytes.extend_from_slice(&setup_marker(seed));
+    bytes.extend_from_slice(&Sha256::digest(&payload));
+    bytes.extend_from_slice(&payload);
+
+    // Written alongside and renamed over, so a crash mid-write never leaves half a file
+    let partial = path.with_extension("keys.partial");
+    fs::write(&partial, bytes)?;
+    fs::rename(&partial, path)
+}
+
+/// The proving key saved at `path`, if it's for this circuit and was set up from `seed`
+/// (or from OsRng, for None)
+pub fn load<E: Engine>(path: &Path, circuit_hash: &[u8; 32], seed: Option<u64>) -> Result<ProvingKey<E>, LoadError> {
+    let bytes = match fs::read(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(LoadError::Missing),
+        read => read.map_err(LoadError::Io)?,
+    };
+    if bytes.len() < 12 || &bytes[..8] != MAGIC {
+        return Err(LoadError::BadHeader);
+    }
+    let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+    let header_len = match version {
+        1 => V1_HEADER_LEN,
+        FORMAT_VERSION => HEADER_LEN,
+        _ => return Err(LoadError::UnsupportedVersion(version)),
+    };
+    if bytes.len() < header_len {
+        return Err(LoadError::BadHeader);
+    }
+    if bytes[12..44] != circuit_hash[..] {
+        return Err(LoadError::CircuitChanged);
+    }
+    let saved_seed = match (version, bytes[44]) {
+        (1, _) | (_, 0) => None,
+        (_, 1) => Some(u64::from_le_bytes(bytes[45..53].try_into().unwrap())),
+        _ => return Err(LoadError::BadHeader),
+    };
+    if saved_seed != seed {
+        return Err(LoadError::SetupMismatch { saved_seed });
+    }
+    let payload = &bytes[header_len..];
+    if Sha256::digest(payload)[..] != bytes[header_len - 32..header_len] {
+        return Err(LoadError::ChecksumMismatch);
+    }
+    // The checksum already rules out corruption, and checking every curve point of a large
+    // key would cost nearly as much as the setup it saves
+    ProvingKey::deserialize_uncompressed_unchecked(payload).map_err(LoadError::Corrupt)
+}
+
+/// Keys for `circuit` on curve E, from the keys directory when it holds a usable file for
+/// `id`, otherwise from a fresh setup that is then saved there. With no directory, always
+/// a fresh setup
+pub fn load_or_setup<E: Engine, C: ConstraintSynthesizer<E::ScalarField> + Clone>(
+    source: &KeySource,
+    id: &str,
+    circuit: C,
+) -> (ProvingKey<E>, VerifyingKey<E>) {
+    let Some(dir) = &source.dir else {
+        return setup(id, circuit, source.seed);
+    };
+    let path = dir.join(format!("{}_{}.keys", id, E::CURVE.name()));
+    let hash = circuit_hash(E::CURVE, circuit.clone());
+    match load::<E>(&path, &hash, source.seed) {
+        Ok(pk) => {
+            tracing::info!(circuit = id, curve = E::CURVE.name(), path = %path.display(), "loaded keys");
+            let vk = pk.vk.clone();
+            return (pk, vk);
+        }
+        Err(LoadError::Missing) => {}
+        Err(e) => tracing::warn!(path = %path.display(), error = %e, "ignoring saved keys, running setup again"),
+    }
+
+    let (pk, vk) = setup(id, circuit, source.seed);
+    match save(&path, &hash, source.seed, &pk) {
+        Ok(()) => tracing::info!(circuit = id, curve = E::CURVE.name(), path = %path.display(), "saved keys"),
+        Err(e) => tracing::warn!(circuit = id, path = %path.display(), error = %e, "could not save keys"),
+    }
+    (pk, vk)
+}
+
+fn setup<E: Engine, C: ConstraintSynthesizer<E::ScalarField>>(id: &str, circuit: C, seed: Option<u64>) -> (ProvingKey<E>, VerifyingKey<E>) {
+    let keys = match seed {
+        Some(seed) => Groth16::<E>::circuit_specific_setup(circuit, &mut seeded_rng(seed, id, E::CURVE)),
+        None => Groth16::<E>::circuit_specific_setup(circuit, &mut OsRng),
+    };
+    keys.unwrap_or_else(|e| panic!("Setup failed for {} circuit on {}: {}", id, E::CURVE.name(), e))
+}
+
+// Each circuit and curve draws its own stream from the seed, so no two share toxic waste
+fn seeded_rng(seed: u64, id: &str, curve: Curve) -> ChaCha20Rng {
+    let mut hasher = Sha256::new();
+    hasher.update(b"zk-setup-");
+    hasher.update(seed.to_le_bytes());
+    hasher.update(id.as_bytes());
+    hasher.update(curve.name().as_bytes());
+    ChaCha20Rng::from_seed(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CubeRootCircuit, MultiplyCircuit};
+    use ark_bls12_381::Bls12_381;
+    use ark_bn254::{Bn254, Fr};
+    use ark_groth16::prepare_verifying_key;
+    use std::path::PathBuf;
+
+    fn keys_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zk-keys-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn multiply<F: PrimeField>() -> MultiplyCircuit<F> {
+        MultiplyCircuit { a: None, b: None, c: None }
+    }
+
+    fn stored(dir: &Path) -> KeySource {
+        KeySource { dir: Some(dir.to_path_buf()), seed: None }
+    }
+
+    #[test]
+    fn proofs_verify_against_keys_loaded_after_a_restart() {
+        let dir = keys_dir("restart");
+        let (pk, _) = load_or_setup::<Bn254, _>(&stored(&dir), "multiply", multiply());
+        let (a, b) = (Fr::from(3u64), Fr::from(5u64));
+        let circuit = MultiplyCircuit { a: Some(a), b: Some(b), c: Some(a * b) };
+        let proof = Groth16::<Bn254>::prove(&pk, circuit, &mut OsRng).unwrap();
+
+        // As the next start would: the file is there, so no new setup
+        let (_, vk) = load_or_setup::<Bn254, _>(&stored(&dir), "multiply", multiply());
+        assert_eq!(vk, pk.vk);
+        assert!(Groth16::<Bn254>::verify_with_processed_vk(&prepare_verifying_key(&vk), &[a * b], &proof).unwrap());
+
+        // Each curve has a file of its own
+        load_or_setup::<Bls12_381, _>(&stored(&dir), "multiply", multiply());
+        assert!(dir.join("multiply_bn254.keys").exists() && dir.join("multiply_bls12_381.keys").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unusable_files_are_set_up_again() {
+        let dir = keys_dir("unusable");
+        let path = dir.join("multiply_bn254.keys");
+        let hash = circuit_hash(Curve::Bn254, multiply::<Fr>());
+        let (pk, _) = load_or_setup::<Bn254, _>(&stored(&dir), "multiply", multiply());
+
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(load::<Bn254>(&path, &hash, None), Err(LoadError::ChecksumMismatch)));
+        let (regenerated, _) = load_or_setup::<Bn254, _>(&stored(&dir), "multiply", multiply());
+        assert_ne!(regenerated.vk, pk.vk);
+        assert_eq!(load::<Bn254>(&path, &hash, None).unwrap().vk, regenerated.vk);
+
+        // Another circuit's keys under this name
+        let cube_root = CubeRootCircuit::<Fr> { x: None, y: None };
+        assert!(matches!(load::<Bn254>(&path, &circuit_hash(Curve::Bn254, cube_root), None), Err(LoadError::CircuitChanged)));
+
+        bytes[8] = 99;
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(load::<Bn254>(&path, &hash, None), Err(LoadError::UnsupportedVersion(99))));
+        fs::write(&path, b"garbage").unwrap();
+        assert!(matches!(load::<Bn254>(&path, &hash, None), Err(LoadError::BadHeader)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn seeded_setup_is_reproducible() {
+        let seeded = |seed| KeySource { dir: None, seed: Some(seed) };
+        let (_, vk) = load_or_setup::<Bn254, _>(&seeded(7), "multiply", multiply());
+        assert_eq!(load_or_setup::<Bn254, _>(&seeded(7), "multiply", multiply()).1, vk);
+        assert_ne!(load_or_setup::<Bn254, _>(&seeded(8), "multiply", multiply()).1, vk);
+        // Another circuit of the same shape still gets keys of its own
+        assert_ne!(load_or_setup::<Bn254, _>(&seeded(7), "product", multiply()).1, vk);
+    }
+
+    #[test]
+    fn keys_are_only_loaded_by_a_server_that_would_set_them_up_the_same_way() {
+        let dir = keys_dir("seeded");
+        let path = dir.join("multiply_bn254.keys");
+        let hash = circuit_hash(Curve::Bn254, multiply::<Fr>());
+        let seeded = KeySource { dir: Some(dir.clone()), seed: Some(7) };
+        let (pk, _) = load_or_setup::<Bn254, _>(&seeded, "multiply", multiply());
+        assert_eq!(load::<Bn254>(&path, &hash, Some(7)).unwrap().vk, pk.vk);
+        assert!(matches!(load::<Bn254>(&path, &hash, Some(8)), Err(LoadError::SetupMismatch { saved_seed: Some(7) })));
+        assert!(matches!(load::<Bn254>(&path, &hash, None), Err(LoadError::SetupMismatch { saved_seed: Some(7) })));
+
+        // A normal start sets up afresh rather than serve the forgeable keys, and a
+        // seeded start then does the same
+        let (random, _) = load_or_setup::<Bn254, _>(&stored(&dir), "multiply", multiply());
+        assert_ne!(random.vk, pk.vk);
+        assert!(matches!(load::<Bn254>(&path, &hash, Some(7)), Err(LoadError::SetupMismatch { saved_seed: None })));
+        assert_eq!(load_or_setup::<Bn254, _>(&seeded, "multiply", multiply()).1, pk.vk);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn version_1_files_load_as_set_up_from_os_rng() {
+        let dir = keys_dir("v1");
+        let path = dir.join("multiply_bn254.keys");
+        let hash = circuit_hash(Curve::Bn254, multiply::<Fr>());
+        let (pk, _) = load_or_setup::<Bn254, _>(&stored(&dir), "multiply", multiply());
+
+        // Version 1's header is version 2's without the setup marker
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.drain(44..44 + SETUP_MARKER_LEN);
+        bytes[8..12].copy_from_slice(&1u32.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(load::<Bn254>(&path, &hash, None).unwrap().vk, pk.vk);
+        assert!(matches!(load::<Bn254>(&path, &hash, Some(7)), Err(LoadError::SetupMismatch { saved_seed: None })));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,40 +1,82 @@
 use axum::{
-    debug_handler,
     Router,
     routing::{get, post},
     Json,
+    extract::{FromRequest, Request},
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use std::net::SocketAddr;
-use std::time::{SystemTime, UNIX_EPOCH, Instant};
-use tokio::net::TcpListener;
-use serde::{Deserialize, Serialize};
-
-use ark_bn254::{Bn254, Fr};
-use ark_groth16::{Groth16, ProvingKey, VerifyingKey, prepare_verifying_key};
-use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH, Instant};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use clap::Parser;
+use common::{auth::BearerAuthLayer, cors, health, http::{self, HttpTuning}, limits::Limits, listen, machine::MachineInfo, memory, request::{self, FieldError}, stats, tls, uds};
+
+use ark_bls12_381::Bls12_381;
+use ark_bn254::Bn254;
+use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey, prepare_verifying_key};
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
-use ark_ff::PrimeField;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{Field, PrimeField, UniformRand};
 use ark_snark::SNARK;
 use ark_serialize::CanonicalSerialize;
 use rand::rngs::OsRng;
-
-// ============ Health Check ============
-
-#[derive(Serialize)]
-struct HealthResponse {
-    status: String,
-    service: String,
-    timestamp: u64,
+use utoipa::{OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+
+mod chain;
+mod circuits;
+mod config;
+mod curve;
+mod encoding;
+mod keystore;
+mod marlin;
+mod metrics;
+mod openapi;
+mod parallelism;
+mod proofs;
+mod registry;
+
+use curve::{Curve, Engine};
+use encoding::ProofEncoding;
+use config::Config;
+use keystore::KeySource;
+
+// Seconds to let in-flight requests finish after SIGTERM/Ctrl-C, overridable with --shutdown-timeout-secs
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+// Per-request iteration caps, overridable with --max-prove-iterations / --max-verify-iterations.
+// Proving is much slower than verifying, hence the lower cap.
+const DEFAULT_MAX_PROVE_ITERATIONS: u32 = 1000;
+const DEFAULT_MAX_VERIFY_ITERATIONS: u32 = 5000;
+
+// Most raw samples a response carries, overridable with --max-raw-timings
+const DEFAULT_MAX_RAW_TIMINGS: usize = 10_000;
+
+// Most proofs a fresh-mode verify benchmark makes up front, overridable with
+// --max-fresh-statements; past it, iterations cycle through them
+const DEFAULT_MAX_FRESH_STATEMENTS: u32 = 1000;
+
+/// The Groth16 setup seed, if one is configured. Keys set up from a known seed are only fit
+/// for tests pinning their fingerprints, since anyone with the seed can forge proofs, so a
+/// seed is refused unless --allow-insecure-setup is passed as well
+fn setup_seed(config: &Config) -> Result<Option<u64>, String> {
+    match config.setup_seed {
+        Some(_) if !config.allow_insecure_setup => {
+            Err("ZK_SETUP_SEED makes every Groth16 key forgeable and is for tests only; pass --allow-insecure-setup to use it".to_string())
+        }
+        seed => Ok(seed),
+    }
 }
 
-async fn health() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "healthy".into(),
-        service: "zk_service".into(),
-        timestamp: current_timestamp(),
-    })
+/// Ready as soon as it's built: circuits are set up on their first request, so there's no
+/// startup work to wait for
+fn readiness(max_in_flight: usize) -> Arc<health::Readiness> {
+    let readiness = health::Readiness::new(env!("CARGO_PKG_NAME"), max_in_flight);
+    readiness.mark_initialized();
+    Arc::new(readiness)
 }
 
 fn current_timestamp() -> u64 {
@@ -101,290 +143,1948 @@ impl<F: PrimeField> ConstraintSynthesizer<F> for CubeRootCircuit<F> {
     }
 }
 
+// Each iteration proves a different statement, so no two proofs share a witness
+
+impl<F: PrimeField> circuits::BenchCircuit<F> for MultiplyCircuit<F> {
+    fn instance(&self, i: u32) -> Self {
+        let (a, b) = (F::from(u64::from(i) + 3), F::from(u64::from(i) + 7));
+        MultiplyCircuit { a: Some(a), b: Some(b), c: Some(a * b) }
+    }
+}
+
+impl<F: PrimeField> circuits::BenchCircuit<F> for CubeRootCircuit<F> {
+    fn instance(&self, i: u32) -> Self {
+        let x = F::from(u64::from(i) + 5);
+        CubeRootCircuit { x: Some(x), y: Some(x * x * x) }
+    }
+}
 
-// ============ Proving Key Cache ============
+// ============ Shared State ============
 
-struct CircuitKeys {
-    multiply_pk: ProvingKey<Bn254>,
-    multiply_vk: VerifyingKey<Bn254>,
-    cube_root_pk: ProvingKey<Bn254>,
-    cube_root_vk: VerifyingKey<Bn254>,
+#[derive(Clone)]
+struct AppState {
+    /// Keys on each curve, each circuit set up on its first request for that curve
+    bn254: CurveKeys<Bn254>,
+    bls12_381: CurveKeys<Bls12_381>,
+    readiness: Arc<health::Readiness>,
+    metrics: Arc<metrics::Metrics>,
+    max_prove_iterations: u32,
+    max_verify_iterations: u32,
+    /// Detected once at startup; None when the deployment has opted out
+    machine_info: Option<Arc<MachineInfo>>,
+    /// Cost of an empty timed region, calibrated once at startup
+    timer_overhead: Duration,
+    limits: Limits,
+    http: HttpTuning,
+    max_chain_length: u32,
+    max_raw_timings: usize,
+    max_fresh_statements: u32,
+    /// Threads a prove benchmark runs on without prover_threads, at most available_parallelism
+    prover_threads: usize,
+    /// Single-thread proving times that prove benchmarks report their speedup against
+    prover_baselines: Arc<parallelism::Baselines>,
+    /// Groth16 keys came from ZK_SETUP_SEED rather than OsRng
+    seeded_setup: bool,
+    /// What the router is built from: auth token, compression and CORS origins
+    config: Arc<Config>,
 }
 
-fn setup_circuits() -> CircuitKeys {
-    println!("Running trusted setup for circuits...");
+#[derive(Clone)]
+struct CurveKeys<E: Engine> {
+    /// Groth16 keys for the fixed circuits
+    circuits: Arc<registry::Registry<E>>,
+    /// Marlin's universal SRS and the fixed circuits' indexes against it
+    marlin: Arc<marlin::MarlinKeys<E>>,
+    /// Chain circuit keys by length, set up by the first benchmark asking for each
+    chains: Arc<chain::ChainKeyCache<E>>,
+}
+
+impl axum::extract::FromRef<AppState> for Arc<health::Readiness> {
+    fn from_ref(state: &AppState) -> Self {
+        state.readiness.clone()
+    }
+}
+
+impl axum::extract::FromRef<AppState> for Arc<common::metrics::ServiceMetrics> {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.service.clone()
+    }
+}
+
+impl AppState {
+    fn machine_info(&self, requested: bool) -> Option<MachineInfo> {
+        if requested { self.machine_info.as_deref().cloned() } else { None }
+    }
+
+    /// `circuit_id` as a metric label: one of the service's circuits, or "unknown", so a
+    /// client sending made-up names can't grow the label set without bound
+    fn circuit_label(&self, circuit_id: &str) -> String {
+        if circuit_id == "chain" || self.bn254.circuits.contains(circuit_id) {
+            circuit_id.to_string()
+        } else {
+            "unknown".to_string()
+        }
+    }
+
+    /// The chain length a benchmark asks for, checked against its circuit and the cap;
+    /// None for the fixed circuits, which take no chain_length
+    fn chain_length(&self, req: &ZkBenchRequest) -> Result<Option<u32>, AppError> {
+        match (req.circuit_id.as_str(), req.chain_length) {
+            ("chain", None) => Err(AppError::ValidationFailed("chain_length is required for the chain circuit".to_string())),
+            ("chain", Some(length)) if length == 0 || length > self.max_chain_length => Err(AppError::ValidationFailed(
+                format!("chain_length must be between 1 and {}, got {}", self.max_chain_length, length)
+            )),
+            (_, Some(_)) if req.circuit_id != "chain" => {
+                Err(AppError::ValidationFailed("chain_length only applies to the chain circuit".to_string()))
+            }
+            (_, length) => Ok(length),
+        }
+    }
+
+}
+
+impl<E: Engine> CurveKeys<E> {
+    fn new(keys: KeySource) -> Self {
+        CurveKeys {
+            circuits: Arc::new(circuit_registry(keys.clone())),
+            marlin: Arc::new(marlin_keys()),
+            chains: Arc::new(chain::ChainKeyCache::new(keys)),
+        }
+    }
 
-    let dummy_multiply = MultiplyCircuit::<Fr> { a: None, b: None, c: None };
-    let (multiply_pk, multiply_vk) = Groth16::<Bn254>::circuit_specific_setup(
-        dummy_multiply, &mut OsRng
-    ).expect("Setup failed for multiply circuit");
+    /// Chain keys for `length`, running setup if it's new. Setup can take as long as the
+    /// benchmark itself, so this belongs on the blocking pool with it
+    fn chain_run(&self, length: Option<u32>) -> Option<ChainRun<E>> {
+        length.map(|length| {
+            let (keys, setup_time) = self.chains.get_or_setup(length);
+            ChainRun { length, keys, setup_time }
+        })
+    }
+}
 
-    // Setup for CubeRootCircuit
-    let dummy_cube = CubeRootCircuit::<Fr> { x: None, y: None };
-    let (cube_root_pk, cube_root_vk) = Groth16::<Bn254>::circuit_specific_setup(
-        dummy_cube, &mut OsRng
-    ).expect("Setup failed for cube_root circuit");
+struct ChainRun<E: Engine> {
+    length: u32,
+    keys: Arc<chain::ChainKeys<E>>,
+    /// Set when this request ran the setup
+    setup_time: Option<Duration>,
+}
 
-    println!("Trusted setup complete.");
+// Every fixed circuit the service can prove, in the order GET /circuits lists them
+fn circuit_registry<E: Engine>(keys: KeySource) -> registry::Registry<E> {
+    registry::Registry::new(keys)
+        .register(
+            "multiply",
+            "Knowledge of a and b with a * b = c, for public c",
+            MultiplyCircuit::<E::ScalarField> { a: None, b: None, c: None },
+        )
+        .register(
+            "cube_root",
+            "Knowledge of x with x^3 = y, for public y",
+            CubeRootCircuit::<E::ScalarField> { x: None, y: None },
+        )
+}
 
-    CircuitKeys { multiply_pk, multiply_vk, cube_root_pk, cube_root_vk }
+// The same circuits, for Marlin
+fn marlin_keys<E: Engine>() -> marlin::MarlinKeys<E> {
+    marlin::MarlinKeys::new()
+        .register("multiply", MultiplyCircuit::<E::ScalarField> { a: None, b: None, c: None })
+        .register("cube_root", CubeRootCircuit::<E::ScalarField> { x: None, y: None })
 }
 
 // ============ Error Types ============
 
+#[derive(Debug)]
 enum AppError {
     InvalidCircuit(String),
+    InvalidProofSystem(String),
+    ValidationFailed(String),
+    /// The body parsed, but a field is unknown, missing or of the wrong type
+    InvalidField(FieldError),
+    /// A path names a circuit that doesn't exist
+    CircuitNotFound(String),
+    /// A proof that isn't base64, or doesn't decode to a valid compressed Groth16 proof
+    MalformedProof(String),
+    /// Proving or verification panicked, e.g. on an expect that should never fail
+    BenchmarkPanicked { circuit_id: String, operation: &'static str, message: String },
+    /// A verify benchmark's corrupted statement verified: the circuit, or the verifier, is unsound
+    InvalidProofAccepted { circuit_id: String, corruption: Corruption, iteration: u32 },
+    /// A blocking task was cancelled or died outside catch_benchmark_panic
+    Internal(String),
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
+/// JSON body of a 404 for an unknown circuit in the path
+#[derive(Serialize, ToSchema)]
+struct CircuitNotFoundBody {
+    error: String,
+    circuit_id: String,
+}
+
+/// JSON body of a 500 for any other server-side failure
+#[derive(Serialize, ToSchema)]
+struct InternalErrorBody {
+    error: String,
+}
+
+/// JSON body of a 500 from a panicked benchmark
+#[derive(Serialize, ToSchema)]
+struct PanicBody {
+    error: String,
+    circuit_id: String,
+    /// "prove", "verify", or "setup" for a circuit's first request
+    operation: &'static str,
+}
+
+/// JSON body of a 500 from a corrupted statement that verified
+#[derive(Serialize, ToSchema)]
+struct InvalidProofAcceptedBody {
+    error: String,
+    circuit_id: String,
+    corruption: Corruption,
+    /// The measured iteration whose corrupted statement was accepted
+    iteration: u32,
+}
+
+impl AppError {
+    fn kind(&self) -> &'static str {
+        match self {
+            AppError::InvalidCircuit(_) => "invalid_circuit",
+            AppError::InvalidProofSystem(_) => "invalid_proof_system",
+            AppError::ValidationFailed(_) => "validation_failed",
+            AppError::InvalidField(_) => "invalid_field",
+            AppError::CircuitNotFound(_) => "circuit_not_found",
+            AppError::MalformedProof(_) => "malformed_proof",
+            AppError::Internal(_) | AppError::BenchmarkPanicked { .. } => "internal",
+            AppError::InvalidProofAccepted { .. } => "invalid_proof_accepted",
+        }
+    }
+
+    fn response(self) -> Response {
         let (status, message) = match self {
+            // The one JSON error body, so clients can pick out the field programmatically
+            AppError::InvalidField(e) => return (StatusCode::UNPROCESSABLE_ENTITY, Json(e)).into_response(),
+            AppError::BenchmarkPanicked { circuit_id, operation, message } => {
+                let error = format!("Benchmark {} {} panicked: {}", circuit_id, operation, message);
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(PanicBody { error, circuit_id, operation })).into_response();
+            }
+            AppError::InvalidProofAccepted { circuit_id, corruption, iteration } => {
+                let error = format!("Benchmark {} verify accepted a corrupted statement on iteration {}", circuit_id, iteration);
+                let body = InvalidProofAcceptedBody { error, circuit_id, corruption, iteration };
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(body)).into_response();
+            }
+            AppError::Internal(error) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(InternalErrorBody { error })).into_response();
+            }
+            AppError::CircuitNotFound(circuit_id) => {
+                let error = format!("No circuit named '{}'", circuit_id);
+                return (StatusCode::NOT_FOUND, Json(CircuitNotFoundBody { error, circuit_id })).into_response();
+            }
             AppError::InvalidCircuit(circuit_id) => (
                 StatusCode::BAD_REQUEST,
                 format!("Invalid circuit_id: {}", circuit_id)
             ),
+            AppError::InvalidProofSystem(name) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid proof_system: {} (expected groth16 or marlin)", name)
+            ),
+            AppError::ValidationFailed(message) => (StatusCode::UNPROCESSABLE_ENTITY, message),
+            AppError::MalformedProof(message) => (StatusCode::BAD_REQUEST, format!("Malformed proof: {}", message)),
         };
         (status, message).into_response()
     }
 }
 
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let kind = metrics::ErrorKind(self.kind());
+        let mut response = self.response();
+        response.extensions_mut().insert(kind);
+        response
+    }
+}
+
+/// Json<T>, except that a body which parses but doesn't fit T is a 422 naming the field.
+/// Content-type and syntax errors keep axum's usual rejections.
+struct StrictJson<T>(T);
+
+impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for StrictJson<T> {
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Response> {
+        let Json(value) = Json::<serde_json::Value>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        request::from_value(value)
+            .map(StrictJson)
+            .map_err(|e| AppError::InvalidField(e).into_response())
+    }
+}
+
+// The benchmarks expect proving and verification to succeed. If they don't, the panic is
+// caught here and becomes a 500 naming the circuit, instead of axum's empty 500.
+fn catch_benchmark_panic<T>(
+    circuit_id: &str,
+    operation: &'static str,
+    f: impl FnOnce() -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(AppError::BenchmarkPanicked { circuit_id: circuit_id.to_string(), operation, message })
+    })
+}
+
+// Proving, and setup for a new chain length, can take seconds. Run on the blocking pool,
+// they leave the async workers free for /health and everything else meanwhile.
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, AppError> + Send + 'static,
+) -> Result<T, AppError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| AppError::Internal(format!("Benchmark task failed: {}", e)))?
+}
+
 // ============ Benchmark Types ============
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 struct ZkBenchRequest {
     circuit_id: String,
     iterations: u32,
+    /// Attach hostname, CPU model and core count to the response, unless ZK_DISABLE_MACHINE_INFO is set
+    #[serde(default)]
+    include_machine_info: bool,
+    /// Take the calibrated timer overhead off every sample
+    #[serde(default)]
+    subtract_overhead: bool,
+    /// Steps in the chain circuit, required for it and rejected for the others
+    #[serde(default)]
+    chain_length: Option<u32>,
+    /// Pairing curve to prove and verify over
+    #[serde(default)]
+    curve: Curve,
+    /// groth16 (the default) or marlin. A string rather than an enum, so that an unknown
+    /// system is a 400 like an unknown circuit_id
+    #[serde(default)]
+    proof_system: Option<String>,
+    /// Percentiles to report, each in (0, 1]
+    #[serde(default = "default_percentiles")]
+    percentiles: Vec<f64>,
+    /// Return every iteration's time as well, up to ZK_MAX_RAW_TIMINGS of them
+    #[serde(default)]
+    include_raw_timings: bool,
+    /// Untimed iterations run before the measured loop, capped like iterations
+    #[serde(default)]
+    warmup_iterations: u32,
+    /// Time writing each proof out and reading it back, compressed and uncompressed;
+    /// prove benchmarks only
+    #[serde(default)]
+    serialization_bench: bool,
+    /// Verify benchmarks only: one proof checked every iteration, or a different one each time
+    #[serde(default)]
+    verify_mode: VerifyMode,
+    /// Verify benchmarks only: also verify a corrupted copy of each iteration's statement,
+    /// which must be rejected, and time that apart
+    #[serde(default)]
+    corrupt: Option<Corruption>,
+    /// Prove benchmarks only: rayon threads to prove on, at most available_parallelism;
+    /// defaults to the server's ZK_PROVER_THREADS
+    #[serde(default)]
+    prover_threads: Option<u32>,
+}
+
+fn default_percentiles() -> Vec<f64> {
+    vec![0.5, 0.95, 0.99]
+}
+
+fn validate_percentiles(percentiles: &[f64]) -> Result<(), AppError> {
+    match percentiles.iter().find(|&&p| !(p > 0.0 && p <= 1.0)) {
+        Some(p) => Err(AppError::ValidationFailed(format!("percentiles must be in (0, 1], got {}", p))),
+        None => Ok(()),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum VerifyMode {
+    /// Every iteration checks the same proof of the same statement, which the CPU caches
+    /// can hold on to
+    #[default]
+    Fixed,
+    /// Each iteration checks a different proof of a different statement, up to
+    /// ZK_MAX_FRESH_STATEMENTS of them, all proven before the timed loop
+    Fresh,
+}
+
+/// What a verify benchmark corrupts to exercise the reject path
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum Corruption {
+    /// One of the proof's elements is altered, so it no longer proves its statement
+    Proof,
+    /// The first public input is off by one, so the proof is of another statement
+    PublicInput,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum ProofSystem {
+    /// Per-circuit trusted setup
+    Groth16,
+    /// One universal SRS per curve, with each circuit indexed against it
+    Marlin,
+}
+
+impl ProofSystem {
+    fn name(self) -> &'static str {
+        match self {
+            ProofSystem::Groth16 => "groth16",
+            ProofSystem::Marlin => "marlin",
+        }
+    }
 }
 
-#[derive(Serialize)]
+impl ZkBenchRequest {
+    fn proof_system(&self) -> Result<ProofSystem, AppError> {
+        match self.proof_system.as_deref() {
+            None | Some("groth16") => Ok(ProofSystem::Groth16),
+            Some("marlin") if self.circuit_id == "chain" => {
+                Err(AppError::ValidationFailed("the chain circuit is benchmarked with groth16 only".to_string()))
+            }
+            Some("marlin") => Ok(ProofSystem::Marlin),
+            Some(other) => Err(AppError::InvalidProofSystem(other.to_string())),
+        }
+    }
+}
+
+/// Marlin's setup as a benchmark response reports it
+#[derive(Serialize, ToSchema)]
+struct MarlinSetup {
+    /// Compressed size of the curve's universal SRS, which every circuit shares
+    srs_size_bytes: usize,
+    /// When this request generated the SRS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    srs_setup_ms: Option<f64>,
+    /// When this request indexed the circuit against the SRS, the circuit-specific part of
+    /// Marlin's setup; not included in any timing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index_ms: Option<f64>,
+}
+
+impl From<marlin::Setup> for MarlinSetup {
+    fn from(setup: marlin::Setup) -> Self {
+        MarlinSetup {
+            srs_size_bytes: setup.srs_size_bytes,
+            srs_setup_ms: setup.srs_time.map(|t| t.as_secs_f64() * 1000.0),
+            index_ms: setup.index_time.map(|t| t.as_secs_f64() * 1000.0),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
 struct ZkProveBenchResponse {
     circuit_id: String,
+    curve: Curve,
+    proof_system: ProofSystem,
     iterations: u32,
+    requested_iterations: u32,
+    effective_iterations: u32,
+    iteration_cap: u32,
+    warmup_iterations: u32,
+    /// Per proof, witness generation and proving together; witness_stats and prove_stats
+    /// split them
     avg_prove_ms: f64,
     min_prove_ms: f64,
     max_prove_ms: f64,
     p95_prove_ms: f64,
+    stddev_prove_ms: f64,
+    median_prove_ms: f64,
+    /// Requested percentiles, keyed by the value as sent (e.g. "0.99")
+    percentiles: BTreeMap<String, f64>,
+    /// Per-iteration samples in the order they ran, only when include_raw_timings was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timings_ms: Option<Vec<f64>>,
+    /// Set when timings_ms was cut off at the server's max_raw_timings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timings_truncated: Option<bool>,
+    /// 95% confidence interval for avg_prove_ms
+    ci95_low_prove_ms: f64,
+    ci95_high_prove_ms: f64,
+    /// Compressed, so it depends on the curve: for Groth16, 128 bytes on BN254 and 192 on
+    /// BLS12-381. The same as proof_size_compressed_bytes
     avg_proof_size_bytes: usize,
+    /// Measured on every proof, and the same for all of them
+    proof_size_compressed_bytes: usize,
+    /// Every curve point with both coordinates, so no decompression to read it back
+    proof_size_uncompressed_bytes: usize,
+    /// Only with serialization_bench
+    #[serde(skip_serializing_if = "Option::is_none")]
+    serialization_stats: Option<encoding::SerializationStats>,
     throughput_proofs_sec: f64,
+    /// Proofs per second over the wall-clock time of the whole loop, including the untimed
+    /// work between iterations; throughput_proofs_sec covers only the timed phases
+    wall_clock_proofs_sec: f64,
+    /// Building each iteration's circuit and synthesizing its assignment
+    witness_stats: PhaseStats,
+    /// Proving from that assignment
+    prove_stats: PhaseStats,
+    /// Rayon threads the proofs ran on: prover_threads, or the server's default, clamped to
+    /// available_parallelism
+    prover_threads: usize,
+    available_parallelism: usize,
+    /// Mean single-thread proving time for this circuit, as prove_stats measures it. Taken
+    /// from the first single-thread run, or measured over a few untimed proofs when there
+    /// hasn't been one, and kept for the server's lifetime
+    baseline_prove_ms: f64,
+    /// baseline_prove_ms over prove_stats' mean
+    prover_speedup: f64,
+    /// Anything the server changed about the request, such as clamping prover_threads
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<String>,
+    /// Resident set size in KiB around the benchmark loop; null where the platform can't report it
+    rss_before_kb: Option<u64>,
+    rss_after_kb: Option<u64>,
+    /// Process-lifetime peak RSS in KiB, so it may predate this request
+    peak_rss_kb: Option<u64>,
+    /// How far RSS rose in KiB above where it was when proving started, polled every 50ms
+    /// over the warmup and measured loop; setup isn't counted. The process is shared, so
+    /// concurrent requests' memory shows up here too. Null where RSS can't be read
+    peak_rss_delta_kb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    machine_info: Option<MachineInfo>,
+    /// Cost of an empty timed region on this host, which every sample carries
+    timer_overhead_ns: u64,
+    /// Whether timer_overhead_ns was taken off each sample
+    overhead_subtracted: bool,
+    /// Chain circuit only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chain_length: Option<u32>,
+    #[serde(flatten)]
+    counts: circuits::CircuitCounts,
+    /// The keys this benchmark used: Groth16's proving and verifying keys, or Marlin's
+    /// index prover and verifier keys
+    #[serde(flatten)]
+    key_sizes: circuits::KeySizes,
+    /// Groth16: when this request ran the trusted setup for its circuit (or chain length), or loaded
+    /// its keys from --keys-dir; not included in any prove timing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    setup_ms: Option<f64>,
+    /// Marlin only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    marlin: Option<MarlinSetup>,
     timestamp: u64,
 }
 
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct ZkVerifyBenchResponse {
     circuit_id: String,
+    curve: Curve,
+    proof_system: ProofSystem,
     iterations: u32,
+    requested_iterations: u32,
+    effective_iterations: u32,
+    iteration_cap: u32,
+    warmup_iterations: u32,
+    verify_mode: VerifyMode,
+    /// Distinct statements, each with its own proof, that the iterations cycled through
+    distinct_statements: u32,
     avg_verify_ms: f64,
     min_verify_ms: f64,
     max_verify_ms: f64,
     p95_verify_ms: f64,
+    stddev_verify_ms: f64,
+    median_verify_ms: f64,
+    /// Requested percentiles, keyed by the value as sent (e.g. "0.99")
+    percentiles: BTreeMap<String, f64>,
+    /// Per-iteration samples in the order they ran, only when include_raw_timings was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timings_ms: Option<Vec<f64>>,
+    /// Set when timings_ms was cut off at the server's max_raw_timings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timings_truncated: Option<bool>,
+    /// 95% confidence interval for avg_verify_ms
+    ci95_low_verify_ms: f64,
+    ci95_high_verify_ms: f64,
+    /// Compressed size of the proof verified
+    proof_size_bytes: usize,
     throughput_verifies_sec: f64,
+    /// Verifications per second over the wall-clock time of the whole loop, counting the
+    /// corrupted ones too when there are any
+    wall_clock_verifies_sec: f64,
+    /// Only with corrupt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rejection: Option<RejectionStats>,
+    /// Resident set size in KiB around the benchmark loop; null where the platform can't report it
+    rss_before_kb: Option<u64>,
+    rss_after_kb: Option<u64>,
+    /// Process-lifetime peak RSS in KiB, so it may predate this request
+    peak_rss_kb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    machine_info: Option<MachineInfo>,
+    /// Cost of an empty timed region on this host, which every sample carries
+    timer_overhead_ns: u64,
+    /// Whether timer_overhead_ns was taken off each sample
+    overhead_subtracted: bool,
+    /// Chain circuit only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chain_length: Option<u32>,
+    #[serde(flatten)]
+    counts: circuits::CircuitCounts,
+    /// The keys this benchmark used: Groth16's proving and verifying keys, or Marlin's
+    /// index prover and verifier keys
+    #[serde(flatten)]
+    key_sizes: circuits::KeySizes,
+    /// Groth16: when this request ran the trusted setup for its circuit (or chain length), or loaded
+    /// its keys from --keys-dir; not included in any verify timing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    setup_ms: Option<f64>,
+    /// Marlin only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    marlin: Option<MarlinSetup>,
     timestamp: u64,
 }
 
+/// Both paths of a verify benchmark that also checked corrupted statements, from the
+/// same loop
+#[derive(Serialize, ToSchema)]
+struct RejectionStats {
+    corruption: Corruption,
+    /// The valid statements, which the top-level verify stats also cover
+    accept_stats: PhaseStats,
+    /// The corrupted copies, every one of them rejected
+    reject_stats: PhaseStats,
+}
+
+/// One timed phase of a benchmark iteration
+#[derive(Serialize, ToSchema)]
+struct PhaseStats {
+    avg_ms: f64,
+    min_ms: f64,
+    max_ms: f64,
+    p95_ms: f64,
+    stddev_ms: f64,
+    median_ms: f64,
+    /// 95% confidence interval for avg_ms
+    ci95_low_ms: f64,
+    ci95_high_ms: f64,
+}
+
+impl From<Stats> for PhaseStats {
+    fn from(stats: Stats) -> Self {
+        PhaseStats {
+            avg_ms: stats.avg_ms,
+            min_ms: stats.min_ms,
+            max_ms: stats.max_ms,
+            p95_ms: stats.p95_ms,
+            stddev_ms: stats.stddev_ms,
+            median_ms: stats.median_ms,
+            ci95_low_ms: stats.ci95_low_ms,
+            ci95_high_ms: stats.ci95_high_ms,
+        }
+    }
+}
+
+/// common::stats' summary of microsecond samples, in the milliseconds ZK responses use
 struct Stats {
     avg_ms: f64,
     min_ms: f64,
     max_ms: f64,
     p95_ms: f64,
+    stddev_ms: f64,
+    median_ms: f64,
+    ci95_low_ms: f64,
+    ci95_high_ms: f64,
     throughput: f64,
 }
 
 fn compute_stats(timings_us: &[u128]) -> Stats {
-    if timings_us.is_empty() {
-        return Stats { avg_ms: 0.0, min_ms: 0.0, max_ms: 0.0, p95_ms: 0.0, throughput: 0.0 };
+    let stats = stats::compute_stats(timings_us);
+    Stats {
+        avg_ms: stats.avg / 1000.0,
+        min_ms: stats.min / 1000.0,
+        max_ms: stats.max / 1000.0,
+        p95_ms: stats.p95 / 1000.0,
+        stddev_ms: stats.stddev / 1000.0,
+        median_ms: stats.median / 1000.0,
+        ci95_low_ms: stats.ci95_low / 1000.0,
+        ci95_high_ms: stats.ci95_high / 1000.0,
+        throughput: stats.throughput,
+    }
+}
+
+/// Nearest-rank percentiles of microsecond samples in milliseconds, keyed as requested
+fn compute_percentiles_ms(timings_us: &[u128], percentiles: &[f64]) -> BTreeMap<String, f64> {
+    let mut map = stats::compute_percentiles(timings_us, percentiles);
+    map.values_mut().for_each(|value| *value /= 1000.0);
+    map
+}
+
+/// Up to `cap` samples in milliseconds, in the order they ran, and whether any were left
+/// out; both None unless `requested`, so responses without raw timings keep their shape
+fn raw_timings_ms(timings_us: &[u128], requested: bool, cap: usize) -> (Option<Vec<f64>>, Option<bool>) {
+    if !requested {
+        return (None, None);
     }
+    let raw = timings_us.iter().take(cap).map(|&t| t as f64 / 1000.0).collect();
+    (Some(raw), Some(timings_us.len() > cap))
+}
 
-    let sum: u128 = timings_us.iter().sum();
-    let avg_us = sum as f64 / timings_us.len() as f64;
-    let min_us = *timings_us.iter().min().unwrap() as f64;
-    let max_us = *timings_us.iter().max().unwrap() as f64;
-    let p95_us = compute_percentile(timings_us, 0.95);
+fn ops_per_sec(ops: usize, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() { 0.0 } else { ops as f64 / elapsed.as_secs_f64() }
+}
 
-    // Convert to milliseconds 
-    let avg_ms = avg_us / 1000.0;
-    let throughput = if avg_ms > 0.0 { 1000.0 / avg_ms } else { 0.0 };
 
-    Stats {
-        avg_ms,
-        min_ms: min_us / 1000.0,
-        max_ms: max_us / 1000.0,
-        p95_ms: p95_us / 1000.0,
-        throughput,
+/// Per-iteration prove timings in microseconds, split into synthesizing the witness and
+/// proving from it
+struct ProveTimings {
+    witness: Vec<u128>,
+    prove: Vec<u128>,
+}
+
+impl ProveTimings {
+    fn with_capacity(iterations: u32) -> Self {
+        ProveTimings { witness: Vec::with_capacity(iterations as usize), prove: Vec::with_capacity(iterations as usize) }
+    }
+
+    fn push(&mut self, witness: Duration, prove: Duration) {
+        self.witness.push(witness.as_micros());
+        self.prove.push(prove.as_micros());
+    }
+
+    /// Each iteration's witness and prove time together, what a single Groth16::prove call costs
+    fn totals(&self) -> Vec<u128> {
+        self.witness.iter().zip(&self.prove).map(|(w, p)| w + p).collect()
     }
 }
 
+/// A Groth16 proof from a synthesized assignment: Groth16::prove, after its synthesis step
+fn prove_assignment<E: Engine>(pk: &ProvingKey<E>, matrices: &ConstraintMatrices<E::ScalarField>, assignment: &[E::ScalarField]) -> Proof<E> {
+    let (r, s) = (E::ScalarField::rand(&mut OsRng), E::ScalarField::rand(&mut OsRng));
+    Groth16::<E>::create_proof_with_reduction_and_matrices(
+        pk,
+        r,
+        s,
+        matrices,
+        matrices.num_instance_variables,
+        matrices.num_constraints,
+        assignment,
+    )
+    .expect("Proving failed")
+}
 
-fn compute_percentile(timings: &[u128], percentile: f64) -> f64 {
-    let mut sorted = timings.to_vec();
-    sorted.sort_unstable();
-    let idx = ((sorted.len() as f64) * percentile) as usize;
-    
-    sorted[idx.min(sorted.len() - 1)] as f64
+/// How long a benchmark loop runs, as the request asked for it after capping
+#[derive(Clone, Copy)]
+struct Budget {
+    iterations: u32,
+    /// Untimed iterations before the measured ones
+    warmup: u32,
+    /// Taken off every sample
+    overhead: Duration,
 }
 
+/// Times `iterations` proofs, each iteration's witness synthesized and timed apart from
+/// proving it, after `warmup` untimed ones. Each proof's encoding is measured, and timed
+/// if `serialization` is set. Any registered circuit, or chain length, runs through here
+fn bench_prove<E: Engine>(
+    pk: &ProvingKey<E>,
+    matrices: &ConstraintMatrices<E::ScalarField>,
+    assignments: &circuits::Assignments<E::ScalarField>,
+    budget: Budget,
+    serialization: bool,
+) -> (ProveTimings, ProofEncoding, Duration) {
+    let Budget { iterations, warmup, overhead } = budget;
+    for i in 0..warmup {
+        prove_assignment(pk, matrices, &assignments(i));
+    }
 
-fn bench_prove_multiply(pk: &ProvingKey<Bn254>, iterations: u32) -> (Vec<u128>, usize) {
-    let mut timings = Vec::with_capacity(iterations as usize);
-    let mut proof_size = 0;
+    let mut timings = ProveTimings::with_capacity(iterations);
+    let mut encoding = ProofEncoding::new(serialization);
+    let loop_start = Instant::now();
 
     for i in 0..iterations {
-        
-        let a = Fr::from((i+3) as u64);
-        let b = Fr::from((i+7) as u64);
-        let c = a*b;
+        let start = Instant::now();
+        let assignment = assignments(i);
+        let witness_time = stats::subtract_overhead(start.elapsed(), overhead);
+
+        let start = Instant::now();
+        let proof = prove_assignment(pk, matrices, &assignment);
+        timings.push(witness_time, stats::subtract_overhead(start.elapsed(), overhead));
+        encoding.record(&proof, overhead);
+    }
+    (timings, encoding, loop_start.elapsed())
+}
+
+/// A statement a verify benchmark checks, proven before the timed loop, with a corrupted
+/// copy when the request asked for the reject path too
+struct Statement<F, P> {
+    public_inputs: Vec<F>,
+    proof: P,
+    corrupted: Option<Corrupted<F, P>>,
+}
+
+struct Corrupted<F, P> {
+    corruption: Corruption,
+    public_inputs: Vec<F>,
+    proof: P,
+}
+
+/// Per-iteration verify timings in microseconds: the valid statements', and the corrupted
+/// copies' when there are any
+struct VerifyTimings {
+    accept: Vec<u128>,
+    reject: Option<Vec<u128>>,
+}
+
+/// A corrupted statement that verified, which fails the benchmark
+#[derive(Debug)]
+struct InvalidProofAccepted {
+    corruption: Corruption,
+    iteration: u32,
+}
 
-        let circuit = MultiplyCircuit { a: Some(a), b: Some(b), c: Some(c) };
+impl InvalidProofAccepted {
+    fn into_error(self, circuit_id: &str) -> AppError {
+        AppError::InvalidProofAccepted { circuit_id: circuit_id.to_string(), corruption: self.corruption, iteration: self.iteration }
+    }
+}
 
+/// Times `iterations` verifications, cycling through `statements`. Each iteration verifies
+/// its statement, then its corrupted copy if it has one, timing the two apart. `verify`
+/// says whether a proof was accepted; an error verifying counts as a rejection
+fn time_verifications<F, P>(
+    statements: &[Statement<F, P>],
+    iterations: u32,
+    overhead: Duration,
+    verify: impl Fn(&[F], &P) -> bool,
+) -> Result<(VerifyTimings, Duration), InvalidProofAccepted> {
+    let corrupted = statements.iter().any(|s| s.corrupted.is_some());
+    let mut timings = VerifyTimings {
+        accept: Vec::with_capacity(iterations as usize),
+        reject: corrupted.then(|| Vec::with_capacity(iterations as usize)),
+    };
+    let loop_start = Instant::now();
+
+    for i in 0..iterations {
+        let statement = &statements[i as usize % statements.len()];
         let start = Instant::now();
-        let proof = Groth16::<Bn254>::prove(pk, circuit, &mut OsRng)
-            .expect("Proving failed");
-        timings.push(start.elapsed().as_micros());
+        let valid = verify(&statement.public_inputs, &statement.proof);
+        let accept_time = stats::subtract_overhead(start.elapsed(), overhead);
+        assert!(valid, "Verification failed");
+        timings.accept.push(accept_time.as_micros());
 
-        if proof_size == 0 {
-            proof_size = proof.serialized_size(ark_serialize::Compress::Yes);
+        if let (Some(corrupted), Some(reject)) = (&statement.corrupted, &mut timings.reject) {
+            let start = Instant::now();
+            let accepted = verify(&corrupted.public_inputs, &corrupted.proof);
+            let reject_time = stats::subtract_overhead(start.elapsed(), overhead);
+            if accepted {
+                return Err(InvalidProofAccepted { corruption: corrupted.corruption, iteration: i });
+            }
+            reject.push(reject_time.as_micros());
         }
     }
-    (timings, proof_size)
+    Ok((timings, loop_start.elapsed()))
 }
 
-fn bench_verify_multiply(pk: &ProvingKey<Bn254>, vk: &VerifyingKey<Bn254>, iterations: u32) -> Vec<u128> {
-    // Generate one valid proof to verify repeatedly
-    let a = Fr::from(3u64);
-    let b = Fr::from(7u64);
-    let c = a * b;
+/// A copy of a Groth16 statement that its proof doesn't prove
+fn corrupt_groth16<E: Engine>(corruption: Corruption, public_inputs: &[E::ScalarField], proof: &Proof<E>) -> Corrupted<E::ScalarField, Proof<E>> {
+    let (mut public_inputs, mut proof) = (public_inputs.to_vec(), proof.clone());
+    match corruption {
+        Corruption::Proof => proof.a = (proof.a + E::G1Affine::generator()).into_affine(),
+        Corruption::PublicInput => *public_inputs.first_mut().expect("the circuit has no public inputs to corrupt") += E::ScalarField::ONE,
+    }
+    Corrupted { corruption, public_inputs, proof }
+}
 
-    let circuit = MultiplyCircuit { a: Some(a), b: Some(b), c: Some(c) };
-    let proof = Groth16::<Bn254>::prove(pk, circuit, &mut OsRng).expect("Proving failed");
+/// Times `iterations` verifications, after `warmup` untimed ones, cycling through proofs
+/// of the first `statements` iterations' statements, and through corrupted copies of them
+/// too if `corrupt` is set. The proofs are made before the loop, so only verifying is timed
+fn bench_verify<E: Engine>(
+    pk: &ProvingKey<E>,
+    vk: &VerifyingKey<E>,
+    matrices: &ConstraintMatrices<E::ScalarField>,
+    assignments: &circuits::Assignments<E::ScalarField>,
+    budget: Budget,
+    statements: u32,
+    corrupt: Option<Corruption>,
+) -> Result<(VerifyTimings, Duration), InvalidProofAccepted> {
+    let Budget { iterations, warmup, overhead } = budget;
+    let statements: Vec<_> = (0..statements)
+        .map(|i| {
+            let assignment = assignments(i);
+            let proof = prove_assignment(pk, matrices, &assignment);
+            // The constant one comes first, then the public inputs
+            let public_inputs = assignment[1..matrices.num_instance_variables].to_vec();
+            let corrupted = corrupt.map(|corruption| corrupt_groth16(corruption, &public_inputs, &proof));
+            Statement { public_inputs, proof, corrupted }
+        })
+        .collect();
+
+    // Each warmup verification prepares the key afresh, so whatever that does once per
+    // process is paid before the first measured sample
+    for i in 0..warmup {
+        let statement = &statements[i as usize % statements.len()];
+        let pvk = prepare_verifying_key(vk);
+        let valid = Groth16::<E>::verify_with_processed_vk(&pvk, &statement.public_inputs, &statement.proof).expect("Verification failed");
+        assert!(valid);
+    }
     let pvk = prepare_verifying_key(vk);
 
-    let public_inputs = vec![c];
-
-    (0..iterations)
-        .map(|_| {
-            let start = Instant::now();
-            let valid = Groth16::<Bn254>::verify_with_processed_vk(&pvk, &public_inputs, &proof)
-                .expect("Verification failed");
-            assert!(valid);
-            start.elapsed().as_micros()
-        })
-        .collect()
+    time_verifications(&statements, iterations, overhead, |public_inputs, proof| {
+        Groth16::<E>::verify_with_processed_vk(&pvk, public_inputs, proof).unwrap_or(false)
+    })
 }
 
-fn bench_prove_cube_root(pk: &ProvingKey<Bn254>, iterations: u32) -> (Vec<u128>, usize) {
-    let mut timings = Vec::with_capacity(iterations as usize);
-    let mut proof_size = 0;
-
-    for i in 0..iterations {
-        let x = Fr::from((i + 5) as u64);
-        let y = x * x * x; // x^3
+/// A benchmark's measurements, with what the response reports about the keys it used
+struct BenchRun<T> {
+    result: T,
+    chain_length: Option<u32>,
+    counts: circuits::CircuitCounts,
+    key_sizes: circuits::KeySizes,
+    /// Set when this request ran Groth16 setup for its circuit (or chain length) on the curve
+    setup_time: Option<Duration>,
+    marlin: Option<marlin::Setup>,
+    /// Prove benchmarks only: the resident set size over the benchmark loop, where the
+    /// platform can sample it
+    peak: Option<memory::PeakSample>,
+}
 
-        let circuit = CubeRootCircuit { x: Some(x), y: Some(y) };
+impl<E: Engine> CurveKeys<E> {
+    /// Fixed-circuit keys ahead of the run, or None for the chain circuit, whose keys
+    /// are set up on the blocking pool with the benchmark
+    async fn fixed_keys(&self, circuit_id: &str, chain_length: Option<u32>) -> Result<(Option<Arc<registry::CircuitKeys<E>>>, Option<Duration>), AppError> {
+        if chain_length.is_some() {
+            return Ok((None, None));
+        }
+        let (keys, setup_time) = self.circuits.get(circuit_id).await?;
+        Ok((Some(keys), setup_time))
+    }
+}
 
-        let start = Instant::now();
-        let proof = Groth16::<Bn254>::prove(pk, circuit, &mut OsRng)
-            .expect("Proving failed");
-        timings.push(start.elapsed().as_micros());
+impl<T> BenchRun<T> {
+    fn new<E: Engine>(result: T, fixed: Option<&registry::CircuitKeys<E>>, chain: Option<ChainRun<E>>, setup_time: Option<Duration>) -> Self {
+        let (counts, key_sizes) = match (fixed, &chain) {
+            (Some(keys), _) => (keys.sizes.counts(), keys.sizes.keys),
+            (None, Some(run)) => (run.keys.counts, run.keys.key_sizes),
+            // fixed_keys only skips the registry for the chain circuit
+            (None, None) => unreachable!("a benchmark runs on a fixed circuit or a chain"),
+        };
+        BenchRun {
+            result,
+            chain_length: chain.as_ref().map(|run| run.length),
+            counts,
+            key_sizes,
+            setup_time: setup_time.or(chain.and_then(|run| run.setup_time)),
+            marlin: None,
+            peak: None,
+        }
+    }
 
-        if proof_size == 0 {
-            proof_size = proof.serialized_size(ark_serialize::Compress::Yes);
+    fn marlin<E: Engine>(result: T, index: &marlin::MarlinIndex<E>, setup: marlin::Setup) -> Self {
+        BenchRun {
+            result,
+            chain_length: None,
+            counts: index.counts(),
+            key_sizes: index.key_sizes(),
+            setup_time: None,
+            marlin: Some(setup),
+            peak: None,
         }
     }
+}
 
-    (timings, proof_size)
+// Groth16 proofs are the same size for every circuit on a curve
+fn groth16_proof_size<E: Engine>() -> usize {
+    ark_groth16::Proof::<E>::default().serialized_size(ark_serialize::Compress::Yes)
 }
 
-fn bench_verify_cube_root(pk: &ProvingKey<Bn254>, vk: &VerifyingKey<Bn254>, iterations: u32) -> Vec<u128> {
-    let x = Fr::from(5u64);
-    let y = x * x * x;
+async fn run_prove_bench<E: Engine>(
+    keys: &CurveKeys<E>,
+    circuit_id: String,
+    chain_length: Option<u32>,
+    budget: Budget,
+    serialization: bool,
+    threads: usize,
+) -> Result<BenchRun<(ProveTimings, ProofEncoding, Duration)>, AppError> {
+    let (fixed, setup_time) = keys.fixed_keys(&circuit_id, chain_length).await?;
+    let job = keys.clone();
+    run_blocking(move || {
+        catch_benchmark_panic(&circuit_id, "prove", || {
+            let chain = job.chain_run(chain_length);
+            let pool = parallelism::pool(threads);
+            // Sampled once the keys are ready, so setup's memory isn't counted
+            let (result, peak) = memory::sample_peak(memory::PEAK_SAMPLE_INTERVAL, || {
+                pool.install(|| match (&fixed, &chain) {
+                    (Some(keys), _) => Some(bench_prove(&keys.pk, &keys.matrices, &keys.assignments, budget, serialization)),
+                    (_, Some(run)) => Some(bench_prove(&run.keys.pk, &run.keys.matrices, &run.keys.assignments, budget, serialization)),
+                    _ => None,
+                })
+            });
+            let result = result.ok_or_else(|| AppError::InvalidCircuit(circuit_id.clone()))?;
+            Ok(BenchRun { peak, ..BenchRun::new(result, fixed.as_deref(), chain, setup_time) })
+        })
+    })
+    .await
+}
 
-    let circuit = CubeRootCircuit { x: Some(x), y: Some(y) };
-    let proof = Groth16::<Bn254>::prove(pk, circuit, &mut OsRng).expect("Proving failed");
-    let pvk = prepare_verifying_key(vk);
+async fn run_verify_bench<E: Engine>(
+    keys: &CurveKeys<E>,
+    circuit_id: String,
+    chain_length: Option<u32>,
+    budget: Budget,
+    statements: u32,
+    corrupt: Option<Corruption>,
+) -> Result<BenchRun<(VerifyTimings, usize, Duration)>, AppError> {
+    let (fixed, setup_time) = keys.fixed_keys(&circuit_id, chain_length).await?;
+    let job = keys.clone();
+    run_blocking(move || {
+        catch_benchmark_panic(&circuit_id, "verify", || {
+            let chain = job.chain_run(chain_length);
+            let result = match (&fixed, &chain) {
+                (Some(keys), _) => bench_verify(&keys.pk, &keys.vk, &keys.matrices, &keys.assignments, budget, statements, corrupt),
+                (_, Some(run)) => bench_verify(&run.keys.pk, &run.keys.vk, &run.keys.matrices, &run.keys.assignments, budget, statements, corrupt),
+                _ => return Err(AppError::InvalidCircuit(circuit_id.clone())),
+            };
+            let (timings, loop_time) = result.map_err(|accepted| accepted.into_error(&circuit_id))?;
+            Ok(BenchRun::new((timings, groth16_proof_size::<E>(), loop_time), fixed.as_deref(), chain, setup_time))
+        })
+    })
+    .await
+}
 
-    let public_inputs = vec![y];
+// The Marlin benchmarks prove the same statements as the Groth16 ones above
 
-    (0..iterations)
-        .map(|_| {
-            let start = Instant::now();
-            let valid = Groth16::<Bn254>::verify_with_processed_vk(&pvk, &public_inputs, &proof)
-                .expect("Verification failed");
-            assert!(valid);
-            start.elapsed().as_micros()
+async fn run_marlin_prove_bench<E: Engine>(
+    keys: &CurveKeys<E>,
+    circuit_id: String,
+    budget: Budget,
+    serialization: bool,
+    threads: usize,
+) -> Result<BenchRun<(ProveTimings, ProofEncoding, Duration)>, AppError> {
+    let marlin = keys.marlin.clone();
+    run_blocking(move || {
+        catch_benchmark_panic(&circuit_id, "prove", || {
+            let (index, setup) = marlin.get(&circuit_id).ok_or_else(|| AppError::InvalidCircuit(circuit_id.clone()))?;
+            let pool = parallelism::pool(threads);
+            let (result, peak) = memory::sample_peak(memory::PEAK_SAMPLE_INTERVAL, || pool.install(|| marlin::bench_prove(&index, budget, serialization)));
+            Ok(BenchRun { peak, ..BenchRun::marlin(result, &index, setup) })
         })
-        .collect()
+    })
+    .await
+}
+
+async fn run_marlin_verify_bench<E: Engine>(
+    keys: &CurveKeys<E>,
+    circuit_id: String,
+    budget: Budget,
+    statements: u32,
+    corrupt: Option<Corruption>,
+) -> Result<BenchRun<(VerifyTimings, usize, Duration)>, AppError> {
+    let marlin = keys.marlin.clone();
+    run_blocking(move || {
+        catch_benchmark_panic(&circuit_id, "verify", || {
+            let (index, setup) = marlin.get(&circuit_id).ok_or_else(|| AppError::InvalidCircuit(circuit_id.clone()))?;
+            let result = marlin::bench_verify(&index, budget, statements, corrupt).map_err(|accepted| accepted.into_error(&circuit_id))?;
+            Ok(BenchRun::marlin(result, &index, setup))
+        })
+    })
+    .await
 }
 
+/// A prove benchmark of `circuit_id` on `threads` rayon threads, with whichever proof
+/// system and curve were asked for
+#[allow(clippy::too_many_arguments)]
+async fn run_prove(
+    state: &AppState,
+    proof_system: ProofSystem,
+    curve: Curve,
+    circuit_id: String,
+    chain_length: Option<u32>,
+    budget: Budget,
+    serialization: bool,
+    threads: usize,
+) -> Result<BenchRun<(ProveTimings, ProofEncoding, Duration)>, AppError> {
+    match (proof_system, curve) {
+        (ProofSystem::Groth16, Curve::Bn254) => run_prove_bench(&state.bn254, circuit_id, chain_length, budget, serialization, threads).await,
+        (ProofSystem::Groth16, Curve::Bls12_381) => run_prove_bench(&state.bls12_381, circuit_id, chain_length, budget, serialization, threads).await,
+        (ProofSystem::Marlin, Curve::Bn254) => run_marlin_prove_bench(&state.bn254, circuit_id, budget, serialization, threads).await,
+        (ProofSystem::Marlin, Curve::Bls12_381) => run_marlin_prove_bench(&state.bls12_381, circuit_id, budget, serialization, threads).await,
+    }
+}
+
+// Over-cap requests are capped rather than rejected; responses report both counts
+fn cap_iterations(requested: u32, cap: u32) -> Result<u32, AppError> {
+    if requested == 0 {
+        return Err(AppError::ValidationFailed(format!("iterations must be between 1 and {}, got 0", cap)));
+    }
+    Ok(requested.min(cap))
+}
+
+#[utoipa::path(
+    post,
+    path = "/zk_prove_bench",
+    request_body = ZkBenchRequest,
+    responses(
+        (status = 200, description = "Prove benchmark statistics", body = ZkProveBenchResponse),
+        (status = 400, description = "InvalidCircuit: unknown circuit_id; or InvalidProofSystem: proof_system other than groth16 or marlin", body = String, content_type = "text/plain"),
+        (status = 422, description = "ValidationFailed: iterations, chain_length, percentiles or prover_threads out of range, chain_length missing or misplaced, or marlin asked of the chain circuit, as text/plain; or InvalidField: an unknown, missing or wrongly-typed field, as a JSON FieldError", body = String, content_type = "text/plain"),
+        (status = 500, description = "BenchmarkPanicked: proving or verification panicked, as a PanicBody; or Internal: the benchmark task failed otherwise, as an InternalErrorBody", body = PanicBody),
+    )
+)]
 async fn zk_prove_bench(
-    axum::extract::State(keys): axum::extract::State<std::sync::Arc<CircuitKeys>>,
-    Json(req): Json<ZkBenchRequest>,
+    axum::extract::State(state): axum::extract::State<AppState>,
+    StrictJson(req): StrictJson<ZkBenchRequest>,
 ) -> Result<Json<ZkProveBenchResponse>, AppError> {
-    let iterations = req.iterations.clamp(1, 1000); // ZK is slower, lower cap
+    let (metrics, circuit) = (state.metrics.clone(), state.circuit_label(&req.circuit_id));
+    prove_bench(state, req).await.inspect_err(|e| metrics.bench_failed("prove", &circuit, e.kind()))
+}
 
-    let (timings, proof_size) = match req.circuit_id.as_str() {
-        "multiply" => bench_prove_multiply(&keys.multiply_pk, iterations),
-        "cube_root" => bench_prove_cube_root(&keys.cube_root_pk, iterations),
-        _ => return Err(AppError::InvalidCircuit(req.circuit_id)),
+async fn prove_bench(state: AppState, req: ZkBenchRequest) -> Result<Json<ZkProveBenchResponse>, AppError> {
+    let _in_flight = state.readiness.track();
+    let iterations = cap_iterations(req.iterations, state.max_prove_iterations)?;
+    let warmup = req.warmup_iterations.min(state.max_prove_iterations);
+    let overhead = if req.subtract_overhead { state.timer_overhead } else { Duration::ZERO };
+    let budget = Budget { iterations, warmup, overhead };
+    let proof_system = req.proof_system()?;
+    let chain_length = state.chain_length(&req)?;
+    validate_percentiles(&req.percentiles)?;
+    let available = parallelism::available();
+    let threads = parallelism::ProverThreads::resolve(req.prover_threads, state.prover_threads, available)?;
+
+    let rss_before_kb = memory::rss_kb();
+    let run = run_prove(&state, proof_system, req.curve, req.circuit_id.clone(), chain_length, budget, req.serialization_bench, threads.threads).await?;
+    let (phases, encoding, loop_time) = run.result;
+    let timings = phases.totals();
+    let prove_stats = compute_stats(&phases.prove);
+    state.metrics.observe_benchmark("prove", &req.circuit_id, req.curve, proof_system, &timings);
+
+    // Measured outside the response's timings and memory readings
+    let rss_after_kb = memory::rss_kb();
+    let baseline_key = parallelism::BaselineKey { proof_system, curve: req.curve, circuit_id: req.circuit_id.clone(), chain_length };
+    let baseline_prove_ms = match state.prover_baselines.get(&baseline_key) {
+        Some(baseline) => baseline,
+        None if threads.threads == 1 => state.prover_baselines.record(baseline_key, prove_stats.avg_ms),
+        None => {
+            let budget = Budget { iterations: iterations.min(parallelism::BASELINE_ITERATIONS), ..budget };
+            let baseline = run_prove(&state, proof_system, req.curve, req.circuit_id.clone(), chain_length, budget, false, 1).await?;
+            state.prover_baselines.record(baseline_key, compute_stats(&baseline.result.0.prove).avg_ms)
+        }
     };
 
     let stats = compute_stats(&timings);
+    let (timings_ms, timings_truncated) = raw_timings_ms(&timings, req.include_raw_timings, state.max_raw_timings);
 
     Ok(Json(ZkProveBenchResponse {
         circuit_id: req.circuit_id,
+        curve: req.curve,
+        proof_system,
         iterations,
+        requested_iterations: req.iterations,
+        effective_iterations: iterations,
+        iteration_cap: state.max_prove_iterations,
+        warmup_iterations: warmup,
         avg_prove_ms: stats.avg_ms,
         min_prove_ms: stats.min_ms,
         max_prove_ms: stats.max_ms,
         p95_prove_ms: stats.p95_ms,
-        avg_proof_size_bytes: proof_size,
+        stddev_prove_ms: stats.stddev_ms,
+        median_prove_ms: stats.median_ms,
+        percentiles: compute_percentiles_ms(&timings, &req.percentiles),
+        timings_ms,
+        timings_truncated,
+        ci95_low_prove_ms: stats.ci95_low_ms,
+        ci95_high_prove_ms: stats.ci95_high_ms,
+        avg_proof_size_bytes: encoding.compressed_size(),
+        proof_size_compressed_bytes: encoding.compressed_size(),
+        proof_size_uncompressed_bytes: encoding.uncompressed_size(),
+        serialization_stats: encoding.timings.as_ref().map(encoding::SerializationStats::from),
         throughput_proofs_sec: stats.throughput,
+        wall_clock_proofs_sec: ops_per_sec(timings.len(), loop_time),
+        witness_stats: compute_stats(&phases.witness).into(),
+        prover_threads: threads.threads,
+        available_parallelism: available,
+        baseline_prove_ms,
+        prover_speedup: baseline_prove_ms / prove_stats.avg_ms,
+        notes: threads.note.into_iter().collect(),
+        prove_stats: prove_stats.into(),
+        rss_before_kb,
+        rss_after_kb,
+        peak_rss_kb: memory::peak_rss_kb(),
+        peak_rss_delta_kb: run.peak.map(|peak| peak.delta_kb()),
+        machine_info: state.machine_info(req.include_machine_info),
+        timer_overhead_ns: state.timer_overhead.as_nanos() as u64,
+        overhead_subtracted: req.subtract_overhead,
+        chain_length: run.chain_length,
+        counts: run.counts,
+        key_sizes: run.key_sizes,
+        setup_ms: run.setup_time.map(|t| t.as_secs_f64() * 1000.0),
+        marlin: run.marlin.map(MarlinSetup::from),
         timestamp: current_timestamp(),
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/zk_verify_bench",
+    request_body = ZkBenchRequest,
+    responses(
+        (status = 200, description = "Verify benchmark statistics", body = ZkVerifyBenchResponse),
+        (status = 400, description = "InvalidCircuit: unknown circuit_id; or InvalidProofSystem: proof_system other than groth16 or marlin", body = String, content_type = "text/plain"),
+        (status = 422, description = "ValidationFailed: iterations, chain_length or percentiles out of range, chain_length missing or misplaced, or marlin asked of the chain circuit, as text/plain; or InvalidField: an unknown, missing or wrongly-typed field, as a JSON FieldError", body = String, content_type = "text/plain"),
+        (status = 500, description = "BenchmarkPanicked: proving or verification panicked, as a PanicBody; or Internal: the benchmark task failed otherwise, as an InternalErrorBody; or InvalidProofAccepted: a corrupted statement verified, as an InvalidProofAcceptedBody", body = PanicBody),
+    )
+)]
 async fn zk_verify_bench(
-    axum::extract::State(keys): axum::extract::State<std::sync::Arc<CircuitKeys>>,
-    Json(req): Json<ZkBenchRequest>,
+    axum::extract::State(state): axum::extract::State<AppState>,
+    StrictJson(req): StrictJson<ZkBenchRequest>,
 ) -> Result<Json<ZkVerifyBenchResponse>, AppError> {
-    let iterations = req.iterations.clamp(1, 5000);
+    let (metrics, circuit) = (state.metrics.clone(), state.circuit_label(&req.circuit_id));
+    verify_bench(state, req).await.inspect_err(|e| metrics.bench_failed("verify", &circuit, e.kind()))
+}
 
-    let timings = match req.circuit_id.as_str() {
-        "multiply" => bench_verify_multiply(&keys.multiply_pk, &keys.multiply_vk, iterations),
-        "cube_root" => bench_verify_cube_root(&keys.cube_root_pk, &keys.cube_root_vk, iterations),
-        _ => return Err(AppError::InvalidCircuit(req.circuit_id)),
+async fn verify_bench(state: AppState, req: ZkBenchRequest) -> Result<Json<ZkVerifyBenchResponse>, AppError> {
+    let _in_flight = state.readiness.track();
+    let iterations = cap_iterations(req.iterations, state.max_verify_iterations)?;
+    let warmup = req.warmup_iterations.min(state.max_verify_iterations);
+    let overhead = if req.subtract_overhead { state.timer_overhead } else { Duration::ZERO };
+    let budget = Budget { iterations, warmup, overhead };
+    let statements = match req.verify_mode {
+        VerifyMode::Fixed => 1,
+        VerifyMode::Fresh => iterations.min(state.max_fresh_statements),
     };
-
+    let proof_system = req.proof_system()?;
+    let chain_length = state.chain_length(&req)?;
+    validate_percentiles(&req.percentiles)?;
+
+    let rss_before_kb = memory::rss_kb();
+    let circuit_id = req.circuit_id.clone();
+    let run = match (proof_system, req.curve) {
+        (ProofSystem::Groth16, Curve::Bn254) => run_verify_bench(&state.bn254, circuit_id, chain_length, budget, statements, req.corrupt).await?,
+        (ProofSystem::Groth16, Curve::Bls12_381) => run_verify_bench(&state.bls12_381, circuit_id, chain_length, budget, statements, req.corrupt).await?,
+        (ProofSystem::Marlin, Curve::Bn254) => run_marlin_verify_bench(&state.bn254, circuit_id, budget, statements, req.corrupt).await?,
+        (ProofSystem::Marlin, Curve::Bls12_381) => run_marlin_verify_bench(&state.bls12_381, circuit_id, budget, statements, req.corrupt).await?,
+    };
+    let (verify_timings, proof_size, loop_time) = run.result;
+    let timings = verify_timings.accept;
+    state.metrics.observe_benchmark("verify", &req.circuit_id, req.curve, proof_system, &timings);
+    let rejection = verify_timings.reject.map(|reject| RejectionStats {
+        corruption: req.corrupt.expect("only a corrupt request has reject timings"),
+        accept_stats: compute_stats(&timings).into(),
+        reject_stats: compute_stats(&reject).into(),
+    });
+    let verifications = timings.len() + rejection.as_ref().map_or(0, |_| timings.len());
+
+    let rss_after_kb = memory::rss_kb();
     let stats = compute_stats(&timings);
+    let (timings_ms, timings_truncated) = raw_timings_ms(&timings, req.include_raw_timings, state.max_raw_timings);
 
     Ok(Json(ZkVerifyBenchResponse {
         circuit_id: req.circuit_id,
+        curve: req.curve,
+        proof_system,
         iterations,
+        requested_iterations: req.iterations,
+        effective_iterations: iterations,
+        iteration_cap: state.max_verify_iterations,
+        warmup_iterations: warmup,
+        verify_mode: req.verify_mode,
+        distinct_statements: statements,
         avg_verify_ms: stats.avg_ms,
         min_verify_ms: stats.min_ms,
         max_verify_ms: stats.max_ms,
         p95_verify_ms: stats.p95_ms,
+        stddev_verify_ms: stats.stddev_ms,
+        median_verify_ms: stats.median_ms,
+        percentiles: compute_percentiles_ms(&timings, &req.percentiles),
+        timings_ms,
+        timings_truncated,
+        ci95_low_verify_ms: stats.ci95_low_ms,
+        ci95_high_verify_ms: stats.ci95_high_ms,
+        proof_size_bytes: proof_size,
         throughput_verifies_sec: stats.throughput,
+        wall_clock_verifies_sec: ops_per_sec(verifications, loop_time),
+        rejection,
+        rss_before_kb,
+        rss_after_kb,
+        peak_rss_kb: memory::peak_rss_kb(),
+        machine_info: state.machine_info(req.include_machine_info),
+        timer_overhead_ns: state.timer_overhead.as_nanos() as u64,
+        overhead_subtracted: req.subtract_overhead,
+        chain_length: run.chain_length,
+        counts: run.counts,
+        key_sizes: run.key_sizes,
+        setup_ms: run.setup_time.map(|t| t.as_secs_f64() * 1000.0),
+        marlin: run.marlin.map(MarlinSetup::from),
         timestamp: current_timestamp(),
     }))
 }
 
+// ============ Server Parameters ============
+
+#[derive(Serialize, ToSchema)]
+struct ParamsResponse {
+    max_prove_iterations: u32,
+    max_verify_iterations: u32,
+    /// Longest chain circuit a benchmark may ask for
+    max_chain_length: u32,
+    /// Most samples include_raw_timings returns
+    max_raw_timings: usize,
+    /// Most distinct statements a fresh verify_mode benchmark proves
+    max_fresh_statements: u32,
+    /// Rayon threads a prove benchmark runs on without prover_threads, and the most it may ask for
+    prover_threads: usize,
+    available_parallelism: usize,
+    /// Body size, handler timeout and connection limits the server was started with
+    limits: Limits,
+    /// HTTP/1.1 keep-alive and HTTP/2 settings
+    http: HttpTuning,
+}
+
+#[utoipa::path(get, path = "/params", responses((status = 200, description = "Iteration caps and request limits", body = ParamsResponse)))]
+async fn params(axum::extract::State(state): axum::extract::State<AppState>) -> Json<ParamsResponse> {
+    Json(ParamsResponse {
+        max_prove_iterations: state.max_prove_iterations,
+        max_verify_iterations: state.max_verify_iterations,
+        max_chain_length: state.max_chain_length,
+        max_raw_timings: state.max_raw_timings,
+        max_fresh_statements: state.max_fresh_statements,
+        prover_threads: state.prover_threads,
+        available_parallelism: parallelism::available(),
+        limits: state.limits,
+        http: state.http,
+    })
+}
+
+/// Every route with its middleware. Auth, CORS and compression come from the environment
+fn app(state: AppState) -> Router {
+    // Benchmarks are bounded by their iteration caps instead of the handler timeout
+    let benchmarks = Router::new()
+        .route("/zk_prove_bench", post(zk_prove_bench))
+        .route("/zk_verify_bench", post(zk_verify_bench));
+
+    let router = Router::new()
+        .route("/health", get(health::health))
+        .route("/live", get(health::live))
+        .route("/ready", get(health::ready))
+        .route("/params", get(params))
+        .route("/prove", post(proofs::prove))
+        .route("/verify", post(proofs::verify))
+        .route("/circuits", get(circuits::circuits))
+        .route("/circuits/{circuit_id}/vk", get(proofs::verifying_key))
+        .route("/metrics", get(metrics::metrics))
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", openapi::ApiDoc::openapi()));
+    let router = state
+        .limits
+        .limit_body(state.limits.time_out(router).merge(benchmarks))
+        .layer(axum::middleware::from_fn_with_state(state.metrics.service.clone(), common::metrics::track));
+
+    // Bearer token required on everything but the /health, /live and /ready probes when set
+    let router = match state.config.auth_token.as_deref() {
+        Some(token) if !token.is_empty() => router.layer(BearerAuthLayer::new(token)),
+        _ => router,
+    };
+
+    let router = if state.config.disable_compression {
+        router
+    } else {
+        router.layer(tower_http::compression::CompressionLayer::new())
+    };
+    // Outermost, so preflight requests are answered before anything else runs
+    let router = match cors::layer(&state.config.cors_allowed_origins) {
+        Some(cors) => router.layer(cors),
+        None => router,
+    };
+    router.with_state(state)
+}
 
 #[tokio::main]
 async fn main() {
+    // Startup, shutdown and the drain common runs on shutdown are all logged through tracing
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")))
+        .init();
+
+    let config = Config::parse();
+    if let Some(dir) = &config.keys_dir {
+        std::fs::create_dir_all(dir).expect("failed to create --keys-dir");
+    }
+    // Test-only: the same Groth16 keys on every run, so tests can pin their fingerprints
+    let seed = setup_seed(&config).unwrap_or_else(|e| panic!("{}", e));
+    if let Some(seed) = seed {
+        tracing::warn!(seed, "Groth16 setup is seeded from ZK_SETUP_SEED; proofs against these keys can be forged. Never serve real traffic like this");
+    }
+    let keys = KeySource { dir: config.keys_dir.clone(), seed };
+    let drain_timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    let tls_files = config.tls_cert.clone().zip(config.tls_key.clone());
+    let uds = config.uds.clone().map(|path| (path, config.uds_mode));
+    let bind = config.bind;
+    let state = AppState {
+        // Both curves start empty; BLS12-381 costs nothing until a request names it
+        bn254: CurveKeys::new(keys.clone()),
+        bls12_381: CurveKeys::new(keys),
+        readiness: readiness(config.max_concurrent_benchmarks()),
+        metrics: Arc::new(metrics::Metrics::new()),
+        max_prove_iterations: config.max_prove_iterations,
+        max_verify_iterations: config.max_verify_iterations,
+        machine_info: (!config.disable_machine_info).then(|| Arc::new(MachineInfo::detect())),
+        timer_overhead: stats::calibrate_timer_overhead(stats::TIMER_CALIBRATION_ITERATIONS),
+        limits: config.limits(),
+        http: config.http_tuning(),
+        max_chain_length: config.max_chain_length,
+        max_raw_timings: config.max_raw_timings,
+        max_fresh_statements: config.max_fresh_statements,
+        prover_threads: config.prover_threads(),
+        prover_baselines: Arc::new(parallelism::Baselines::default()),
+        seeded_setup: seed.is_some(),
+        config: Arc::new(config),
+    };
 
-    let keys = std::sync::Arc::new(setup_circuits());
+    let connections = state.limits.connections();
+    let tuning = state.http;
+    let router = app(state);
+
+    #[cfg(unix)]
+    if let Some((path, mode)) = uds {
+        let (listener, _socket) = uds::bind(&path, mode).expect("failed to bind Unix domain socket");
+        tracing::info!(path = %path.display(), mode = format!("{:o}", mode), "zk_service listening");
+        http::serve_with_drain(connections.listener(listener), router, drain_timeout, http::signal()).await.unwrap();
+        tracing::info!("zk_service stopped");
+        return;
+    }
+    #[cfg(not(unix))]
+    if uds.is_some() {
+        panic!("--uds is only supported on Unix");
+    }
+    // With port 0 the OS picks the port, so this is the only place to learn it
+    let (listener, addr) = listen::bind(bind).unwrap_or_else(|e| panic!("failed to bind {}: {}", bind, e));
+    match tls_files {
+        Some((cert, key)) => {
+            let tls_config = tls::load(&cert, &key).await.expect("failed to load TLS certificate and key");
+            tls::reload_on_sighup(tls_config.clone(), cert, key);
+            tracing::info!(%addr, "zk_service listening (https)");
+            tls::serve(listener, router, tls_config, tuning, connections, drain_timeout, http::signal()).await.unwrap();
+        }
+        None => {
+            tracing::info!(%addr, "zk_service listening");
+            // HTTP/1.1, or HTTP/2 for clients that speak it with prior knowledge (h2c)
+            http::serve(listener, router, tuning, connections, drain_timeout, http::signal()).await.unwrap();
+        }
+    }
+    tracing::info!("zk_service stopped");
+}
 
-    let router = Router::new()
-        .route("/health", get(health))
-        .route("/zk_prove_bench", post(zk_prove_bench))
-        .route("/zk_verify_bench", post(zk_verify_bench))
-        .with_state(keys);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::State;
+
+    /// A measured loop of `iterations`, with no warmup or overhead
+    pub fn budget(iterations: u32) -> Budget {
+        Budget { iterations, warmup: 0, overhead: Duration::ZERO }
+    }
+
+    /// State as handlers see it on a fresh start, with no circuit set up yet
+    pub fn ready_state() -> AppState {
+        AppState {
+            bn254: CurveKeys::new(KeySource::default()),
+            bls12_381: CurveKeys::new(KeySource::default()),
+            readiness: readiness(1),
+            metrics: Arc::new(metrics::Metrics::new()),
+            max_prove_iterations: DEFAULT_MAX_PROVE_ITERATIONS,
+            max_verify_iterations: DEFAULT_MAX_VERIFY_ITERATIONS,
+            machine_info: None,
+            timer_overhead: Duration::ZERO,
+            limits: Config::default().limits(),
+            http: HttpTuning::default(),
+            max_chain_length: chain::DEFAULT_MAX_CHAIN_LENGTH,
+            max_raw_timings: DEFAULT_MAX_RAW_TIMINGS,
+            max_fresh_statements: DEFAULT_MAX_FRESH_STATEMENTS,
+            prover_threads: parallelism::available(),
+            prover_baselines: Arc::new(parallelism::Baselines::default()),
+            seeded_setup: false,
+            config: Arc::new(Config::default()),
+        }
+    }
+
+    fn bench_request(circuit_id: &str, chain_length: Option<u32>) -> ZkBenchRequest {
+        ZkBenchRequest {
+            circuit_id: circuit_id.to_string(),
+            iterations: 1,
+            include_machine_info: false,
+            subtract_overhead: false,
+            chain_length,
+            curve: Curve::default(),
+            proof_system: None,
+            percentiles: default_percentiles(),
+            include_raw_timings: false,
+            warmup_iterations: 0,
+            serialization_bench: false,
+            verify_mode: VerifyMode::Fixed,
+            corrupt: None,
+            prover_threads: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn benchmarks_report_setup_once_per_circuit() {
+        let state = ready_state();
+        let Json(first) = zk_prove_bench(State(state.clone()), StrictJson(bench_request("chain", Some(16)))).await.unwrap();
+        assert_eq!((first.chain_length, first.counts.num_constraints), (Some(16), 17));
+        assert!(first.setup_ms.is_some());
+        // Witness generation and proving are timed apart, and the totals are the two together
+        let total = first.witness_stats.avg_ms + first.prove_stats.avg_ms;
+        assert!((first.avg_prove_ms - total).abs() < 1e-9);
+        assert!(first.prove_stats.min_ms > 0.0);
+        // Setup ran in this request, but only proving is sampled
+        assert_eq!(first.peak_rss_delta_kb.is_some(), cfg!(target_os = "linux"));
+
+        let Json(again) = zk_verify_bench(State(state.clone()), StrictJson(bench_request("chain", Some(16)))).await.unwrap();
+        assert_eq!(again.counts, first.counts);
+        assert!(again.setup_ms.is_none());
+
+        // Fixed circuits are set up lazily too
+        let Json(fixed) = zk_prove_bench(State(state.clone()), StrictJson(bench_request("multiply", None))).await.unwrap();
+        assert!(fixed.chain_length.is_none() && fixed.setup_ms.is_some());
+        let counts = circuits::CircuitCounts { num_constraints: 2, num_witness_variables: 3, num_public_inputs: 1 };
+        assert_eq!(fixed.counts, counts);
+        // As ark-serialize measures the keys themselves
+        let (keys, _) = state.bn254.circuits.get("multiply").await.unwrap();
+        assert_eq!(fixed.key_sizes.verifying_key_bytes, keys.vk.serialized_size(ark_serialize::Compress::Yes));
+        assert_eq!(fixed.key_sizes.verifying_key_uncompressed_bytes, keys.vk.serialized_size(ark_serialize::Compress::No));
+        assert_eq!(fixed.key_sizes.proving_key_bytes, keys.pk.serialized_size(ark_serialize::Compress::Yes));
+        let Json(fixed) = zk_verify_bench(State(state), StrictJson(bench_request("multiply", None))).await.unwrap();
+        assert!(fixed.setup_ms.is_none());
+    }
+
+    #[tokio::test]
+    async fn curves_are_set_up_separately_and_echoed() {
+        let state = ready_state();
+        let bls12_381 = ZkBenchRequest { curve: Curve::Bls12_381, ..bench_request("multiply", None) };
+        let Json(bls) = zk_prove_bench(State(state.clone()), StrictJson(bls12_381)).await.unwrap();
+        assert_eq!((bls.curve, bls.avg_proof_size_bytes), (Curve::Bls12_381, 192));
+        assert!(bls.setup_ms.is_some());
+
+        // BN254 keys for the same circuit are still to be set up
+        let Json(bn) = zk_prove_bench(State(state.clone()), StrictJson(bench_request("multiply", None))).await.unwrap();
+        assert_eq!((bn.curve, bn.avg_proof_size_bytes), (Curve::Bn254, 128));
+        assert!(bn.setup_ms.is_some());
+
+        let bls_chain = ZkBenchRequest { curve: Curve::Bls12_381, ..bench_request("chain", Some(4)) };
+        let Json(chain) = zk_verify_bench(State(state.clone()), StrictJson(bls_chain)).await.unwrap();
+        assert_eq!((chain.curve, chain.counts.num_constraints), (Curve::Bls12_381, 5));
+        assert!(state.bn254.chains.ready().is_empty());
+    }
+
+    #[tokio::test]
+    async fn marlin_reports_its_srs_and_index_separately() {
+        let state = ready_state();
+        let marlin = |circuit_id: &str| ZkBenchRequest { proof_system: Some("marlin".to_string()), ..bench_request(circuit_id, None) };
+        let Json(first) = zk_prove_bench(State(state.clone()), StrictJson(marlin("multiply"))).await.unwrap();
+        assert_eq!(first.proof_system, ProofSystem::Marlin);
+        assert!(first.avg_proof_size_bytes > 128 && first.setup_ms.is_none());
+        // Counted from the same constraint system Groth16 would prove
+        assert_eq!(first.counts, circuits::CircuitCounts::of(MultiplyCircuit::<ark_bn254::Fr> { a: None, b: None, c: None }));
+        let setup = first.marlin.unwrap();
+        assert!(setup.srs_setup_ms.is_some() && setup.index_ms.is_some());
+        assert!(first.key_sizes.proving_key_bytes > first.key_sizes.verifying_key_bytes);
+
+        // The SRS is shared, so a second circuit only needs indexing
+        let Json(verified) = zk_verify_bench(State(state.clone()), StrictJson(marlin("cube_root"))).await.unwrap();
+        assert_eq!(verified.proof_size_bytes, first.avg_proof_size_bytes);
+        let setup = verified.marlin.unwrap();
+        assert!(setup.srs_setup_ms.is_none() && setup.index_ms.is_some());
+        // Groth16 keys were never needed
+        assert!(state.bn254.circuits.statuses().all(|s| s.setup == registry::SetupStatus::NotSetUp));
+
+        let unknown = ZkBenchRequest { proof_system: Some("plonk".to_string()), ..bench_request("multiply", None) };
+        let result = zk_prove_bench(State(state.clone()), StrictJson(unknown)).await;
+        assert!(matches!(result, Err(AppError::InvalidProofSystem(name)) if name == "plonk"));
+        let chain = ZkBenchRequest { proof_system: Some("marlin".to_string()), ..bench_request("chain", Some(4)) };
+        let result = zk_verify_bench(State(state), StrictJson(chain)).await;
+        assert!(matches!(result, Err(AppError::ValidationFailed(_))));
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn stats_are_common_stats_in_milliseconds() {
+        // common::stats' fixture, in microseconds: mean 5ms, sample variance 32/7 ms^2
+        let stats = compute_stats(&[2000, 4000, 4000, 4000, 5000, 5000, 7000, 9000]);
+        assert_close(stats.avg_ms, 5.0);
+        assert_close(stats.median_ms, 4.5);
+        assert_close(stats.stddev_ms, (32.0f64 / 7.0).sqrt());
+        assert_close(stats.p95_ms, 9.0);
+        assert_close(stats.throughput, 200.0);
+
+        let single = compute_stats(&[1500]);
+        assert_close(single.median_ms, 1.5);
+        assert_close(single.stddev_ms, 0.0);
+        assert_eq!((single.ci95_low_ms, single.ci95_high_ms), (1.5, 1.5));
+    }
+
+    #[test]
+    fn percentiles_take_the_nearest_rank() {
+        // Truncating the index would make p50 of two samples the upper one
+        let map = compute_percentiles_ms(&[10_000, 20_000], &[0.5, 0.95]);
+        assert_close(map["0.5"], 10.0);
+        assert_close(map["0.95"], 20.0);
+        let map = compute_percentiles_ms(&[15_000, 20_000, 35_000, 40_000, 50_000], &[0.3, 0.4, 0.5, 1.0]);
+        let values: Vec<f64> = map.values().copied().collect();
+        assert_eq!(values, [20.0, 20.0, 35.0, 50.0]);
+    }
+
+    #[tokio::test]
+    async fn requested_percentiles_are_reported() {
+        let state = ready_state();
+        let req = ZkBenchRequest { iterations: 3, percentiles: vec![0.5, 0.999], ..bench_request("multiply", None) };
+        let Json(resp) = zk_verify_bench(State(state.clone()), StrictJson(req)).await.unwrap();
+        assert_eq!(resp.percentiles.keys().collect::<Vec<_>>(), ["0.5", "0.999"]);
+        assert_close(resp.percentiles["0.999"], resp.max_verify_ms);
+        assert!(resp.median_verify_ms >= resp.min_verify_ms && resp.stddev_verify_ms >= 0.0);
+
+        let req = ZkBenchRequest { percentiles: vec![0.5, 0.0], ..bench_request("multiply", None) };
+        let result = zk_prove_bench(State(state), StrictJson(req)).await;
+        assert!(matches!(result, Err(AppError::ValidationFailed(message)) if message.contains("percentiles")));
+    }
+
+    #[tokio::test]
+    async fn raw_timings_are_only_returned_on_request_and_capped() {
+        let mut state = ready_state();
+        let Json(resp) = zk_verify_bench(State(state.clone()), StrictJson(bench_request("multiply", None))).await.unwrap();
+        let json = serde_json::to_value(&resp).unwrap();
+        assert!(json.get("timings_ms").is_none() && json.get("timings_truncated").is_none());
+
+        state.max_raw_timings = 2;
+        let req = ZkBenchRequest { iterations: 3, include_raw_timings: true, ..bench_request("multiply", None) };
+        let Json(resp) = zk_prove_bench(State(state.clone()), StrictJson(req)).await.unwrap();
+        let raw = resp.timings_ms.unwrap();
+        assert_eq!((raw.len(), resp.timings_truncated), (2, Some(true)));
+        assert!(raw.iter().all(|&t| t >= resp.min_prove_ms && t <= resp.max_prove_ms));
+
+        let req = ZkBenchRequest { iterations: 2, include_raw_timings: true, ..bench_request("multiply", None) };
+        let Json(resp) = zk_verify_bench(State(state), StrictJson(req)).await.unwrap();
+        assert_eq!((resp.timings_ms.unwrap().len(), resp.timings_truncated), (2, Some(false)));
+    }
+
+    #[tokio::test]
+    async fn groth16_proofs_encode_to_constant_sizes() {
+        let state = ready_state();
+        let (keys, _) = state.bls12_381.circuits.get("cube_root").await.unwrap();
+        let (_, encoding, _) = bench_prove(&keys.pk, &keys.matrices, &keys.assignments, budget(4), false);
+        // Two G1 points and a G2 point, whatever the statement
+        assert_eq!(encoding.compressed_bytes, [192; 4]);
+        assert_eq!(encoding.uncompressed_bytes, [384; 4]);
+        assert!(encoding.timings.is_none());
+
+        let req = ZkBenchRequest { iterations: 3, serialization_bench: true, ..bench_request("multiply", None) };
+        let Json(resp) = zk_prove_bench(State(state.clone()), StrictJson(req)).await.unwrap();
+        assert_eq!((resp.proof_size_compressed_bytes, resp.proof_size_uncompressed_bytes), (128, 256));
+        assert_eq!(resp.avg_proof_size_bytes, resp.proof_size_compressed_bytes);
+        let json = serde_json::to_value(&resp).unwrap();
+        for phase in ["serialize_compressed", "deserialize_compressed", "serialize_uncompressed", "deserialize_uncompressed"] {
+            assert!(json["serialization_stats"][phase]["avg_ms"].is_number(), "{} missing", phase);
+        }
+
+        let Json(resp) = zk_prove_bench(State(state), StrictJson(bench_request("multiply", None))).await.unwrap();
+        assert!(resp.serialization_stats.is_none());
+    }
+
+    #[tokio::test]
+    async fn fresh_verify_mode_cycles_through_distinct_statements() {
+        let mut state = ready_state();
+        let Json(fixed) = zk_verify_bench(State(state.clone()), StrictJson(bench_request("cube_root", None))).await.unwrap();
+        assert_eq!((fixed.verify_mode, fixed.distinct_statements), (VerifyMode::Fixed, 1));
+
+        state.max_fresh_statements = 3;
+        let fresh = |iterations| ZkBenchRequest { iterations, verify_mode: VerifyMode::Fresh, ..bench_request("cube_root", None) };
+        let Json(resp) = zk_verify_bench(State(state.clone()), StrictJson(fresh(2))).await.unwrap();
+        assert_eq!((resp.verify_mode, resp.distinct_statements), (VerifyMode::Fresh, 2));
+        // Past the cap, the iterations reuse the statements already proven
+        let Json(resp) = zk_verify_bench(State(state.clone()), StrictJson(fresh(5))).await.unwrap();
+        assert_eq!((resp.iterations, resp.distinct_statements), (5, 3));
+        let json = serde_json::to_value(&resp).unwrap();
+        assert_eq!(json["verify_mode"], "fresh");
+
+        let (keys, _) = state.bn254.circuits.get("cube_root").await.unwrap();
+        let (timings, _) = bench_verify(&keys.pk, &keys.vk, &keys.matrices, &keys.assignments, budget(4), 4, None).unwrap();
+        assert_eq!(timings.accept.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn corrupted_statements_are_rejected() {
+        let state = ready_state();
+        let corrupt = |corruption, proof_system: &str| ZkBenchRequest {
+            iterations: 3,
+            corrupt: Some(corruption),
+            proof_system: Some(proof_system.to_string()),
+            ..bench_request("multiply", None)
+        };
+        for (corruption, proof_system) in [(Corruption::Proof, "groth16"), (Corruption::PublicInput, "groth16"), (Corruption::PublicInput, "marlin")] {
+            let Json(resp) = zk_verify_bench(State(state.clone()), StrictJson(corrupt(corruption, proof_system))).await.unwrap();
+            let rejection = resp.rejection.unwrap();
+            assert_eq!(rejection.corruption, corruption);
+            assert!(rejection.reject_stats.max_ms >= rejection.reject_stats.min_ms);
+            assert_eq!(rejection.accept_stats.avg_ms, resp.avg_verify_ms);
+        }
+        let Json(resp) = zk_verify_bench(State(state), StrictJson(bench_request("multiply", None))).await.unwrap();
+        assert!(serde_json::to_value(&resp).unwrap().get("rejection").is_none());
+
+        // A verifier that accepts anything fails the benchmark on the first corrupted statement
+        let statements = [Statement {
+            public_inputs: vec![1u8],
+            proof: (),
+            corrupted: Some(Corrupted { corruption: Corruption::Proof, public_inputs: vec![2], proof: () }),
+        }];
+        let accepted = time_verifications(&statements, 2, Duration::ZERO, |_, _| true).err().unwrap();
+        let response = accepted.into_error("multiply").into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!((body["corruption"].as_str(), body["iteration"].as_u64()), (Some("proof"), Some(0)));
+    }
 
-    
+    #[tokio::test]
+    async fn warmup_iterations_are_not_measured() {
+        let state = ready_state();
+        let req = ZkBenchRequest { iterations: 2, warmup_iterations: 3, include_raw_timings: true, ..bench_request("multiply", None) };
+        let Json(resp) = zk_prove_bench(State(state.clone()), StrictJson(req)).await.unwrap();
+        assert_eq!((resp.warmup_iterations, resp.iterations, resp.effective_iterations), (3, 2, 2));
+        assert_eq!(resp.timings_ms.map(|t| t.len()), Some(2));
+
+        let req = ZkBenchRequest {
+            iterations: 2,
+            warmup_iterations: 3,
+            include_raw_timings: true,
+            proof_system: Some("marlin".to_string()),
+            ..bench_request("multiply", None)
+        };
+        let Json(resp) = zk_verify_bench(State(state), StrictJson(req)).await.unwrap();
+        assert_eq!((resp.warmup_iterations, resp.iterations), (3, 2));
+        assert_eq!(resp.timings_ms.map(|t| t.len()), Some(2));
+    }
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8001));
-    println!("zk_service listening on {}", addr);
+    // On the single-threaded test runtime, proving inline would hold the only worker for
+    // the whole run and every /health below would wait for it
+    #[tokio::test]
+    async fn health_answers_promptly_during_a_prove_benchmark() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let router = Router::new()
+            .route("/health", get(health::health))
+            .route("/zk_prove_bench", post(zk_prove_bench))
+            .with_state(ready_state());
+        let body = r#"{"circuit_id": "chain", "chain_length": 64, "iterations": 8}"#;
+        let request = Request::post("/zk_prove_bench").header("content-type", "application/json").body(Body::from(body));
+        let bench = tokio::spawn(router.clone().oneshot(request.unwrap()));
+
+        let mut checks = 0;
+        while !bench.is_finished() {
+            let start = Instant::now();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let health = router.clone().oneshot(Request::get("/health").body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(health.status(), StatusCode::OK);
+            assert!(start.elapsed() < Duration::from_millis(250), "/health took {:?}", start.elapsed());
+            checks += 1;
+        }
+        assert_eq!(bench.await.unwrap().unwrap().status(), StatusCode::OK);
+        assert!(checks > 1, "benchmark finished before /health was checked");
+    }
 
-    let tcp = TcpListener::bind(&addr).await.unwrap();
-    axum::serve(tcp, router).await.unwrap();
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn chain_length_must_match_the_circuit() {
+        let state = ready_state();
+        for (circuit_id, chain_length) in [("chain", None), ("chain", Some(0)), ("chain", Some(chain::DEFAULT_MAX_CHAIN_LENGTH + 1)), ("multiply", Some(4))] {
+            let result = zk_prove_bench(State(state.clone()), StrictJson(bench_request(circuit_id, chain_length))).await;
+            assert!(matches!(result, Err(AppError::ValidationFailed(_))), "{} {:?}", circuit_id, chain_length);
+        }
+    }
+
+    #[tokio::test]
+    async fn prove_benchmarks_report_their_threads_and_speedup() {
+        let state = ready_state();
+        let available = parallelism::available();
+        let req = ZkBenchRequest { prover_threads: Some(1), iterations: 2, ..bench_request("multiply", None) };
+        let Json(single) = zk_prove_bench(State(state.clone()), StrictJson(req)).await.unwrap();
+        assert_eq!((single.prover_threads, single.available_parallelism), (1, available));
+        // The first single-thread run is the baseline
+        assert_eq!(single.baseline_prove_ms, single.prove_stats.avg_ms);
+        assert_eq!(single.prover_speedup, 1.0);
+        assert!(single.notes.is_empty());
+
+        let req = ZkBenchRequest { prover_threads: Some(available as u32 + 1), ..bench_request("multiply", None) };
+        let Json(clamped) = zk_prove_bench(State(state.clone()), StrictJson(req)).await.unwrap();
+        assert_eq!(clamped.prover_threads, available);
+        assert_eq!(clamped.notes.len(), 1);
+        assert_eq!(clamped.baseline_prove_ms, single.baseline_prove_ms);
+
+        // Without a single-thread run to go on, one is measured
+        let Json(cube_root) = zk_prove_bench(State(state.clone()), StrictJson(bench_request("cube_root", None))).await.unwrap();
+        assert!(cube_root.baseline_prove_ms > 0.0 && cube_root.prover_speedup > 0.0);
+
+        let req = ZkBenchRequest { prover_threads: Some(0), ..bench_request("multiply", None) };
+        assert!(matches!(zk_prove_bench(State(state), StrictJson(req)).await, Err(AppError::ValidationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn benchmarks_and_setups_show_up_in_metrics() {
+        use axum::body::{to_bytes, Body};
+        use tower::ServiceExt;
+
+        let state = ready_state();
+        let router = Router::new()
+            .route("/zk_prove_bench", post(zk_prove_bench))
+            .route("/metrics", get(metrics::metrics))
+            .layer(axum::middleware::from_fn_with_state(state.metrics.service.clone(), common::metrics::track))
+            .with_state(state);
+        for circuit_id in ["multiply", "no_such_circuit"] {
+            let body = format!(r#"{{"circuit_id": "{}", "iterations": 2}}"#, circuit_id);
+            let request = Request::post("/zk_prove_bench").header("content-type", "application/json").body(Body::from(body));
+            router.clone().oneshot(request.unwrap()).await.unwrap();
+        }
+
+        let response = router.oneshot(Request::get("/metrics").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let text = String::from_utf8(to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap();
+        for series in [
+            r#"zk_bench_requests_total{circuit_id="multiply",curve="bn254",operation="prove",proof_system="groth16"} 1"#,
+            r#"zk_proof_operation_duration_seconds_count{circuit_id="multiply",curve="bn254",operation="prove",proof_system="groth16"} 2"#,
+            r#"zk_bench_failures_total{circuit_id="unknown",kind="invalid_circuit",operation="prove"} 1"#,
+            r#"zk_circuits_ready{curve="bn254",proof_system="groth16"} 1"#,
+            r#"zk_circuits_ready{curve="bls12_381",proof_system="groth16"} 0"#,
+            r#"zk_errors_total{kind="invalid_circuit"} 1"#,
+            r#"zk_http_requests_total{method="POST",path="/zk_prove_bench",status="200"} 1"#,
+        ] {
+            assert!(text.contains(series), "missing {}", series);
+        }
+        let key_bytes = text.lines().find(|l| l.starts_with(r#"zk_proving_key_bytes{curve="bn254",proof_system="groth16"}"#)).unwrap();
+        assert!(key_bytes.rsplit(' ').next().unwrap().parse::<u64>().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn cors_preflight_follows_the_allowed_origins() {
+        use axum::{body::Body, http::header};
+        use tower::ServiceExt;
+
+        let preflight = |origin: &str| {
+            Request::options("/zk_prove_bench")
+                .header(header::ORIGIN, origin)
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let allow_origin = |resp: &Response| resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).cloned();
+
+        let resp = app(ready_state()).oneshot(preflight("http://dash.local")).await.unwrap();
+        assert_eq!(allow_origin(&resp), None);
+
+        let config = Config::parse_from(["zk_service", "--cors-allowed-origins", "http://other.local,http://dash.local"]);
+        let state = AppState { config: Arc::new(config), ..ready_state() };
+        let router = app(state);
+        let resp = router.clone().oneshot(preflight("http://dash.local")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(allow_origin(&resp).unwrap(), "http://dash.local");
+        let methods = resp.headers().get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap().to_str().unwrap();
+        assert!(methods.contains("POST"), "{}", methods);
+        let headers = resp.headers().get(header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap().to_str().unwrap();
+        assert!(headers.contains("authorization"), "{}", headers);
+        // Disallowed origins get no CORS headers, so the browser blocks the response
+        let resp = router.oneshot(preflight("http://evil.local")).await.unwrap();
+        assert_eq!(allow_origin(&resp), None);
+
+        let state = AppState { config: Arc::new(Config::parse_from(["zk_service", "--cors-allowed-origins", "*"])), ..ready_state() };
+        let resp = app(state).oneshot(preflight("http://dash.local")).await.unwrap();
+        assert_eq!(allow_origin(&resp).unwrap(), "*");
+    }
+
+    #[test]
+    fn flags_parse_like_lattice_services() {
+        let parse = |args: &[&str]| Config::try_parse_from([&["zk_service"], args].concat());
+        assert_eq!(parse(&["--bind", "9000"]).unwrap().bind, "0.0.0.0:9000".parse().unwrap());
+        assert_eq!(parse(&["--bind=127.0.0.1"]).unwrap().bind, "127.0.0.1:8001".parse().unwrap());
+        assert_eq!(parse(&["--bind=[::1]:0"]).unwrap().bind, "[::1]:0".parse().unwrap());
+        assert!(parse(&["--bind"]).is_err());
+        assert!(parse(&["--bind", "zk:8001"]).is_err());
+        assert!(parse(&["--allow-insecure-setup"]).unwrap().allow_insecure_setup);
+        // Misspelled flags used to be ignored
+        assert!(parse(&["--allow-insecure-set-up"]).is_err());
+        // So were bad values, which fell back to the default
+        assert!(parse(&["--max-connections", "0"]).is_err());
+        assert!(parse(&["--max-prove-iterations", "lots"]).is_err());
+        assert!(parse(&["--tls-cert", "cert.pem"]).is_err());
+        assert_eq!(parse(&["--cors-allowed-origins", "http://a.local,http://b.local"]).unwrap().cors_allowed_origins.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn boots_on_an_ephemeral_port_and_answers_health() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let config = Config::parse_from(["zk_service", "--bind", "127.0.0.1:0"]);
+        let (listener, addr) = listen::bind(config.bind).unwrap();
+        assert_ne!(addr.port(), 0);
+        let state = ready_state();
+        let connections = state.limits.connections();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(http::serve(listener, app(state), HttpTuning::default(), connections, Duration::from_secs(1), async {
+            let _ = stopped.await;
+        }));
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.contains(r#""status":"healthy""#), "{}", response);
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn in_flight_benchmark_completes_after_shutdown_signal() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (listener, addr) = listen::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let state = ready_state();
+        let connections = state.limits.connections();
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(http::serve(listener, app(state), HttpTuning::default(), connections, Duration::from_secs(30), async {
+            let _ = stopped.await;
+        }));
+
+        let bench = tokio::spawn(async move {
+            let body = r#"{"circuit_id": "chain", "chain_length": 64, "iterations": 8}"#;
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let request = format!(
+                "POST /zk_prove_bench HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(), body
+            );
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!bench.is_finished(), "benchmark finished before the shutdown signal");
+        stop.send(()).unwrap();
+
+        let response = bench.await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.contains(r#""chain_length":64"#), "{}", response);
+        server.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn a_setup_seed_needs_the_insecure_flag() {
+        let seed = |args: &[&str]| setup_seed(&Config::parse_from([&["zk_service"], args].concat()));
+        assert_eq!(seed(&[]), Ok(None));
+        assert!(seed(&["--setup-seed", "42"]).unwrap_err().contains("--allow-insecure-setup"));
+        assert_eq!(seed(&["--setup-seed", "42", "--allow-insecure-setup"]), Ok(Some(42)));
+        assert!(Config::try_parse_from(["zk_service", "--setup-seed", "forty-two", "--allow-insecure-setup"]).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use ark_ec_03::PairingEngine;
+use ark_ff::{BigInteger, PrimeField};
+use ark_ff_03::{One, PrimeField as PrimeField03};
+use ark_marlin::{AHPForR1CS, IndexProverKey, IndexVerifierKey, Marlin, UniversalSRS};
+use ark_poly_03::univariate::DensePolynomial;
+use ark_poly_commit::marlin_pc::MarlinKZG10;
+use ark_poly_commit::PolynomialCommitment;
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_relations_03::r1cs as r1cs_03;
+use ark_serialize_03::{CanonicalDeserialize, CanonicalSerialize};
+use blake2::Blake2s;
+use common::stats;
+use rand::rngs::OsRng;
+
+use crate::circuits::{self, Assignments, BenchCircuit, CircuitCounts, KeySizes};
+use crate::encoding::{ProofCodec, ProofEncoding};
+use crate::{time_verifications, Budget, Corrupted, Corruption, InvalidProofAccepted, ProveTimings, Statement, VerifyTimings};
+use crate::curve::Engine;
+
+// ============ Marlin ============
+//
+// A universal-setup SNARK, benchmarked on the same circuits as Groth16: one SRS per curve
+// serves every circuit, and each circuit is only indexed against it. ark-marlin is built
+// on arkworks 0.3, so each circuit is synthesized by the same 0.5 code the Groth16 paths
+// run and its constraints and witness are replayed, one for one, into a 0.3 circuit.
+// The SRS is sized at startup to fit every circuit and generated by the first Marlin
+// request on its curve, so startup doesn't pay for it.
+
+type Fr<E> = <<E as Engine>::Marlin as PairingEngine>::Fr;
+type Pc<E> = MarlinKZG10<<E as Engine>::Marlin, DensePolynomial<Fr<E>>>;
+type MarlinOf<E> = Marlin<Fr<E>, Pc<E>, Blake2s>;
+type ProofOf<E> = ark_marlin::Proof<Fr<E>, Pc<E>>;
+type Matrix<F> = Vec<Vec<(F, usize)>>;
+
+fn to_03<E: Engine>(x: E::ScalarField) -> Fr<E> {
+    Fr::<E>::from_le_bytes_mod_order(&x.into_bigint().to_bytes_le())
+}
+
+/// A circuit's R1CS in the 0.3 field, columns numbered as arkworks 0.5 numbers them:
+/// the constant one, then the public inputs, then the witness
+struct Matrices<F> {
+    num_instance: usize,
+    num_witness: usize,
+    a: Matrix<F>,
+    b: Matrix<F>,
+    c: Matrix<F>,
+}
+
+impl<F: PrimeField03> Matrices<F> {
+    fn of<G: PrimeField, C: ConstraintSynthesizer<G>>(blank: C, convert: fn(G) -> F) -> Self {
+        let matrices = circuits::matrices(blank);
+        let convert_matrix = |m: Vec<Vec<(G, usize)>>| -> Matrix<F> {
+            m.into_iter().map(|row| row.into_iter().map(|(coeff, var)| (convert(coeff), var)).collect()).collect()
+        };
+        Matrices {
+            num_instance: matrices.num_instance_variables,
+            num_witness: matrices.num_witness_variables,
+            a: convert_matrix(matrices.a),
+            b: convert_matrix(matrices.b),
+            c: convert_matrix(matrices.c),
+        }
+    }
+}
+
+/// The replayed circuit, with every variable's value in column order when proving
+struct Replay<F> {
+    matrices: Arc<Matrices<F>>,
+    assignment: Option<Vec<F>>,
+}
+
+impl<F: PrimeField03> r1cs_03::ConstraintSynthesizer<F> for Replay<F> {
+    fn generate_constraints(self, cs: r1cs_03::ConstraintSystemRef<F>) -> r1cs_03::Result<()> {
+        let m = &self.matrices;
+        let value = |i: usize| {
+            let assignment = &self.assignment;
+            move || assignment.as_ref().map(|a| a[i]).ok_or(r1cs_03::SynthesisError::AssignmentMissing)
+        };
+        let mut variables = vec![r1cs_03::Variable::One];
+        for i in 1..m.num_instance {
+            variables.push(cs.new_input_variable(value(i))?);
+        }
+        for i in m.num_instance..m.num_instance + m.num_witness {
+            variables.push(cs.new_witness_variable(value(i))?);
+        }
+        let lc = |row: &[(F, usize)]| r1cs_03::LinearCombination(row.iter().map(|&(coeff, var)| (coeff, variables[var])).collect());
+        for ((a, b), c) in m.a.iter().zip(&m.b).zip(&m.c) {
+            cs.enforce_constraint(lc(a), lc(b), lc(c))?;
+        }
+        Ok(())
+    }
+}
+
+/// Iteration `i`'s assignment, synthesized by the same 0.5 code as Groth16's, in the 0.3 field
+fn assignment<E: Engine>(assignments: &Assignments<E::ScalarField>, i: u32) -> Vec<Fr<E>> {
+    assignments(i).into_iter().map(to_03::<E>).collect()
+}
+
+pub struct MarlinIndex<E: Engine> {
+    /// ark-poly-commit 0.3 keeps the prover key's polynomials behind Rc, so it can't be
+    /// shared between threads; it's kept serialized and rebuilt by each benchmark instead
+    pk_bytes: Vec<u8>,
+    vk: IndexVerifierKey<Fr<E>, Pc<E>>,
+    matrices: Arc<Matrices<Fr<E>>>,
+    assignments: Assignments<E::ScalarField>,
+    key_sizes: KeySizes,
+}
+
+impl<E: Engine> MarlinIndex<E> {
+    /// Sizes of the index's prover and verifier keys, Marlin's counterparts of Groth16's
+    pub fn key_sizes(&self) -> KeySizes {
+        self.key_sizes
+    }
+
+    /// The same counts as the Groth16 circuit, since these are its matrices
+    pub fn counts(&self) -> CircuitCounts {
+        CircuitCounts {
+            num_constraints: self.matrices.a.len(),
+            num_witness_variables: self.matrices.num_witness,
+            num_public_inputs: self.matrices.num_instance - 1,
+        }
+    }
+
+    fn pk(&self) -> IndexProverKey<Fr<E>, Pc<E>> {
+        // Written by us just after indexing, so the points need no checking
+        IndexProverKey::deserialize_unchecked(&*self.pk_bytes).expect("the prover key was serialized by MarlinKeys::get")
+    }
+}
+
+/// What a request learned about the universal SRS and the circuit's index
+pub struct Setup {
+    /// Compressed, as the SRS would be published
+    pub srs_size_bytes: usize,
+    /// Set when this request generated the SRS
+    pub srs_time: Option<Duration>,
+    /// Set when this request indexed the circuit, the only circuit-specific setup Marlin needs
+    pub index_time: Option<Duration>,
+}
+
+struct Entry<E: Engine> {
+    id: &'static str,
+    matrices: Arc<Matrices<Fr<E>>>,
+    assignments: Assignments<E::ScalarField>,
+    index: OnceLock<Arc<MarlinIndex<E>>>,
+}
+
+/// The universal SRS on curve E and each circuit's index against it, set up on first use.
+/// Lookups block, so they belong on the blocking pool with the benchmark
+pub struct MarlinKeys<E: Engine> {
+    entries: Vec<Entry<E>>,
+    /// Largest constraint, variable and non-zero counts of any circuit's index
+    bound: (usize, usize, usize),
+    srs: OnceLock<Arc<UniversalSRS<Fr<E>, Pc<E>>>>,
+}
+
+impl<E: Engine> Default for MarlinKeys<E> {
+    fn default() -> Self {
+        MarlinKeys::new()
+    }
+}
+
+impl<E: Engine> MarlinKeys<E> {
+    pub fn new() -> Self {
+        MarlinKeys { entries: Vec::new(), bound: (0, 0, 0), srs: OnceLock::new() }
+    }
+
+    /// Adds a circuit, described by `blank` as for Groth16's setup, and grows the SRS to fit it
+    pub fn register<C: BenchCircuit<E::ScalarField>>(mut self, id: &'static str, blank: C) -> Self {
+        let matrices = Arc::new(Matrices::of(blank.clone(), to_03::<E>));
+        let info = AHPForR1CS::index(Replay { matrices: matrices.clone(), assignment: None })
+            .expect("indexing a circuit that synthesized in setup mode")
+            .index_info;
+        self.bound = (
+            self.bound.0.max(info.num_constraints),
+            self.bound.1.max(info.num_variables),
+            self.bound.2.max(info.num_non_zero),
+        );
+        self.entries.push(Entry { id, matrices, assignments: circuits::assignments(blank), index: OnceLock::new() });
+        self
+    }
+
+    /// The index for `id`, generating the SRS and indexing first as needed; None for a
+    /// circuit that isn't registered
+    pub fn get(&self, id: &str) -> Option<(Arc<MarlinIndex<E>>, Setup)> {
+        let entry = self.entries.iter().find(|e| e.id == id)?;
+        let mut srs_time = None;
+        let srs = self.srs.get_or_init(|| {
+            let start = Instant::now();
+            let (constraints, variables, non_zero) = self.bound;
+            let srs = MarlinOf::<E>::universal_setup(constraints, variables, non_zero, &mut OsRng)
+                .expect("Marlin universal setup failed");
+            srs_time = Some(start.elapsed());
+            Arc::new(srs)
+        });
+        let mut index_time = None;
+        let index = entry.index.get_or_init(|| {
+            let start = Instant::now();
+            let blank = Replay { matrices: entry.matrices.clone(), assignment: None };
+            let (pk, vk) = MarlinOf::<E>::index(srs, blank)
+                .unwrap_or_else(|e| panic!("Marlin indexing failed for {}: {:?}", id, e));
+            index_time = Some(start.elapsed());
+            let key_sizes = KeySizes {
+                proving_key_bytes: pk.serialized_size(),
+                verifying_key_bytes: vk.serialized_size(),
+                proving_key_uncompressed_bytes: pk.uncompressed_size(),
+                verifying_key_uncompressed_bytes: vk.uncompressed_size(),
+            };
+            let mut pk_bytes = Vec::new();
+            pk.serialize_unchecked(&mut pk_bytes).expect("serializing to a Vec cannot fail");
+            let (matrices, assignments) = (entry.matrices.clone(), entry.assignments.clone());
+            Arc::new(MarlinIndex { pk_bytes, vk, matrices, assignments, key_sizes })
+        });
+        let setup = Setup { srs_size_bytes: srs.serialized_size(), srs_time, index_time };
+        Some((index.clone(), setup))
+    }
+
+    /// Key sizes of every circuit indexed so far
+    pub fn ready(&self) -> impl Iterator<Item = KeySizes> + '_ {
+        self.entries.iter().filter_map(|entry| entry.index.get().map(|index| index.key_sizes))
+    }
+}
+
+impl<F: PrimeField03, PC: PolynomialCommitment<F, DensePolynomial<F>>> ProofCodec for ark_marlin::Proof<F, PC> {
+    fn encode(&self, compress: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let written = if compress { self.serialize(&mut bytes) } else { self.serialize_uncompressed(&mut bytes) };
+        written.expect("serializing to a Vec cannot fail");
+        bytes
+    }
+
+    fn decode(bytes: &[u8], compress: bool) -> Self {
+        let read = if compress { Self::deserialize(bytes) } else { Self::deserialize_uncompressed(bytes) };
+        read.expect("a proof just serialized reads back")
+    }
+}
+
+/// Times `iterations` proofs, synthesizing each iteration's witness apart from proving
+/// it, after `warmup` untimed ones, and measures each proof's encoding as the Groth16
+/// benchmark does. The prove timing still includes ark-marlin replaying the constraints,
+/// as its own synthesis step
+pub fn bench_prove<E: Engine>(index: &MarlinIndex<E>, budget: Budget, serialization: bool) -> (ProveTimings, ProofEncoding, Duration) {
+    let Budget { iterations, warmup, overhead } = budget;
+    let pk = index.pk();
+    for i in 0..warmup {
+        let replay = Replay { matrices: index.matrices.clone(), assignment: Some(assignment::<E>(&index.assignments, i)) };
+        MarlinOf::<E>::prove(&pk, replay, &mut OsRng).expect("Proving failed");
+    }
+
+    let mut timings = ProveTimings::with_capacity(iterations);
+    let mut encoding = ProofEncoding::new(serialization);
+    let loop_start = Instant::now();
+
+    for i in 0..iterations {
+        let start = Instant::now();
+        let assignment = assignment::<E>(&index.assignments, i);
+        let witness_time = stats::subtract_overhead(start.elapsed(), overhead);
+
+        let start = Instant::now();
+        let replay = Replay { matrices: index.matrices.clone(), assignment: Some(assignment) };
+        let proof = MarlinOf::<E>::prove(&pk, replay, &mut OsRng).expect("Proving failed");
+        timings.push(witness_time, stats::subtract_overhead(start.elapsed(), overhead));
+        encoding.record(&proof, overhead);
+    }
+    (timings, encoding, loop_start.elapsed())
+}
+
+/// A copy of a Marlin statement that its proof doesn't prove. ark-marlin proofs aren't
+/// Clone, so the copy is read back from the proof's encoding
+fn corrupt<E: Engine>(corruption: Corruption, public_inputs: &[Fr<E>], proof: &ProofOf<E>) -> Corrupted<Fr<E>, ProofOf<E>> {
+    let (mut public_inputs, mut proof) = (public_inputs.to_vec(), ProofOf::<E>::decode(&proof.encode(false), false));
+    match corruption {
+        Corruption::Proof => proof.evaluations[0] += Fr::<E>::one(),
+        Corruption::PublicInput => *public_inputs.first_mut().expect("the circuit has no public inputs to corrupt") += Fr::<E>::one(),
+    }
+    Corrupted { corruption, public_inputs, proof }
+}
+
+/// Times `iterations` verifications, after `warmup` untimed ones, cycling through proofs
+/// of the first `statements` iterations' statements and any corrupted copies of them, as
+/// the Groth16 benchmark does
+pub fn bench_verify<E: Engine>(
+    index: &MarlinIndex<E>,
+    budget: Budget,
+    statements: u32,
+    corrupt_with: Option<Corruption>,
+) -> Result<(VerifyTimings, usize, Duration), InvalidProofAccepted> {
+    let Budget { iterations, warmup, overhead } = budget;
+    let pk = index.pk();
+    let statements: Vec<_> = (0..statements)
+        .map(|i| {
+            let assignment = assignment::<E>(&index.assignments, i);
+            let public_inputs = assignment[1..index.matrices.num_instance].to_vec();
+            let replay = Replay { matrices: index.matrices.clone(), assignment: Some(assignment) };
+            let proof = MarlinOf::<E>::prove(&pk, replay, &mut OsRng).expect("Proving failed");
+            let corrupted = corrupt_with.map(|corruption| corrupt::<E>(corruption, &public_inputs, &proof));
+            Statement { public_inputs, proof, corrupted }
+        })
+        .collect();
+    for i in 0..warmup {
+        let statement = &statements[i as usize % statements.len()];
+        let valid = MarlinOf::<E>::verify(&index.vk, &statement.public_inputs, &statement.proof, &mut OsRng).expect("Verification failed");
+        assert!(valid);
+    }
+
+    let (timings, loop_time) = time_verifications(&statements, iterations, overhead, |public_inputs, proof| {
+        MarlinOf::<E>::verify(&index.vk, public_inputs, proof, &mut OsRng).unwrap_or(false)
+    })?;
+    Ok((timings, statements[0].proof.serialized_size(), loop_time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::budget;
+    use crate::{CubeRootCircuit, MultiplyCircuit};
+    use ark_bls12_381::Bls12_381;
+    use ark_bn254::Bn254;
+
+    fn keys<E: Engine>() -> MarlinKeys<E> {
+        MarlinKeys::new()
+            .register("multiply", MultiplyCircuit::<E::ScalarField> { a: None, b: None, c: None })
+            .register("cube_root", CubeRootCircuit::<E::ScalarField> { x: None, y: None })
+    }
+
+    #[test]
+    fn one_srs_serves_every_circuit() {
+        let keys = keys::<Bn254>();
+        let (multiply, setup) = keys.get("multiply").unwrap();
+        assert!(setup.srs_time.is_some() && setup.index_time.is_some());
+        let (_, setup) = keys.get("cube_root").unwrap();
+        assert!(setup.srs_time.is_none() && setup.index_time.is_some());
+        let (_, again) = keys.get("cube_root").unwrap();
+        assert!(again.srs_time.is_none() && again.index_time.is_none());
+        assert_eq!(again.srs_size_bytes, setup.srs_size_bytes);
+        assert!(keys.get("chain").is_none());
+
+        let (timings, encoding, _) = bench_prove(&multiply, budget(2), true);
+        let proof_size = encoding.compressed_size();
+        assert_eq!((timings.witness.len(), timings.prove.len()), (2, 2));
+        assert!(proof_size > 128, "Marlin proofs are larger than Groth16's");
+        assert!(encoding.uncompressed_size() > proof_size);
+        assert_eq!(encoding.timings.map(|t| t.deserialize_compressed.len()), Some(2));
+    }
+
+    #[test]
+    fn replayed_proofs_hold_only_for_their_statement() {
+        let keys = keys::<Bls12_381>();
+        let (cube_root, _) = keys.get("cube_root").unwrap();
+        let (timings, _, _) = bench_verify(&cube_root, budget(2), 2, Some(Corruption::Proof)).unwrap();
+        assert_eq!((timings.accept.len(), timings.reject.map(|r| r.len())), (2, Some(2)));
+
+        // Iteration 0 proves knowledge of the cube root of 125
+        let x = ark_bls12_381::Fr::from(5u64);
+        let replay = Replay { matrices: cube_root.matrices.clone(), assignment: Some(assignment::<Bls12_381>(&cube_root.assignments, 0)) };
+        let proof = MarlinOf::<Bls12_381>::prove(&cube_root.pk(), replay, &mut OsRng).unwrap();
+        let wrong = [to_03::<Bls12_381>(x * x)];
+        assert!(!MarlinOf::<Bls12_381>::verify(&cube_root.vk, &wrong, &proof, &mut OsRng).unwrap());
+    }
+}
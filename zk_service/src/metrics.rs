@@ -0,0 +1,100 @@
+use axum::{extract::State, response::IntoResponse};
+use common::metrics::{self as service, ServiceMetrics};
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts};
+use std::sync::Arc;
+
+use crate::curve::{Curve, Engine};
+use crate::{AppState, CurveKeys, ProofSystem};
+
+pub use common::metrics::ErrorKind;
+
+// ============ Prometheus Metrics ============
+//
+// The HTTP series, and the middleware recording them, are common::metrics, shared with
+// lattice_service; this adds the proof benchmark series. How many circuits are set up,
+// and how big their proving keys are, is read from the key caches on each scrape rather
+// than tracked as setups finish.
+
+pub struct Metrics {
+    pub service: Arc<ServiceMetrics>,
+    pub bench_requests: IntCounterVec,
+    pub bench_failures: IntCounterVec,
+    pub operation_latency: HistogramVec,
+    circuits_ready: IntGaugeVec,
+    proving_key_bytes: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let service = Arc::new(ServiceMetrics::new("zk"));
+        let bench_requests = IntCounterVec::new(
+            Opts::new(service.name("bench_requests_total"), "Benchmark runs by operation, circuit, curve and proof system"),
+            &["operation", "circuit_id", "curve", "proof_system"],
+        )
+        .unwrap();
+        let bench_failures = IntCounterVec::new(
+            Opts::new(service.name("bench_failures_total"), "Benchmark requests that failed, by operation, circuit and AppError variant"),
+            &["operation", "circuit_id", "kind"],
+        )
+        .unwrap();
+        // Verifying a small circuit takes about a millisecond; proving a long chain, minutes
+        let operation_latency = HistogramVec::new(
+            HistogramOpts::new(service.name("proof_operation_duration_seconds"), "Measured latency of individual benchmarked proofs and verifications")
+                .buckets(prometheus::exponential_buckets(1e-4, 4.0, 12).unwrap()),
+            &["operation", "circuit_id", "curve", "proof_system"],
+        )
+        .unwrap();
+        let circuits_ready = IntGaugeVec::new(
+            Opts::new(service.name("circuits_ready"), "Circuits, and chain lengths, whose setup has completed"),
+            &["curve", "proof_system"],
+        )
+        .unwrap();
+        let proving_key_bytes = IntGaugeVec::new(
+            Opts::new(service.name("proving_key_bytes"), "Uncompressed size of every cached proving key"),
+            &["curve", "proof_system"],
+        )
+        .unwrap();
+
+        service.register(&bench_requests);
+        service.register(&bench_failures);
+        service.register(&operation_latency);
+        service.register(&circuits_ready);
+        service.register(&proving_key_bytes);
+
+        Metrics { service, bench_requests, bench_failures, operation_latency, circuits_ready, proving_key_bytes }
+    }
+
+    pub fn observe_benchmark(&self, operation: &str, circuit_id: &str, curve: Curve, proof_system: ProofSystem, timings_us: &[u128]) {
+        let labels = [operation, circuit_id, curve.name(), proof_system.name()];
+        self.bench_requests.with_label_values(&labels).inc();
+        let histogram = self.operation_latency.with_label_values(&labels);
+        for &t in timings_us {
+            histogram.observe(t as f64 / 1_000_000.0);
+        }
+    }
+
+    pub fn bench_failed(&self, operation: &str, circuit_id: &str, kind: &str) {
+        self.bench_failures.with_label_values(&[operation, circuit_id, kind]).inc();
+    }
+
+    fn record_setup<E: Engine>(&self, keys: &CurveKeys<E>) {
+        let groth16: Vec<usize> = keys
+            .circuits
+            .statuses()
+            .filter_map(|status| status.sizes.map(|sizes| sizes.keys.proving_key_uncompressed_bytes))
+            .chain(keys.chains.ready().into_iter().map(|(_, sizes)| sizes.proving_key_uncompressed_bytes))
+            .collect();
+        let marlin: Vec<usize> = keys.marlin.ready().map(|sizes| sizes.proving_key_uncompressed_bytes).collect();
+        for (proof_system, sizes) in [(ProofSystem::Groth16, groth16), (ProofSystem::Marlin, marlin)] {
+            let labels = [E::CURVE.name(), proof_system.name()];
+            self.circuits_ready.with_label_values(&labels).set(sizes.len() as i64);
+            self.proving_key_bytes.with_label_values(&labels).set(sizes.iter().sum::<usize>() as i64);
+        }
+    }
+}
+
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    state.metrics.record_setup(&state.bn254);
+    state.metrics.record_setup(&state.bls12_381);
+    service::exposition(&state.metrics.service)
+}
@@ -0,0 +1,10 @@
+use utoipa::OpenApi;
+
+// ============ OpenAPI Document ============
+//
+// Served at /api-docs/openapi.json with Swagger UI at /swagger-ui, same layout as lattice_service
+// so the two specs can be merged.
+
+#[derive(OpenApi)]
+#[openapi(paths(crate::zk_prove_bench, crate::zk_verify_bench, crate::params, crate::proofs::prove, crate::proofs::verify, crate::proofs::verifying_key, crate::circuits::circuits, common::health::health))]
+pub struct ApiDoc;
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::curve::Curve;
+use crate::{AppError, ProofSystem};
+
+// ============ Prover Parallelism ============
+//
+// Arkworks spreads proving (the MSMs, the FFTs, Marlin's polynomial commitments) over
+// rayon's global pool, which is sized to the machine. A prove benchmark instead runs in a
+// pool of its own with the number of threads the request asked for, so how proving scales
+// can be measured on one machine without rebuilding. Each run reports its speedup over a
+// single-thread baseline for the same circuit, measured once and kept for the process's
+// lifetime.
+
+/// Proofs timed for a single-thread baseline when no run has measured one yet
+pub const BASELINE_ITERATIONS: u32 = 3;
+
+/// Threads the machine can run at once
+pub fn available() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Threads a prove benchmark runs with, and why it isn't what the request asked for
+#[derive(Debug, PartialEq)]
+pub struct ProverThreads {
+    pub threads: usize,
+    pub note: Option<String>,
+}
+
+impl ProverThreads {
+    /// The request's prover_threads, or the server's default without one, clamped to the
+    /// `available` threads rather than oversubscribing them
+    pub fn resolve(requested: Option<u32>, default: usize, available: usize) -> Result<ProverThreads, AppError> {
+        let Some(requested) = requested else {
+            return Ok(ProverThreads { threads: default.min(available), note: None });
+        };
+        if requested == 0 {
+            return Err(AppError::ValidationFailed("prover_threads must be at least 1, got 0".to_string()));
+        }
+        let threads = (requested as usize).min(available);
+        let note = (threads < requested as usize).then(|| {
+            format!("prover_threads {} is more than the {} this machine can run at once, so proving ran on {}", requested, available, threads)
+        });
+        Ok(ProverThreads { threads, note })
+    }
+}
+
+/// A rayon pool of `threads` for one benchmark; arkworks' parallel iterators run on it
+/// for anything called inside its install
+pub fn pool(threads: usize) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("zk-prover-{}", i))
+        .build()
+        .expect("failed to start the prover thread pool")
+}
+
+/// What a single-thread baseline was measured for
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BaselineKey {
+    pub proof_system: ProofSystem,
+    pub curve: Curve,
+    pub circuit_id: String,
+    pub chain_length: Option<u32>,
+}
+
+/// Mean single-thread proving time in milliseconds per circuit, the first measured for each
+#[derive(Default)]
+pub struct Baselines(Mutex<HashMap<BaselineKey, f64>>);
+
+impl Baselines {
+    pub fn get(&self, key: &BaselineKey) -> Option<f64> {
+        self.0.lock().unwrap().get(key).copied()
+    }
+
+    /// Keeps `prove_ms` unless a baseline was already measured, and returns the one kept
+    pub fn record(&self, key: BaselineKey, prove_ms: f64) -> f64 {
+        *self.0.lock().unwrap().entry(key).or_insert(prove_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prover_threads_are_clamped_to_the_machine() {
+        assert_eq!(ProverThreads::resolve(None, 4, 8).unwrap(), ProverThreads { threads: 4, note: None });
+        assert_eq!(ProverThreads::resolve(None, 16, 8).unwrap().threads, 8);
+        assert_eq!(ProverThreads::resolve(Some(2), 4, 8).unwrap(), ProverThreads { threads: 2, note: None });
+        let clamped = ProverThreads::resolve(Some(64), 4, 8).unwrap();
+        assert_eq!(clamped.threads, 8);
+        assert!(clamped.note.unwrap().contains("64"));
+        assert!(matches!(ProverThreads::resolve(Some(0), 4, 8), Err(AppError::ValidationFailed(_))));
+
+        assert_eq!(pool(1).install(rayon::current_num_threads), 1);
+    }
+}
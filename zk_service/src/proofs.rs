@@ -0,0 +1,431 @@
+use axum::{extract::{Path, State}, Json};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Instant;
+use utoipa::ToSchema;
+
+use ark_bn254::{Bn254, Fr};
+use ark_ff::{BigInt, PrimeField};
+use ark_groth16::{Groth16, Proof, ProvingKey};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_snark::SNARK;
+use rand::rngs::OsRng;
+use common::request::{self, FieldError};
+
+use crate::circuits;
+use crate::{catch_benchmark_panic, current_timestamp, run_blocking, AppError, AppState, CubeRootCircuit, MultiplyCircuit, StrictJson};
+
+// ============ Proof Generation and Verification ============
+//
+// Unlike the benchmarks, these prove statements the caller chooses and hand back the
+// proof, so it can be checked by another verifier, and check proofs made elsewhere.
+// Field elements travel as strings, since most exceed what a JSON number can hold
+// exactly: decimal, or hex with a 0x prefix.
+
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProveRequest {
+    circuit_id: String,
+    /// Private inputs as field element strings: {"a", "b"} for multiply, {"x"} for cube_root
+    #[schema(value_type = Object)]
+    inputs: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MultiplyInputs {
+    a: String,
+    b: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CubeRootInputs {
+    x: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ProveResponse {
+    circuit_id: String,
+    /// Groth16 proof over BN254, compressed and base64-encoded
+    proof: String,
+    proof_size_bytes: usize,
+    /// Public inputs the proof is for, as decimal strings: [c] for multiply, [y] for cube_root
+    public_inputs: Vec<String>,
+    prove_time_ms: f64,
+    /// Set when this request was the circuit's first and ran its setup, which prove_time_ms leaves out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    setup_ms: Option<f64>,
+    timestamp: u64,
+}
+
+// Wrapped so that a bad field is reported under inputs, e.g. "inputs.a"
+#[derive(Deserialize)]
+struct Inputs<T> {
+    inputs: T,
+}
+
+fn circuit_inputs<T: DeserializeOwned>(inputs: serde_json::Value) -> Result<T, AppError> {
+    request::from_value::<Inputs<T>>(serde_json::json!({ "inputs": inputs }))
+        .map(|wrapped| wrapped.inputs)
+        .map_err(AppError::InvalidField)
+}
+
+// Up to 64 hex digits, most significant first
+fn parse_hex(digits: &str) -> Option<BigInt<4>> {
+    if digits.is_empty() || digits.len() > 64 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let padded = format!("{:0>64}", digits);
+    let mut limbs = [0u64; 4];
+    for (i, chunk) in padded.as_bytes().chunks(16).enumerate() {
+        limbs[3 - i] = u64::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+    }
+    Some(BigInt(limbs))
+}
+
+/// Parses a decimal or 0x-prefixed hex field element, refusing anything at or above the
+/// scalar field modulus rather than silently reducing it
+fn field_element(field: &str, value: &str) -> Result<Fr, AppError> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(digits) => parse_hex(digits),
+        None => value.parse::<BigInt<4>>().ok(),
+    };
+    parsed.and_then(Fr::from_bigint).ok_or_else(|| {
+        AppError::InvalidField(FieldError {
+            error: format!("Invalid field '{}': '{}' is not a BN254 scalar field element", field, value),
+            field: field.to_string(),
+            expected: Some("decimal or 0x-prefixed hex string below the BN254 scalar field modulus".to_string()),
+        })
+    })
+}
+
+fn groth16_prove<C: ConstraintSynthesizer<Fr>>(pk: &ProvingKey<Bn254>, circuit: C) -> Proof<Bn254> {
+    Groth16::<Bn254>::prove(pk, circuit, &mut OsRng).expect("Proving failed")
+}
+
+type Prover = Box<dyn FnOnce(&ProvingKey<Bn254>) -> Proof<Bn254> + Send>;
+
+#[utoipa::path(
+    post,
+    path = "/prove",
+    request_body = ProveRequest,
+    responses(
+        (status = 200, description = "Proof and the public inputs it proves", body = ProveResponse),
+        (status = 400, description = "InvalidCircuit: unknown circuit_id", body = String, content_type = "text/plain"),
+        (status = 422, description = "InvalidField: a missing, unknown or unparseable input", body = FieldError),
+        (status = 500, description = "BenchmarkPanicked: proving panicked, as a PanicBody; or Internal: the proving task failed otherwise", body = crate::PanicBody),
+    )
+)]
+pub async fn prove(
+    State(state): State<AppState>,
+    StrictJson(req): StrictJson<ProveRequest>,
+) -> Result<Json<ProveResponse>, AppError> {
+    let _in_flight = state.readiness.track();
+
+    // Public inputs are computed here from the witness, so the statement always holds
+    let (public_inputs, prover): (Vec<Fr>, Prover) = match req.circuit_id.as_str() {
+        "multiply" => {
+            let MultiplyInputs { a, b } = circuit_inputs(req.inputs)?;
+            let (a, b) = (field_element("inputs.a", &a)?, field_element("inputs.b", &b)?);
+            let c = a * b;
+            let circuit = MultiplyCircuit { a: Some(a), b: Some(b), c: Some(c) };
+            (vec![c], Box::new(move |pk| groth16_prove(pk, circuit)))
+        }
+        "cube_root" => {
+            let CubeRootInputs { x } = circuit_inputs(req.inputs)?;
+            let x = field_element("inputs.x", &x)?;
+            let y = x * x * x;
+            let circuit = CubeRootCircuit { x: Some(x), y: Some(y) };
+            (vec![y], Box::new(move |pk| groth16_prove(pk, circuit)))
+        }
+        _ => return Err(AppError::InvalidCircuit(req.circuit_id)),
+    };
+
+    let (keys, setup_time) = state.bn254.circuits.get(&req.circuit_id).await?;
+    let circuit_id = req.circuit_id.clone();
+    let (proof, prove_time) = run_blocking(move || {
+        catch_benchmark_panic(&circuit_id, "prove", || {
+            let start = Instant::now();
+            let proof = prover(&keys.pk);
+            Ok((proof, start.elapsed()))
+        })
+    })
+    .await?;
+
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).expect("serializing to a Vec cannot fail");
+
+    Ok(Json(ProveResponse {
+        circuit_id: req.circuit_id,
+        proof: STANDARD.encode(&bytes),
+        proof_size_bytes: bytes.len(),
+        public_inputs: public_inputs.iter().map(Fr::to_string).collect(),
+        prove_time_ms: prove_time.as_secs_f64() * 1000.0,
+        setup_ms: setup_time.map(|t| t.as_secs_f64() * 1000.0),
+        timestamp: current_timestamp(),
+    }))
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct VerifyRequest {
+    circuit_id: String,
+    /// Groth16 proof over BN254, compressed and base64-encoded, as /prove returns it
+    proof: String,
+    /// Public inputs in circuit order, as decimal or 0x-prefixed hex strings
+    public_inputs: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct VerifyResponse {
+    circuit_id: String,
+    /// Whether the proof holds for these public inputs
+    valid: bool,
+    verify_time_ms: f64,
+    /// Set when this request was the circuit's first and ran its setup, which verify_time_ms leaves out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    setup_ms: Option<f64>,
+    timestamp: u64,
+}
+
+#[utoipa::path(
+    post,
+    path = "/verify",
+    request_body = VerifyRequest,
+    responses(
+        (status = 200, description = "Whether the proof verified; an invalid proof is valid: false, not an error", body = VerifyResponse),
+        (status = 400, description = "InvalidCircuit: unknown circuit_id; or MalformedProof: the proof isn't base64 or doesn't decode to a compressed Groth16 proof", body = String, content_type = "text/plain"),
+        (status = 422, description = "ValidationFailed: wrong number of public inputs, as text/plain; or InvalidField: an unparseable public input, as a JSON FieldError", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn verify(
+    State(state): State<AppState>,
+    StrictJson(req): StrictJson<VerifyRequest>,
+) -> Result<Json<VerifyResponse>, AppError> {
+    let (keys, setup_time) = state.bn254.circuits.get(&req.circuit_id).await?;
+    let vk = &keys.vk;
+
+    // Points off the curve or outside the prime-order subgroup are rejected here, so a
+    // proof that decodes is one the pairing check can be trusted on
+    let bytes = STANDARD
+        .decode(&req.proof)
+        .map_err(|e| AppError::MalformedProof(format!("not valid base64: {}", e)))?;
+    let proof = Proof::<Bn254>::deserialize_compressed(&*bytes)
+        .map_err(|e| AppError::MalformedProof(format!("not a compressed BN254 Groth16 proof: {}", e)))?;
+
+    let expected = vk.gamma_abc_g1.len() - 1;
+    if req.public_inputs.len() != expected {
+        return Err(AppError::ValidationFailed(format!(
+            "{} takes {} public input(s), got {}", req.circuit_id, expected, req.public_inputs.len()
+        )));
+    }
+    let public_inputs = req
+        .public_inputs
+        .iter()
+        .enumerate()
+        .map(|(i, value)| field_element(&format!("public_inputs[{}]", i), value))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let start = Instant::now();
+    let valid = Groth16::<Bn254>::verify(vk, &public_inputs, &proof)
+        .expect("public input count was checked against the verifying key");
+    let verify_time = start.elapsed();
+
+    Ok(Json(VerifyResponse {
+        circuit_id: req.circuit_id,
+        valid,
+        verify_time_ms: verify_time.as_secs_f64() * 1000.0,
+        setup_ms: setup_time.map(|t| t.as_secs_f64() * 1000.0),
+        timestamp: current_timestamp(),
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct VerifyingKeyResponse {
+    circuit_id: String,
+    /// Groth16 verifying key over BN254, compressed and base64-encoded
+    verifying_key: String,
+    compressed_size_bytes: usize,
+    uncompressed_size_bytes: usize,
+    /// Public inputs a proof for this circuit is checked against
+    public_inputs: usize,
+    /// Hex SHA-256 of the compressed verifying key
+    fingerprint: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/circuits/{circuit_id}/vk",
+    params(("circuit_id" = String, Path, description = "multiply or cube_root")),
+    responses(
+        (status = 200, description = "Verifying key, for checking this service's proofs elsewhere without a trusted setup of your own", body = VerifyingKeyResponse),
+        (status = 404, description = "CircuitNotFound: unknown circuit_id", body = crate::CircuitNotFoundBody),
+    )
+)]
+pub async fn verifying_key(
+    State(state): State<AppState>,
+    Path(circuit_id): Path<String>,
+) -> Result<Json<VerifyingKeyResponse>, AppError> {
+    if !state.bn254.circuits.contains(&circuit_id) {
+        return Err(AppError::CircuitNotFound(circuit_id));
+    }
+    let (keys, _) = state.bn254.circuits.get(&circuit_id).await?;
+    let vk = &keys.vk;
+
+    let mut bytes = Vec::new();
+    vk.serialize_compressed(&mut bytes).expect("serializing to a Vec cannot fail");
+
+    Ok(Json(VerifyingKeyResponse {
+        circuit_id,
+        verifying_key: STANDARD.encode(&bytes),
+        compressed_size_bytes: bytes.len(),
+        uncompressed_size_bytes: vk.uncompressed_size(),
+        public_inputs: vk.gamma_abc_g1.len() - 1,
+        fingerprint: circuits::vk_fingerprint(vk),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::ready_state;
+    use ark_groth16::VerifyingKey;
+    use serde_json::json;
+    use sha2::{Digest, Sha256};
+
+    async fn prove_json(state: &AppState, body: serde_json::Value) -> Result<ProveResponse, AppError> {
+        let req: ProveRequest = serde_json::from_value(body).unwrap();
+        prove(State(state.clone()), StrictJson(req)).await.map(|Json(resp)| resp)
+    }
+
+    fn verifies(vk: &VerifyingKey<Bn254>, resp: &ProveResponse) -> bool {
+        let proof = Proof::<Bn254>::deserialize_compressed(&*STANDARD.decode(&resp.proof).unwrap()).unwrap();
+        let public_inputs: Vec<Fr> = resp.public_inputs.iter().map(|s| s.parse().unwrap()).collect();
+        Groth16::<Bn254>::verify(vk, &public_inputs, &proof).unwrap()
+    }
+
+    #[tokio::test]
+    async fn returned_proofs_verify_against_the_verifying_key() {
+        let state = ready_state();
+
+        let resp = prove_json(&state, json!({ "circuit_id": "multiply", "inputs": { "a": "3", "b": "7" } })).await.unwrap();
+        assert_eq!(resp.public_inputs, ["21"]);
+        assert_eq!(resp.proof_size_bytes, 128);
+        // The first request for a circuit sets it up, and says so
+        assert!(resp.setup_ms.is_some());
+        let (multiply, _) = state.bn254.circuits.get("multiply").await.unwrap();
+        assert!(verifies(&multiply.vk, &resp));
+
+        let resp = prove_json(&state, json!({ "circuit_id": "cube_root", "inputs": { "x": "5" } })).await.unwrap();
+        assert_eq!(resp.public_inputs, ["125"]);
+        let (cube_root, _) = state.bn254.circuits.get("cube_root").await.unwrap();
+        assert!(verifies(&cube_root.vk, &resp));
+        assert!(!verifies(&multiply.vk, &resp));
+
+        let again = prove_json(&state, json!({ "circuit_id": "cube_root", "inputs": { "x": "5" } })).await.unwrap();
+        assert!(again.setup_ms.is_none());
+    }
+
+    #[tokio::test]
+    async fn bad_inputs_are_422s_naming_the_field() {
+        let state = ready_state();
+        let field = |resp: Result<ProveResponse, AppError>| match resp {
+            Err(AppError::InvalidField(e)) => e.field,
+            _ => panic!("expected an InvalidField error"),
+        };
+
+        let missing = prove_json(&state, json!({ "circuit_id": "multiply", "inputs": { "a": "3" } })).await;
+        assert_eq!(field(missing), "inputs.b");
+        let not_a_number = prove_json(&state, json!({ "circuit_id": "multiply", "inputs": { "a": "3", "b": "seven" } })).await;
+        assert_eq!(field(not_a_number), "inputs.b");
+        let negative = prove_json(&state, json!({ "circuit_id": "cube_root", "inputs": { "x": "-5" } })).await;
+        assert_eq!(field(negative), "inputs.x");
+        let modulus = Fr::MODULUS.to_string();
+        let too_big = prove_json(&state, json!({ "circuit_id": "cube_root", "inputs": { "x": modulus } })).await;
+        assert_eq!(field(too_big), "inputs.x");
+        let unknown = prove_json(&state, json!({ "circuit_id": "cube_root", "inputs": { "x": "5", "y": "125" } })).await;
+        assert_eq!(field(unknown), "inputs.y");
+
+        let circuit = prove_json(&state, json!({ "circuit_id": "square", "inputs": {} })).await;
+        assert!(matches!(circuit, Err(AppError::InvalidCircuit(_))));
+    }
+
+    async fn verify_json(state: &AppState, body: serde_json::Value) -> Result<VerifyResponse, AppError> {
+        let req: VerifyRequest = serde_json::from_value(body).unwrap();
+        verify(State(state.clone()), StrictJson(req)).await.map(|Json(resp)| resp)
+    }
+
+    #[test]
+    fn field_elements_parse_from_decimal_or_hex() {
+        assert_eq!(field_element("x", "255").unwrap(), Fr::from(255u64));
+        assert_eq!(field_element("x", "0xff").unwrap(), Fr::from(255u64));
+        assert_eq!(field_element("x", "0x0000000000000000000000000000000000000000000000010000000000000000").unwrap(), Fr::from(u64::MAX) + Fr::from(1u64));
+        for bad in ["", "0x", "0xfg", "0x+1", "-1", "1.5", &format!("0x{}", "f".repeat(65))] {
+            assert!(field_element("x", bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_accepts_proofs_from_prove_and_only_for_their_inputs() {
+        let state = ready_state();
+        let proved = prove_json(&state, json!({ "circuit_id": "multiply", "inputs": { "a": "3", "b": "7" } })).await.unwrap();
+
+        let valid = verify_json(&state, json!({ "circuit_id": "multiply", "proof": proved.proof, "public_inputs": ["21"] })).await.unwrap();
+        assert!(valid.valid);
+        let hex = verify_json(&state, json!({ "circuit_id": "multiply", "proof": proved.proof, "public_inputs": ["0x15"] })).await.unwrap();
+        assert!(hex.valid);
+
+        // Well-formed, but for a different statement: a 200 saying no
+        let other = verify_json(&state, json!({ "circuit_id": "multiply", "proof": proved.proof, "public_inputs": ["22"] })).await.unwrap();
+        assert!(!other.valid);
+        let other_circuit = verify_json(&state, json!({ "circuit_id": "cube_root", "proof": proved.proof, "public_inputs": ["21"] })).await.unwrap();
+        assert!(!other_circuit.valid);
+
+        let count = verify_json(&state, json!({ "circuit_id": "multiply", "proof": proved.proof, "public_inputs": ["3", "21"] })).await;
+        assert!(matches!(count, Err(AppError::ValidationFailed(_))));
+        let unparseable = verify_json(&state, json!({ "circuit_id": "multiply", "proof": proved.proof, "public_inputs": ["twenty-one"] })).await;
+        assert!(matches!(unparseable, Err(AppError::InvalidField(e)) if e.field == "public_inputs[0]"));
+    }
+
+    #[tokio::test]
+    async fn corrupted_proofs_are_400s() {
+        let state = ready_state();
+        let proved = prove_json(&state, json!({ "circuit_id": "multiply", "inputs": { "a": "3", "b": "7" } })).await.unwrap();
+        let mut bytes = STANDARD.decode(&proved.proof).unwrap();
+
+        let not_base64 = verify_json(&state, json!({ "circuit_id": "multiply", "proof": "not base64!", "public_inputs": ["21"] })).await;
+        assert!(matches!(not_base64, Err(AppError::MalformedProof(_))));
+
+        let truncated = STANDARD.encode(&bytes[..bytes.len() - 1]);
+        let short = verify_json(&state, json!({ "circuit_id": "multiply", "proof": truncated, "public_inputs": ["21"] })).await;
+        assert!(matches!(short, Err(AppError::MalformedProof(_))));
+
+        // The first point's x coordinate pushed past the base field modulus
+        bytes[..32].fill(0xff);
+        let corrupted = verify_json(&state, json!({ "circuit_id": "multiply", "proof": STANDARD.encode(&bytes), "public_inputs": ["21"] })).await;
+        assert!(matches!(corrupted, Err(AppError::MalformedProof(_))));
+    }
+
+    #[tokio::test]
+    async fn exported_verifying_keys_check_benchmark_proofs() {
+        let state = ready_state();
+        let Json(exported) = verifying_key(State(state.clone()), Path("multiply".to_string())).await.unwrap();
+        let bytes = STANDARD.decode(&exported.verifying_key).unwrap();
+        assert_eq!(exported.compressed_size_bytes, bytes.len());
+        assert_eq!(exported.public_inputs, 1);
+        assert_eq!(exported.fingerprint, hex::encode(Sha256::digest(&bytes)));
+        let vk = VerifyingKey::<Bn254>::deserialize_compressed(&*bytes).unwrap();
+        assert_eq!(exported.uncompressed_size_bytes, vk.uncompressed_size());
+
+        // A proof made just as zk_prove_bench makes them, with the service's proving key
+        let (a, b) = (Fr::from(3u64), Fr::from(7u64));
+        let circuit = MultiplyCircuit { a: Some(a), b: Some(b), c: Some(a * b) };
+        let proof = groth16_prove(&state.bn254.circuits.get("multiply").await.unwrap().0.pk, circuit);
+        assert!(Groth16::<Bn254>::verify(&vk, &[a * b], &proof).unwrap());
+
+        let unknown = verifying_key(State(state), Path("square".to_string())).await;
+        assert!(matches!(unknown, Err(AppError::CircuitNotFound(id)) if id == "square"));
+    }
+}
@@ -0,0 +1,186 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_relations::r1cs::ConstraintMatrices;
+use serde::Serialize;
+use tokio::sync::OnceCell;
+use utoipa::ToSchema;
+
+use crate::circuits::{self, Assignments, BenchCircuit, CircuitSizes};
+use crate::keystore::KeySource;
+use crate::curve::{Curve, Engine};
+use crate::{catch_benchmark_panic, keystore, run_blocking, AppError};
+
+// ============ Circuit Registry ============
+//
+// Keys for each fixed circuit on one curve, set up by the first request that needs them
+// rather than at boot, so the service is ready at once and only pays for the circuits
+// and curves that get used.
+// Each circuit's async once-cell holds later first requests until the one running setup
+// finishes, so concurrent first requests share a single setup.
+
+pub struct CircuitKeys<E: Engine> {
+    pub pk: ProvingKey<E>,
+    pub vk: VerifyingKey<E>,
+    pub sizes: CircuitSizes,
+    /// The R1CS the proving key was set up for, so the benchmarks can prove from an
+    /// assignment they synthesized and timed themselves
+    pub matrices: ConstraintMatrices<E::ScalarField>,
+    pub assignments: Assignments<E::ScalarField>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SetupStatus {
+    /// No request has needed this circuit yet
+    NotSetUp,
+    SettingUp,
+    Ready,
+}
+
+type SetUp<E> = Box<dyn Fn(&KeySource) -> CircuitKeys<E> + Send + Sync>;
+
+struct Entry<E: Engine> {
+    id: &'static str,
+    description: &'static str,
+    set_up: SetUp<E>,
+    keys: OnceCell<Arc<CircuitKeys<E>>>,
+    setting_up: AtomicBool,
+    setup_time: OnceLock<Duration>,
+}
+
+// Clears Entry::setting_up however setup ends, including the request being dropped
+struct SettingUp<'a>(&'a AtomicBool);
+
+impl Drop for SettingUp<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// How a circuit stands on one curve, as GET /circuits lists it
+pub struct Status {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub curve: Curve,
+    pub setup: SetupStatus,
+    pub setup_time: Option<Duration>,
+    pub sizes: Option<CircuitSizes>,
+    pub vk_fingerprint: Option<String>,
+}
+
+pub struct Registry<E: Engine> {
+    /// Where keys are loaded from and saved to, and how they're set up; see keystore
+    keys: KeySource,
+    entries: Vec<Entry<E>>,
+}
+
+impl<E: Engine> Registry<E> {
+    pub fn new(keys: KeySource) -> Self {
+        Registry { keys, entries: Vec::new() }
+    }
+
+    /// Adds a circuit, described by `blank`: an instance without a witness, as setup takes
+    pub fn register<C: BenchCircuit<E::ScalarField>>(mut self, id: &'static str, description: &'static str, blank: C) -> Self {
+        let set_up: SetUp<E> = Box::new(move |keys| {
+            let (pk, vk) = keystore::load_or_setup(keys, id, blank.clone());
+            let sizes = CircuitSizes::measure(blank.clone(), &pk, &vk);
+            let matrices = circuits::matrices(blank.clone());
+            CircuitKeys { pk, vk, sizes, matrices, assignments: circuits::assignments(blank.clone()) }
+        });
+        self.entries.push(Entry {
+            id,
+            description,
+            set_up,
+            keys: OnceCell::new(),
+            setting_up: AtomicBool::new(false),
+            setup_time: OnceLock::new(),
+        });
+        self
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.entries.iter().any(|e| e.id == id)
+    }
+
+    /// Keys for `id`, set up first if no request has needed them yet, with the time setup
+    /// took if this call ran it. Setup runs on the blocking pool like the benchmarks
+    pub async fn get(self: &Arc<Self>, id: &str) -> Result<(Arc<CircuitKeys<E>>, Option<Duration>), AppError> {
+        let index = self
+            .entries
+            .iter()
+            .position(|e| e.id == id)
+            .ok_or_else(|| AppError::InvalidCircuit(id.to_string()))?;
+        let entry = &self.entries[index];
+        let mut setup_time = None;
+
+        let keys = entry
+            .keys
+            .get_or_try_init(|| async {
+                entry.setting_up.store(true, Ordering::SeqCst);
+                let _setting_up = SettingUp(&entry.setting_up);
+                let registry = self.clone();
+                let start = Instant::now();
+                let keys = run_blocking(move || {
+                    let entry = &registry.entries[index];
+                    catch_benchmark_panic(entry.id, "setup", || Ok((entry.set_up)(&registry.keys)))
+                })
+                .await?;
+                let elapsed = start.elapsed();
+                let _ = entry.setup_time.set(elapsed);
+                setup_time = Some(elapsed);
+                Ok::<_, AppError>(Arc::new(keys))
+            })
+            .await?;
+        Ok((keys.clone(), setup_time))
+    }
+
+    /// Every circuit in registration order
+    pub fn statuses(&self) -> impl Iterator<Item = Status> + '_ {
+        self.entries.iter().map(|entry| {
+            let keys = entry.keys.get();
+            let setup = if keys.is_some() {
+                SetupStatus::Ready
+            } else if entry.setting_up.load(Ordering::SeqCst) {
+                SetupStatus::SettingUp
+            } else {
+                SetupStatus::NotSetUp
+            };
+            Status {
+                id: entry.id,
+                description: entry.description,
+                curve: E::CURVE,
+                setup,
+                setup_time: entry.setup_time.get().copied(),
+                sizes: keys.map(|keys| keys.sizes.clone()),
+                vk_fingerprint: keys.map(|keys| circuits::vk_fingerprint(&keys.vk)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MultiplyCircuit;
+    use ark_bn254::{Bn254, Fr};
+
+    #[tokio::test]
+    async fn concurrent_first_requests_share_one_setup() {
+        let registry = Arc::new(Registry::<Bn254>::new(KeySource::default()).register("multiply", "a * b = c", MultiplyCircuit::<Fr> { a: None, b: None, c: None }));
+        assert_eq!(registry.statuses().next().unwrap().setup, SetupStatus::NotSetUp);
+
+        let (first, second) = tokio::join!(registry.get("multiply"), registry.get("multiply"));
+        let ((first, first_setup), (second, second_setup)) = (first.unwrap(), second.unwrap());
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(first_setup.is_some() as u8 + second_setup.is_some() as u8, 1);
+
+        let status = registry.statuses().next().unwrap();
+        assert_eq!(status.setup, SetupStatus::Ready);
+        assert_eq!(status.setup_time, first_setup.or(second_setup));
+        assert!(registry.get("multiply").await.unwrap().1.is_none());
+        assert!(matches!(registry.get("square").await, Err(AppError::InvalidCircuit(_))));
+    }
+}